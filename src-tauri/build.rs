@@ -11,6 +11,8 @@ fn main() {
     prost_build::Config::new()
         .type_attribute(".", "#[derive(serde::Serialize, serde::Deserialize)]")
         .out_dir(&out_dir)
+        // Field tables for preserving unknown fields on round-trip (core::protobuf::unknown_fields)
+        .file_descriptor_set_path(std::path::Path::new(&out_dir).join("tibia_descriptor.bin"))
         .compile_protos(
             &[
                 "protobuf/appearances.proto",
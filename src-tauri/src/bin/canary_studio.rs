@@ -0,0 +1,639 @@
+// Headless command-line front-end for the Canary Studio library.
+//
+// Every subcommand works on the same parsers/writers the Tauri commands use, so
+// asset builds can be scripted on CI machines without starting the UI:
+//
+//   canary-studio appearances stats <appearances.dat>
+//   canary-studio sprites export-png <assets_dir> <out_dir> 100-200 355
+//   canary-studio qm import-csv <file.qm> <translations.csv> --out <out.qm>
+//
+// Structured results are printed as JSON on stdout; errors go to stderr with a
+// non-zero exit code (1 = usage, 2 = operation failed). Like the app, every
+// file overwritten is snapshotted first (see `--backup-dir` / `--no-backup`).
+
+use std::collections::HashMap;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use tibia_assets_editor_lib::core::backups::{self, snapshot_before_write, BackupManager, DEFAULT_BACKUPS_PER_FILE};
+use tibia_assets_editor_lib::core::jobs::JobHandle;
+use tibia_assets_editor_lib::features::appearances::commands::helpers::{get_index_for_category, get_items_by_category, rebuild_indexes};
+use tibia_assets_editor_lib::features::appearances::commands::{import_appearance_files, query_appearance_ids, validate_loaded_assets, write_appearance_aec, write_appearance_json, write_appearances_file};
+use tibia_assets_editor_lib::features::appearances::parsers::{get_statistics, load_appearances, load_appearances_preserving, LegacyClientVersion};
+use tibia_assets_editor_lib::features::appearances::AppearanceCategory;
+use tibia_assets_editor_lib::features::dat_merge::diff::{diff_appearances_paths, write_diff, DiffExportFormat};
+use tibia_assets_editor_lib::features::otb::commands::{load_items_otb_file, loaded_items_otb_mapping, save_items_otb_file, sync_loaded_items_otb};
+use tibia_assets_editor_lib::features::qm::commands::{apply_translations, read_csv_translations, write_entries_csv};
+use tibia_assets_editor_lib::features::qm::{qm_parser, qm_writer};
+use tibia_assets_editor_lib::features::rcc::commands::extract_entries;
+use tibia_assets_editor_lib::features::rcc::rcc_parser::RccFile;
+use tibia_assets_editor_lib::features::rcc::rcc_writer;
+use tibia_assets_editor_lib::features::sounds::parsers::SoundsParser;
+use tibia_assets_editor_lib::features::sprites::commands::{
+    compile_imported_sprite_sheet, compute_appearance_metrics, export_animation, export_legacy_files, import_image_tiles, AnimationFormat, AnimationPattern,
+};
+use tibia_assets_editor_lib::features::sprites::SpriteLoader;
+use tibia_assets_editor_lib::features::staticdata::parsers::{doc_statistics, load_staticdata_doc, save_staticdata_doc, StaticDataDoc};
+use tibia_assets_editor_lib::state::AppState;
+
+const USAGE: &str = "\
+Usage: canary-studio <domain> <action> [args...] [--option value]
+
+appearances stats <appearances.dat>
+appearances dump <appearances.dat> <category> <id>
+appearances export-json <appearances.dat> <category> <id> <out.json>
+appearances export-aec <appearances.dat> <category> <id> <out.aec> --assets <assets_dir>
+appearances import <appearances.dat> <file.json|file.aec>... [--assets <assets_dir>] [--out <out.dat>]
+appearances diff <old.dat> <new.dat> [--json <out.json>] [--html <out.html>]
+appearances validate <appearances.dat> [--assets <assets_dir>]
+appearances query <appearances.dat> <category> <query>
+appearances animate <appearances.dat> <category> <id> <out_dir> --assets <assets_dir> [--format gif|apng] [--group <n>] [--pattern-y <n>] [--pattern-z <n>]
+appearances metrics <appearances.dat> --assets <assets_dir> [<category> [<id>]] [--out <out.dat>]
+appearances export-legacy <appearances.dat> <Tibia.dat> <Tibia.spr> --assets <assets_dir> [--version 1098|12xx] [--pixels rgb|rgba]
+sprites export-png <assets_dir> <out_dir> <id|first-last>...
+sprites compile <assets_dir> <image>... [--tile <w>x<h>] [--chroma <RRGGBB>]
+staticdata stats <staticdata.dat>
+staticdata export-json <staticdata.dat> <out.json>
+staticdata import-json <staticdata.dat> <in.json> [--out <out.dat>]
+sounds stats <sounds_dir>
+sounds export-json <sounds_dir> <out.json>
+otb mapping <items.otb> <appearances.dat>
+otb sync <items.otb> <appearances.dat> [--refresh true|false] [--out <out.otb>]
+qm export-csv <file.qm> <out.csv>
+qm import-csv <file.qm> <in.csv> [--out <out.qm>]
+rcc list <file.rcc>
+rcc extract <file.rcc> <out_dir>
+rcc replace <file.rcc> <resource_path> <new_file> [--out <out.rcc>]
+
+<category> is one of: objects, outfits, effects, missiles
+
+Overwritten files are backed up under --backup-dir <dir> (default
+.canary-studio-backups); pass --no-backup to skip.";
+
+/// Where snapshots go when `--backup-dir` is not given.
+const DEFAULT_BACKUP_DIR: &str = ".canary-studio-backups";
+/// Options that take no value.
+/// Upper bound on how many ids `100-200` style arguments may expand to.
+const MAX_RANGE_IDS: u64 = 1_000_000;
+
+const FLAGS: &[&str] = &["no-backup"];
+
+/// Positional arguments plus `--key value` options.
+struct Args {
+    positional: Vec<String>,
+    options: HashMap<String, String>,
+}
+
+impl Args {
+    fn parse(raw: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut positional = Vec::new();
+        let mut options = HashMap::new();
+        let mut raw = raw.peekable();
+        while let Some(arg) = raw.next() {
+            if let Some(key) = arg.strip_prefix("--") {
+                if FLAGS.contains(&key) {
+                    options.insert(key.to_string(), "true".to_string());
+                    continue;
+                }
+                let value = raw.next().ok_or_else(|| format!("Missing value for --{}", key))?;
+                options.insert(key.to_string(), value);
+            } else {
+                positional.push(arg);
+            }
+        }
+        Ok(Self {
+            positional,
+            options,
+        })
+    }
+
+    fn get(&self, index: usize, name: &str) -> Result<&str, String> {
+        self.positional.get(index).map(String::as_str).ok_or_else(|| format!("Missing argument <{}>", name))
+    }
+
+    fn rest(&self, from: usize) -> &[String] {
+        self.positional.get(from..).unwrap_or(&[])
+    }
+
+    fn option(&self, key: &str) -> Option<&str> {
+        self.options.get(key).map(String::as_str)
+    }
+}
+
+enum CliError {
+    Usage(String),
+    Failed(String),
+}
+
+impl From<String> for CliError {
+    fn from(e: String) -> Self {
+        CliError::Failed(e)
+    }
+}
+
+fn main() -> ExitCode {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+
+    let args = match Args::parse(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => return usage_error(&e),
+    };
+
+    let domain = args.positional.first().map(String::as_str).unwrap_or("");
+    let action = args.positional.get(1).map(String::as_str).unwrap_or("");
+    let sub = Args {
+        positional: args.rest(2).to_vec(),
+        options: args.options.clone(),
+    };
+
+    if args.option("no-backup").is_none() {
+        let dir = args.option("backup-dir").unwrap_or(DEFAULT_BACKUP_DIR);
+        match BackupManager::open(dir, DEFAULT_BACKUPS_PER_FILE) {
+            Ok(manager) => backups::init(manager),
+            Err(e) => {
+                eprintln!("error: {:#} (pass --no-backup to write without backups)", e);
+                return ExitCode::from(2);
+            }
+        }
+    }
+
+    let result = match domain {
+        "appearances" => run_appearances(action, &sub),
+        "sprites" => run_sprites(action, &sub),
+        "staticdata" => run_staticdata(action, &sub),
+        "sounds" => run_sounds(action, &sub),
+        "otb" => run_otb(action, &sub),
+        "qm" => run_qm(action, &sub),
+        "rcc" => run_rcc(action, &sub),
+        "" | "help" | "-h" => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        other => Err(CliError::Usage(format!("Unknown domain: {}", other))),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(CliError::Usage(e)) => usage_error(&e),
+        Err(CliError::Failed(e)) => {
+            eprintln!("error: {}", e);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn usage_error(message: &str) -> ExitCode {
+    eprintln!("error: {}\n\n{}", message, USAGE);
+    ExitCode::from(1)
+}
+
+fn unknown_action(domain: &str, action: &str) -> CliError {
+    CliError::Usage(format!("Unknown {} action: '{}'", domain, action))
+}
+
+fn print_json<T: serde::Serialize>(value: &T) -> Result<(), CliError> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| format!("Failed to serialize output: {}", e))?;
+    println!("{}", json);
+    Ok(())
+}
+
+fn parse_category(value: &str) -> Result<AppearanceCategory, CliError> {
+    match value.to_ascii_lowercase().as_str() {
+        "objects" | "object" => Ok(AppearanceCategory::Objects),
+        "outfits" | "outfit" => Ok(AppearanceCategory::Outfits),
+        "effects" | "effect" => Ok(AppearanceCategory::Effects),
+        "missiles" | "missile" => Ok(AppearanceCategory::Missiles),
+        other => Err(CliError::Usage(format!("Unknown category: {}", other))),
+    }
+}
+
+fn parse_id(value: &str) -> Result<u32, CliError> {
+    value.parse::<u32>().map_err(|_| CliError::Usage(format!("Invalid id: {}", value)))
+}
+
+/// Expands `100`, `100-200` style arguments into a flat id list, refusing
+/// more than [`MAX_RANGE_IDS`] ids in total.
+fn parse_id_ranges(values: &[String]) -> Result<Vec<u32>, CliError> {
+    let mut ids = Vec::new();
+    let mut total: u64 = 0;
+    for value in values {
+        match value.split_once('-') {
+            Some((first, last)) => {
+                let (first, last) = (parse_id(first)?, parse_id(last)?);
+                if first > last {
+                    return Err(CliError::Usage(format!("Invalid range: {}", value)));
+                }
+                total += u64::from(last - first) + 1;
+                if total > MAX_RANGE_IDS {
+                    return Err(CliError::Usage(format!("Range {} expands to too many ids (limit {})", value, MAX_RANGE_IDS)));
+                }
+                ids.extend(first..=last);
+            }
+            None => ids.push(parse_id(value)?),
+        }
+    }
+    Ok(ids)
+}
+
+/// Loads `appearances.dat` into a fresh [`AppState`] with its indexes built,
+/// mirroring `load_appearances_file`.
+fn load_state(dat_path: &str) -> Result<AppState, CliError> {
+    let (appearances, unknown_fields) = load_appearances_preserving(dat_path).map_err(|e| format!("Failed to load appearances: {}", e))?;
+    let state = AppState::new();
+    rebuild_indexes(&state, &appearances);
+    *state.appearances.write() = Some(appearances);
+    *state.appearance_unknown_fields.write() = unknown_fields;
+    *state.tibia_path.lock() = Some(PathBuf::from(dat_path));
+    Ok(state)
+}
+
+/// Loads the sprite catalog from `<assets_dir>/catalog-content.json`.
+fn load_sprites(state: &AppState, assets_dir: &str) -> Result<(), CliError> {
+    let catalog = Path::new(assets_dir).join("catalog-content.json");
+    let loader = SpriteLoader::new(catalog.as_path(), Path::new(assets_dir)).map_err(|e| format!("Failed to load sprite catalog: {}", e))?;
+    *state.sprite_loader.write() = Some(loader);
+    Ok(())
+}
+
+fn catalog_path(assets_dir: &str) -> String {
+    Path::new(assets_dir).join("catalog-content.json").to_string_lossy().to_string()
+}
+
+// ── Appearances ─────────────────────────────────────────────────────────────
+
+fn run_appearances(action: &str, args: &Args) -> Result<(), CliError> {
+    match action {
+        "stats" => {
+            let appearances = load_appearances(args.get(0, "appearances.dat")?).map_err(|e| format!("Failed to load appearances: {}", e))?;
+            print_json(&get_statistics(&appearances))
+        }
+        "dump" => {
+            let state = load_state(args.get(0, "appearances.dat")?)?;
+            let category = parse_category(args.get(1, "category")?)?;
+            let id = parse_id(args.get(2, "id")?)?;
+
+            let appearances_lock = state.appearances.read();
+            let appearances = appearances_lock.as_ref().ok_or_else(|| "No appearances loaded".to_string())?;
+            let items = get_items_by_category(appearances, &category);
+            let appearance = get_index_for_category(&state, &category).get(&id).and_then(|idx| items.get(*idx)).ok_or_else(|| format!("Appearance with ID {} not found in {:?}", id, category))?;
+            println!("{:#?}", appearance);
+            Ok(())
+        }
+        "export-json" => {
+            let state = load_state(args.get(0, "appearances.dat")?)?;
+            let category = parse_category(args.get(1, "category")?)?;
+            let id = parse_id(args.get(2, "id")?)?;
+            let out = args.get(3, "out.json")?;
+            snapshot_before_write(out);
+            write_appearance_json(&state, &category, id, out)?;
+            println!("{}", out);
+            Ok(())
+        }
+        "export-aec" => {
+            let state = load_state(args.get(0, "appearances.dat")?)?;
+            let category = parse_category(args.get(1, "category")?)?;
+            let id = parse_id(args.get(2, "id")?)?;
+            let out = args.get(3, "out.aec")?;
+            let assets = args.option("assets").ok_or_else(|| CliError::Usage("export-aec needs --assets <assets_dir> to embed sprites".to_string()))?;
+            load_sprites(&state, assets)?;
+            snapshot_before_write(out);
+            write_appearance_aec(&state, &category, id, out)?;
+            println!("{}", out);
+            Ok(())
+        }
+        "animate" => {
+            let state = load_state(args.get(0, "appearances.dat")?)?;
+            let category = parse_category(args.get(1, "category")?)?;
+            let id = parse_id(args.get(2, "id")?)?;
+            let out_dir = args.get(3, "out_dir")?;
+            let assets = args.option("assets").ok_or_else(|| CliError::Usage("animate needs --assets <assets_dir> to render sprites".to_string()))?;
+            let format = match args.option("format").unwrap_or("gif") {
+                "gif" => AnimationFormat::Gif,
+                "apng" | "png" => AnimationFormat::Apng,
+                other => return Err(CliError::Usage(format!("Unknown animation format: {}", other))),
+            };
+            let group = match args.option("group") {
+                Some(value) => value.parse::<usize>().map_err(|_| CliError::Usage(format!("Invalid frame group: {}", value)))?,
+                None => 0,
+            };
+            let pattern = |name: &str| match args.option(name) {
+                Some(value) => value.parse::<u32>().map_err(|_| CliError::Usage(format!("Invalid --{}: {}", name, value))),
+                None => Ok(0),
+            };
+            let pattern = AnimationPattern {
+                y: pattern("pattern-y")?,
+                z: pattern("pattern-z")?,
+            };
+            load_sprites(&state, assets)?;
+            for path in export_animation(&state, &category, id, group, pattern, format, out_dir)? {
+                println!("{}", path);
+            }
+            Ok(())
+        }
+        "metrics" => {
+            let dat = args.get(0, "appearances.dat")?;
+            let state = load_state(dat)?;
+            let assets = args.option("assets").ok_or_else(|| CliError::Usage("metrics needs --assets <assets_dir> to decode sprites".to_string()))?;
+            let category = args.rest(1).first().map(|value| parse_category(value)).transpose()?;
+            let id = args.rest(2).first().map(|value| parse_id(value)).transpose()?;
+            load_sprites(&state, assets)?;
+            let out = args.option("out");
+            let report = compute_appearance_metrics(&state, category, id, out.is_some())?;
+            if let Some(out) = out {
+                let appearances_lock = state.appearances.read();
+                let appearances = appearances_lock.as_ref().ok_or_else(|| "No appearances loaded".to_string())?;
+                write_appearances_file(appearances, &state.appearance_unknown_fields.read(), Path::new(out))?;
+            }
+            print_json(&report)
+        }
+        "export-legacy" => {
+            let state = load_state(args.get(0, "appearances.dat")?)?;
+            let dat_out = args.get(1, "Tibia.dat")?;
+            let spr_out = args.get(2, "Tibia.spr")?;
+            let assets = args.option("assets").ok_or_else(|| CliError::Usage("export-legacy needs --assets <assets_dir> to read sprites".to_string()))?;
+            let version = match args.option("version") {
+                Some(value) => value.parse::<u32>().map_err(|_| CliError::Usage(format!("Invalid client version: {}", value)))?,
+                None => 1098,
+            };
+            let client = LegacyClientVersion::known(version).ok_or_else(|| CliError::Usage(format!("Unknown client version: {}", version)))?;
+            let transparency = match args.option("pixels").unwrap_or("rgb") {
+                "rgb" => false,
+                "rgba" => true,
+                other => return Err(CliError::Usage(format!("Unknown pixel format: {}", other))),
+            };
+            load_sprites(&state, assets)?;
+            print_json(&export_legacy_files(&state, dat_out, spr_out, client, transparency)?)
+        }
+        "import" => {
+            let dat = args.get(0, "appearances.dat")?;
+            let files = args.rest(1);
+            if files.is_empty() {
+                return Err(CliError::Usage("import needs at least one .json/.aec file".to_string()));
+            }
+            let state = load_state(dat)?;
+            if let Some(assets) = args.option("assets") {
+                load_sprites(&state, assets)?;
+            }
+
+            let result = import_appearance_files(&state, files, None)?;
+
+            // AEC sprites land in the imported-sprite buffer; bake them into the
+            // catalog so the saved .dat never references ids the client lacks.
+            if !state.imported_sprites.is_empty() {
+                let assets = args.option("assets").ok_or_else(|| CliError::Usage("Imported files carry sprites; pass --assets <assets_dir> to compile them".to_string()))?;
+                let compiled = compile_imported_sprite_sheet(&state, assets, &catalog_path(assets), &JobHandle::detached("compile_imported_sprites"))?;
+                let sheets: Vec<&str> = compiled.sheets.iter().map(|sheet| sheet.sheet_file.as_str()).collect();
+                eprintln!("Compiled {} sprites into {}", compiled.sprites_compiled, sheets.join(", "));
+            }
+
+            let out = PathBuf::from(args.option("out").unwrap_or(dat));
+            let appearances_lock = state.appearances.read();
+            let appearances = appearances_lock.as_ref().ok_or_else(|| "No appearances loaded".to_string())?;
+            write_appearances_file(appearances, &state.appearance_unknown_fields.read(), &out)?;
+            print_json(&result)
+        }
+        "diff" => {
+            let diff = diff_appearances_paths(args.get(0, "old.dat")?, args.get(1, "new.dat")?)?;
+            let json_out = args.option("json");
+            let html_out = args.option("html");
+            if let Some(out) = json_out {
+                snapshot_before_write(out);
+                write_diff(&diff, out, DiffExportFormat::Json)?;
+            }
+            if let Some(out) = html_out {
+                snapshot_before_write(out);
+                write_diff(&diff, out, DiffExportFormat::Html)?;
+            }
+            if json_out.is_none() && html_out.is_none() {
+                print_json(&diff)?;
+            }
+            Ok(())
+        }
+        "query" => {
+            let appearances = load_appearances(args.get(0, "appearances.dat")?).map_err(|e| format!("Failed to load appearances: {}", e))?;
+            let category = parse_category(args.get(1, "category")?)?;
+            let ids = query_appearance_ids(get_items_by_category(&appearances, &category), args.get(2, "query")?)?;
+            print_json(&ids)
+        }
+        "validate" => {
+            let state = load_state(args.get(0, "appearances.dat")?)?;
+            if let Some(assets) = args.option("assets") {
+                load_sprites(&state, assets)?;
+            }
+            let report = validate_loaded_assets(&state)?;
+            print_json(&report)?;
+            // Non-zero exit so CI pipelines stop on a broken bundle
+            if report.is_valid() {
+                Ok(())
+            } else {
+                Err(CliError::Failed(format!("{} integrity issue(s) found", report.issues.len())))
+            }
+        }
+        other => Err(unknown_action("appearances", other)),
+    }
+}
+
+// ── Sprites ─────────────────────────────────────────────────────────────────
+
+fn run_sprites(action: &str, args: &Args) -> Result<(), CliError> {
+    match action {
+        "export-png" => {
+            let assets = args.get(0, "assets_dir")?;
+            let out_dir = PathBuf::from(args.get(1, "out_dir")?);
+            let ids = parse_id_ranges(args.rest(2))?;
+            if ids.is_empty() {
+                return Err(CliError::Usage("export-png needs at least one sprite id".to_string()));
+            }
+            std::fs::create_dir_all(&out_dir).map_err(|e| format!("Failed to create {}: {}", out_dir.display(), e))?;
+
+            let loader = SpriteLoader::new(Path::new(&catalog_path(assets)), Path::new(assets)).map_err(|e| format!("Failed to load sprite catalog: {}", e))?;
+            let mut written = 0usize;
+            for id in ids {
+                let png = loader.get_sprite(id).and_then(|sprite| sprite.to_png_bytes()).map_err(|e| format!("Failed to export sprite {}: {}", id, e))?;
+                let path = out_dir.join(format!("sprite_{}.png", id));
+                snapshot_before_write(&path);
+                std::fs::write(&path, png).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+                written += 1;
+            }
+            println!("{}", written);
+            Ok(())
+        }
+        "compile" => {
+            let assets = args.get(0, "assets_dir")?;
+            let images = args.rest(1);
+            if images.is_empty() {
+                return Err(CliError::Usage("compile needs at least one image".to_string()));
+            }
+            let (tw, th) = match args.option("tile") {
+                Some(tile) => {
+                    let (w, h) = tile.split_once('x').ok_or_else(|| CliError::Usage(format!("Invalid --tile: {}", tile)))?;
+                    (parse_id(w)?.max(1), parse_id(h)?.max(1))
+                }
+                None => (32, 32),
+            };
+            let chroma = match args.option("chroma") {
+                Some(hex) => Some(parse_rgb(hex)?),
+                None => None,
+            };
+
+            let state = AppState::new();
+            load_sprites(&state, assets)?;
+            for image in images {
+                import_image_tiles(&state, image, tw, th, chroma)?;
+            }
+            let result = compile_imported_sprite_sheet(&state, assets, &catalog_path(assets), &JobHandle::detached("compile_imported_sprites"))?;
+            print_json(&result)
+        }
+        other => Err(unknown_action("sprites", other)),
+    }
+}
+
+fn parse_rgb(hex: &str) -> Result<[u8; 3], CliError> {
+    let s = hex.trim_start_matches('#');
+    let channel = |range: std::ops::Range<usize>| s.get(range).and_then(|c| u8::from_str_radix(c, 16).ok());
+    match (s.len(), channel(0..2), channel(2..4), channel(4..6)) {
+        (6, Some(r), Some(g), Some(b)) => Ok([r, g, b]),
+        _ => Err(CliError::Usage(format!("Invalid colour: {}", hex))),
+    }
+}
+
+// ── Staticdata ──────────────────────────────────────────────────────────────
+
+fn run_staticdata(action: &str, args: &Args) -> Result<(), CliError> {
+    let path = args.get(0, "staticdata.dat")?;
+    let doc = load_staticdata_doc(path).map_err(|e| format!("Failed to parse staticdata: {}", e))?;
+    match action {
+        "stats" => print_json(&doc_statistics(&doc)),
+        "export-json" => {
+            let out = args.get(1, "out.json")?;
+            let json = match &doc {
+                StaticDataDoc::Old(o) => serde_json::to_string_pretty(o),
+                StaticDataDoc::New(n) => serde_json::to_string_pretty(n),
+            }
+            .map_err(|e| format!("Failed to serialize staticdata: {}", e))?;
+            snapshot_before_write(out);
+            std::fs::write(out, json).map_err(|e| format!("Failed to write {}: {}", out, e))?;
+            println!("{}", out);
+            Ok(())
+        }
+        "import-json" => {
+            // The JSON is decoded into the schema the target file was loaded
+            // with, so a legacy file stays legacy on save.
+            let input = args.get(1, "in.json")?;
+            let text = std::fs::read_to_string(input).map_err(|e| format!("Failed to read {}: {}", input, e))?;
+            let updated = match &doc {
+                StaticDataDoc::Old(_) => serde_json::from_str(&text).map(StaticDataDoc::Old),
+                StaticDataDoc::New(_) => serde_json::from_str(&text).map(StaticDataDoc::New),
+            }
+            .map_err(|e| format!("Invalid staticdata JSON: {}", e))?;
+            let out = args.option("out").unwrap_or(path);
+            save_staticdata_doc(out, &updated).map_err(|e| format!("Failed to save staticdata: {}", e))?;
+            print_json(&doc_statistics(&updated))
+        }
+        other => Err(unknown_action("staticdata", other)),
+    }
+}
+
+// ── Sounds ──────────────────────────────────────────────────────────────────
+
+fn run_sounds(action: &str, args: &Args) -> Result<(), CliError> {
+    let dir = args.get(0, "sounds_dir")?;
+    let mut parser = SoundsParser::new();
+    let stats = parser.load_from_directory(Path::new(dir)).map_err(|e| format!("Failed to load sounds: {}", e))?;
+    match action {
+        "stats" => print_json(&stats),
+        "export-json" => {
+            let out = args.get(1, "out.json")?;
+            let data = parser.get_sounds_data().ok_or_else(|| "No sounds loaded".to_string())?;
+            let json = serde_json::to_string_pretty(data).map_err(|e| format!("Failed to serialize sounds: {}", e))?;
+            snapshot_before_write(out);
+            std::fs::write(out, json).map_err(|e| format!("Failed to write {}: {}", out, e))?;
+            println!("{}", out);
+            Ok(())
+        }
+        other => Err(unknown_action("sounds", other)),
+    }
+}
+
+// ── QM ──────────────────────────────────────────────────────────────────────
+
+fn run_qm(action: &str, args: &Args) -> Result<(), CliError> {
+    let path = args.get(0, "file.qm")?;
+    let data = std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let mut qm = qm_parser::parse_qm(&data).map_err(|e| format!("Failed to parse QM: {}", e))?;
+    match action {
+        "export-csv" => {
+            let out = args.get(1, "out.csv")?;
+            snapshot_before_write(out);
+            let count = write_entries_csv(&qm, out)?;
+            println!("{}", count);
+            Ok(())
+        }
+        "import-csv" => {
+            let csv = args.get(1, "in.csv")?;
+            let updated = apply_translations(&mut qm, read_csv_translations(csv)?);
+            let out = args.option("out").unwrap_or(path);
+            snapshot_before_write(out);
+            std::fs::write(out, qm_writer::write_qm(&qm)).map_err(|e| format!("Failed to write {}: {}", out, e))?;
+            println!("{}", updated);
+            Ok(())
+        }
+        other => Err(unknown_action("qm", other)),
+    }
+}
+
+// ── OTB ─────────────────────────────────────────────────────────────────────
+
+fn run_otb(action: &str, args: &Args) -> Result<(), CliError> {
+    let path = args.get(0, "items.otb")?;
+    let state = load_state(args.get(1, "appearances.dat")?)?;
+    load_items_otb_file(&state, path)?;
+    match action {
+        "mapping" => print_json(&loaded_items_otb_mapping(&state)?),
+        "sync" => {
+            let refresh = match args.option("refresh").unwrap_or("false") {
+                "true" => true,
+                "false" => false,
+                other => return Err(CliError::Usage(format!("Invalid --refresh value: {}", other))),
+            };
+            let result = sync_loaded_items_otb(&state, refresh)?;
+            save_items_otb_file(&state, args.option("out").unwrap_or(path))?;
+            print_json(&result)
+        }
+        other => Err(unknown_action("otb", other)),
+    }
+}
+
+// ── RCC ─────────────────────────────────────────────────────────────────────
+
+fn run_rcc(action: &str, args: &Args) -> Result<(), CliError> {
+    let path = args.get(0, "file.rcc")?;
+    let data = std::fs::read(path).map_err(|e| format!("Failed to read RCC file: {}", e))?;
+    let mut rcc = RccFile::parse(&data)?;
+    match action {
+        "list" => print_json(&rcc.files),
+        "extract" => {
+            let count = extract_entries(&rcc, Path::new(args.get(1, "out_dir")?))?;
+            println!("{}", count);
+            Ok(())
+        }
+        "replace" => {
+            let resource = args.get(1, "resource_path")?.trim_start_matches(":/").trim_start_matches('/');
+            let new_file = args.get(2, "new_file")?;
+            let new_data = std::fs::read(new_file).map_err(|e| format!("Failed to read {}: {}", new_file, e))?;
+
+            let entry = rcc.entries.iter_mut().find(|e| !e.is_directory && e.path.trim_start_matches('/') == resource).ok_or_else(|| format!("Resource not found: {}", resource))?;
+            entry.data = new_data;
+            entry.compressed = false;
+
+            let out = args.option("out").unwrap_or(path);
+            let output = rcc_writer::write_rcc(&rcc.entries)?;
+            snapshot_before_write(out);
+            std::fs::write(out, &output).map_err(|e| format!("Failed to write RCC: {}", e))?;
+            println!("{}", out);
+            Ok(())
+        }
+        other => Err(unknown_action("rcc", other)),
+    }
+}
@@ -0,0 +1,356 @@
+// Versioned file backups
+// Every save path calls `snapshot_before_write` so the previous contents of a
+// file survive the save. Snapshots live under `<root>/<path hash>/` as
+// `<unix ms>.bak`, next to a `source.txt` naming the original file, and only
+// the newest `keep` snapshots per file are kept.
+
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::disk_cache::content_hash;
+use super::fs_util::write_atomic;
+
+/// Snapshots kept per file.
+pub const DEFAULT_BACKUPS_PER_FILE: usize = 10;
+
+const SOURCE_FILE: &str = "source.txt";
+const BACKUP_EXTENSION: &str = "bak";
+/// Line diffs fall back to one hunk when the changed region is larger than this (lines x lines).
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+static MANAGER: OnceLock<BackupManager> = OnceLock::new();
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupEntry {
+    /// `<path hash>/<unix ms>`, stable handle for diff/restore.
+    pub id: String,
+    pub original_path: String,
+    pub created_at_ms: u64,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffHunk {
+    /// 1-based first line of the hunk in the backup and in the current file.
+    pub old_start: usize,
+    pub new_start: usize,
+    pub removed: Vec<String>,
+    pub added: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupDiff {
+    pub backup: BackupEntry,
+    /// None when the original file no longer exists.
+    pub current_size: Option<u64>,
+    pub identical: bool,
+    /// Both sides are UTF-8, so `hunks` holds a line diff.
+    pub text: bool,
+    pub hunks: Vec<DiffHunk>,
+    /// Byte offset of the first difference, for binary files.
+    pub first_difference: Option<u64>,
+}
+
+pub struct BackupManager {
+    root: PathBuf,
+    keep: usize,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+impl BackupManager {
+    pub fn open(root: impl Into<PathBuf>, keep: usize) -> Result<Self> {
+        let root = root.into();
+        fs::create_dir_all(&root).with_context(|| format!("Failed to create backup directory {:?}", root))?;
+        Ok(Self {
+            root,
+            keep: keep.max(1),
+        })
+    }
+
+    fn file_key(path: &Path) -> (String, PathBuf) {
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        (format!("{:016x}", content_hash(path.to_string_lossy().as_bytes())), path)
+    }
+
+    /// Snapshots in `key`'s folder, oldest first.
+    fn stamps(&self, key: &str) -> Vec<u64> {
+        let Ok(entries) = fs::read_dir(self.root.join(key)) else {
+            return Vec::new();
+        };
+        let mut stamps: Vec<u64> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let path = entry.path();
+                (path.extension().and_then(|e| e.to_str()) == Some(BACKUP_EXTENSION)).then(|| path.file_stem()?.to_str()?.parse().ok()).flatten()
+            })
+            .collect();
+        stamps.sort_unstable();
+        stamps
+    }
+
+    fn entry(&self, key: &str, stamp: u64) -> Result<BackupEntry> {
+        let dir = self.root.join(key);
+        let original_path = fs::read_to_string(dir.join(SOURCE_FILE)).with_context(|| format!("Backup {} has no source record", key))?;
+        let size = fs::metadata(dir.join(format!("{}.{}", stamp, BACKUP_EXTENSION))).with_context(|| format!("Backup {}/{} not found", key, stamp))?.len();
+        Ok(BackupEntry {
+            id: format!("{}/{}", key, stamp),
+            original_path,
+            created_at_ms: stamp,
+            size,
+        })
+    }
+
+    /// Copies the current contents of `path` into a new snapshot. Returns None when
+    /// the file does not exist yet, and the newest snapshot when it already matches.
+    pub fn snapshot(&self, path: &Path) -> Result<Option<BackupEntry>> {
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let (key, canonical) = Self::file_key(path);
+        let dir = self.root.join(&key);
+        fs::create_dir_all(&dir).with_context(|| format!("Failed to create backup directory {:?}", dir))?;
+        fs::write(dir.join(SOURCE_FILE), canonical.to_string_lossy().as_bytes()).context("Failed to record backup source")?;
+
+        let stamps = self.stamps(&key);
+        let contents = fs::read(path).with_context(|| format!("Failed to read {:?} for backup", path))?;
+        if let Some(latest) = stamps.last() {
+            let latest_path = dir.join(format!("{}.{}", latest, BACKUP_EXTENSION));
+            if fs::metadata(&latest_path).map(|m| m.len() == contents.len() as u64).unwrap_or(false) && fs::read(&latest_path).map(|b| b == contents).unwrap_or(false) {
+                return self.entry(&key, *latest).map(Some);
+            }
+        }
+
+        // Two saves in the same millisecond still get distinct snapshots.
+        let stamp = now_ms().max(stamps.last().map_or(0, |s| s + 1));
+        write_atomic(&dir.join(format!("{}.{}", stamp, BACKUP_EXTENSION)), &contents)?;
+        for old in stamps.iter().take((stamps.len() + 1).saturating_sub(self.keep)) {
+            let _ = fs::remove_file(dir.join(format!("{}.{}", old, BACKUP_EXTENSION)));
+        }
+        self.entry(&key, stamp).map(Some)
+    }
+
+    /// Snapshots of `path` (or of every file when None), newest first.
+    pub fn list(&self, path: Option<&Path>) -> Result<Vec<BackupEntry>> {
+        let keys: Vec<String> = match path {
+            Some(path) => vec![Self::file_key(path).0],
+            None => fs::read_dir(&self.root)
+                .with_context(|| format!("Failed to read backup directory {:?}", self.root))?
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.path().is_dir())
+                .filter_map(|entry| entry.file_name().to_str().map(str::to_string))
+                .collect(),
+        };
+        let mut entries = Vec::new();
+        for key in keys {
+            for stamp in self.stamps(&key) {
+                match self.entry(&key, stamp) {
+                    Ok(entry) => entries.push(entry),
+                    Err(e) => log::warn!("Skipping backup {}/{}: {}", key, stamp, e),
+                }
+            }
+        }
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.created_at_ms));
+        Ok(entries)
+    }
+
+    /// Resolves a backup id, rejecting anything that is not `<hex>/<digits>`.
+    fn resolve(&self, id: &str) -> Result<(BackupEntry, PathBuf)> {
+        let Some((key, stamp)) = id.split_once('/') else {
+            bail!("Invalid backup id {}", id);
+        };
+        if key.is_empty() || !key.chars().all(|c| c.is_ascii_hexdigit()) {
+            bail!("Invalid backup id {}", id);
+        }
+        let stamp: u64 = stamp.parse().with_context(|| format!("Invalid backup id {}", id))?;
+        let entry = self.entry(key, stamp)?;
+        Ok((entry, self.root.join(key).join(format!("{}.{}", stamp, BACKUP_EXTENSION))))
+    }
+
+    pub fn read(&self, id: &str) -> Result<(BackupEntry, Vec<u8>)> {
+        let (entry, path) = self.resolve(id)?;
+        let contents = fs::read(&path).with_context(|| format!("Failed to read backup {}", id))?;
+        Ok((entry, contents))
+    }
+
+    /// Compares a snapshot with the current contents of its original file.
+    pub fn diff(&self, id: &str) -> Result<BackupDiff> {
+        let (backup, old) = self.read(id)?;
+        let current = fs::read(&backup.original_path).ok();
+        let new = current.as_deref().unwrap_or_default();
+        let identical = current.is_some() && old == new;
+        let (text, hunks, first_difference) = match (std::str::from_utf8(&old), std::str::from_utf8(new)) {
+            (Ok(old_text), Ok(new_text)) if !identical => (true, line_diff(old_text, new_text), None),
+            _ if identical => (true, Vec::new(), None),
+            _ => (false, Vec::new(), Some(old.iter().zip(new).position(|(a, b)| a != b).unwrap_or(old.len().min(new.len())) as u64)),
+        };
+        Ok(BackupDiff {
+            backup,
+            current_size: current.map(|c| c.len() as u64),
+            identical,
+            text,
+            hunks,
+            first_difference,
+        })
+    }
+
+    /// Writes a snapshot back over its original file. The current contents are
+    /// snapshotted first, so a restore can itself be undone.
+    pub fn restore(&self, id: &str) -> Result<BackupEntry> {
+        let (entry, contents) = self.read(id)?;
+        let original = PathBuf::from(&entry.original_path);
+        self.snapshot(&original)?;
+        write_atomic(&original, &contents)?;
+        Ok(entry)
+    }
+}
+
+/// Installs the process-wide manager used by `snapshot_before_write`.
+pub fn init(manager: BackupManager) {
+    if MANAGER.set(manager).is_err() {
+        log::warn!("Backup manager already initialized");
+    }
+}
+
+/// The process-wide manager; None outside the app (CLI, tests).
+pub fn manager() -> Option<&'static BackupManager> {
+    MANAGER.get()
+}
+
+/// Snapshots `path` before a save overwrites it. A failed backup is logged and
+/// does not block the save; without a manager this does nothing.
+pub fn snapshot_before_write(path: impl AsRef<Path>) {
+    let Some(manager) = manager() else {
+        return;
+    };
+    let path = path.as_ref();
+    if let Err(e) = manager.snapshot(path) {
+        log::warn!("Failed to back up {:?} before writing: {}", path, e);
+    }
+}
+
+/// Line diff of `old` against `new`: common prefix/suffix trimmed, LCS on the rest.
+pub fn line_diff(old: &str, new: &str) -> Vec<DiffHunk> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..].iter().rev().zip(new[prefix..].iter().rev()).take_while(|(a, b)| a == b).count();
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+    if old_mid.is_empty() && new_mid.is_empty() {
+        return Vec::new();
+    }
+
+    let to_strings = |lines: &[&str]| lines.iter().map(|l| l.to_string()).collect::<Vec<_>>();
+    if old_mid.len().saturating_mul(new_mid.len()) > MAX_DIFF_CELLS {
+        return vec![DiffHunk {
+            old_start: prefix + 1,
+            new_start: prefix + 1,
+            removed: to_strings(old_mid),
+            added: to_strings(new_mid),
+        }];
+    }
+
+    // lcs[i][j] = LCS length of old_mid[i..] and new_mid[j..]
+    let (n, m) = (old_mid.len(), new_mid.len());
+    let mut lcs = vec![0u32; (n + 1) * (m + 1)];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i * (m + 1) + j] = if old_mid[i] == new_mid[j] {
+                lcs[(i + 1) * (m + 1) + j + 1] + 1
+            } else {
+                lcs[(i + 1) * (m + 1) + j].max(lcs[i * (m + 1) + j + 1])
+            };
+        }
+    }
+
+    let mut hunks = Vec::new();
+    let mut current: Option<DiffHunk> = None;
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && old_mid[i] == new_mid[j] {
+            hunks.extend(current.take());
+            i += 1;
+            j += 1;
+            continue;
+        }
+        let hunk = current.get_or_insert_with(|| DiffHunk {
+            old_start: prefix + i + 1,
+            new_start: prefix + j + 1,
+            removed: Vec::new(),
+            added: Vec::new(),
+        });
+        if j == m || (i < n && lcs[(i + 1) * (m + 1) + j] >= lcs[i * (m + 1) + j + 1]) {
+            hunk.removed.push(old_mid[i].to_string());
+            i += 1;
+        } else {
+            hunk.added.push(new_mid[j].to_string());
+            j += 1;
+        }
+    }
+    hunks.extend(current);
+    hunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshots_are_pruned_and_restorable() {
+        let dir = std::env::temp_dir().join(format!("backups-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let manager = BackupManager::open(dir.join("backups"), 2).unwrap();
+        let file = dir.join("monster.lua");
+
+        assert!(manager.snapshot(&file).unwrap().is_none());
+        for version in ["one", "two", "two", "three"] {
+            fs::write(&file, version).unwrap();
+            manager.snapshot(&file).unwrap();
+        }
+        let backups = manager.list(Some(&file)).unwrap();
+        // "two" was unchanged between saves, and only the newest two are kept.
+        assert_eq!(backups.len(), 2);
+        assert_eq!(manager.read(&backups[1].id).unwrap().1, b"two");
+
+        fs::write(&file, "four").unwrap();
+        manager.restore(&backups[1].id).unwrap();
+        assert_eq!(fs::read(&file).unwrap(), b"two");
+        assert_eq!(manager.read(&manager.list(Some(&file)).unwrap()[0].id).unwrap().1, b"four");
+        assert!(manager.read("../../etc/1").is_err());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn line_diff_reports_changed_hunks() {
+        let hunks = line_diff("a\nb\nc\nd\ne\n", "a\nB\nc\nd\ne\nf\n");
+        assert_eq!(
+            hunks,
+            vec![
+                DiffHunk {
+                    old_start: 2,
+                    new_start: 2,
+                    removed: vec!["b".to_string()],
+                    added: vec!["B".to_string()],
+                },
+                DiffHunk {
+                    old_start: 6,
+                    new_start: 6,
+                    removed: Vec::new(),
+                    added: vec!["f".to_string()],
+                },
+            ]
+        );
+        assert!(line_diff("same\n", "same\n").is_empty());
+    }
+}
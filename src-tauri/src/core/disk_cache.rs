@@ -0,0 +1,251 @@
+// Persistent on-disk cache for encoded sprite PNGs
+// Entries are content-addressed by (source file hash, sprite id): when a sprite
+// sheet changes on disk its hash changes too, so stale entries are simply never
+// hit again and age out through the size-bounded LRU eviction.
+
+use dashmap::DashMap;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use super::fs_util::write_atomic;
+
+/// Default budget for the sprite disk cache (512 MB).
+pub const DEFAULT_DISK_CACHE_BYTES: u64 = 512 * 1024 * 1024;
+
+const ENTRY_EXTENSION: &str = "png";
+
+/// FNV-1a 64-bit: stable across runs and platforms, unlike `ahash`/`DefaultHasher`.
+pub fn content_hash(data: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in data {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+#[derive(Default)]
+struct Entries {
+    /// file name -> (size in bytes, last access tick)
+    files: HashMap<String, (u64, u64)>,
+    total_bytes: u64,
+    tick: u64,
+}
+
+/// Size-bounded, LRU-evicted directory of cached blobs.
+pub struct DiskCache {
+    dir: PathBuf,
+    max_bytes: u64,
+    entries: Mutex<Entries>,
+    /// source path -> ((len, mtime), content hash), so each sheet is hashed once per change
+    source_hashes: DashMap<PathBuf, ((u64, u64), u64), ahash::RandomState>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiskCacheStats {
+    pub entries: usize,
+    pub total_bytes: u64,
+    pub max_bytes: u64,
+    pub dir: String,
+}
+
+fn modified_secs(metadata: &fs::Metadata) -> u64 {
+    metadata.modified().ok().and_then(|t| t.duration_since(UNIX_EPOCH).ok()).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+impl DiskCache {
+    /// Opens (or creates) the cache in `dir`. Existing entries are indexed with
+    /// their modification time as the initial LRU order.
+    pub fn open(dir: impl Into<PathBuf>, max_bytes: u64) -> anyhow::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+
+        let mut found: Vec<(String, u64, u64)> = Vec::new();
+        for entry in fs::read_dir(&dir)?.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some(ENTRY_EXTENSION) {
+                continue;
+            }
+            if let (Some(name), Ok(metadata)) = (path.file_name().and_then(|n| n.to_str()), entry.metadata()) {
+                found.push((name.to_string(), metadata.len(), modified_secs(&metadata)));
+            }
+        }
+        found.sort_by_key(|(_, _, modified)| *modified);
+
+        let mut entries = Entries::default();
+        for (name, size, _) in found {
+            entries.tick += 1;
+            entries.total_bytes += size;
+            entries.files.insert(name, (size, entries.tick));
+        }
+
+        let cache = Self {
+            dir,
+            max_bytes,
+            entries: Mutex::new(entries),
+            source_hashes: DashMap::with_hasher(ahash::RandomState::new()),
+        };
+        cache.evict_to(cache.max_bytes);
+        Ok(cache)
+    }
+
+    #[inline]
+    fn entry_name(source_hash: u64, sprite_id: u32) -> String {
+        format!("{:016x}_{}.{}", source_hash, sprite_id, ENTRY_EXTENSION)
+    }
+
+    /// Content hash of `path`, recomputed only when its size or mtime changes.
+    pub fn source_hash(&self, path: &Path) -> Option<u64> {
+        let metadata = fs::metadata(path).ok()?;
+        let fingerprint = (metadata.len(), modified_secs(&metadata));
+        if let Some(known) = self.source_hashes.get(path) {
+            if known.0 == fingerprint {
+                return Some(known.1);
+            }
+        }
+        let hash = content_hash(&fs::read(path).ok()?);
+        self.source_hashes.insert(path.to_path_buf(), (fingerprint, hash));
+        Some(hash)
+    }
+
+    pub fn get(&self, source_hash: u64, sprite_id: u32) -> Option<Vec<u8>> {
+        let name = Self::entry_name(source_hash, sprite_id);
+        {
+            let mut entries = self.entries.lock();
+            entries.tick += 1;
+            let tick = entries.tick;
+            entries.files.get_mut(&name)?.1 = tick;
+        }
+        match fs::read(self.dir.join(&name)) {
+            Ok(bytes) => Some(bytes),
+            Err(_) => {
+                // Deleted behind our back; forget it.
+                let mut entries = self.entries.lock();
+                if let Some((size, _)) = entries.files.remove(&name) {
+                    entries.total_bytes -= size;
+                }
+                None
+            }
+        }
+    }
+
+    pub fn put(&self, source_hash: u64, sprite_id: u32, bytes: &[u8]) {
+        let name = Self::entry_name(source_hash, sprite_id);
+        if let Err(e) = write_atomic(&self.dir.join(&name), bytes) {
+            log::warn!("Failed to write sprite disk cache entry {}: {}", name, e);
+            return;
+        }
+        let over_budget = {
+            let mut entries = self.entries.lock();
+            entries.tick += 1;
+            let tick = entries.tick;
+            if let Some((old_size, _)) = entries.files.insert(name, (bytes.len() as u64, tick)) {
+                entries.total_bytes -= old_size;
+            }
+            entries.total_bytes += bytes.len() as u64;
+            entries.total_bytes > self.max_bytes
+        };
+        if over_budget {
+            // Evict down to 90% so we don't evict again on the very next insert.
+            self.evict_to(self.max_bytes / 10 * 9);
+        }
+    }
+
+    /// Removes least recently used entries until the cache fits in `target` bytes.
+    fn evict_to(&self, target: u64) {
+        let victims: Vec<String> = {
+            let mut entries = self.entries.lock();
+            if entries.total_bytes <= target {
+                return;
+            }
+            let mut by_age: Vec<(String, u64, u64)> = entries.files.iter().map(|(name, (size, tick))| (name.clone(), *size, *tick)).collect();
+            by_age.sort_unstable_by_key(|(_, _, tick)| *tick);
+
+            let mut victims = Vec::new();
+            for (name, size, _) in by_age {
+                if entries.total_bytes <= target {
+                    break;
+                }
+                entries.files.remove(&name);
+                entries.total_bytes -= size;
+                victims.push(name);
+            }
+            victims
+        };
+        for name in &victims {
+            let _ = fs::remove_file(self.dir.join(name));
+        }
+        log::debug!("Sprite disk cache evicted {} entries", victims.len());
+    }
+
+    /// Deletes every entry; returns how many were removed.
+    pub fn clear(&self) -> usize {
+        let names: Vec<String> = {
+            let mut entries = self.entries.lock();
+            entries.total_bytes = 0;
+            entries.files.drain().map(|(name, _)| name).collect()
+        };
+        for name in &names {
+            let _ = fs::remove_file(self.dir.join(name));
+        }
+        names.len()
+    }
+
+    pub fn stats(&self) -> DiskCacheStats {
+        let entries = self.entries.lock();
+        DiskCacheStats {
+            entries: entries.files.len(),
+            total_bytes: entries.total_bytes,
+            max_bytes: self.max_bytes,
+            dir: self.dir.to_string_lossy().to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_cache_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("canary_disk_cache_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn entries_survive_reopen() {
+        let dir = temp_cache_dir("reopen");
+        let cache = DiskCache::open(&dir, 1024).unwrap();
+        cache.put(0xABCD, 7, b"png-bytes");
+        assert_eq!(cache.get(0xABCD, 7).as_deref(), Some(&b"png-bytes"[..]));
+        assert_eq!(cache.get(0xABCE, 7), None);
+        drop(cache);
+
+        let reopened = DiskCache::open(&dir, 1024).unwrap();
+        assert_eq!(reopened.stats().entries, 1);
+        assert_eq!(reopened.get(0xABCD, 7).as_deref(), Some(&b"png-bytes"[..]));
+        assert_eq!(reopened.clear(), 1);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let dir = temp_cache_dir("evict");
+        let cache = DiskCache::open(&dir, 100).unwrap();
+        cache.put(1, 1, &[0u8; 40]);
+        cache.put(1, 2, &[0u8; 40]);
+        // Touch sprite 1 so sprite 2 becomes the oldest.
+        assert!(cache.get(1, 1).is_some());
+        cache.put(1, 3, &[0u8; 40]);
+
+        assert!(cache.get(1, 1).is_some());
+        assert!(cache.get(1, 2).is_none());
+        assert!(cache.get(1, 3).is_some());
+        assert!(cache.stats().total_bytes <= 100);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,263 @@
+// Long-running job tracking
+// Every slow backend operation runs under a `JobHandle`: it reports progress
+// (forwarded to the frontend as events by the jobs feature) and carries a
+// cancellation flag that the operation polls inside its loops.
+
+use dashmap::DashMap;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Error message returned by operations that stopped because their job was cancelled.
+pub const JOB_CANCELLED: &str = "Operation cancelled";
+
+/// Finished jobs kept around for `list_jobs`.
+const MAX_FINISHED_JOBS: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum JobStatus {
+    Running,
+    Completed,
+    Cancelled,
+    Failed,
+}
+
+/// Snapshot of a job, as listed and emitted to the frontend.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobInfo {
+    pub id: u64,
+    pub kind: String,
+    pub status: JobStatus,
+    pub done: u64,
+    pub total: u64,
+    pub message: String,
+    pub error: Option<String>,
+    pub started_at_ms: u64,
+}
+
+type JobSink = Box<dyn Fn(JobInfo) + Send + Sync>;
+
+pub struct JobHandle {
+    id: u64,
+    kind: String,
+    started_at_ms: u64,
+    cancelled: AtomicBool,
+    done: AtomicU64,
+    total: AtomicU64,
+    /// Last whole percentage emitted, to throttle progress events.
+    last_percent: AtomicU64,
+    message: Mutex<String>,
+    outcome: Mutex<(JobStatus, Option<String>)>,
+    sink: Option<JobSink>,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+impl JobHandle {
+    fn new(id: u64, kind: &str, sink: Option<JobSink>) -> Self {
+        Self {
+            id,
+            kind: kind.to_string(),
+            started_at_ms: now_ms(),
+            cancelled: AtomicBool::new(false),
+            done: AtomicU64::new(0),
+            total: AtomicU64::new(0),
+            last_percent: AtomicU64::new(0),
+            message: Mutex::new(String::new()),
+            outcome: Mutex::new((JobStatus::Running, None)),
+            sink,
+        }
+    }
+
+    /// A job that is not registered anywhere (headless CLI, tests).
+    pub fn detached(kind: &str) -> Self {
+        Self::new(0, kind, None)
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// `Err(JOB_CANCELLED)` once the job was cancelled; use with `?` between steps.
+    #[inline]
+    pub fn check_cancelled(&self) -> Result<(), String> {
+        if self.is_cancelled() {
+            Err(JOB_CANCELLED.to_string())
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn set_total(&self, total: usize) {
+        self.total.store(total as u64, Ordering::Relaxed);
+        self.done.store(0, Ordering::Relaxed);
+        self.last_percent.store(0, Ordering::Relaxed);
+        self.emit();
+    }
+
+    /// Marks `steps` more units done. Safe to call from rayon workers; an event
+    /// is emitted only when the whole percentage changes.
+    pub fn advance(&self, steps: usize) {
+        let done = self.done.fetch_add(steps as u64, Ordering::Relaxed) + steps as u64;
+        let total = self.total.load(Ordering::Relaxed);
+        if total == 0 {
+            return;
+        }
+        let percent = (done.min(total) * 100) / total;
+        if self.last_percent.fetch_max(percent, Ordering::Relaxed) < percent {
+            self.emit();
+        }
+    }
+
+    pub fn set_message(&self, message: impl Into<String>) {
+        *self.message.lock() = message.into();
+        self.emit();
+    }
+
+    pub fn info(&self) -> JobInfo {
+        let (status, error) = self.outcome.lock().clone();
+        JobInfo {
+            id: self.id,
+            kind: self.kind.clone(),
+            status,
+            done: self.done.load(Ordering::Relaxed),
+            total: self.total.load(Ordering::Relaxed),
+            message: self.message.lock().clone(),
+            error,
+            started_at_ms: self.started_at_ms,
+        }
+    }
+
+    fn status(&self) -> JobStatus {
+        self.outcome.lock().0
+    }
+
+    fn emit(&self) {
+        if let Some(sink) = &self.sink {
+            sink(self.info());
+        }
+    }
+}
+
+/// All jobs of the session, running and recently finished.
+pub struct JobRegistry {
+    jobs: DashMap<u64, Arc<JobHandle>, ahash::RandomState>,
+    next_id: AtomicU64,
+}
+
+impl Default for JobRegistry {
+    fn default() -> Self {
+        Self {
+            jobs: DashMap::with_hasher(ahash::RandomState::new()),
+            next_id: AtomicU64::new(1),
+        }
+    }
+}
+
+impl JobRegistry {
+    /// Registers a running job; `sink` receives every progress snapshot.
+    pub fn start(&self, kind: &str, sink: Option<JobSink>) -> Arc<JobHandle> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let job = Arc::new(JobHandle::new(id, kind, sink));
+        self.jobs.insert(id, job.clone());
+        job.emit();
+        job
+    }
+
+    /// Records how the job ended. An error after cancellation counts as `Cancelled`.
+    pub fn finish(&self, job: &JobHandle, error: Option<&str>) {
+        let status = match error {
+            None => JobStatus::Completed,
+            Some(_) if job.is_cancelled() => JobStatus::Cancelled,
+            Some(_) => JobStatus::Failed,
+        };
+        *job.outcome.lock() = (status, error.map(str::to_string));
+        job.emit();
+        self.prune();
+    }
+
+    /// Requests cancellation; false when the job is unknown or already finished.
+    pub fn cancel(&self, id: u64) -> bool {
+        match self.jobs.get(&id) {
+            Some(job) if job.status() == JobStatus::Running => {
+                job.cancel();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn list(&self) -> Vec<JobInfo> {
+        let mut jobs: Vec<JobInfo> = self.jobs.iter().map(|entry| entry.value().info()).collect();
+        jobs.sort_by_key(|job| job.id);
+        jobs
+    }
+
+    fn prune(&self) {
+        let mut finished: Vec<u64> = self.jobs.iter().filter(|entry| entry.value().status() != JobStatus::Running).map(|entry| *entry.key()).collect();
+        if finished.len() <= MAX_FINISHED_JOBS {
+            return;
+        }
+        finished.sort_unstable();
+        for id in &finished[..finished.len() - MAX_FINISHED_JOBS] {
+            self.jobs.remove(id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_events_are_throttled_to_whole_percents() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink_events = events.clone();
+        let registry = JobRegistry::default();
+        let job = registry.start("test", Some(Box::new(move |info| sink_events.lock().push(info))));
+
+        job.set_total(1000);
+        for _ in 0..1000 {
+            job.advance(1);
+        }
+        registry.finish(&job, None);
+
+        let events = events.lock();
+        // start + set_total + 100 percent steps + finish
+        assert_eq!(events.len(), 103);
+        assert_eq!(events.last().unwrap().status, JobStatus::Completed);
+        assert_eq!(events.last().unwrap().done, 1000);
+    }
+
+    #[test]
+    fn cancelled_jobs_stop_and_report_cancelled() {
+        let registry = JobRegistry::default();
+        let job = registry.start("test", None);
+        assert!(job.check_cancelled().is_ok());
+        assert!(registry.cancel(job.id()));
+        assert_eq!(job.check_cancelled(), Err(JOB_CANCELLED.to_string()));
+
+        registry.finish(&job, Some(JOB_CANCELLED));
+        assert!(!registry.cancel(job.id()));
+        assert_eq!(registry.list()[0].status, JobStatus::Cancelled);
+
+        let failed = registry.start("test", None);
+        registry.finish(&failed, Some("disk full"));
+        assert_eq!(registry.list()[1].status, JobStatus::Failed);
+    }
+}
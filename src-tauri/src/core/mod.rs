@@ -1,9 +1,12 @@
 // Core module - shared utilities
 // Feature-specific code moved to src/features/
 
+pub mod backups;
 pub mod cache;
+pub mod disk_cache;
 pub mod errors;
 pub mod fs_util;
+pub mod jobs;
 pub mod lua;
 pub mod lzma;
 pub mod protobuf; // Generated by build.rs from .proto files
@@ -11,4 +14,6 @@ pub mod validation;
 
 // Re-export commonly used types
 pub use cache::{CacheStats, LRUCache};
+pub use disk_cache::DiskCache;
 pub use errors::{AppError, AppResult};
+pub use jobs::{JobHandle, JobRegistry, JOB_CANCELLED};
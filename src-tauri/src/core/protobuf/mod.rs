@@ -49,5 +49,7 @@ pub mod staticmapdata {
     include!(concat!(env!("OUT_DIR"), "/tibia.protobuf.staticmapdata.rs"));
 }
 
+pub mod unknown_fields;
+
 pub use appearances::*;
 pub use shared::*;
@@ -0,0 +1,434 @@
+// Unknown protobuf field preservation
+// prost drops fields it has no schema for, so a `.dat` from a newer client
+// would lose them on save. We walk the raw wire bytes with the field tables
+// emitted by build.rs, keep every unknown field (per message, nested), and
+// append them back to the matching messages after prost encodes.
+//
+// Repeated messages are matched by their `id` field when the schema has one
+// (appearances, frame groups), otherwise by position, so adding or removing
+// appearances does not shift unknown data onto the wrong entry.
+
+use anyhow::{bail, Context, Result};
+use prost::Message;
+use prost_types::field_descriptor_proto::{Label, Type};
+use prost_types::{DescriptorProto, FileDescriptorSet};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::OnceLock;
+
+static DESCRIPTOR_SET: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/tibia_descriptor.bin"));
+
+/// Fully qualified name of the appearances.dat root message.
+pub const APPEARANCES_MESSAGE: &str = ".tibia.protobuf.appearances.Appearances";
+/// Fully qualified name of a single appearance.
+pub const APPEARANCE_MESSAGE: &str = ".tibia.protobuf.appearances.Appearance";
+
+const WIRE_VARINT: u8 = 0;
+const WIRE_FIXED64: u8 = 1;
+const WIRE_LEN: u8 = 2;
+const WIRE_FIXED32: u8 = 5;
+
+#[derive(Debug)]
+struct FieldSchema {
+    name: String,
+    /// Fully qualified type name for message fields.
+    message_type: Option<String>,
+    repeated: bool,
+}
+
+#[derive(Debug, Default)]
+struct MessageSchema {
+    fields: HashMap<u32, FieldSchema>,
+    /// Number of a varint field called `id`, used to match repeated entries.
+    id_field: Option<u32>,
+}
+
+fn collect_messages(prefix: &str, messages: &[DescriptorProto], out: &mut HashMap<String, MessageSchema>) {
+    for message in messages {
+        let full_name = format!("{}.{}", prefix, message.name());
+        let mut schema = MessageSchema::default();
+        for field in &message.field {
+            let message_type = (field.r#type() == Type::Message).then(|| field.type_name().to_string());
+            if field.name() == "id" && matches!(field.r#type(), Type::Uint32 | Type::Uint64 | Type::Int32 | Type::Int64) {
+                schema.id_field = Some(field.number() as u32);
+            }
+            schema.fields.insert(
+                field.number() as u32,
+                FieldSchema {
+                    name: field.name().to_string(),
+                    message_type,
+                    repeated: field.label() == Label::Repeated,
+                },
+            );
+        }
+        collect_messages(&full_name, &message.nested_type, out);
+        out.insert(full_name, schema);
+    }
+}
+
+fn schemas() -> &'static HashMap<String, MessageSchema> {
+    static SCHEMAS: OnceLock<HashMap<String, MessageSchema>> = OnceLock::new();
+    SCHEMAS.get_or_init(|| {
+        let mut out = HashMap::new();
+        match FileDescriptorSet::decode(DESCRIPTOR_SET) {
+            Ok(set) => {
+                for file in &set.file {
+                    collect_messages(&format!(".{}", file.package()), &file.message_type, &mut out);
+                }
+            }
+            Err(e) => log::error!("Failed to decode protobuf descriptor set: {}", e),
+        }
+        out
+    })
+}
+
+/// How a nested message is matched between load and save.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ChildKey {
+    Id(u64),
+    Index(u32),
+}
+
+/// Unknown fields of one message plus those of its nested messages.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UnknownFields {
+    /// Raw unknown fields (tag + value) of this message, in wire order.
+    pub raw: Vec<u8>,
+    pub children: BTreeMap<(u32, ChildKey), UnknownFields>,
+}
+
+impl UnknownFields {
+    pub fn is_empty(&self) -> bool {
+        self.raw.is_empty() && self.children.is_empty()
+    }
+
+    /// Number of unknown fields in this subtree.
+    pub fn count(&self) -> usize {
+        let own = WireFields::new(&self.raw).filter(|field| field.is_ok()).count();
+        own + self.children.values().map(UnknownFields::count).sum::<usize>()
+    }
+
+    /// Subtree of the appearance `id` stored under the repeated field `field`
+    /// (object = 1, outfit = 2, effect = 3, missile = 4).
+    pub fn appearance(&self, field: u32, id: u32) -> Option<&UnknownFields> {
+        self.children.get(&(field, ChildKey::Id(id as u64)))
+    }
+
+    /// Removes and returns the subtree of appearance `id` under `field`.
+    pub fn take_appearance(&mut self, field: u32, id: u32) -> Option<UnknownFields> {
+        self.children.remove(&(field, ChildKey::Id(id as u64)))
+    }
+
+    pub fn set_appearance(&mut self, field: u32, id: u32, fields: UnknownFields) {
+        self.children.insert((field, ChildKey::Id(id as u64)), fields);
+    }
+
+    /// Moves the appearance subtrees under `field` from old to new ids.
+    pub fn remap_appearance_ids(&mut self, field: u32, mapping: &HashMap<u32, u32>) {
+        let moved: Vec<(u32, UnknownFields)> = mapping.iter().filter_map(|(old, new)| self.children.remove(&(field, ChildKey::Id(*old as u64))).map(|child| (*new, child))).collect();
+        for (new, child) in moved {
+            self.children.insert((field, ChildKey::Id(new as u64)), child);
+        }
+    }
+}
+
+fn read_varint(data: &[u8], pos: &mut usize) -> Result<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *data.get(*pos).context("Truncated varint")?;
+        *pos += 1;
+        value |= ((byte & 0x7F) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    bail!("Varint too long")
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// One field on the wire: number, wire type and byte offsets of the tag,
+/// the value (after any length prefix) and the end.
+struct WireField {
+    number: u32,
+    wire_type: u8,
+    start: usize,
+    tag_end: usize,
+    value_start: usize,
+    end: usize,
+}
+
+struct WireFields<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> WireFields<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            pos: 0,
+        }
+    }
+
+    fn read_field(&mut self) -> Result<WireField> {
+        let start = self.pos;
+        let tag = read_varint(self.data, &mut self.pos)?;
+        let number = (tag >> 3) as u32;
+        let wire_type = (tag & 0x7) as u8;
+        let tag_end = self.pos;
+        let (value_start, length) = match wire_type {
+            WIRE_VARINT => {
+                let value_start = self.pos;
+                read_varint(self.data, &mut self.pos)?;
+                (value_start, self.pos - value_start)
+            }
+            WIRE_FIXED64 => (self.pos, 8),
+            WIRE_LEN => {
+                let length = read_varint(self.data, &mut self.pos)? as usize;
+                (self.pos, length)
+            }
+            WIRE_FIXED32 => (self.pos, 4),
+            other => bail!("Unsupported wire type {} for field {}", other, number),
+        };
+        let end = value_start.checked_add(length).filter(|end| *end <= self.data.len()).context("Truncated field")?;
+        self.pos = end;
+        Ok(WireField {
+            number,
+            wire_type,
+            start,
+            tag_end,
+            value_start,
+            end,
+        })
+    }
+}
+
+impl Iterator for WireFields<'_> {
+    type Item = Result<WireField>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos >= self.data.len() {
+            return None;
+        }
+        let field = self.read_field();
+        if field.is_err() {
+            self.pos = self.data.len();
+        }
+        Some(field)
+    }
+}
+
+fn child_key(schema: Option<&MessageSchema>, payload: &[u8], index: u32) -> ChildKey {
+    let id = schema.and_then(|s| s.id_field).and_then(|id_field| {
+        let field = WireFields::new(payload).filter_map(Result::ok).find(|f| f.number == id_field && f.wire_type == WIRE_VARINT)?;
+        read_varint(payload, &mut field.value_start.clone()).ok()
+    });
+    match id {
+        Some(id) => ChildKey::Id(id),
+        None => ChildKey::Index(index),
+    }
+}
+
+fn capture_message(data: &[u8], message: &str) -> Result<UnknownFields> {
+    let schemas = schemas();
+    let schema = schemas.get(message).with_context(|| format!("Unknown protobuf message {}", message))?;
+    let mut node = UnknownFields::default();
+    let mut occurrences: HashMap<u32, u32> = HashMap::new();
+
+    for field in WireFields::new(data) {
+        let field = field?;
+        match schema.fields.get(&field.number) {
+            None => node.raw.extend_from_slice(&data[field.start..field.end]),
+            Some(FieldSchema {
+                message_type: Some(child_type),
+                ..
+            }) if field.wire_type == WIRE_LEN => {
+                let index = occurrences.entry(field.number).or_insert(0);
+                let payload = &data[field.value_start..field.end];
+                let key = child_key(schemas.get(child_type), payload, *index);
+                *index += 1;
+                let child = capture_message(payload, child_type)?;
+                if !child.is_empty() {
+                    node.children.insert((field.number, key), child);
+                }
+            }
+            Some(_) => {}
+        }
+    }
+    Ok(node)
+}
+
+fn inject_message(data: &[u8], message: &str, node: &UnknownFields, out: &mut Vec<u8>) -> Result<()> {
+    if node.children.is_empty() {
+        out.extend_from_slice(data);
+        out.extend_from_slice(&node.raw);
+        return Ok(());
+    }
+    let schemas = schemas();
+    let schema = schemas.get(message).with_context(|| format!("Unknown protobuf message {}", message))?;
+    let mut occurrences: HashMap<u32, u32> = HashMap::new();
+
+    for field in WireFields::new(data) {
+        let field = field?;
+        let child = match schema.fields.get(&field.number) {
+            Some(FieldSchema {
+                message_type: Some(child_type),
+                ..
+            }) if field.wire_type == WIRE_LEN => {
+                let index = occurrences.entry(field.number).or_insert(0);
+                let payload = &data[field.value_start..field.end];
+                let key = child_key(schemas.get(child_type), payload, *index);
+                *index += 1;
+                node.children.get(&(field.number, key)).map(|child| (child_type, payload, child))
+            }
+            _ => None,
+        };
+        match child {
+            Some((child_type, payload, child)) => {
+                let mut rewritten = Vec::with_capacity(payload.len() + child.raw.len());
+                inject_message(payload, child_type, child, &mut rewritten)?;
+                out.extend_from_slice(&data[field.start..field.tag_end]);
+                write_varint(out, rewritten.len() as u64);
+                out.extend_from_slice(&rewritten);
+            }
+            None => out.extend_from_slice(&data[field.start..field.end]),
+        }
+    }
+    out.extend_from_slice(&node.raw);
+    Ok(())
+}
+
+/// Collects every field of `data` (an encoded `message`) that the compiled
+/// schema does not know.
+pub fn capture_unknown_fields(data: &[u8], message: &str) -> Result<UnknownFields> {
+    capture_message(data, message)
+}
+
+/// Appends `unknown` back into `encoded` (prost output for `message`).
+pub fn inject_unknown_fields(encoded: &[u8], message: &str, unknown: &UnknownFields) -> Result<Vec<u8>> {
+    if unknown.is_empty() {
+        return Ok(encoded.to_vec());
+    }
+    let mut out = Vec::with_capacity(encoded.len() + unknown.raw.len());
+    inject_message(encoded, message, unknown, &mut out)?;
+    Ok(out)
+}
+
+/// Human-readable lines (`flags.900 varint = 1`) for a subtree rooted at `message`.
+pub fn describe_unknown_fields(unknown: &UnknownFields, message: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    describe_into(unknown, message, "", &mut lines);
+    lines
+}
+
+fn describe_into(node: &UnknownFields, message: &str, path: &str, lines: &mut Vec<String>) {
+    let prefix = if path.is_empty() {
+        String::new()
+    } else {
+        format!("{}.", path)
+    };
+    for field in WireFields::new(&node.raw) {
+        let Ok(field) = field else {
+            lines.push(format!("{}<malformed>", prefix));
+            break;
+        };
+        let value = &node.raw[field.value_start..field.end];
+        let rendered = match field.wire_type {
+            WIRE_VARINT => format!("varint = {}", read_varint(value, &mut 0).unwrap_or(0)),
+            WIRE_FIXED64 => format!("fixed64 = 0x{:016x}", u64::from_le_bytes(value.try_into().unwrap_or_default())),
+            WIRE_FIXED32 => format!("fixed32 = 0x{:08x}", u32::from_le_bytes(value.try_into().unwrap_or_default())),
+            _ => format!("bytes[{}] = {}", value.len(), value.iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(" ")),
+        };
+        lines.push(format!("{}{} {}", prefix, field.number, rendered));
+    }
+
+    let schemas = schemas();
+    let schema = schemas.get(message);
+    for ((number, key), child) in &node.children {
+        let Some(field) = schema.and_then(|s| s.fields.get(number)) else {
+            continue;
+        };
+        let segment = match key {
+            ChildKey::Id(id) => format!("{}[id={}]", field.name, id),
+            ChildKey::Index(index) if field.repeated => format!("{}[{}]", field.name, index),
+            ChildKey::Index(_) => field.name.clone(),
+        };
+        describe_into(child, field.message_type.as_deref().unwrap_or_default(), &format!("{}{}", prefix, segment), lines);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::protobuf::{Appearance, AppearanceFlags, Appearances};
+
+    fn appearance(id: u32) -> Appearance {
+        Appearance {
+            id: Some(id),
+            flags: Some(AppearanceFlags {
+                clip: Some(true),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    /// Appends an unknown varint field to the flags message of `appearance`.
+    fn with_unknown_flag(appearance: &Appearance, number: u32, value: u64) -> Vec<u8> {
+        let mut flags = appearance.flags.clone().unwrap_or_default().encode_to_vec();
+        write_varint(&mut flags, (number as u64) << 3);
+        write_varint(&mut flags, value);
+        let mut bare = appearance.clone();
+        bare.flags = None;
+        let mut out = bare.encode_to_vec();
+        write_varint(&mut out, (3 << 3) | WIRE_LEN as u64);
+        write_varint(&mut out, flags.len() as u64);
+        out.extend_from_slice(&flags);
+        out
+    }
+
+    #[test]
+    fn unknown_fields_survive_decode_edit_encode() {
+        let first = appearance(100);
+        let second = appearance(101);
+        let mut data = Vec::new();
+        for item in [with_unknown_flag(&first, 900, 7), with_unknown_flag(&second, 901, 1)] {
+            write_varint(&mut data, (1 << 3) | WIRE_LEN as u64);
+            write_varint(&mut data, item.len() as u64);
+            data.extend_from_slice(&item);
+        }
+
+        let unknown = capture_unknown_fields(&data, APPEARANCES_MESSAGE).unwrap();
+        assert_eq!(unknown.count(), 2);
+
+        // Edit: drop the first appearance, add a new one in front of the second.
+        let mut decoded = Appearances::decode(&data[..]).unwrap();
+        decoded.object.remove(0);
+        decoded.object.insert(0, appearance(200));
+        let saved = inject_unknown_fields(&decoded.encode_to_vec(), APPEARANCES_MESSAGE, &unknown).unwrap();
+
+        let recaptured = capture_unknown_fields(&saved, APPEARANCES_MESSAGE).unwrap();
+        assert_eq!(recaptured.count(), 1);
+        let lines = describe_unknown_fields(recaptured.appearance(1, 101).unwrap(), APPEARANCE_MESSAGE);
+        assert_eq!(lines, vec!["flags.901 varint = 1".to_string()]);
+        assert_eq!(Appearances::decode(&saved[..]).unwrap(), decoded);
+    }
+
+    #[test]
+    fn known_only_data_is_untouched() {
+        let appearances = Appearances {
+            object: vec![appearance(100), appearance(101)],
+            ..Default::default()
+        };
+        let data = appearances.encode_to_vec();
+        let unknown = capture_unknown_fields(&data, APPEARANCES_MESSAGE).unwrap();
+        assert!(unknown.is_empty());
+        assert_eq!(inject_unknown_fields(&data, APPEARANCES_MESSAGE, &unknown).unwrap(), data);
+    }
+}
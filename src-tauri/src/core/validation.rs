@@ -36,6 +36,13 @@ pub fn validate_frame_group_dimensions(pattern_width: u32, pattern_height: u32,
     Ok(())
 }
 
+/// Validate a whole `SpriteInfo`: one sprite per pattern cell, layer and animation phase.
+/// `phases` is the number of animation phases (1 for static sprites).
+pub fn validate_sprite_info_count(pattern_width: u32, pattern_height: u32, pattern_depth: u32, layers: u32, phases: u32, sprite_count: usize) -> AppResult<()> {
+    let layers_and_phases = layers.checked_mul(phases).ok_or_else(|| AppError::frame_group_validation(format!("Frame group dimensions overflow: {} layers x {} phases", layers, phases)))?;
+    validate_frame_group_dimensions(pattern_width, pattern_height, pattern_depth, layers_and_phases, sprite_count)
+}
+
 /// Validate animation phase counts
 pub fn validate_animation_phases(phase_count: usize, sprite_count: usize) -> AppResult<()> {
     if phase_count == 0 {
@@ -112,6 +119,14 @@ mod tests {
         assert!(validate_frame_group_dimensions(1, 1, 1, 1, 2).is_err());
     }
 
+    #[test]
+    fn test_validate_sprite_info_count() {
+        assert!(validate_sprite_info_count(2, 2, 1, 1, 1, 4).is_ok());
+        assert!(validate_sprite_info_count(4, 1, 1, 2, 3, 24).is_ok());
+        assert!(validate_sprite_info_count(4, 1, 1, 2, 3, 8).is_err());
+        assert!(validate_sprite_info_count(1, 1, 1, u32::MAX, 2, 1).is_err());
+    }
+
     #[test]
     fn test_validate_animation_phases() {
         // Valid phases
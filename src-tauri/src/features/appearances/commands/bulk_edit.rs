@@ -0,0 +1,302 @@
+// Bulk flag editing
+// Applies one flag patch to many appearances, selected by id list or by a
+// query-language expression. `preview_bulk_flag_patch` returns the per-item diff
+// without touching state; `apply_bulk_flag_patch` validates every item first and
+// only then swaps them in under a single write lock (all or nothing).
+
+use super::category_types::AppearanceCategory;
+use super::conversion::complete_flags_to_proto;
+use super::helpers::{get_index_for_category, get_items_by_category, get_items_by_category_mut, invalidate_search_cache};
+use super::journal::{record_changes, AppearanceChange};
+use super::query::query_appearance_ids;
+use crate::core::protobuf::{Appearance, AppearanceFlags};
+use crate::features::appearances::parsers::resolve_flag_path;
+use crate::features::appearances::CompleteFlags;
+use crate::features::dat_merge::diff::{diff_appearance, AppearanceDiff};
+use crate::state::AppState;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use tauri::State;
+
+/// Which appearances a bulk edit targets. Exactly one of `ids` / `query` must be set.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BulkSelection {
+    pub ids: Option<Vec<u32>>,
+    pub query: Option<String>,
+}
+
+/// Partial flag patch, applied in order set -> unset -> increment.
+/// Paths use the query-language names (`light.brightness`, `market`, `take`, ...).
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct FlagPatch {
+    /// Partial `CompleteFlags` object. Sub-flag objects are merged key by key,
+    /// everything else is replaced.
+    #[serde(default)]
+    pub set: Map<String, Value>,
+    /// Paths to clear. Lists (`restrict_to_vocation`, `npc_sale_data`) become empty.
+    #[serde(default)]
+    pub unset: Vec<String>,
+    /// Numeric paths to bump by the given (possibly negative) amount. Missing values count as 0.
+    #[serde(default)]
+    pub increment: BTreeMap<String, i64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BulkEditPreview {
+    /// Number of appearances selected.
+    pub matched: usize,
+    /// Only appearances the patch actually changes.
+    pub changes: Vec<AppearanceDiff>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BulkEditResult {
+    pub matched: usize,
+    pub changed_ids: Vec<u32>,
+}
+
+/// Applies `patch` to a single flags message and returns the patched copy.
+pub fn patch_flags(flags: Option<&AppearanceFlags>, patch: &FlagPatch) -> Result<AppearanceFlags, String> {
+    let current = CompleteFlags::from_protobuf(flags.unwrap_or(&AppearanceFlags::default()));
+    let mut value = serde_json::to_value(&current).map_err(|e| format!("Failed to serialize flags: {}", e))?;
+
+    for (key, patch_value) in &patch.set {
+        let path = resolve_flag_path(key).ok_or_else(|| format!("Unknown flag '{}'", key))?;
+        merge_json(slot_mut(&mut value, &path), patch_value);
+    }
+
+    for key in &patch.unset {
+        let path = resolve_flag_path(key).ok_or_else(|| format!("Unknown flag '{}'", key))?;
+        let slot = slot_mut(&mut value, &path);
+        *slot = if slot.is_array() {
+            Value::Array(Vec::new())
+        } else {
+            Value::Null
+        };
+    }
+
+    for (key, amount) in &patch.increment {
+        let path = resolve_flag_path(key).ok_or_else(|| format!("Unknown flag '{}'", key))?;
+        let slot = slot_mut(&mut value, &path);
+        let base = match slot {
+            Value::Null => 0,
+            Value::Number(n) => n.as_i64().ok_or_else(|| format!("'{}' is not an integer", key))?,
+            _ => return Err(format!("'{}' is not numeric", key)),
+        };
+        *slot = Value::from(base.saturating_add(*amount));
+    }
+
+    let patched: CompleteFlags = serde_json::from_value(value.clone()).map_err(|e| format!("Invalid flag patch: {}", e))?;
+
+    // serde silently drops unknown keys; catch typos like `light.brightnes`.
+    let roundtrip = serde_json::to_value(&patched).map_err(|e| format!("Failed to serialize flags: {}", e))?;
+    if let Some(path) = unknown_key(&value, &roundtrip, "") {
+        return Err(format!("Unknown flag field '{}'", path));
+    }
+
+    Ok(complete_flags_to_proto(&patched))
+}
+
+/// Walks `path` inside `value`, turning nulls into objects on the way.
+fn slot_mut<'a>(value: &'a mut Value, path: &[String]) -> &'a mut Value {
+    let mut current = value;
+    for segment in path {
+        if !current.is_object() {
+            *current = Value::Object(Map::new());
+        }
+        current = current.as_object_mut().expect("object ensured above").entry(segment.clone()).or_insert(Value::Null);
+    }
+    current
+}
+
+fn merge_json(target: &mut Value, patch: &Value) {
+    match (target, patch) {
+        (Value::Object(target_map), Value::Object(patch_map)) => {
+            for (key, value) in patch_map {
+                merge_json(target_map.entry(key.clone()).or_insert(Value::Null), value);
+            }
+        }
+        (target, patch) => *target = patch.clone(),
+    }
+}
+
+fn unknown_key(patched: &Value, roundtrip: &Value, prefix: &str) -> Option<String> {
+    match (patched, roundtrip) {
+        (Value::Object(patched_map), Value::Object(roundtrip_map)) => patched_map.iter().find_map(|(key, child)| {
+            let path = if prefix.is_empty() {
+                key.clone()
+            } else {
+                format!("{}.{}", prefix, key)
+            };
+            match roundtrip_map.get(key) {
+                Some(round_child) => unknown_key(child, round_child, &path),
+                None => Some(path),
+            }
+        }),
+        (Value::Array(patched_items), Value::Array(roundtrip_items)) => {
+            patched_items.iter().zip(roundtrip_items).enumerate().find_map(|(i, (child, round_child))| unknown_key(child, round_child, &format!("{}[{}]", prefix, i)))
+        }
+        _ => None,
+    }
+}
+
+/// Resolves the selection and computes the patched appearances, without mutating
+/// anything. Returns `(matched, [(index into items, patched appearance)])` for the
+/// appearances that actually change. Any invalid item aborts the whole plan.
+fn plan_bulk_patch(items: &[Appearance], index_of: impl Fn(u32) -> Option<usize>, selection: &BulkSelection, patch: &FlagPatch) -> Result<(usize, Vec<(usize, Appearance)>), String> {
+    let ids = match (&selection.ids, &selection.query) {
+        (Some(ids), None) => ids.clone(),
+        (None, Some(query)) => query_appearance_ids(items, query)?,
+        _ => return Err("Provide either ids or a query".to_string()),
+    };
+
+    let mut planned = Vec::new();
+    for id in &ids {
+        let index = index_of(*id).ok_or_else(|| format!("Appearance {} not found", id))?;
+        let appearance = items.get(index).ok_or_else(|| format!("Appearance {} not found", id))?;
+
+        let flags = patch_flags(appearance.flags.as_ref(), patch).map_err(|e| format!("Appearance {}: {}", id, e))?;
+        if appearance.flags.as_ref() != Some(&flags) {
+            let mut patched = appearance.clone();
+            patched.flags = Some(flags);
+            planned.push((index, patched));
+        }
+    }
+
+    Ok((ids.len(), planned))
+}
+
+/// Shows what `apply_bulk_flag_patch` would change, without modifying anything.
+#[tauri::command]
+pub async fn preview_bulk_flag_patch(category: AppearanceCategory, selection: BulkSelection, patch: FlagPatch, state: State<'_, AppState>) -> Result<BulkEditPreview, String> {
+    let appearances_lock = state.appearances.read();
+    let appearances = appearances_lock.as_ref().ok_or_else(|| "No appearances loaded".to_string())?;
+
+    let items = get_items_by_category(appearances, &category);
+    let index_map = get_index_for_category(&state, &category);
+    let (matched, planned) = plan_bulk_patch(items, |id| index_map.get(&id).map(|idx| *idx), &selection, &patch)?;
+
+    let changes = planned.iter().map(|(index, patched)| diff_appearance(patched.id.unwrap_or(0), &items[*index], patched)).collect();
+
+    Ok(BulkEditPreview {
+        matched,
+        changes,
+    })
+}
+
+/// Applies the patch to every selected appearance, or to none if any item fails.
+/// Recorded as a single undo step.
+#[tauri::command]
+pub async fn apply_bulk_flag_patch(category: AppearanceCategory, selection: BulkSelection, patch: FlagPatch, state: State<'_, AppState>) -> Result<BulkEditResult, String> {
+    let mut appearances_lock = state.appearances.write();
+    let appearances = appearances_lock.as_mut().ok_or_else(|| "No appearances loaded".to_string())?;
+
+    let index_map = get_index_for_category(&state, &category);
+    let (matched, planned) = plan_bulk_patch(get_items_by_category(appearances, &category), |id| index_map.get(&id).map(|idx| *idx), &selection, &patch)?;
+
+    let items = get_items_by_category_mut(appearances, &category);
+    let mut changes = Vec::with_capacity(planned.len());
+    let mut changed_ids = Vec::with_capacity(planned.len());
+    for (index, patched) in planned {
+        let id = patched.id.unwrap_or(0);
+        let before = std::mem::replace(&mut items[index], patched.clone());
+        changes.push(AppearanceChange {
+            before: Some(before),
+            after: Some(patched),
+        });
+        changed_ids.push(id);
+
+        let cache_key = format!("{:?}:{}", category, id);
+        state.sprite_cache.remove(&cache_key);
+        state.preview_cache.remove(&cache_key);
+    }
+
+    if !changed_ids.is_empty() {
+        invalidate_search_cache(&state);
+        record_changes(&state, format!("Bulk edit {} appearance(s)", changed_ids.len()), &category, changes);
+    }
+
+    Ok(BulkEditResult {
+        matched,
+        changed_ids,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::protobuf::AppearanceFlagLight;
+    use serde_json::json;
+
+    fn patch(value: Value) -> FlagPatch {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn lamp(id: u32, brightness: u32) -> Appearance {
+        Appearance {
+            id: Some(id),
+            flags: Some(AppearanceFlags {
+                take: Some(true),
+                light: Some(AppearanceFlagLight {
+                    brightness: Some(brightness),
+                    color: Some(215),
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn set_unset_and_increment() {
+        let flags = lamp(1, 3).flags;
+        let patched = patch_flags(
+            flags.as_ref(),
+            &patch(json!({
+                "set": { "unpass": true, "market": { "category": 5 } },
+                "unset": ["take"],
+                "increment": { "light.brightness": 2 }
+            })),
+        )
+        .unwrap();
+
+        assert_eq!(patched.unpass, Some(true));
+        assert_eq!(patched.take, None);
+        assert_eq!(patched.market.as_ref().and_then(|m| m.category), Some(5));
+        let light = patched.light.unwrap();
+        assert_eq!((light.brightness, light.color), (Some(5), Some(215)));
+    }
+
+    #[test]
+    fn rejects_unknown_and_mistyped_fields() {
+        let flags = lamp(1, 3).flags;
+        assert!(patch_flags(flags.as_ref(), &patch(json!({ "set": { "nope": true } }))).is_err());
+        assert!(patch_flags(flags.as_ref(), &patch(json!({ "set": { "light": { "brightnes": 1 } } }))).is_err());
+        assert!(patch_flags(flags.as_ref(), &patch(json!({ "set": { "take": "yes" } }))).is_err());
+        assert!(patch_flags(flags.as_ref(), &patch(json!({ "increment": { "take": 1 } }))).is_err());
+    }
+
+    #[test]
+    fn plan_is_all_or_nothing_and_skips_noops() {
+        let items = vec![lamp(10, 3), lamp(11, 8)];
+        let index_of = |id: u32| items.iter().position(|a| a.id == Some(id));
+
+        let selection = BulkSelection {
+            ids: None,
+            query: Some("light.brightness < 5".to_string()),
+        };
+        let (matched, planned) = plan_bulk_patch(&items, index_of, &selection, &patch(json!({ "set": { "light": { "brightness": 4 } } }))).unwrap();
+        assert_eq!(matched, 1);
+        assert_eq!(planned.len(), 1);
+
+        let noop = plan_bulk_patch(&items, index_of, &selection, &patch(json!({ "set": { "take": true } }))).unwrap();
+        assert!(noop.1.is_empty());
+
+        let missing = BulkSelection {
+            ids: Some(vec![10, 99]),
+            query: None,
+        };
+        assert!(plan_bulk_patch(&items, index_of, &missing, &FlagPatch::default()).is_err());
+    }
+}
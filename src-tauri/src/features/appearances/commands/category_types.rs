@@ -11,7 +11,7 @@ pub struct AppearanceItem {
 }
 
 /// Category of appearances
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum AppearanceCategory {
     Objects,
     Outfits,
@@ -19,6 +19,18 @@ pub enum AppearanceCategory {
     Missiles,
 }
 
+impl AppearanceCategory {
+    /// Number of this category's repeated field in the `Appearances` message.
+    pub fn appearances_field(&self) -> u32 {
+        match self {
+            AppearanceCategory::Objects => 1,
+            AppearanceCategory::Outfits => 2,
+            AppearanceCategory::Effects => 3,
+            AppearanceCategory::Missiles => 4,
+        }
+    }
+}
+
 /// Subcategory for Objects (based on ITEM_CATEGORY from protobuf)
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum ItemSubcategory {
@@ -292,19 +292,27 @@ pub fn clone_with_new_id(original: &Appearance, new_id: u32) -> Appearance {
 /// changed-to-expire former object). Fields that are NOT object ids (lens-help
 /// type id, cyclopedia type) are intentionally left untouched.
 pub fn remap_internal_references(appearance: &mut Appearance, old_id: u32, new_id: u32) {
+    remap_object_references(appearance, |id| (id == old_id).then_some(new_id));
+}
+
+/// Rewrites the object-id fields listed on [`remap_internal_references`] through
+/// `map` (None keeps the id). Returns how many fields changed.
+pub fn remap_object_references(appearance: &mut Appearance, map: impl Fn(u32) -> Option<u32>) -> usize {
+    let mut changed = 0;
+    let mut remap = |field: &mut Option<u32>| {
+        if let Some(new_id) = field.and_then(&map) {
+            *field = Some(new_id);
+            changed += 1;
+        }
+    };
     if let Some(flags) = appearance.flags.as_mut() {
         if let Some(market) = flags.market.as_mut() {
-            if market.trade_as_object_id == Some(old_id) {
-                market.trade_as_object_id = Some(new_id);
-            }
-            if market.show_as_object_id == Some(old_id) {
-                market.show_as_object_id = Some(new_id);
-            }
+            remap(&mut market.trade_as_object_id);
+            remap(&mut market.show_as_object_id);
         }
         if let Some(cte) = flags.changedtoexpire.as_mut() {
-            if cte.former_object_typeid == Some(old_id) {
-                cte.former_object_typeid = Some(new_id);
-            }
+            remap(&mut cte.former_object_typeid);
         }
     }
+    changed
 }
@@ -1,5 +1,6 @@
 use super::conversion::{clone_with_new_id, complete_flags_to_proto, complete_to_protobuf, remap_internal_references};
 use super::helpers::{get_items_by_category, get_items_by_category_mut, get_index_for_category, rebuild_indexes, invalidate_search_cache};
+use super::journal::{record_changes, record_deletion, record_edit, AppearanceChange};
 use crate::core::protobuf::{Appearance, Appearances};
 use crate::features::appearances::{AppearanceCategory, CompleteAppearanceItem, CompleteFlags};
 use crate::state::AppState;
@@ -12,6 +13,7 @@ use tauri::State;
 use ahash::AHasher;
 use std::hash::{Hash, Hasher};
 use crate::features::sounds::commands::SoundsState;
+use crate::features::otb::commands::register_new_objects;
 
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -65,14 +67,21 @@ pub struct ImportContext {
 
 #[tauri::command]
 pub async fn export_appearance_to_json(category: AppearanceCategory, id: u32, path: String, state: State<'_, AppState>) -> Result<String, String> {
+    write_appearance_json(state.inner(), &category, id, &path)?;
+    Ok(path)
+}
+
+/// Writes one appearance as pretty-printed [`CompleteAppearanceItem`] JSON.
+/// Shared by the Tauri command and the headless CLI.
+pub fn write_appearance_json(state: &AppState, category: &AppearanceCategory, id: u32, path: &str) -> Result<(), String> {
     let appearances_lock = state.appearances.read();
     let appearances = match &*appearances_lock {
         Some(a) => a,
         None => return Err("No appearances loaded".to_string()),
     };
 
-    let items = get_items_by_category(appearances, &category);
-    let index_map = get_index_for_category(&state, &category);
+    let items = get_items_by_category(appearances, category);
+    let index_map = get_index_for_category(state, category);
 
     // OPTIMIZATION: Use O(1) index lookup instead of O(n) linear search
     let appearance = if let Some(idx) = index_map.get(&id) {
@@ -84,27 +93,34 @@ pub async fn export_appearance_to_json(category: AppearanceCategory, id: u32, pa
     let complete = CompleteAppearanceItem::from_protobuf_with_category(appearance, Some(category.clone()));
     let json = serde_json::to_string_pretty(&complete).map_err(|e| format!("Failed to serialize appearance: {}", e))?;
 
-    if let Some(parent) = Path::new(&path).parent() {
+    if let Some(parent) = Path::new(path).parent() {
         if !parent.as_os_str().is_empty() && !parent.exists() {
             fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory {:?}: {}", parent, e))?;
         }
     }
 
-    fs::write(&path, json).map_err(|e| format!("Failed to write file {}: {}", path, e))?;
+    fs::write(path, json).map_err(|e| format!("Failed to write file {}: {}", path, e))?;
 
-    Ok(path)
+    Ok(())
 }
 
 #[tauri::command]
 pub async fn export_appearance_to_aec(category: AppearanceCategory, id: u32, path: String, state: State<'_, AppState>) -> Result<String, String> {
+    write_appearance_aec(state.inner(), &category, id, &path)?;
+    Ok(path)
+}
+
+/// Writes one appearance as a single-entry AEC container (sprite ids renumbered
+/// to `0..n`) plus its `.sprites` companion holding the PNG bytes.
+pub fn write_appearance_aec(state: &AppState, category: &AppearanceCategory, id: u32, path: &str) -> Result<(), String> {
     let appearances_lock = state.appearances.read();
     let appearances = match &*appearances_lock {
         Some(a) => a,
         None => return Err("No appearances loaded".to_string()),
     };
 
-    let items = get_items_by_category(appearances, &category);
-    let index_map = get_index_for_category(&state, &category);
+    let items = get_items_by_category(appearances, category);
+    let index_map = get_index_for_category(state, category);
 
     let appearance = if let Some(idx) = index_map.get(&id) {
         items.get(*idx).ok_or_else(|| format!("Appearance with ID {} not found", id))?
@@ -153,7 +169,7 @@ pub async fn export_appearance_to_aec(category: AppearanceCategory, id: u32, pat
         AppearanceCategory::Missiles => container.missile.push(appearance),
     }
 
-    if let Some(parent) = Path::new(&path).parent() {
+    if let Some(parent) = Path::new(path).parent() {
         if !parent.as_os_str().is_empty() && !parent.exists() {
             fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory {:?}: {}", parent, e))?;
         }
@@ -161,15 +177,15 @@ pub async fn export_appearance_to_aec(category: AppearanceCategory, id: u32, pat
 
     let mut buf = Vec::new();
     container.encode(&mut buf).map_err(|e| format!("Failed to encode AEC: {}", e))?;
-    fs::write(&path, buf).map_err(|e| format!("Failed to write file {}: {}", path, e))?;
+    fs::write(path, buf).map_err(|e| format!("Failed to write file {}: {}", path, e))?;
 
     // Persist the actual sprite bytes alongside the AEC so the appearance can be
     // reconstructed when imported in another environment. The IDs in the AEC were
     // renumbered to 0..n in the same iteration order as `sprite_data`, so the
     // companion stores the bytes in that exact sequential order.
-    write_aec_sprite_companion(&path, &sprite_data)?;
+    write_aec_sprite_companion(path, &sprite_data)?;
 
-    Ok(path)
+    Ok(())
 }
 
 #[tauri::command]
@@ -229,6 +245,9 @@ pub async fn import_appearance_from_json(
             result_id
         }
         ImportMode::New => {
+            if category == AppearanceCategory::Objects {
+                register_new_objects(&state, std::slice::from_ref(&proto));
+            }
             {
                 let items = get_items_by_category_mut(appearances, &category);
                 items.push(proto);
@@ -291,6 +310,9 @@ pub async fn import_appearances_from_files(category: AppearanceCategory, paths:
     let to_insert = process_import_bucket(&category, parsed_items, start_id, state.inner(), &mut seen_signatures, &mut result)?;
 
     if !to_insert.is_empty() {
+        if category == AppearanceCategory::Objects {
+            register_new_objects(&state, &to_insert);
+        }
         let items = get_items_by_category_mut(appearances, &category);
         items.extend(to_insert);
         sort_by_id(items);
@@ -303,6 +325,12 @@ pub async fn import_appearances_from_files(category: AppearanceCategory, paths:
 
 #[tauri::command]
 pub async fn import_appearances_from_files_all(paths: Vec<String>, start_ids: Option<ImportStartIds>, state: State<'_, AppState>) -> Result<ImportBatchResult, String> {
+    import_appearance_files(state.inner(), &paths, start_ids.as_ref())
+}
+
+/// Imports JSON/AEC files into whichever category each entry belongs to. AEC
+/// sprites are registered as imported sprites (see `compile_imported_sprites`).
+pub fn import_appearance_files(state: &AppState, paths: &[String], start_ids: Option<&ImportStartIds>) -> Result<ImportBatchResult, String> {
     let mut appearances_lock = state.appearances.write();
     let appearances = appearances_lock.as_mut().ok_or_else(|| "No appearances loaded".to_string())?;
 
@@ -314,7 +342,7 @@ pub async fn import_appearances_from_files_all(paths: Vec<String>, start_ids: Op
     }
 
     let mut buckets = ImportBuckets::default();
-    for path in &paths {
+    for path in paths {
         let extension = Path::new(path).extension().and_then(|ext| ext.to_str()).unwrap_or("").to_lowercase();
 
         if extension == "aec" {
@@ -322,19 +350,19 @@ pub async fn import_appearances_from_files_all(paths: Vec<String>, start_ids: Op
             let sprite_overrides = read_aec_sprite_companion(path)?;
 
             if !aec.outfit.is_empty() {
-                remap_imported_sprites(&mut aec.outfit, state.inner(), &sprite_overrides)?;
+                remap_imported_sprites(&mut aec.outfit, state, &sprite_overrides)?;
                 buckets.outfits.extend(aec.outfit.into_iter().map(ImportedAppearance::Proto));
             }
             if !aec.object.is_empty() {
-                remap_imported_sprites(&mut aec.object, state.inner(), &sprite_overrides)?;
+                remap_imported_sprites(&mut aec.object, state, &sprite_overrides)?;
                 buckets.objects.extend(aec.object.into_iter().map(ImportedAppearance::Proto));
             }
             if !aec.effect.is_empty() {
-                remap_imported_sprites(&mut aec.effect, state.inner(), &sprite_overrides)?;
+                remap_imported_sprites(&mut aec.effect, state, &sprite_overrides)?;
                 buckets.effects.extend(aec.effect.into_iter().map(ImportedAppearance::Proto));
             }
             if !aec.missile.is_empty() {
-                remap_imported_sprites(&mut aec.missile, state.inner(), &sprite_overrides)?;
+                remap_imported_sprites(&mut aec.missile, state, &sprite_overrides)?;
                 buckets.missiles.extend(aec.missile.into_iter().map(ImportedAppearance::Proto));
             }
         } else {
@@ -358,9 +386,9 @@ pub async fn import_appearances_from_files_all(paths: Vec<String>, start_ids: Op
         if items.is_empty() {
             continue;
         }
-        let existing_signatures = collect_existing_signatures(&category, get_items_by_category(appearances, &category), state.inner())?;
+        let existing_signatures = collect_existing_signatures(&category, get_items_by_category(appearances, &category), state)?;
         let mut seen_signatures = existing_signatures;
-        let to_import = process_import_bucket(&category, items, start_id_for_category(start_ids.as_ref(), &category), state.inner(), &mut seen_signatures, &mut result)?;
+        let to_import = process_import_bucket(&category, items, start_id_for_category(start_ids, &category), state, &mut seen_signatures, &mut result)?;
         if !to_import.is_empty() {
             to_insert.push(category, to_import);
         }
@@ -368,6 +396,7 @@ pub async fn import_appearances_from_files_all(paths: Vec<String>, start_ids: Op
 
     if to_insert.has_any() {
         if !to_insert.objects.is_empty() {
+            register_new_objects(state, &to_insert.objects);
             let items = get_items_by_category_mut(appearances, &AppearanceCategory::Objects);
             items.extend(to_insert.objects);
             sort_by_id(items);
@@ -387,8 +416,8 @@ pub async fn import_appearances_from_files_all(paths: Vec<String>, start_ids: Op
             items.extend(to_insert.missiles);
             sort_by_id(items);
         }
-        rebuild_indexes(&state, appearances);
-        invalidate_search_cache(&state);
+        rebuild_indexes(state, appearances);
+        invalidate_search_cache(state);
     }
 
     Ok(result)
@@ -457,6 +486,15 @@ pub async fn duplicate_appearance(category: AppearanceCategory, source_id: u32,
     } else {
         return Err("Failed to duplicate appearance".to_string());
     };
+    record_changes(
+        &state,
+        format!("Duplicate appearance {}", source_id),
+        &category,
+        vec![AppearanceChange {
+            before: None,
+            after: Some(stored.clone()),
+        }],
+    );
 
     Ok(CompleteAppearanceItem::from_protobuf(&stored))
 }
@@ -508,6 +546,14 @@ pub async fn duplicate_appearances_batch(category: AppearanceCategory, source_id
         assigned.push((*old_id, new_id));
     }
 
+    let changes = new_items
+        .iter()
+        .map(|dup| AppearanceChange {
+            before: None,
+            after: Some(dup.clone()),
+        })
+        .collect();
+
     {
         let items_mut = get_items_by_category_mut(appearances, &category);
         items_mut.extend(new_items);
@@ -516,13 +562,14 @@ pub async fn duplicate_appearances_batch(category: AppearanceCategory, source_id
 
     rebuild_indexes(&state, appearances);
     invalidate_search_cache(&state);
+    record_changes(&state, format!("Duplicate {} appearance(s)", assigned.len()), &category, changes);
 
     Ok(assigned)
 }
 
 /// Renames an appearance to `new_id` (DatEditor "edit id"). Rejects collisions.
 /// Note: references from other appearances (trade_as, etc.) are not rewired —
-/// matches the original editor's behavior.
+/// matches the original editor's behavior. `apply_id_remap` does the cascading version.
 #[tauri::command]
 pub async fn change_appearance_id(category: AppearanceCategory, old_id: u32, new_id: u32, state: State<'_, AppState>) -> Result<(), String> {
     if new_id == old_id {
@@ -545,15 +592,28 @@ pub async fn change_appearance_id(category: AppearanceCategory, old_id: u32, new
         }
     }
 
-    {
+    let change = {
         let items = get_items_by_category_mut(appearances, &category);
         let app = items.iter_mut().find(|a| a.id.unwrap_or(0) == old_id).ok_or_else(|| format!("Appearance {} not found in {:?}", old_id, category))?;
+        let before = app.clone();
         app.id = Some(new_id);
+        let change = AppearanceChange {
+            before: Some(before),
+            after: Some(app.clone()),
+        };
         sort_by_id(items);
+        change
+    };
+    {
+        let mut unknown = state.appearance_unknown_fields.write();
+        if let Some(fields) = unknown.take_appearance(category.appearances_field(), old_id) {
+            unknown.set_appearance(category.appearances_field(), new_id, fields);
+        }
     }
 
     rebuild_indexes(&state, appearances);
     invalidate_search_cache(&state);
+    record_changes(&state, format!("Change id {} -> {}", old_id, new_id), &category, vec![change]);
     Ok(())
 }
 
@@ -604,6 +664,18 @@ pub async fn create_empty_appearance(
     } else {
         return Err("Failed to create appearance".to_string());
     };
+    if category == AppearanceCategory::Objects {
+        register_new_objects(&state, std::slice::from_ref(&stored));
+    }
+    record_changes(
+        &state,
+        "Create appearance",
+        &category,
+        vec![AppearanceChange {
+            before: None,
+            after: Some(stored.clone()),
+        }],
+    );
 
     Ok(CompleteAppearanceItem::from_protobuf(&stored))
 }
@@ -661,7 +733,9 @@ pub async fn paste_appearance_flags(category: AppearanceCategory, id: u32, state
             items.iter_mut().find(|app| app.id.unwrap_or(0) == id).ok_or_else(|| format!("Appearance {} not found in {:?}", id, category))?
         };
 
+        let before = appearance.clone();
         appearance.flags = Some(complete_flags_to_proto(&flags_to_apply));
+        record_edit(&state, "Paste flags", &category, before, appearance);
     }
 
     // CRITICAL: Invalidate search cache after mutating flags
@@ -681,11 +755,23 @@ pub async fn delete_appearance(category: AppearanceCategory, id: u32, state: Sta
 
     // OPTIMIZATION: Use O(1) index lookup instead of O(n) linear search
     if let Some(pos) = index_map.get(&id) {
-        items.remove(*pos);
+        let removed = items.remove(*pos);
+        // Unknown fields are keyed by id; park them on the journal entry for undo.
+        let unknown_fields = state.appearance_unknown_fields.write().take_appearance(category.appearances_field(), id).map(|fields| (id, fields)).into_iter().collect();
 
         // CRITICAL: Rebuild indexes after deletion (all subsequent indexes are stale)
         rebuild_indexes(&state, appearances);
         invalidate_search_cache(&state);
+        record_deletion(
+            &state,
+            format!("Delete appearance {}", id),
+            &category,
+            vec![AppearanceChange {
+                before: Some(removed),
+                after: None,
+            }],
+            unknown_fields,
+        );
 
         Ok(())
     } else {
@@ -1,6 +1,8 @@
-use crate::features::appearances::parsers::{get_statistics, load_appearances, AppearanceStats};
+use crate::core::protobuf::Appearances;
+use crate::core::protobuf::unknown_fields::UnknownFields;
+use crate::features::appearances::parsers::{encode_appearances, get_statistics, load_appearances_preserving, AppearanceStats};
 use crate::state::AppState;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tauri::State;
 use super::helpers::{rebuild_indexes, invalidate_search_cache};
 
@@ -17,8 +19,10 @@ pub async fn load_appearances_file(path: String, state: State<'_, AppState>) ->
     // This prevents UI freeze during large file loads (10-100+ MB)
     // Uses Tauri's runtime instead of raw tokio (Tauri doesn't create a tokio runtime by default)
     let path_clone = path.clone();
-    let appearances =
-        tauri::async_runtime::spawn_blocking(move || load_appearances(&path_clone)).await.map_err(|e| format!("Task join error: {}", e))?.map_err(|e| format!("Failed to load appearances: {}", e))?;
+    let (appearances, unknown_fields) = tauri::async_runtime::spawn_blocking(move || load_appearances_preserving(&path_clone))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+        .map_err(|e| format!("Failed to load appearances: {}", e))?;
 
     let stats = get_statistics(&appearances);
 
@@ -29,6 +33,7 @@ pub async fn load_appearances_file(path: String, state: State<'_, AppState>) ->
     {
         let mut appearances_lock = state.appearances.write();
         *appearances_lock = Some(appearances);
+        *state.appearance_unknown_fields.write() = unknown_fields;
 
         // Build indexes AFTER storing while holding write lock (readers blocked until both complete)
         // This ensures indexes always match the stored data
@@ -38,6 +43,9 @@ pub async fn load_appearances_file(path: String, state: State<'_, AppState>) ->
         invalidate_search_cache(&state);
     } // Write lock released here
 
+    // History refers to the previous file's appearances
+    state.appearance_journal.lock().clear();
+
     *state.tibia_path.lock() = Some(PathBuf::from(path));
 
     log::info!("Load complete with indexes built");
@@ -126,10 +134,8 @@ pub async fn list_appearance_files(tibia_path: String) -> Result<Vec<String>, St
 /// - Uses parking_lot locks (3x faster)
 #[tauri::command]
 pub async fn save_appearances_file(state: tauri::State<'_, AppState>) -> Result<usize, String> {
-    use prost::Message;
-
     // Clone necessary data while holding locks (minimize lock time)
-    let (appearances_clone, path_clone) = {
+    let (appearances_clone, unknown_clone, path_clone) = {
         let appearances_lock = state.appearances.read();
         let appearances = match &*appearances_lock {
             Some(appearances) => appearances.clone(),
@@ -142,24 +148,26 @@ pub async fn save_appearances_file(state: tauri::State<'_, AppState>) -> Result<
             None => return Err("No appearances file path available".to_string()),
         };
 
-        (appearances, path)
+        (appearances, state.appearance_unknown_fields.read().clone(), path)
     }; // Locks dropped here!
 
     // CRITICAL OPTIMIZATION: Move blocking encode + write to thread pool
     // Encoding protobuf + writing 10-100+ MB files would freeze UI
     // Uses Tauri's runtime instead of raw tokio (Tauri doesn't create a tokio runtime by default)
-    let size = tauri::async_runtime::spawn_blocking(move || {
-        let mut buf = Vec::new();
-        appearances_clone.encode(&mut buf).map_err(|e| format!("Failed to encode appearances: {}", e))?;
-
-        std::fs::write(&path_clone, &buf).map_err(|e| format!("Failed to write appearances to {:?}: {}", path_clone, e))?;
-
-        Ok::<usize, String>(buf.len())
-    })
-    .await
-    .map_err(|e| format!("Task join error: {}", e))??;
+    let size = tauri::async_runtime::spawn_blocking(move || write_appearances_file(&appearances_clone, &unknown_clone, &path_clone)).await.map_err(|e| format!("Task join error: {}", e))??;
 
     log::info!("Saved {} bytes to disk", size);
 
     Ok(size)
 }
+
+/// Encodes `appearances` (plus the `unknown` fields kept from load) and writes
+/// them to `path`. Returns the encoded size.
+pub fn write_appearances_file(appearances: &Appearances, unknown: &UnknownFields, path: &Path) -> Result<usize, String> {
+    let buf = encode_appearances(appearances, unknown).map_err(|e| format!("Failed to encode appearances: {}", e))?;
+
+    crate::core::backups::snapshot_before_write(path);
+    std::fs::write(path, &buf).map_err(|e| format!("Failed to write appearances to {:?}: {}", path, e))?;
+
+    Ok(buf.len())
+}
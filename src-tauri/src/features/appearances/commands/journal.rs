@@ -0,0 +1,394 @@
+// Undo/redo journal for appearance mutations
+// Each entry stores before/after snapshots of only the appearances it touched,
+// so undo/redo is a by-id swap instead of reloading the whole .dat file.
+// Unknown protobuf fields are keyed by appearance id, so the swap moves them
+// along; those of removed appearances are parked on the entry until restored.
+
+use super::category_types::AppearanceCategory;
+use super::helpers::{get_items_by_category_mut, invalidate_search_cache, rebuild_indexes};
+use crate::core::protobuf::unknown_fields::UnknownFields;
+use crate::core::protobuf::{Appearance, Appearances};
+use crate::state::AppState;
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use tauri::State;
+
+/// Maximum number of undoable entries kept in memory. Older entries are dropped.
+pub const JOURNAL_CAPACITY: usize = 200;
+
+/// One appearance before and after a mutation.
+/// `before: None` means the appearance was created, `after: None` that it was deleted.
+#[derive(Debug, Clone)]
+pub struct AppearanceChange {
+    pub before: Option<Appearance>,
+    pub after: Option<Appearance>,
+}
+
+#[derive(Debug, Clone)]
+pub struct JournalEntry {
+    pub id: u64,
+    pub label: String,
+    pub category: AppearanceCategory,
+    pub changes: Vec<AppearanceChange>,
+    /// Unknown fields of appearances this entry currently has removed, by id.
+    pub unknown_fields: BTreeMap<u32, UnknownFields>,
+}
+
+/// Frontend-facing summary of a journal entry.
+#[derive(Debug, Clone, Serialize)]
+pub struct JournalEntryInfo {
+    pub id: u64,
+    pub label: String,
+    pub category: AppearanceCategory,
+    /// Every appearance id touched by the entry (old and new ids for renames).
+    pub appearance_ids: Vec<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AppearanceHistory {
+    /// Oldest first; the last element is what `undo_appearance_edit` reverts.
+    pub undo: Vec<JournalEntryInfo>,
+    /// Most recently undone last; the last element is what `redo_appearance_edit` re-applies.
+    pub redo: Vec<JournalEntryInfo>,
+}
+
+/// Bounded undo/redo stacks. Recording a new entry clears the redo stack.
+#[derive(Debug)]
+pub struct AppearanceJournal {
+    undo: VecDeque<JournalEntry>,
+    redo: Vec<JournalEntry>,
+    next_id: u64,
+    capacity: usize,
+}
+
+impl AppearanceJournal {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            next_id: 1,
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Records a mutation. No-op changes (before == after) are dropped, and an
+    /// entry with nothing left is not recorded at all.
+    pub fn record(&mut self, label: impl Into<String>, category: &AppearanceCategory, changes: Vec<AppearanceChange>) {
+        self.record_with_unknown(label, category, changes, BTreeMap::new());
+    }
+
+    /// Like [`Self::record`], parking the unknown fields of removed appearances
+    /// so undoing the removal brings them back.
+    pub fn record_with_unknown(&mut self, label: impl Into<String>, category: &AppearanceCategory, changes: Vec<AppearanceChange>, unknown_fields: BTreeMap<u32, UnknownFields>) {
+        let changes: Vec<AppearanceChange> = changes.into_iter().filter(|c| c.before != c.after).collect();
+        if changes.is_empty() {
+            return;
+        }
+
+        let entry = JournalEntry {
+            id: self.next_id,
+            label: label.into(),
+            category: category.clone(),
+            changes,
+            unknown_fields,
+        };
+        self.next_id += 1;
+        self.redo.clear();
+        self.undo.push_back(entry);
+        while self.undo.len() > self.capacity {
+            self.undo.pop_front();
+        }
+    }
+
+    pub fn pop_undo(&mut self) -> Option<JournalEntry> {
+        self.undo.pop_back()
+    }
+
+    pub fn pop_redo(&mut self) -> Option<JournalEntry> {
+        self.redo.pop()
+    }
+
+    /// Moves an undone entry onto the redo stack.
+    pub fn push_redo(&mut self, entry: JournalEntry) {
+        self.redo.push(entry);
+    }
+
+    /// Moves a redone entry back onto the undo stack without clearing redo.
+    pub fn push_undo(&mut self, entry: JournalEntry) {
+        self.undo.push_back(entry);
+        while self.undo.len() > self.capacity {
+            self.undo.pop_front();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    pub fn history(&self) -> AppearanceHistory {
+        AppearanceHistory {
+            undo: self.undo.iter().map(JournalEntry::info).collect(),
+            redo: self.redo.iter().map(JournalEntry::info).collect(),
+        }
+    }
+}
+
+impl Default for AppearanceJournal {
+    fn default() -> Self {
+        Self::new(JOURNAL_CAPACITY)
+    }
+}
+
+impl JournalEntry {
+    pub fn info(&self) -> JournalEntryInfo {
+        let mut appearance_ids: Vec<u32> = Vec::new();
+        for change in &self.changes {
+            for app in [&change.before, &change.after].into_iter().flatten() {
+                let id = app.id.unwrap_or(0);
+                if !appearance_ids.contains(&id) {
+                    appearance_ids.push(id);
+                }
+            }
+        }
+        JournalEntryInfo {
+            id: self.id,
+            label: self.label.clone(),
+            category: self.category.clone(),
+            appearance_ids,
+        }
+    }
+}
+
+/// Records a single in-place edit. Call with a clone taken right after the
+/// appearance lookup and the mutated appearance right before returning.
+pub fn record_edit(state: &AppState, label: impl Into<String>, category: &AppearanceCategory, before: Appearance, after: &Appearance) {
+    if before == *after {
+        return;
+    }
+    state.appearance_journal.lock().record(
+        label,
+        category,
+        vec![AppearanceChange {
+            before: Some(before),
+            after: Some(after.clone()),
+        }],
+    );
+}
+
+/// Records a multi-appearance mutation (batch duplicate, delete, id change).
+pub fn record_changes(state: &AppState, label: impl Into<String>, category: &AppearanceCategory, changes: Vec<AppearanceChange>) {
+    state.appearance_journal.lock().record(label, category, changes);
+}
+
+/// Records a deletion, keeping the unknown fields already taken out of
+/// `state.appearance_unknown_fields` for the deleted appearances.
+pub fn record_deletion(state: &AppState, label: impl Into<String>, category: &AppearanceCategory, changes: Vec<AppearanceChange>, unknown_fields: BTreeMap<u32, UnknownFields>) {
+    state.appearance_journal.lock().record_with_unknown(label, category, changes, unknown_fields);
+}
+
+/// Swaps `from` snapshots out for `to` snapshots, matching appearances by id.
+/// A missing `from` is ignored and an existing `to` id is overwritten, so the
+/// swap stays well-defined even if an unjournaled edit touched the same ids.
+/// Unknown fields follow the same swap: they move with renamed appearances,
+/// are parked in `parked` for removed ones and taken back when they return.
+fn apply_changes(appearances: &mut Appearances, unknown: &mut UnknownFields, parked: &mut BTreeMap<u32, UnknownFields>, category: &AppearanceCategory, changes: &[AppearanceChange], undo: bool) {
+    let items = get_items_by_category_mut(appearances, category);
+    let field = category.appearances_field();
+
+    let ordered: Box<dyn Iterator<Item = &AppearanceChange>> = if undo {
+        Box::new(changes.iter().rev())
+    } else {
+        Box::new(changes.iter())
+    };
+
+    for change in ordered {
+        let (from, to) = if undo {
+            (&change.after, &change.before)
+        } else {
+            (&change.before, &change.after)
+        };
+
+        let from_id = from.as_ref().and_then(|app| app.id);
+        let mut moving = None;
+        if let Some(from_id) = from_id {
+            items.retain(|app| app.id != Some(from_id));
+            moving = unknown.take_appearance(field, from_id);
+        }
+        if let Some(to) = to {
+            items.retain(|app| app.id != to.id);
+            items.push(to.clone());
+            let to_id = to.id.unwrap_or(0);
+            if let Some(fields) = moving.take().or_else(|| parked.remove(&to_id)) {
+                unknown.set_appearance(field, to_id, fields);
+            }
+        }
+        if let (Some(from_id), Some(fields)) = (from_id, moving) {
+            parked.insert(from_id, fields);
+        }
+    }
+
+    items.sort_by_key(|app| app.id.unwrap_or(u32::MAX));
+}
+
+fn apply_entry(state: &AppState, entry: &mut JournalEntry, undo: bool) -> Result<(), String> {
+    let mut appearances_lock = state.appearances.write();
+    let appearances = appearances_lock.as_mut().ok_or_else(|| "No appearances loaded".to_string())?;
+
+    apply_changes(appearances, &mut state.appearance_unknown_fields.write(), &mut entry.unknown_fields, &entry.category, &entry.changes, undo);
+
+    rebuild_indexes(state, appearances);
+    invalidate_search_cache(state);
+    for id in entry.info().appearance_ids {
+        let cache_key = format!("{:?}:{}", entry.category, id);
+        state.sprite_cache.remove(&cache_key);
+        state.preview_cache.remove(&cache_key);
+    }
+    Ok(())
+}
+
+/// Reverts the most recent journaled appearance mutation.
+/// Returns the reverted entry, or `None` when there is nothing to undo.
+#[tauri::command]
+pub async fn undo_appearance_edit(state: State<'_, AppState>) -> Result<Option<JournalEntryInfo>, String> {
+    let Some(mut entry) = state.appearance_journal.lock().pop_undo() else {
+        return Ok(None);
+    };
+
+    if let Err(e) = apply_entry(&state, &mut entry, true) {
+        state.appearance_journal.lock().push_undo(entry);
+        return Err(e);
+    }
+
+    let info = entry.info();
+    state.appearance_journal.lock().push_redo(entry);
+    Ok(Some(info))
+}
+
+/// Re-applies the most recently undone mutation.
+#[tauri::command]
+pub async fn redo_appearance_edit(state: State<'_, AppState>) -> Result<Option<JournalEntryInfo>, String> {
+    let Some(mut entry) = state.appearance_journal.lock().pop_redo() else {
+        return Ok(None);
+    };
+
+    if let Err(e) = apply_entry(&state, &mut entry, false) {
+        state.appearance_journal.lock().push_redo(entry);
+        return Err(e);
+    }
+
+    let info = entry.info();
+    state.appearance_journal.lock().push_undo(entry);
+    Ok(Some(info))
+}
+
+#[tauri::command]
+pub async fn get_appearance_history(state: State<'_, AppState>) -> Result<AppearanceHistory, String> {
+    Ok(state.appearance_journal.lock().history())
+}
+
+#[tauri::command]
+pub async fn clear_appearance_history(state: State<'_, AppState>) -> Result<(), String> {
+    state.appearance_journal.lock().clear();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app(id: u32, name: &str) -> Appearance {
+        Appearance {
+            id: Some(id),
+            name: Some(name.as_bytes().to_vec()),
+            ..Default::default()
+        }
+    }
+
+    fn objects(appearances: &Appearances) -> Vec<(u32, Vec<u8>)> {
+        appearances.object.iter().map(|a| (a.id.unwrap_or(0), a.name.clone().unwrap_or_default())).collect()
+    }
+
+    #[test]
+    fn record_drops_noops_and_clears_redo() {
+        let mut journal = AppearanceJournal::new(10);
+        journal.record(
+            "noop",
+            &AppearanceCategory::Objects,
+            vec![AppearanceChange {
+                before: Some(app(1, "a")),
+                after: Some(app(1, "a")),
+            }],
+        );
+        assert!(journal.history().undo.is_empty());
+
+        let change = AppearanceChange {
+            before: Some(app(1, "a")),
+            after: Some(app(1, "b")),
+        };
+        journal.record("rename", &AppearanceCategory::Objects, vec![change.clone()]);
+        let entry = journal.pop_undo().unwrap();
+        journal.push_redo(entry);
+        assert_eq!(journal.history().redo.len(), 1);
+
+        journal.record("rename again", &AppearanceCategory::Objects, vec![change]);
+        assert!(journal.history().redo.is_empty());
+    }
+
+    #[test]
+    fn capacity_drops_oldest_entries() {
+        let mut journal = AppearanceJournal::new(2);
+        for i in 0..3 {
+            journal.record(
+                format!("edit {}", i),
+                &AppearanceCategory::Objects,
+                vec![AppearanceChange {
+                    before: None,
+                    after: Some(app(100 + i, "x")),
+                }],
+            );
+        }
+        let labels: Vec<String> = journal.history().undo.into_iter().map(|e| e.label).collect();
+        assert_eq!(labels, vec!["edit 1".to_string(), "edit 2".to_string()]);
+    }
+
+    #[test]
+    fn undo_and_redo_swap_by_id() {
+        let mut appearances = Appearances {
+            object: vec![app(1, "a"), app(2, "b")],
+            ..Default::default()
+        };
+
+        // Rename id 2 -> 5 plus a delete of id 1, recorded as one entry.
+        let changes = vec![
+            AppearanceChange {
+                before: Some(app(2, "b")),
+                after: Some(app(5, "b")),
+            },
+            AppearanceChange {
+                before: Some(app(1, "a")),
+                after: None,
+            },
+        ];
+        let fields = |raw: u8| UnknownFields {
+            raw: vec![raw],
+            ..Default::default()
+        };
+        let mut unknown = UnknownFields::default();
+        unknown.set_appearance(1, 1, fields(1));
+        unknown.set_appearance(1, 2, fields(2));
+        let mut parked = BTreeMap::new();
+
+        apply_changes(&mut appearances, &mut unknown, &mut parked, &AppearanceCategory::Objects, &changes, false);
+        assert_eq!(objects(&appearances), vec![(5, b"b".to_vec())]);
+        assert_eq!(unknown.appearance(1, 5), Some(&fields(2)));
+        assert_eq!(unknown.appearance(1, 1), None);
+        assert_eq!(unknown.appearance(1, 2), None);
+
+        apply_changes(&mut appearances, &mut unknown, &mut parked, &AppearanceCategory::Objects, &changes, true);
+        assert_eq!(objects(&appearances), vec![(1, b"a".to_vec()), (2, b"b".to_vec())]);
+        assert_eq!(unknown.appearance(1, 1), Some(&fields(1)));
+        assert_eq!(unknown.appearance(1, 2), Some(&fields(2)));
+        assert!(parked.is_empty());
+    }
+}
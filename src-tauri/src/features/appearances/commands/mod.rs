@@ -1,20 +1,26 @@
 // Appearance commands module
 // All Tauri commands for appearances
 
+mod bulk_edit;
 mod category_types;
 mod conversion;
 pub mod helpers;
 mod import_export;
 mod io;
+mod journal;
 mod query;
 mod update;
+mod validate;
 
 // Re-export command types
 pub use category_types::*;
 
 // Re-export all command functions
+pub use bulk_edit::*;
 pub use conversion::*;
 pub use import_export::*;
 pub use io::*;
+pub use journal::*;
 pub use query::*;
 pub use update::*;
+pub use validate::*;
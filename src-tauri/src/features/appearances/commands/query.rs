@@ -1,5 +1,8 @@
 use super::category_types::{AppearanceCategory, AppearanceDetails, AppearanceFlagsInfo, AppearanceItem, FrameGroupInfo, ItemSubcategory};
 use super::helpers::{create_appearance_item_response, get_items_by_category, get_index_for_category};
+use crate::core::protobuf::unknown_fields::{describe_unknown_fields, APPEARANCE_MESSAGE};
+use crate::core::protobuf::Appearance;
+use crate::features::appearances::parsers::{parse_query, QueryTarget};
 use crate::features::appearances::CompleteAppearanceItem;
 use crate::state::AppState;
 use tauri::State;
@@ -29,6 +32,15 @@ fn build_position_cache_key(category: &AppearanceCategory, subcategory: &ItemSub
     format!("p:{:x}", hasher.finish())
 }
 
+/// Build a cache key for query-language results (separate "q:" namespace).
+#[inline]
+fn build_query_cache_key(category: &AppearanceCategory, query: &str) -> String {
+    let mut hasher = ahash::AHasher::default();
+    std::mem::discriminant(category).hash(&mut hasher);
+    query.hash(&mut hasher);
+    format!("q:{:x}", hasher.finish())
+}
+
 #[derive(Serialize)]
 pub struct AppearancePage {
     pub total: usize,
@@ -375,6 +387,73 @@ pub async fn search_appearances_by_flags(
     })
 }
 
+/// Returns the sorted ids of `items` matching a query-language expression
+/// (see `parsers::query_language`). A blank query matches everything.
+pub fn query_appearance_ids(items: &[Appearance], query: &str) -> Result<Vec<u32>, String> {
+    let mut ids: Vec<u32> = if query.trim().is_empty() {
+        items.iter().map(|app| app.id.unwrap_or(0)).collect()
+    } else {
+        let expr = parse_query(query).map_err(|e| format!("Invalid query: {}", e))?;
+        items.par_iter().filter(|app| expr.eval(&QueryTarget::new(app))).map(|app| app.id.unwrap_or(0)).collect()
+    };
+    ids.par_sort_unstable();
+    Ok(ids)
+}
+
+/// Structured search, e.g. `take and light.brightness > 0 and not market`.
+/// Matching ids are memoized in `search_cache` (cleared on every mutation), so
+/// paging through results doesn't re-evaluate the query. Same page shape as
+/// `list_appearances_by_category`.
+#[tauri::command]
+pub async fn query_appearances(category: AppearanceCategory, query: String, page: usize, page_size: usize, state: State<'_, AppState>) -> Result<AppearancePage, String> {
+    let appearances_lock = state.appearances.read();
+    let appearances = match &*appearances_lock {
+        Some(a) => a,
+        None => return Err("No appearances loaded".to_string()),
+    };
+
+    let items = get_items_by_category(appearances, &category);
+    let index_map = get_index_for_category(&state, &category);
+
+    let cache_key = build_query_cache_key(&category, query.trim());
+    let cached = state.search_cache.get(&cache_key).map(|ids| ids.clone());
+    let filtered_ids = match cached {
+        Some(ids) => ids,
+        None => {
+            let ids = Arc::new(query_appearance_ids(items, &query)?);
+            state.search_cache.insert(cache_key, ids.clone());
+            ids
+        }
+    };
+
+    let total = filtered_ids.len();
+    let start = page * page_size;
+    let end = std::cmp::min(start + page_size, total);
+    if start >= total {
+        return Ok(AppearancePage {
+            total,
+            items: vec![],
+        });
+    }
+
+    let mut result: Vec<AppearanceItem> = Vec::with_capacity(end - start);
+    for &id in &filtered_ids[start..end] {
+        let appearance = if let Some(idx) = index_map.get(&id) {
+            items.get(*idx)
+        } else {
+            items.iter().find(|app| app.id.unwrap_or(0) == id)
+        };
+        if let Some(app) = appearance {
+            result.push(create_appearance_item_response(id, app));
+        }
+    }
+
+    Ok(AppearancePage {
+        total,
+        items: result,
+    })
+}
+
 /// HEAVILY OPTIMIZED find_appearance_position:
 /// - Uses search cache (no repeated filtering)
 /// - Binary search on sorted IDs for O(log n) instead of O(n)
@@ -648,7 +727,25 @@ pub async fn get_appearance_raw_dump(category: AppearanceCategory, id: u32, stat
         items.iter().find(|app| app.id.unwrap_or(0) == id).ok_or_else(|| format!("Appearance with ID {} not found in {:?}", id, category))?
     };
 
-    Ok(format!("{:#?}", appearance))
+    let mut dump = format!("{:#?}", appearance);
+
+    // Fields from a newer client schema that prost could not decode (kept for save)
+    let category_field = match category {
+        AppearanceCategory::Objects => 1,
+        AppearanceCategory::Outfits => 2,
+        AppearanceCategory::Effects => 3,
+        AppearanceCategory::Missiles => 4,
+    };
+    let unknown_fields = state.appearance_unknown_fields.read();
+    if let Some(unknown) = unknown_fields.appearance(category_field, id) {
+        dump.push_str("\n\nUnknown fields (preserved on save):");
+        for line in describe_unknown_fields(unknown, APPEARANCE_MESSAGE) {
+            dump.push_str("\n  ");
+            dump.push_str(&line);
+        }
+    }
+
+    Ok(dump)
 }
 
 /// Batch version of get_complete_appearance - returns multiple items in one IPC call.
@@ -1,5 +1,6 @@
 use super::category_types::{AppearanceCategory, AppearanceItem};
 use super::helpers::{create_appearance_item_response, ensure_flags, get_items_by_category_mut, get_index_for_category, invalidate_search_cache};
+use super::journal::record_edit;
 use crate::core::protobuf::{Box as ProtoBoundingBox, FrameGroup as ProtoFrameGroup, SpriteInfo as ProtoSpriteInfo, SpritePhase as ProtoSpritePhase};
 use crate::state::AppState;
 use serde::Deserialize;
@@ -22,6 +23,7 @@ pub async fn add_frame_group(category: AppearanceCategory, id: u32, state: tauri
     } else {
         items.iter_mut().find(|app| app.id.unwrap_or(0) == id).ok_or_else(|| format!("Appearance with ID {} not found in {:?}", id, category))?
     };
+    let before = appearance.clone();
 
     if appearance.frame_group.len() >= 2 {
         return Err("An appearance can have at most 2 frame groups".to_string());
@@ -36,6 +38,8 @@ pub async fn add_frame_group(category: AppearanceCategory, id: u32, state: tauri
     state.preview_cache.remove(&cache_key);
     invalidate_search_cache(&state);
 
+    record_edit(&state, "Add frame group", &category, before, appearance);
+
     Ok(create_appearance_item_response(id, appearance))
 }
 
@@ -55,6 +59,7 @@ pub async fn remove_frame_group(category: AppearanceCategory, id: u32, frame_gro
     } else {
         items.iter_mut().find(|app| app.id.unwrap_or(0) == id).ok_or_else(|| format!("Appearance with ID {} not found in {:?}", id, category))?
     };
+    let before = appearance.clone();
 
     if appearance.frame_group.len() <= 1 {
         return Err("Cannot remove the only frame group".to_string());
@@ -69,6 +74,8 @@ pub async fn remove_frame_group(category: AppearanceCategory, id: u32, frame_gro
     state.preview_cache.remove(&cache_key);
     invalidate_search_cache(&state);
 
+    record_edit(&state, "Remove frame group", &category, before, appearance);
+
     Ok(create_appearance_item_response(id, appearance))
 }
 
@@ -103,6 +110,7 @@ pub async fn remove_appearance_sprites(category: AppearanceCategory, id: u32, up
     } else {
         items.iter_mut().find(|app| app.id.unwrap_or(0) == id).ok_or_else(|| format!("Appearance with ID {} not found in {:?}", id, category))?
     };
+    let before = appearance.clone();
 
     if update.frame_group_index >= appearance.frame_group.len() {
         return Err(format!("Frame group {} not found for appearance {}", update.frame_group_index, id));
@@ -139,6 +147,8 @@ pub async fn remove_appearance_sprites(category: AppearanceCategory, id: u32, up
     state.preview_cache.remove(&cache_key);
     invalidate_search_cache(&state);
 
+    record_edit(&state, "Remove sprites", &category, before, appearance);
+
     Ok(create_appearance_item_response(id, appearance))
 }
 
@@ -167,6 +177,7 @@ pub async fn append_appearance_sprites(category: AppearanceCategory, id: u32, up
     } else {
         items.iter_mut().find(|app| app.id.unwrap_or(0) == id).ok_or_else(|| format!("Appearance with ID {} not found in {:?}", id, category))?
     };
+    let before = appearance.clone();
 
     if update.frame_group_index >= appearance.frame_group.len() {
         return Err(format!("Frame group {} not found for appearance {}", update.frame_group_index, id));
@@ -196,6 +207,8 @@ pub async fn append_appearance_sprites(category: AppearanceCategory, id: u32, up
     state.preview_cache.remove(&cache_key);
     invalidate_search_cache(&state);
 
+    record_edit(&state, "Append sprites", &category, before, appearance);
+
     Ok(create_appearance_item_response(id, appearance))
 }
 
@@ -274,6 +287,7 @@ pub async fn update_appearance_texture_settings(category: AppearanceCategory, id
         // Fallback to linear search if index not found
         items.iter_mut().find(|app| app.id.unwrap_or(0) == id).ok_or_else(|| format!("Appearance with ID {} not found in {:?}", id, category))?
     };
+    let before = appearance.clone();
 
     if update.frame_group_index >= appearance.frame_group.len() {
         return Err(format!("Frame group {} not found for appearance {}", update.frame_group_index, id));
@@ -347,6 +361,8 @@ pub async fn update_appearance_texture_settings(category: AppearanceCategory, id
     state.sprite_cache.remove(&cache_key);
     state.preview_cache.remove(&cache_key);
 
+    record_edit(&state, "Edit texture settings", &category, before, appearance);
+
     Ok(create_appearance_item_response(id, appearance))
 }
 
@@ -371,6 +387,7 @@ pub async fn replace_appearance_sprites(category: AppearanceCategory, id: u32, u
     } else {
         items.iter_mut().find(|app| app.id.unwrap_or(0) == id).ok_or_else(|| format!("Appearance with ID {} not found in {:?}", id, category))?
     };
+    let before = appearance.clone();
 
     if update.frame_group_index >= appearance.frame_group.len() {
         return Err(format!("Frame group {} not found for appearance {}", update.frame_group_index, id));
@@ -419,6 +436,8 @@ pub async fn replace_appearance_sprites(category: AppearanceCategory, id: u32, u
     state.preview_cache.remove(&cache_key);
     invalidate_search_cache(&state);
 
+    record_edit(&state, "Replace sprites", &category, before, appearance);
+
     Ok(create_appearance_item_response(id, appearance))
 }
 
@@ -445,6 +464,7 @@ pub async fn update_appearance_name(category: AppearanceCategory, id: u32, new_n
     } else {
         items.iter_mut().find(|app| app.id.unwrap_or(0) == id).ok_or_else(|| format!("Appearance {} not found", id))?
     };
+    let before = appearance.clone();
 
     appearance.name = if new_name.trim().is_empty() {
         None
@@ -455,6 +475,8 @@ pub async fn update_appearance_name(category: AppearanceCategory, id: u32, new_n
     // Invalidate cache (name changed - affects search!)
     invalidate_search_cache(&state);
 
+    record_edit(&state, "Edit name", &category, before, appearance);
+
     Ok(create_appearance_item_response(id, appearance))
 }
 
@@ -481,6 +503,7 @@ pub async fn update_appearance_automap(category: AppearanceCategory, id: u32, co
     } else {
         items.iter_mut().find(|app| app.id.unwrap_or(0) == id).ok_or_else(|| format!("Appearance {} not found", id))?
     };
+    let before = appearance.clone();
 
     let flags = ensure_flags(appearance);
 
@@ -495,6 +518,8 @@ pub async fn update_appearance_automap(category: AppearanceCategory, id: u32, co
     // Invalidate cache (data changed)
     invalidate_search_cache(&state);
 
+    record_edit(&state, "Edit automap", &category, before, appearance);
+
     Ok(create_appearance_item_response(id, appearance))
 }
 
@@ -517,6 +542,7 @@ pub async fn update_appearance_hook(category: AppearanceCategory, id: u32, direc
     } else {
         items.iter_mut().find(|app| app.id.unwrap_or(0) == id).ok_or_else(|| format!("Appearance {} not found", id))?
     };
+    let before = appearance.clone();
 
     let flags = ensure_flags(appearance);
 
@@ -531,6 +557,8 @@ pub async fn update_appearance_hook(category: AppearanceCategory, id: u32, direc
     // Invalidate cache (data changed)
     invalidate_search_cache(&state);
 
+    record_edit(&state, "Edit hook", &category, before, appearance);
+
     Ok(create_appearance_item_response(id, appearance))
 }
 
@@ -553,6 +581,7 @@ pub async fn update_appearance_lenshelp(category: AppearanceCategory, id: u32, l
     } else {
         items.iter_mut().find(|app| app.id.unwrap_or(0) == id).ok_or_else(|| format!("Appearance {} not found", id))?
     };
+    let before = appearance.clone();
 
     let flags = ensure_flags(appearance);
 
@@ -567,6 +596,8 @@ pub async fn update_appearance_lenshelp(category: AppearanceCategory, id: u32, l
     // Invalidate cache (data changed)
     invalidate_search_cache(&state);
 
+    record_edit(&state, "Edit lenshelp", &category, before, appearance);
+
     Ok(create_appearance_item_response(id, appearance))
 }
 
@@ -589,6 +620,7 @@ pub async fn update_appearance_clothes(category: AppearanceCategory, id: u32, sl
     } else {
         items.iter_mut().find(|app| app.id.unwrap_or(0) == id).ok_or_else(|| format!("Appearance {} not found", id))?
     };
+    let before = appearance.clone();
 
     let flags = ensure_flags(appearance);
 
@@ -603,6 +635,8 @@ pub async fn update_appearance_clothes(category: AppearanceCategory, id: u32, sl
     // Invalidate cache (data changed)
     invalidate_search_cache(&state);
 
+    record_edit(&state, "Edit clothes", &category, before, appearance);
+
     Ok(create_appearance_item_response(id, appearance))
 }
 
@@ -625,6 +659,7 @@ pub async fn update_appearance_default_action(category: AppearanceCategory, id:
     } else {
         items.iter_mut().find(|app| app.id.unwrap_or(0) == id).ok_or_else(|| format!("Appearance {} not found", id))?
     };
+    let before = appearance.clone();
 
     let flags = ensure_flags(appearance);
 
@@ -639,6 +674,8 @@ pub async fn update_appearance_default_action(category: AppearanceCategory, id:
     // Invalidate cache (data changed)
     invalidate_search_cache(&state);
 
+    record_edit(&state, "Edit default action", &category, before, appearance);
+
     Ok(create_appearance_item_response(id, appearance))
 }
 
@@ -668,6 +705,7 @@ pub async fn update_appearance_market(
     } else {
         items.iter_mut().find(|app| app.id.unwrap_or(0) == id).ok_or_else(|| format!("Appearance {} not found", id))?
     };
+    let before = appearance.clone();
 
     let flags = ensure_flags(appearance);
 
@@ -686,6 +724,8 @@ pub async fn update_appearance_market(
     // Invalidate cache (data changed)
     invalidate_search_cache(&state);
 
+    record_edit(&state, "Edit market", &category, before, appearance);
+
     Ok(create_appearance_item_response(id, appearance))
 }
 
@@ -708,6 +748,7 @@ pub async fn update_appearance_bank(category: AppearanceCategory, id: u32, waypo
     } else {
         items.iter_mut().find(|app| app.id.unwrap_or(0) == id).ok_or_else(|| format!("Appearance {} not found", id))?
     };
+    let before = appearance.clone();
 
     let flags = ensure_flags(appearance);
 
@@ -722,6 +763,8 @@ pub async fn update_appearance_bank(category: AppearanceCategory, id: u32, waypo
     // Invalidate cache (data changed)
     invalidate_search_cache(&state);
 
+    record_edit(&state, "Edit bank", &category, before, appearance);
+
     Ok(create_appearance_item_response(id, appearance))
 }
 
@@ -744,6 +787,7 @@ pub async fn update_appearance_changed_to_expire(category: AppearanceCategory, i
     } else {
         items.iter_mut().find(|app| app.id.unwrap_or(0) == id).ok_or_else(|| format!("Appearance {} not found", id))?
     };
+    let before = appearance.clone();
 
     let flags = ensure_flags(appearance);
 
@@ -758,6 +802,8 @@ pub async fn update_appearance_changed_to_expire(category: AppearanceCategory, i
     // Invalidate cache (data changed)
     invalidate_search_cache(&state);
 
+    record_edit(&state, "Edit changed to expire", &category, before, appearance);
+
     Ok(create_appearance_item_response(id, appearance))
 }
 
@@ -780,6 +826,7 @@ pub async fn update_appearance_cyclopedia_item(category: AppearanceCategory, id:
     } else {
         items.iter_mut().find(|app| app.id.unwrap_or(0) == id).ok_or_else(|| format!("Appearance {} not found", id))?
     };
+    let before = appearance.clone();
 
     let flags = ensure_flags(appearance);
 
@@ -794,6 +841,8 @@ pub async fn update_appearance_cyclopedia_item(category: AppearanceCategory, id:
     // Invalidate cache (data changed)
     invalidate_search_cache(&state);
 
+    record_edit(&state, "Edit cyclopedia item", &category, before, appearance);
+
     Ok(create_appearance_item_response(id, appearance))
 }
 
@@ -816,6 +865,7 @@ pub async fn update_appearance_upgrade_classification(category: AppearanceCatego
     } else {
         items.iter_mut().find(|app| app.id.unwrap_or(0) == id).ok_or_else(|| format!("Appearance {} not found", id))?
     };
+    let before = appearance.clone();
 
     let flags = ensure_flags(appearance);
 
@@ -830,6 +880,8 @@ pub async fn update_appearance_upgrade_classification(category: AppearanceCatego
     // Invalidate cache (data changed)
     invalidate_search_cache(&state);
 
+    record_edit(&state, "Edit upgrade classification", &category, before, appearance);
+
     Ok(create_appearance_item_response(id, appearance))
 }
 
@@ -858,6 +910,7 @@ pub async fn update_appearance_skillwheel_gem(
     } else {
         items.iter_mut().find(|app| app.id.unwrap_or(0) == id).ok_or_else(|| format!("Appearance {} not found", id))?
     };
+    let before = appearance.clone();
 
     let flags = ensure_flags(appearance);
 
@@ -873,6 +926,8 @@ pub async fn update_appearance_skillwheel_gem(
     // Invalidate cache (data changed)
     invalidate_search_cache(&state);
 
+    record_edit(&state, "Edit skillwheel gem", &category, before, appearance);
+
     Ok(create_appearance_item_response(id, appearance))
 }
 
@@ -895,6 +950,7 @@ pub async fn update_appearance_imbueable(category: AppearanceCategory, id: u32,
     } else {
         items.iter_mut().find(|app| app.id.unwrap_or(0) == id).ok_or_else(|| format!("Appearance {} not found", id))?
     };
+    let before = appearance.clone();
 
     let flags = ensure_flags(appearance);
 
@@ -909,6 +965,8 @@ pub async fn update_appearance_imbueable(category: AppearanceCategory, id: u32,
     // Invalidate cache (data changed)
     invalidate_search_cache(&state);
 
+    record_edit(&state, "Edit imbueable", &category, before, appearance);
+
     Ok(create_appearance_item_response(id, appearance))
 }
 
@@ -931,6 +989,7 @@ pub async fn update_appearance_proficiency(category: AppearanceCategory, id: u32
     } else {
         items.iter_mut().find(|app| app.id.unwrap_or(0) == id).ok_or_else(|| format!("Appearance {} not found", id))?
     };
+    let before = appearance.clone();
 
     let flags = ensure_flags(appearance);
 
@@ -945,6 +1004,8 @@ pub async fn update_appearance_proficiency(category: AppearanceCategory, id: u32
     // Invalidate cache (data changed)
     invalidate_search_cache(&state);
 
+    record_edit(&state, "Edit proficiency", &category, before, appearance);
+
     Ok(create_appearance_item_response(id, appearance))
 }
 
@@ -974,6 +1035,7 @@ pub async fn update_appearance_transparency_level(category: AppearanceCategory,
     } else {
         items.iter_mut().find(|app| app.id.unwrap_or(0) == id).ok_or_else(|| format!("Appearance {} not found", id))?
     };
+    let before = appearance.clone();
 
     // Intentionally blank as transparencylevel has been removed from protobuf
     // This allows the frontend signature to stay the same until we clean it up
@@ -982,6 +1044,8 @@ pub async fn update_appearance_transparency_level(category: AppearanceCategory,
     // Invalidate cache (data changed)
     invalidate_search_cache(&state);
 
+    record_edit(&state, "Edit transparency level", &category, before, appearance);
+
     Ok(create_appearance_item_response(id, appearance))
 }
 
@@ -1004,6 +1068,7 @@ pub async fn update_appearance_weapon_type(category: AppearanceCategory, id: u32
     } else {
         items.iter_mut().find(|app| app.id.unwrap_or(0) == id).ok_or_else(|| format!("Appearance {} not found", id))?
     };
+    let before = appearance.clone();
 
     let flags = ensure_flags(appearance);
 
@@ -1013,6 +1078,8 @@ pub async fn update_appearance_weapon_type(category: AppearanceCategory, id: u32
     // Invalidate cache (data changed)
     invalidate_search_cache(&state);
 
+    record_edit(&state, "Edit weapon type", &category, before, appearance);
+
     Ok(create_appearance_item_response(id, appearance))
 }
 
@@ -1035,6 +1102,7 @@ pub async fn update_appearance_description(category: AppearanceCategory, id: u32
     } else {
         items.iter_mut().find(|app| app.id.unwrap_or(0) == id).ok_or_else(|| format!("Appearance {} not found", id))?
     };
+    let before = appearance.clone();
 
     appearance.description = if new_description.trim().is_empty() {
         None
@@ -1045,6 +1113,8 @@ pub async fn update_appearance_description(category: AppearanceCategory, id: u32
     // Invalidate cache (data changed)
     invalidate_search_cache(&state);
 
+    record_edit(&state, "Edit description", &category, before, appearance);
+
     Ok(create_appearance_item_response(id, appearance))
 }
 
@@ -1206,6 +1276,7 @@ pub async fn update_appearance_flag_bool(category: AppearanceCategory, id: u32,
     } else {
         items.iter_mut().find(|app| app.id.unwrap_or(0) == id).ok_or_else(|| format!("Appearance {} not found", id))?
     };
+    let before = appearance.clone();
 
     let flags = ensure_flags(appearance);
 
@@ -1214,6 +1285,8 @@ pub async fn update_appearance_flag_bool(category: AppearanceCategory, id: u32,
     // Invalidate cache (data changed)
     invalidate_search_cache(&state);
 
+    record_edit(&state, format!("Set flag {} = {}", flag, value), &category, before, appearance);
+
     Ok(create_appearance_item_response(id, appearance))
 }
 
@@ -1236,6 +1309,7 @@ pub async fn update_appearance_light(category: AppearanceCategory, id: u32, brig
     } else {
         items.iter_mut().find(|app| app.id.unwrap_or(0) == id).ok_or_else(|| format!("Appearance {} not found", id))?
     };
+    let before = appearance.clone();
 
     let flags = ensure_flags(appearance);
 
@@ -1251,6 +1325,8 @@ pub async fn update_appearance_light(category: AppearanceCategory, id: u32, brig
     // Invalidate cache (data changed)
     invalidate_search_cache(&state);
 
+    record_edit(&state, "Edit light", &category, before, appearance);
+
     Ok(create_appearance_item_response(id, appearance))
 }
 
@@ -1273,6 +1349,7 @@ pub async fn update_appearance_shift(category: AppearanceCategory, id: u32, x: O
     } else {
         items.iter_mut().find(|app| app.id.unwrap_or(0) == id).ok_or_else(|| format!("Appearance {} not found", id))?
     };
+    let before = appearance.clone();
 
     let flags = ensure_flags(appearance);
 
@@ -1288,6 +1365,8 @@ pub async fn update_appearance_shift(category: AppearanceCategory, id: u32, x: O
     // Invalidate cache (data changed)
     invalidate_search_cache(&state);
 
+    record_edit(&state, "Edit shift", &category, before, appearance);
+
     Ok(create_appearance_item_response(id, appearance))
 }
 
@@ -1310,6 +1389,7 @@ pub async fn update_appearance_height(category: AppearanceCategory, id: u32, ele
     } else {
         items.iter_mut().find(|app| app.id.unwrap_or(0) == id).ok_or_else(|| format!("Appearance {} not found", id))?
     };
+    let before = appearance.clone();
 
     let flags = ensure_flags(appearance);
 
@@ -1324,6 +1404,8 @@ pub async fn update_appearance_height(category: AppearanceCategory, id: u32, ele
     // Invalidate cache (data changed)
     invalidate_search_cache(&state);
 
+    record_edit(&state, "Edit height", &category, before, appearance);
+
     Ok(create_appearance_item_response(id, appearance))
 }
 
@@ -1346,6 +1428,7 @@ pub async fn update_appearance_write(category: AppearanceCategory, id: u32, max_
     } else {
         items.iter_mut().find(|app| app.id.unwrap_or(0) == id).ok_or_else(|| format!("Appearance {} not found", id))?
     };
+    let before = appearance.clone();
 
     let flags = ensure_flags(appearance);
 
@@ -1360,6 +1443,8 @@ pub async fn update_appearance_write(category: AppearanceCategory, id: u32, max_
     // Invalidate cache (data changed)
     invalidate_search_cache(&state);
 
+    record_edit(&state, "Edit write", &category, before, appearance);
+
     Ok(create_appearance_item_response(id, appearance))
 }
 
@@ -1382,6 +1467,7 @@ pub async fn update_appearance_write_once(category: AppearanceCategory, id: u32,
     } else {
         items.iter_mut().find(|app| app.id.unwrap_or(0) == id).ok_or_else(|| format!("Appearance {} not found", id))?
     };
+    let before = appearance.clone();
 
     let flags = ensure_flags(appearance);
 
@@ -1396,6 +1482,8 @@ pub async fn update_appearance_write_once(category: AppearanceCategory, id: u32,
     // Invalidate cache (data changed)
     invalidate_search_cache(&state);
 
+    record_edit(&state, "Edit write once", &category, before, appearance);
+
     Ok(create_appearance_item_response(id, appearance))
 }
 
@@ -1417,11 +1505,14 @@ pub async fn update_appearance_minimum_level(category: AppearanceCategory, id: u
     } else {
         items.iter_mut().find(|app| app.id.unwrap_or(0) == id).ok_or_else(|| format!("Appearance {} not found", id))?
     };
+    let before = appearance.clone();
 
     let flags = ensure_flags(appearance);
     flags.minimum_level = minimum_level;
 
     invalidate_search_cache(&state);
+    record_edit(&state, "Edit minimum level", &category, before, appearance);
+
     Ok(create_appearance_item_response(id, appearance))
 }
 
@@ -1443,11 +1534,14 @@ pub async fn update_appearance_restrict_to_vocation(category: AppearanceCategory
     } else {
         items.iter_mut().find(|app| app.id.unwrap_or(0) == id).ok_or_else(|| format!("Appearance {} not found", id))?
     };
+    let before = appearance.clone();
 
     let flags = ensure_flags(appearance);
     flags.restrict_to_vocation = vocations;
 
     invalidate_search_cache(&state);
+    record_edit(&state, "Edit restrict to vocation", &category, before, appearance);
+
     Ok(create_appearance_item_response(id, appearance))
 }
 
@@ -1479,6 +1573,7 @@ pub async fn update_appearance_npc_sale_data(category: AppearanceCategory, id: u
     } else {
         items.iter_mut().find(|app| app.id.unwrap_or(0) == id).ok_or_else(|| format!("Appearance {} not found", id))?
     };
+    let before = appearance.clone();
 
     let flags = ensure_flags(appearance);
 
@@ -1495,5 +1590,7 @@ pub async fn update_appearance_npc_sale_data(category: AppearanceCategory, id: u
     }
 
     invalidate_search_cache(&state);
+    record_edit(&state, "Edit npc sale data", &category, before, appearance);
+
     Ok(create_appearance_item_response(id, appearance))
 }
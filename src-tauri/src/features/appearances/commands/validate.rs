@@ -0,0 +1,252 @@
+// Asset bundle integrity check
+// Walks every appearance and cross-checks it against the sprite catalog and the
+// object list, using the per-field validators from core::validation.
+
+use super::category_types::AppearanceCategory;
+use super::helpers::get_items_by_category;
+use crate::core::protobuf::{Appearance, Appearances};
+use crate::core::validation::validate_sprite_info_count;
+use crate::state::AppState;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use tauri::State;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AssetIssueKind {
+    /// Sprite id not covered by any catalog entry (or imported sprite).
+    MissingSprite,
+    /// Sprite id count doesn't match pattern_width*height*depth*layers*phases.
+    SpriteCountMismatch,
+    /// Market trade_as/show_as or changed-to-expire points at a missing object.
+    DanglingReference,
+    /// The same id appears more than once in a category.
+    DuplicateId,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AssetIssue {
+    pub kind: AssetIssueKind,
+    pub category: AppearanceCategory,
+    pub appearance_id: u32,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AssetValidationReport {
+    pub checked_appearances: usize,
+    /// False when no sprite catalog was loaded; missing-sprite checks were skipped.
+    pub sprite_catalog_checked: bool,
+    pub issues: Vec<AssetIssue>,
+}
+
+impl AssetValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// Validates the whole bundle. `sprite_exists` is `None` to skip sprite coverage checks.
+pub fn validate_appearances(appearances: &Appearances, sprite_exists: Option<&dyn Fn(u32) -> bool>) -> AssetValidationReport {
+    let object_ids: HashSet<u32> = appearances.object.iter().filter_map(|a| a.id).collect();
+    let mut issues = Vec::new();
+    let mut checked_appearances = 0;
+
+    for category in [AppearanceCategory::Objects, AppearanceCategory::Outfits, AppearanceCategory::Effects, AppearanceCategory::Missiles] {
+        let items = get_items_by_category(appearances, &category);
+        checked_appearances += items.len();
+
+        let mut seen: HashMap<u32, usize> = HashMap::with_capacity(items.len());
+        for appearance in items {
+            let id = appearance.id.unwrap_or(0);
+            *seen.entry(id).or_insert(0) += 1;
+
+            check_sprites(appearance, &category, sprite_exists, &mut issues);
+            check_references(appearance, &category, &object_ids, &mut issues);
+        }
+
+        let mut duplicates: Vec<(u32, usize)> = seen.into_iter().filter(|(_, count)| *count > 1).collect();
+        duplicates.sort_unstable();
+        for (id, count) in duplicates {
+            issues.push(AssetIssue {
+                kind: AssetIssueKind::DuplicateId,
+                category: category.clone(),
+                appearance_id: id,
+                message: format!("Id {} appears {} times", id, count),
+            });
+        }
+    }
+
+    AssetValidationReport {
+        checked_appearances,
+        sprite_catalog_checked: sprite_exists.is_some(),
+        issues,
+    }
+}
+
+fn check_sprites(appearance: &Appearance, category: &AppearanceCategory, sprite_exists: Option<&dyn Fn(u32) -> bool>, issues: &mut Vec<AssetIssue>) {
+    let id = appearance.id.unwrap_or(0);
+
+    for (group_index, group) in appearance.frame_group.iter().enumerate() {
+        let Some(info) = group.sprite_info.as_ref() else {
+            continue;
+        };
+
+        let phases = info.animation.as_ref().map(|a| a.sprite_phase.len() as u32).filter(|count| *count > 0).unwrap_or(1);
+        if let Err(e) =
+            validate_sprite_info_count(info.pattern_width.unwrap_or(1), info.pattern_height.unwrap_or(1), info.pattern_depth.unwrap_or(1), info.layers.unwrap_or(1), phases, info.sprite_id.len())
+        {
+            issues.push(AssetIssue {
+                kind: AssetIssueKind::SpriteCountMismatch,
+                category: category.clone(),
+                appearance_id: id,
+                message: format!("Frame group {}: {}", group_index, e),
+            });
+        }
+
+        if let Some(exists) = sprite_exists {
+            // Slot 0 is a valid "empty" sprite reference in the client files.
+            let mut missing: Vec<u32> = info.sprite_id.iter().copied().filter(|sprite_id| *sprite_id != 0 && !exists(*sprite_id)).collect();
+            if !missing.is_empty() {
+                missing.sort_unstable();
+                missing.dedup();
+                issues.push(AssetIssue {
+                    kind: AssetIssueKind::MissingSprite,
+                    category: category.clone(),
+                    appearance_id: id,
+                    message: format!("Frame group {}: sprite ids not in catalog: {:?}", group_index, missing),
+                });
+            }
+        }
+    }
+}
+
+fn check_references(appearance: &Appearance, category: &AppearanceCategory, object_ids: &HashSet<u32>, issues: &mut Vec<AssetIssue>) {
+    let Some(flags) = appearance.flags.as_ref() else {
+        return;
+    };
+
+    let mut references: Vec<(&str, Option<u32>)> = Vec::new();
+    if let Some(market) = flags.market.as_ref() {
+        references.push(("market.trade_as_object_id", market.trade_as_object_id));
+        references.push(("market.show_as_object_id", market.show_as_object_id));
+    }
+    if let Some(cte) = flags.changedtoexpire.as_ref() {
+        references.push(("changedtoexpire.former_object_typeid", cte.former_object_typeid));
+    }
+
+    for (field, target) in references {
+        match target {
+            Some(target) if target != 0 && !object_ids.contains(&target) => issues.push(AssetIssue {
+                kind: AssetIssueKind::DanglingReference,
+                category: category.clone(),
+                appearance_id: appearance.id.unwrap_or(0),
+                message: format!("{} points to missing object {}", field, target),
+            }),
+            _ => {}
+        }
+    }
+}
+
+/// Runs [`validate_appearances`] against the loaded state. Sprites staged by an
+/// import (not yet compiled into the catalog) count as present.
+pub fn validate_loaded_assets(state: &AppState) -> Result<AssetValidationReport, String> {
+    let appearances_lock = state.appearances.read();
+    let appearances = appearances_lock.as_ref().ok_or_else(|| "No appearances loaded".to_string())?;
+
+    let loader_lock = state.sprite_loader.read();
+    let report = match loader_lock.as_ref() {
+        Some(loader) => {
+            let exists = |sprite_id: u32| loader.contains_sprite(sprite_id) || state.imported_sprites.contains_key(&sprite_id);
+            validate_appearances(appearances, Some(&exists))
+        }
+        None => validate_appearances(appearances, None),
+    };
+    Ok(report)
+}
+
+/// Cross-check the loaded appearances against the sprite catalog before shipping a client.
+#[tauri::command]
+pub async fn validate_assets(state: State<'_, AppState>) -> Result<AssetValidationReport, String> {
+    validate_loaded_assets(&state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::protobuf::{AppearanceFlagMarket, AppearanceFlags, FrameGroup, SpriteAnimation, SpriteInfo, SpritePhase};
+
+    fn object(id: u32, sprite_ids: Vec<u32>) -> Appearance {
+        Appearance {
+            id: Some(id),
+            frame_group: vec![FrameGroup {
+                sprite_info: Some(SpriteInfo {
+                    pattern_width: Some(1),
+                    pattern_height: Some(1),
+                    pattern_depth: Some(1),
+                    layers: Some(1),
+                    sprite_id: sprite_ids,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn kinds(report: &AssetValidationReport) -> Vec<(AssetIssueKind, u32)> {
+        report.issues.iter().map(|i| (i.kind, i.appearance_id)).collect()
+    }
+
+    #[test]
+    fn clean_bundle_has_no_issues() {
+        let appearances = Appearances {
+            object: vec![object(100, vec![1]), object(101, vec![2])],
+            ..Default::default()
+        };
+        let exists = |id: u32| id <= 2;
+        assert!(validate_appearances(&appearances, Some(&exists)).is_valid());
+    }
+
+    #[test]
+    fn flags_missing_sprites_and_count_mismatch() {
+        let mut animated = object(101, vec![1, 2]);
+        animated.frame_group[0].sprite_info.as_mut().unwrap().animation = Some(SpriteAnimation {
+            sprite_phase: vec![SpritePhase::default(); 3],
+            ..Default::default()
+        });
+        let appearances = Appearances {
+            object: vec![object(100, vec![1, 99]), animated],
+            ..Default::default()
+        };
+        let exists = |id: u32| id < 10;
+
+        let report = validate_appearances(&appearances, Some(&exists));
+        assert_eq!(kinds(&report), vec![(AssetIssueKind::SpriteCountMismatch, 100), (AssetIssueKind::MissingSprite, 100), (AssetIssueKind::SpriteCountMismatch, 101)]);
+
+        let without_catalog = validate_appearances(&appearances, None);
+        assert!(!without_catalog.sprite_catalog_checked);
+        assert!(without_catalog.issues.iter().all(|i| i.kind != AssetIssueKind::MissingSprite));
+    }
+
+    #[test]
+    fn flags_dangling_references_and_duplicates() {
+        let mut item = object(100, vec![1]);
+        item.flags = Some(AppearanceFlags {
+            market: Some(AppearanceFlagMarket {
+                trade_as_object_id: Some(100),
+                show_as_object_id: Some(555),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        let appearances = Appearances {
+            object: vec![item],
+            outfit: vec![object(7, vec![1]), object(7, vec![1])],
+            ..Default::default()
+        };
+
+        let report = validate_appearances(&appearances, None);
+        assert_eq!(kinds(&report), vec![(AssetIssueKind::DanglingReference, 100), (AssetIssueKind::DuplicateId, 7)]);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::core::protobuf::unknown_fields::{capture_unknown_fields, inject_unknown_fields, UnknownFields, APPEARANCES_MESSAGE};
 use crate::core::protobuf::Appearances;
 use anyhow::{Context, Result};
 use prost::Message;
@@ -6,8 +7,31 @@ use std::path::Path;
 
 /// Load and parse an appearances.dat file
 pub fn load_appearances<P: AsRef<Path>>(path: P) -> Result<Appearances> {
-    let path = path.as_ref();
+    read_appearances(path.as_ref()).map(|(appearances, _)| appearances)
+}
+
+/// Like [`load_appearances`], also returning the fields this build's schema does
+/// not know (newer client versions) so [`encode_appearances`] can write them back.
+pub fn load_appearances_preserving<P: AsRef<Path>>(path: P) -> Result<(Appearances, UnknownFields)> {
+    let (appearances, data) = read_appearances(path.as_ref())?;
+    let unknown = capture_unknown_fields(&data, APPEARANCES_MESSAGE).unwrap_or_else(|e| {
+        log::warn!("Could not scan appearances for unknown fields: {}", e);
+        UnknownFields::default()
+    });
+    if !unknown.is_empty() {
+        log::info!("Preserving {} unknown protobuf fields", unknown.count());
+    }
+    Ok((appearances, unknown))
+}
+
+/// Encodes `appearances`, re-inserting the `unknown` fields captured on load.
+pub fn encode_appearances(appearances: &Appearances, unknown: &UnknownFields) -> Result<Vec<u8>> {
+    let encoded = appearances.encode_to_vec();
+    inject_unknown_fields(&encoded, APPEARANCES_MESSAGE, unknown).context("Failed to restore unknown protobuf fields")
+}
 
+/// Decodes the file, returning the appearances and the protobuf bytes they came from.
+fn read_appearances(path: &Path) -> Result<(Appearances, Vec<u8>)> {
     log::info!("Loading appearances file: {:?}", path);
 
     // Read the file
@@ -19,7 +43,7 @@ pub fn load_appearances<P: AsRef<Path>>(path: P) -> Result<Appearances> {
     match Appearances::decode(&data[..]) {
         Ok(appearances) => {
             log::info!("Appearances decoded directly without decompression");
-            return Ok(appearances);
+            return Ok((appearances, data));
         }
         Err(e) => {
             log::warn!("Direct protobuf decode failed: {}. Trying LZMA/XZ decompress fallback...", e);
@@ -40,7 +64,7 @@ pub fn load_appearances<P: AsRef<Path>>(path: P) -> Result<Appearances> {
 
     log::info!("Successfully parsed appearances: {} objects, {} outfits, {} effects, {} missiles", object_count, outfit_count, effect_count, missile_count);
 
-    Ok(appearances)
+    Ok((appearances, decompressed))
 }
 
 #[inline]
@@ -0,0 +1,832 @@
+// Legacy Tibia.dat codec (10.98 and 12.x layouts, as read by OTClient)
+// Things are a list of attribute bytes terminated by 0xFF followed by the
+// texture block. Flags map onto the protobuf `AppearanceFlags` so the rest of
+// the editor can work with legacy files unchanged. The 12.x layout is 10.98
+// plus the item flags added since (upgrade classification, wear-out, expiry).
+
+use crate::core::protobuf::{
+    AnimationLoopType, Appearance, AppearanceFlagAutomap, AppearanceFlagBank, AppearanceFlagClothes, AppearanceFlagDefaultAction, AppearanceFlagHeight, AppearanceFlagHook, AppearanceFlagLenshelp,
+    AppearanceFlagLight, AppearanceFlagMarket, AppearanceFlagShift, AppearanceFlagUpgradeClassification, AppearanceFlagWrite, AppearanceFlagWriteOnce, AppearanceFlags, Appearances, FixedFrameGroup,
+    FrameGroup, HookType, SpriteAnimation, SpriteInfo, SpritePhase,
+};
+use anyhow::{anyhow, bail, Context, Result};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::io::{Cursor, Read};
+
+/// First item id in a legacy dat; ids below are reserved.
+pub const LEGACY_FIRST_ITEM_ID: u32 = 100;
+/// Legacy sprites are always 32x32 tiles; bigger things use several tiles.
+pub const LEGACY_TILE_SIZE: u32 = 32;
+
+const LAST_FLAG: u8 = 0xFF;
+
+/// Which flags a legacy dat may carry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LegacyDatLayout {
+    V1098,
+    /// 10.98 plus the 12.x item flags (0x27-0x2B).
+    V12,
+}
+
+/// Client version plus the signatures written into the dat/spr headers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LegacyClientVersion {
+    pub version: u32,
+    pub layout: LegacyDatLayout,
+    pub dat_signature: u32,
+    pub spr_signature: u32,
+}
+
+impl LegacyClientVersion {
+    /// Built-in signatures for `version`: the official 10.98 client's, and for
+    /// 12.00-12.99 (which never shipped a Tibia.dat) the version number itself in
+    /// both headers, so [`LegacyClientVersion::from_dat_signature`] recognises
+    /// our own exports.
+    pub fn known(version: u32) -> Option<Self> {
+        match version {
+            1098 => Some(Self {
+                version,
+                layout: LegacyDatLayout::V1098,
+                dat_signature: 0x42A3,
+                spr_signature: 0x57BB_D603,
+            }),
+            1200..=1299 => Some(Self {
+                version,
+                layout: LegacyDatLayout::V12,
+                dat_signature: version,
+                spr_signature: version,
+            }),
+            _ => None,
+        }
+    }
+
+    /// The built-in version whose dat signature is `signature`.
+    pub fn from_dat_signature(signature: u32) -> Option<Self> {
+        Self::known(1098).filter(|client| client.dat_signature == signature).or_else(|| Self::known(signature).filter(|client| client.layout == LegacyDatLayout::V12))
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LegacyAnimation {
+    pub synchronized: bool,
+    /// 0 = infinite, -1 = ping-pong, > 0 = counted.
+    pub loop_count: i32,
+    pub start_frame: i8,
+    pub durations: Vec<(u32, u32)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LegacyFrameGroup {
+    /// 0 = idle, 1 = moving (only written for outfits).
+    pub group_type: u8,
+    pub width: u8,
+    pub height: u8,
+    pub exact_size: u8,
+    pub layers: u8,
+    pub pattern_x: u8,
+    pub pattern_y: u8,
+    pub pattern_z: u8,
+    pub frames: u8,
+    pub animation: Option<LegacyAnimation>,
+    /// `width * height` tile ids per slot, in client order.
+    pub sprite_ids: Vec<u32>,
+}
+
+impl Default for LegacyFrameGroup {
+    fn default() -> Self {
+        Self {
+            group_type: 0,
+            width: 1,
+            height: 1,
+            exact_size: LEGACY_TILE_SIZE as u8,
+            layers: 1,
+            pattern_x: 1,
+            pattern_y: 1,
+            pattern_z: 1,
+            frames: 1,
+            animation: None,
+            sprite_ids: vec![0],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LegacyThing {
+    pub id: u32,
+    pub flags: AppearanceFlags,
+    /// Market name (the only text a legacy dat carries).
+    pub name: Option<Vec<u8>>,
+    /// Market vocation bitmask (bit `n - 1` = vocation `n`) and minimum level;
+    /// the protobuf market flag has no room for either.
+    pub market_vocations: u16,
+    pub market_level: u16,
+    pub groups: Vec<LegacyFrameGroup>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LegacyDat {
+    pub signature: u32,
+    pub items: Vec<LegacyThing>,
+    pub outfits: Vec<LegacyThing>,
+    pub effects: Vec<LegacyThing>,
+    pub missiles: Vec<LegacyThing>,
+}
+
+// ── Reading ─────────────────────────────────────────────────────────────────
+
+/// Reads either layout; the 12.x flags never occur in a 10.98 file.
+pub fn read_legacy_dat(data: &[u8]) -> Result<LegacyDat> {
+    let mut cursor = Cursor::new(data);
+    let signature = cursor.read_u32::<LittleEndian>().context("Failed to read dat signature")?;
+    let item_count = cursor.read_u16::<LittleEndian>()? as u32;
+    let outfit_count = cursor.read_u16::<LittleEndian>()? as u32;
+    let effect_count = cursor.read_u16::<LittleEndian>()? as u32;
+    let missile_count = cursor.read_u16::<LittleEndian>()? as u32;
+
+    let mut read_range = |first: u32, last: u32, outfit: bool| -> Result<Vec<LegacyThing>> {
+        (first..=last).map(|id| read_thing(&mut cursor, id, outfit).with_context(|| format!("Failed to read thing {}", id))).collect()
+    };
+    let items = read_range(LEGACY_FIRST_ITEM_ID, item_count, false)?;
+    let outfits = read_range(1, outfit_count, true)?;
+    let effects = read_range(1, effect_count, false)?;
+    let missiles = read_range(1, missile_count, false)?;
+
+    Ok(LegacyDat {
+        signature,
+        items,
+        outfits,
+        effects,
+        missiles,
+    })
+}
+
+fn read_thing(cursor: &mut Cursor<&[u8]>, id: u32, outfit: bool) -> Result<LegacyThing> {
+    let mut flags = AppearanceFlags::default();
+    let mut name = None;
+    let mut market_vocations = 0;
+    let mut market_level = 0;
+
+    loop {
+        let flag = cursor.read_u8()?;
+        match flag {
+            LAST_FLAG => break,
+            0x00 => {
+                flags.bank = Some(AppearanceFlagBank {
+                    waypoints: Some(cursor.read_u16::<LittleEndian>()? as u32),
+                })
+            }
+            0x01 => flags.clip = Some(true),
+            0x02 => flags.bottom = Some(true),
+            0x03 => flags.top = Some(true),
+            0x04 => flags.container = Some(true),
+            0x05 => flags.cumulative = Some(true),
+            0x06 => flags.forceuse = Some(true),
+            0x07 => flags.multiuse = Some(true),
+            0x08 => {
+                flags.write = Some(AppearanceFlagWrite {
+                    max_text_length: Some(cursor.read_u16::<LittleEndian>()? as u32),
+                })
+            }
+            0x09 => {
+                flags.write_once = Some(AppearanceFlagWriteOnce {
+                    max_text_length_once: Some(cursor.read_u16::<LittleEndian>()? as u32),
+                })
+            }
+            0x0A => flags.liquidcontainer = Some(true),
+            0x0B => flags.liquidpool = Some(true),
+            0x0C => flags.unpass = Some(true),
+            0x0D => flags.unmove = Some(true),
+            0x0E => flags.unsight = Some(true),
+            0x0F => flags.avoid = Some(true),
+            0x10 => flags.no_movement_animation = Some(true),
+            0x11 => flags.take = Some(true),
+            0x12 => flags.hang = Some(true),
+            0x13 => {
+                flags.hook = Some(AppearanceFlagHook {
+                    direction: Some(HookType::South as i32),
+                })
+            }
+            0x14 => {
+                flags.hook = Some(AppearanceFlagHook {
+                    direction: Some(HookType::East as i32),
+                })
+            }
+            0x15 => flags.rotate = Some(true),
+            0x16 => {
+                let brightness = cursor.read_u16::<LittleEndian>()? as u32;
+                let color = cursor.read_u16::<LittleEndian>()? as u32;
+                flags.light = Some(AppearanceFlagLight {
+                    brightness: Some(brightness),
+                    color: Some(color),
+                });
+            }
+            0x17 => flags.dont_hide = Some(true),
+            0x18 => flags.translucent = Some(true),
+            0x19 => {
+                let x = cursor.read_u16::<LittleEndian>()? as u32;
+                let y = cursor.read_u16::<LittleEndian>()? as u32;
+                flags.shift = Some(AppearanceFlagShift {
+                    x: Some(x),
+                    y: Some(y),
+                });
+            }
+            0x1A => {
+                flags.height = Some(AppearanceFlagHeight {
+                    elevation: Some(cursor.read_u16::<LittleEndian>()? as u32),
+                })
+            }
+            0x1B => flags.lying_object = Some(true),
+            0x1C => flags.animate_always = Some(true),
+            0x1D => {
+                flags.automap = Some(AppearanceFlagAutomap {
+                    color: Some(cursor.read_u16::<LittleEndian>()? as u32),
+                })
+            }
+            0x1E => {
+                flags.lenshelp = Some(AppearanceFlagLenshelp {
+                    id: Some(cursor.read_u16::<LittleEndian>()? as u32),
+                })
+            }
+            0x1F => flags.fullbank = Some(true),
+            0x20 => flags.ignore_look = Some(true),
+            0x21 => {
+                flags.clothes = Some(AppearanceFlagClothes {
+                    slot: Some(cursor.read_u16::<LittleEndian>()? as u32),
+                })
+            }
+            0x22 => {
+                let category = cursor.read_u16::<LittleEndian>()? as i32;
+                let trade_as = cursor.read_u16::<LittleEndian>()? as u32;
+                let show_as = cursor.read_u16::<LittleEndian>()? as u32;
+                let name_len = cursor.read_u16::<LittleEndian>()? as usize;
+                let mut market_name = vec![0u8; name_len];
+                cursor.read_exact(&mut market_name)?;
+                market_vocations = cursor.read_u16::<LittleEndian>()?;
+                market_level = cursor.read_u16::<LittleEndian>()?;
+                flags.market = Some(AppearanceFlagMarket {
+                    category: Some(category),
+                    trade_as_object_id: Some(trade_as),
+                    show_as_object_id: Some(show_as),
+                });
+                name = Some(market_name);
+            }
+            0x23 => {
+                flags.default_action = Some(AppearanceFlagDefaultAction {
+                    action: Some(cursor.read_u16::<LittleEndian>()? as i32),
+                })
+            }
+            0x24 => flags.wrap = Some(true),
+            0x25 => flags.unwrap = Some(true),
+            0x26 => flags.topeffect = Some(true),
+            0x27 => {
+                flags.upgradeclassification = Some(AppearanceFlagUpgradeClassification {
+                    upgrade_classification: Some(cursor.read_u16::<LittleEndian>()? as u32),
+                })
+            }
+            0x28 => flags.wearout = Some(true),
+            0x29 => flags.clockexpire = Some(true),
+            0x2A => flags.expire = Some(true),
+            0x2B => flags.expirestop = Some(true),
+            0xFE => flags.usable = Some(true),
+            other => bail!("Unknown flag 0x{:02X}", other),
+        }
+    }
+
+    let group_count = if outfit {
+        cursor.read_u8()?
+    } else {
+        1
+    };
+    let mut groups = Vec::with_capacity(group_count as usize);
+    for _ in 0..group_count {
+        let group_type = if outfit {
+            cursor.read_u8()?
+        } else {
+            0
+        };
+        groups.push(read_frame_group(cursor, group_type)?);
+    }
+
+    Ok(LegacyThing {
+        id,
+        flags,
+        name,
+        market_vocations,
+        market_level,
+        groups,
+    })
+}
+
+fn read_frame_group(cursor: &mut Cursor<&[u8]>, group_type: u8) -> Result<LegacyFrameGroup> {
+    let width = cursor.read_u8()?;
+    let height = cursor.read_u8()?;
+    let exact_size = if width > 1 || height > 1 {
+        cursor.read_u8()?
+    } else {
+        LEGACY_TILE_SIZE as u8
+    };
+    let layers = cursor.read_u8()?;
+    let pattern_x = cursor.read_u8()?;
+    let pattern_y = cursor.read_u8()?;
+    let pattern_z = cursor.read_u8()?;
+    let frames = cursor.read_u8()?;
+
+    let animation = if frames > 1 {
+        let synchronized = cursor.read_u8()? == 1;
+        let loop_count = cursor.read_i32::<LittleEndian>()?;
+        let start_frame = cursor.read_i8()?;
+        let durations = (0..frames).map(|_| Ok((cursor.read_u32::<LittleEndian>()?, cursor.read_u32::<LittleEndian>()?))).collect::<Result<Vec<_>>>()?;
+        Some(LegacyAnimation {
+            synchronized,
+            loop_count,
+            start_frame,
+            durations,
+        })
+    } else {
+        None
+    };
+
+    let count = width as usize * height as usize * layers as usize * pattern_x as usize * pattern_y as usize * pattern_z as usize * frames as usize;
+    let sprite_ids = (0..count).map(|_| cursor.read_u32::<LittleEndian>()).collect::<std::io::Result<Vec<u32>>>()?;
+
+    Ok(LegacyFrameGroup {
+        group_type,
+        width,
+        height,
+        exact_size,
+        layers,
+        pattern_x,
+        pattern_y,
+        pattern_z,
+        frames,
+        animation,
+        sprite_ids,
+    })
+}
+
+// ── Writing ─────────────────────────────────────────────────────────────────
+
+/// Serializes a legacy dat. Every category must be contiguous from its first id.
+/// Flags `layout` has no code for are dropped.
+pub fn write_legacy_dat(dat: &LegacyDat, layout: LegacyDatLayout) -> Result<Vec<u8>> {
+    let last_id = |things: &[LegacyThing], first: u32| -> Result<u16> {
+        for (offset, thing) in things.iter().enumerate() {
+            if thing.id != first + offset as u32 {
+                bail!("Thing ids must be contiguous from {}: found {} at position {}", first, thing.id, offset);
+            }
+        }
+        let last = (first + things.len() as u32).saturating_sub(1);
+        u16::try_from(last).map_err(|_| anyhow!("Id {} does not fit a legacy dat", last))
+    };
+
+    let mut out = Vec::new();
+    out.write_u32::<LittleEndian>(dat.signature)?;
+    out.write_u16::<LittleEndian>(if dat.items.is_empty() {
+        LEGACY_FIRST_ITEM_ID as u16 - 1
+    } else {
+        last_id(&dat.items, LEGACY_FIRST_ITEM_ID)?
+    })?;
+    out.write_u16::<LittleEndian>(last_id(&dat.outfits, 1)?)?;
+    out.write_u16::<LittleEndian>(last_id(&dat.effects, 1)?)?;
+    out.write_u16::<LittleEndian>(last_id(&dat.missiles, 1)?)?;
+
+    for thing in &dat.items {
+        write_thing(&mut out, thing, false, layout)?;
+    }
+    for thing in &dat.outfits {
+        write_thing(&mut out, thing, true, layout)?;
+    }
+    for thing in dat.effects.iter().chain(&dat.missiles) {
+        write_thing(&mut out, thing, false, layout)?;
+    }
+    Ok(out)
+}
+
+fn write_u16_flag(out: &mut Vec<u8>, flag: u8, value: Option<u32>) -> Result<()> {
+    out.push(flag);
+    out.write_u16::<LittleEndian>(value.unwrap_or(0).min(u16::MAX as u32) as u16)?;
+    Ok(())
+}
+
+fn write_thing(out: &mut Vec<u8>, thing: &LegacyThing, outfit: bool, layout: LegacyDatLayout) -> Result<()> {
+    let f = &thing.flags;
+    let set = |value: Option<bool>| value == Some(true);
+
+    if let Some(bank) = &f.bank {
+        write_u16_flag(out, 0x00, bank.waypoints)?;
+    }
+    for (value, code) in [(f.clip, 0x01), (f.bottom, 0x02), (f.top, 0x03), (f.container, 0x04), (f.cumulative, 0x05), (f.forceuse, 0x06), (f.multiuse, 0x07)] {
+        if set(value) {
+            out.push(code);
+        }
+    }
+    if let Some(write) = &f.write {
+        write_u16_flag(out, 0x08, write.max_text_length)?;
+    }
+    if let Some(write_once) = &f.write_once {
+        write_u16_flag(out, 0x09, write_once.max_text_length_once)?;
+    }
+    for (value, code) in
+        [(f.liquidcontainer, 0x0A), (f.liquidpool, 0x0B), (f.unpass, 0x0C), (f.unmove, 0x0D), (f.unsight, 0x0E), (f.avoid, 0x0F), (f.no_movement_animation, 0x10), (f.take, 0x11), (f.hang, 0x12)]
+    {
+        if set(value) {
+            out.push(code);
+        }
+    }
+    if let Some(hook) = &f.hook {
+        match hook.direction.and_then(|d| HookType::try_from(d).ok()) {
+            Some(HookType::South) => out.push(0x13),
+            Some(HookType::East) => out.push(0x14),
+            None => {}
+        }
+    }
+    if set(f.rotate) {
+        out.push(0x15);
+    }
+    if let Some(light) = &f.light {
+        write_u16_flag(out, 0x16, light.brightness)?;
+        out.write_u16::<LittleEndian>(light.color.unwrap_or(0).min(u16::MAX as u32) as u16)?;
+    }
+    if set(f.dont_hide) {
+        out.push(0x17);
+    }
+    if set(f.translucent) {
+        out.push(0x18);
+    }
+    if let Some(shift) = &f.shift {
+        write_u16_flag(out, 0x19, shift.x)?;
+        out.write_u16::<LittleEndian>(shift.y.unwrap_or(0).min(u16::MAX as u32) as u16)?;
+    }
+    if let Some(height) = &f.height {
+        write_u16_flag(out, 0x1A, height.elevation)?;
+    }
+    if set(f.lying_object) {
+        out.push(0x1B);
+    }
+    if set(f.animate_always) {
+        out.push(0x1C);
+    }
+    if let Some(automap) = &f.automap {
+        write_u16_flag(out, 0x1D, automap.color)?;
+    }
+    if let Some(lenshelp) = &f.lenshelp {
+        write_u16_flag(out, 0x1E, lenshelp.id)?;
+    }
+    if set(f.fullbank) {
+        out.push(0x1F);
+    }
+    if set(f.ignore_look) {
+        out.push(0x20);
+    }
+    if let Some(clothes) = &f.clothes {
+        write_u16_flag(out, 0x21, clothes.slot)?;
+    }
+    if let Some(market) = &f.market {
+        out.push(0x22);
+        out.write_u16::<LittleEndian>(market.category.unwrap_or(0).clamp(0, u16::MAX as i32) as u16)?;
+        out.write_u16::<LittleEndian>(market.trade_as_object_id.unwrap_or(0).min(u16::MAX as u32) as u16)?;
+        out.write_u16::<LittleEndian>(market.show_as_object_id.unwrap_or(0).min(u16::MAX as u32) as u16)?;
+        let name = thing.name.as_deref().unwrap_or_default();
+        out.write_u16::<LittleEndian>(name.len().min(u16::MAX as usize) as u16)?;
+        out.extend_from_slice(&name[..name.len().min(u16::MAX as usize)]);
+        out.write_u16::<LittleEndian>(thing.market_vocations)?;
+        out.write_u16::<LittleEndian>(thing.market_level)?;
+    }
+    if let Some(action) = &f.default_action {
+        write_u16_flag(out, 0x23, action.action.map(|a| a.max(0) as u32))?;
+    }
+    for (value, code) in [(f.wrap, 0x24), (f.unwrap, 0x25), (f.topeffect, 0x26)] {
+        if set(value) {
+            out.push(code);
+        }
+    }
+    if layout == LegacyDatLayout::V12 {
+        if let Some(upgrade) = &f.upgradeclassification {
+            write_u16_flag(out, 0x27, upgrade.upgrade_classification)?;
+        }
+        for (value, code) in [(f.wearout, 0x28), (f.clockexpire, 0x29), (f.expire, 0x2A), (f.expirestop, 0x2B)] {
+            if set(value) {
+                out.push(code);
+            }
+        }
+    }
+    if set(f.usable) {
+        out.push(0xFE);
+    }
+    out.push(LAST_FLAG);
+
+    let default_group = [LegacyFrameGroup::default()];
+    let groups: &[LegacyFrameGroup] = if thing.groups.is_empty() {
+        &default_group
+    } else if outfit {
+        &thing.groups
+    } else {
+        &thing.groups[..1]
+    };
+
+    if outfit {
+        out.push(groups.len() as u8);
+    }
+    for group in groups {
+        if outfit {
+            out.push(group.group_type);
+        }
+        write_frame_group(out, group).with_context(|| format!("Thing {}", thing.id))?;
+    }
+    Ok(())
+}
+
+fn write_frame_group(out: &mut Vec<u8>, group: &LegacyFrameGroup) -> Result<()> {
+    let expected = group.width as usize * group.height as usize * group.layers as usize * group.pattern_x as usize * group.pattern_y as usize * group.pattern_z as usize * group.frames as usize;
+    if group.sprite_ids.len() != expected {
+        bail!("Frame group has {} sprite ids, expected {}", group.sprite_ids.len(), expected);
+    }
+
+    out.push(group.width);
+    out.push(group.height);
+    if group.width > 1 || group.height > 1 {
+        out.push(group.exact_size);
+    }
+    out.extend_from_slice(&[group.layers, group.pattern_x, group.pattern_y, group.pattern_z, group.frames]);
+
+    if group.frames > 1 {
+        let animation = group.animation.clone().unwrap_or_default();
+        out.push(u8::from(animation.synchronized));
+        out.write_i32::<LittleEndian>(animation.loop_count)?;
+        out.write_i8(animation.start_frame)?;
+        for frame in 0..group.frames as usize {
+            let (min, max) = animation.durations.get(frame).copied().unwrap_or((100, 100));
+            out.write_u32::<LittleEndian>(min)?;
+            out.write_u32::<LittleEndian>(max)?;
+        }
+    }
+
+    for id in &group.sprite_ids {
+        out.write_u32::<LittleEndian>(*id)?;
+    }
+    Ok(())
+}
+
+// ── Conversion to protobuf ──────────────────────────────────────────────────
+
+/// Largest thing, in tiles per side, that fits one protobuf sprite (64x64).
+pub const LEGACY_MAX_GROUP_TILES: u8 = 2;
+
+/// Converts a legacy dat into protobuf appearances. `compose` turns the
+/// `width * height` tile ids of one slot (in client order, see
+/// `LegacyFrameGroup::sprite_ids`) into the protobuf sprite id for that slot.
+/// Things wider or taller than [`LEGACY_MAX_GROUP_TILES`] are refused.
+pub fn legacy_to_appearances(dat: &LegacyDat, compose: &mut dyn FnMut(&LegacyFrameGroup, &[u32]) -> Result<u32>) -> Result<Appearances> {
+    let mut convert = |things: &[LegacyThing], category: &str, outfit: bool| -> Result<Vec<Appearance>> {
+        things
+            .iter()
+            .map(|thing| {
+                let frame_group = thing
+                    .groups
+                    .iter()
+                    .enumerate()
+                    .map(|(index, group)| legacy_group_to_protobuf(index, group, outfit, &mut *compose))
+                    .collect::<Result<Vec<_>>>()
+                    .with_context(|| format!("Failed to convert {} {}", category, thing.id))?;
+                Ok(Appearance {
+                    id: Some(thing.id),
+                    flags: Some(thing.flags.clone()),
+                    name: thing.name.clone(),
+                    frame_group,
+                    ..Default::default()
+                })
+            })
+            .collect()
+    };
+
+    Ok(Appearances {
+        object: convert(&dat.items, "object", false)?,
+        outfit: convert(&dat.outfits, "outfit", true)?,
+        effect: convert(&dat.effects, "effect", false)?,
+        missile: convert(&dat.missiles, "missile", false)?,
+        ..Default::default()
+    })
+}
+
+fn legacy_group_to_protobuf(index: usize, group: &LegacyFrameGroup, outfit: bool, compose: &mut dyn FnMut(&LegacyFrameGroup, &[u32]) -> Result<u32>) -> Result<FrameGroup> {
+    if group.width > LEGACY_MAX_GROUP_TILES || group.height > LEGACY_MAX_GROUP_TILES {
+        bail!("{}x{} tiles is larger than a 64x64 sprite", group.width, group.height);
+    }
+    let tiles = (group.width as usize * group.height as usize).max(1);
+    let sprite_id = group.sprite_ids.chunks(tiles).map(|slot| compose(group, slot)).collect::<Result<Vec<_>>>()?;
+    let animation = group.animation.as_ref().map(|animation| {
+        let (loop_type, loop_count) = match animation.loop_count {
+            count if count < 0 => (AnimationLoopType::Pingpong, None),
+            0 => (AnimationLoopType::Infinite, None),
+            count => (AnimationLoopType::Counted, Some(count as u32)),
+        };
+        SpriteAnimation {
+            synchronized: Some(animation.synchronized),
+            loop_type: Some(loop_type as i32),
+            loop_count,
+            sprite_phase: animation
+                .durations
+                .iter()
+                .map(|(min, max)| SpritePhase {
+                    duration_min: Some(*min),
+                    duration_max: Some(*max),
+                })
+                .collect(),
+        }
+    });
+
+    let fixed_frame_group = if outfit {
+        match group.group_type {
+            1 => FixedFrameGroup::OutfitMoving,
+            _ => FixedFrameGroup::OutfitIdle,
+        }
+    } else {
+        FixedFrameGroup::ObjectInitial
+    };
+
+    Ok(FrameGroup {
+        fixed_frame_group: Some(fixed_frame_group as i32),
+        id: Some(index as u32),
+        sprite_info: Some(SpriteInfo {
+            pattern_width: Some(group.pattern_x as u32),
+            pattern_height: Some(group.pattern_y as u32),
+            pattern_depth: Some(group.pattern_z as u32),
+            layers: Some(group.layers as u32),
+            sprite_id,
+            bounding_square: Some(group.exact_size as u32),
+            animation,
+            ..Default::default()
+        }),
+    })
+}
+
+/// Builds the legacy texture block for a protobuf sprite info. `tile_ids` returns
+/// the `width * height` legacy tile ids for one protobuf slot, plus the tile size.
+pub fn protobuf_group_to_legacy(info: &SpriteInfo, group_type: u8, width: u8, height: u8, tile_ids: &mut dyn FnMut(u32) -> Vec<u32>) -> LegacyFrameGroup {
+    let animation = info.animation.as_ref().filter(|a| a.sprite_phase.len() > 1).map(|animation| {
+        let loop_count = match AnimationLoopType::try_from(animation.loop_type.unwrap_or(0)) {
+            Ok(AnimationLoopType::Pingpong) => -1,
+            Ok(AnimationLoopType::Counted) => animation.loop_count.unwrap_or(1).min(i32::MAX as u32) as i32,
+            _ => 0,
+        };
+        LegacyAnimation {
+            synchronized: animation.synchronized.unwrap_or(false),
+            loop_count,
+            start_frame: 0,
+            durations: animation.sprite_phase.iter().map(|p| (p.duration_min.unwrap_or(0), p.duration_max.unwrap_or(p.duration_min.unwrap_or(0)))).collect(),
+        }
+    });
+    let frames = animation.as_ref().map(|a| a.durations.len()).unwrap_or(1).min(u8::MAX as usize) as u8;
+
+    LegacyFrameGroup {
+        group_type,
+        width,
+        height,
+        exact_size: (width.max(height) as u32 * LEGACY_TILE_SIZE).min(u8::MAX as u32) as u8,
+        layers: info.layers.unwrap_or(1).clamp(1, 255) as u8,
+        pattern_x: info.pattern_width.unwrap_or(1).clamp(1, 255) as u8,
+        pattern_y: info.pattern_height.unwrap_or(1).clamp(1, 255) as u8,
+        pattern_z: info.pattern_depth.unwrap_or(1).clamp(1, 255) as u8,
+        frames,
+        animation,
+        sprite_ids: info.sprite_id.iter().flat_map(|id| tile_ids(*id)).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_dat() -> LegacyDat {
+        let item = LegacyThing {
+            id: 100,
+            flags: AppearanceFlags {
+                bank: Some(AppearanceFlagBank {
+                    waypoints: Some(150),
+                }),
+                take: Some(true),
+                light: Some(AppearanceFlagLight {
+                    brightness: Some(3),
+                    color: Some(215),
+                }),
+                market: Some(AppearanceFlagMarket {
+                    category: Some(5),
+                    trade_as_object_id: Some(100),
+                    show_as_object_id: Some(100),
+                }),
+                usable: Some(true),
+                ..Default::default()
+            },
+            name: Some(b"sword".to_vec()),
+            market_vocations: 0b101,
+            market_level: 20,
+            groups: vec![LegacyFrameGroup {
+                width: 2,
+                height: 2,
+                exact_size: 64,
+                frames: 2,
+                animation: Some(LegacyAnimation {
+                    synchronized: true,
+                    loop_count: -1,
+                    start_frame: 0,
+                    durations: vec![(100, 200), (300, 300)],
+                }),
+                sprite_ids: (1..=8).collect(),
+                ..Default::default()
+            }],
+        };
+        let outfit = LegacyThing {
+            id: 1,
+            groups: vec![
+                LegacyFrameGroup::default(),
+                LegacyFrameGroup {
+                    group_type: 1,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        LegacyDat {
+            signature: 0x42A3,
+            items: vec![item],
+            outfits: vec![outfit],
+            effects: vec![],
+            missiles: vec![LegacyThing {
+                id: 1,
+                groups: vec![LegacyFrameGroup::default()],
+                ..Default::default()
+            }],
+        }
+    }
+
+    #[test]
+    fn dat_roundtrips() {
+        let dat = sample_dat();
+        let bytes = write_legacy_dat(&dat, LegacyDatLayout::V1098).unwrap();
+        assert_eq!(&bytes[4..6], &100u16.to_le_bytes());
+        assert_eq!(read_legacy_dat(&bytes).unwrap(), dat);
+    }
+
+    #[test]
+    fn v12_layout_keeps_the_newer_item_flags() {
+        let mut dat = sample_dat();
+        let flags = &mut dat.items[0].flags;
+        flags.upgradeclassification = Some(AppearanceFlagUpgradeClassification {
+            upgrade_classification: Some(3),
+        });
+        flags.wearout = Some(true);
+        flags.expirestop = Some(true);
+
+        let client = LegacyClientVersion::known(1281).unwrap();
+        dat.signature = client.dat_signature;
+        let bytes = write_legacy_dat(&dat, client.layout).unwrap();
+        assert_eq!(read_legacy_dat(&bytes).unwrap(), dat);
+        assert_eq!(LegacyClientVersion::from_dat_signature(dat.signature), Some(client));
+
+        // 10.98 has no codes for them.
+        let old = read_legacy_dat(&write_legacy_dat(&dat, LegacyDatLayout::V1098).unwrap()).unwrap();
+        assert_eq!(old.items[0].flags.upgradeclassification, None);
+        assert_eq!(old.items[0].flags.wearout, None);
+        assert_eq!(old.items[0].flags.usable, Some(true));
+        assert_eq!(LegacyClientVersion::from_dat_signature(0x42A3).map(|c| c.version), Some(1098));
+    }
+
+    #[test]
+    fn rejects_gaps_in_ids() {
+        let mut dat = sample_dat();
+        dat.items[0].id = 101;
+        assert!(write_legacy_dat(&dat, LegacyDatLayout::V1098).is_err());
+    }
+
+    #[test]
+    fn conversion_composes_each_slot_and_keeps_animation() {
+        let mut slots = Vec::new();
+        let appearances = legacy_to_appearances(&sample_dat(), &mut |group, tiles| {
+            slots.push((group.width, tiles.to_vec()));
+            Ok(1000 + slots.len() as u32)
+        })
+        .unwrap();
+        // The 2x2 item hands over all four tiles of each frame.
+        assert_eq!(slots[..2], [(2, vec![1, 2, 3, 4]), (2, vec![5, 6, 7, 8])]);
+
+        let item = &appearances.object[0];
+        let info = item.frame_group[0].sprite_info.as_ref().unwrap();
+        assert_eq!(info.sprite_id, vec![1001, 1002]);
+        let animation = info.animation.as_ref().unwrap();
+        assert_eq!(animation.loop_type, Some(AnimationLoopType::Pingpong as i32));
+        assert_eq!(animation.sprite_phase.len(), 2);
+        assert_eq!(item.name.as_deref(), Some(&b"sword"[..]));
+
+        let outfit_groups: Vec<Option<i32>> = appearances.outfit[0].frame_group.iter().map(|g| g.fixed_frame_group).collect();
+        assert_eq!(outfit_groups, vec![Some(0), Some(1)]);
+    }
+
+    #[test]
+    fn conversion_refuses_things_bigger_than_64x64() {
+        let mut dat = sample_dat();
+        dat.items[0].groups[0].width = 3;
+        dat.items[0].groups[0].sprite_ids = (1..=12).collect();
+        let error = legacy_to_appearances(&dat, &mut |_, _| Ok(1)).unwrap_err();
+        assert!(format!("{:#}", error).contains("object 100"));
+    }
+}
@@ -1,5 +1,9 @@
 // Appearance parsers module
 
 mod appearances;
+mod legacy_dat;
+mod query_language;
 
 pub use appearances::*;
+pub use legacy_dat::*;
+pub use query_language::*;
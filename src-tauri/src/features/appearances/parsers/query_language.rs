@@ -0,0 +1,508 @@
+// Small query language for appearances
+//
+//   flags.market.category = 5 and light.brightness > 3 and not unpass
+//   take and light and not market
+//   name ~ "sword" or (id >= 3000 and id < 3100)
+//
+// Grammar (keywords are case-insensitive, `&&` `||` `!` are accepted too):
+//   expr       := and_expr ("or" and_expr)*
+//   and_expr   := unary ("and" unary)*
+//   unary      := "not" unary | "(" expr ")" | predicate
+//   predicate  := field [op value]
+//   op         := = | == | != | <> | > | >= | < | <= | ~   (~ = case-insensitive contains)
+//   value      := number | "quoted" | 'quoted' | true | false | bare_word
+//
+// Fields: `id`, `name`, `description`, `animated`, `frame_groups` (count),
+// `sprite_count`, or any flag path as exported to JSON (`flags.` prefix optional,
+// underscores optional: `changedtoexpire` == `changed_to_expire`).
+// A bare field is a truthiness test (flag set, sub-flag present, non-zero value).
+// Missing fields never satisfy a comparison.
+
+use crate::core::protobuf::{Appearance, AppearanceFlags};
+use crate::features::appearances::CompleteFlags;
+use anyhow::{anyhow, bail, Result};
+use serde_json::Value;
+use std::cell::OnceCell;
+use std::sync::OnceLock;
+
+const ROOT_FIELDS: &[&str] = &["id", "name", "description", "animated", "frame_groups", "sprite_count"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryExpr {
+    And(Box<QueryExpr>, Box<QueryExpr>),
+    Or(Box<QueryExpr>, Box<QueryExpr>),
+    Not(Box<QueryExpr>),
+    Predicate(Predicate),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Predicate {
+    pub path: Vec<String>,
+    pub comparison: Option<(CompareOp, QueryValue)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Contains,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryValue {
+    Number(f64),
+    Text(String),
+    Bool(bool),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(f64),
+    Text(String),
+    Op(CompareOp),
+    And,
+    Or,
+    Not,
+    LParen,
+    RParen,
+}
+
+fn tokenize(input: &str) -> Result<Vec<(usize, Token)>> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+
+    while pos < chars.len() {
+        let c = chars[pos];
+        let start = pos;
+        if c.is_whitespace() {
+            pos += 1;
+            continue;
+        }
+
+        let two: String = chars[pos..(pos + 2).min(chars.len())].iter().collect();
+        let token = match two.as_str() {
+            "==" => Some(Token::Op(CompareOp::Eq)),
+            "!=" | "<>" => Some(Token::Op(CompareOp::Ne)),
+            ">=" => Some(Token::Op(CompareOp::Ge)),
+            "<=" => Some(Token::Op(CompareOp::Le)),
+            "&&" => Some(Token::And),
+            "||" => Some(Token::Or),
+            _ => None,
+        };
+        if let Some(token) = token {
+            tokens.push((start, token));
+            pos += 2;
+            continue;
+        }
+
+        let token = match c {
+            '=' => Token::Op(CompareOp::Eq),
+            '>' => Token::Op(CompareOp::Gt),
+            '<' => Token::Op(CompareOp::Lt),
+            '~' => Token::Op(CompareOp::Contains),
+            '!' => Token::Not,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            '"' | '\'' => {
+                let end = chars[pos + 1..].iter().position(|&ch| ch == c).ok_or_else(|| anyhow!("Unterminated string starting at position {}", start))?;
+                let text: String = chars[pos + 1..pos + 1 + end].iter().collect();
+                pos += end + 2;
+                tokens.push((start, Token::Text(text)));
+                continue;
+            }
+            c if c.is_ascii_digit() || (c == '-' && chars.get(pos + 1).is_some_and(|n| n.is_ascii_digit())) => {
+                pos += 1;
+                while pos < chars.len() && (chars[pos].is_ascii_digit() || chars[pos] == '.') {
+                    pos += 1;
+                }
+                let text: String = chars[start..pos].iter().collect();
+                let number = text.parse::<f64>().map_err(|_| anyhow!("Invalid number '{}' at position {}", text, start))?;
+                tokens.push((start, Token::Number(number)));
+                continue;
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                while pos < chars.len() && (chars[pos].is_alphanumeric() || chars[pos] == '_' || chars[pos] == '.') {
+                    pos += 1;
+                }
+                let word: String = chars[start..pos].iter().collect();
+                let token = match word.to_ascii_lowercase().as_str() {
+                    "and" => Token::And,
+                    "or" => Token::Or,
+                    "not" => Token::Not,
+                    _ => Token::Ident(word),
+                };
+                tokens.push((start, token));
+                continue;
+            }
+            other => bail!("Unexpected character '{}' at position {}", other, start),
+        };
+        tokens.push((start, token));
+        pos += 1;
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+    input_len: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(_, t)| t)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens.get(self.pos).map(|(p, _)| *p).unwrap_or(self.input_len)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).map(|(_, t)| t.clone());
+        self.pos += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Result<QueryExpr> {
+        let mut left = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            let right = self.parse_and()?;
+            left = QueryExpr::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<QueryExpr> {
+        let mut left = self.parse_unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.pos += 1;
+            let right = self.parse_unary()?;
+            left = QueryExpr::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<QueryExpr> {
+        let position = self.position();
+        match self.next() {
+            Some(Token::Not) => Ok(QueryExpr::Not(Box::new(self.parse_unary()?))),
+            Some(Token::LParen) => {
+                let expr = self.parse_or()?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(expr),
+                    _ => bail!("Expected ')' for '(' at position {}", position),
+                }
+            }
+            Some(Token::Ident(field)) => self.parse_predicate(field, position),
+            Some(other) => bail!("Expected a field name at position {}, found {:?}", position, other),
+            None => bail!("Unexpected end of query"),
+        }
+    }
+
+    fn parse_predicate(&mut self, field: String, position: usize) -> Result<QueryExpr> {
+        let path = resolve_path(&field).ok_or_else(|| anyhow!("Unknown field '{}' at position {}", field, position))?;
+
+        let op = match self.peek() {
+            Some(Token::Op(op)) => *op,
+            _ => {
+                return Ok(QueryExpr::Predicate(Predicate {
+                    path,
+                    comparison: None,
+                }))
+            }
+        };
+        self.pos += 1;
+
+        let value_position = self.position();
+        let value = match self.next() {
+            Some(Token::Number(n)) => QueryValue::Number(n),
+            Some(Token::Text(s)) => QueryValue::Text(s),
+            Some(Token::Ident(word)) => match word.to_ascii_lowercase().as_str() {
+                "true" => QueryValue::Bool(true),
+                "false" => QueryValue::Bool(false),
+                _ => QueryValue::Text(word),
+            },
+            _ => bail!("Expected a value at position {}", value_position),
+        };
+
+        Ok(QueryExpr::Predicate(Predicate {
+            path,
+            comparison: Some((op, value)),
+        }))
+    }
+}
+
+/// Parses a query. An empty (whitespace-only) query is rejected; callers treat
+/// "no query" as "match everything" themselves.
+pub fn parse_query(input: &str) -> Result<QueryExpr> {
+    let tokens = tokenize(input)?;
+    if tokens.is_empty() {
+        bail!("Empty query");
+    }
+
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        input_len: input.chars().count(),
+    };
+    let expr = parser.parse_or()?;
+    if parser.pos < parser.tokens.len() {
+        bail!("Unexpected token at position {}", parser.position());
+    }
+    Ok(expr)
+}
+
+/// Top-level flag keys as serialized by [`CompleteFlags`].
+fn flag_keys() -> &'static Vec<String> {
+    static KEYS: OnceLock<Vec<String>> = OnceLock::new();
+    KEYS.get_or_init(|| match serde_json::to_value(CompleteFlags::from_protobuf(&AppearanceFlags::default())) {
+        Ok(Value::Object(map)) => map.keys().cloned().collect(),
+        _ => Vec::new(),
+    })
+}
+
+fn normalize(key: &str) -> String {
+    key.to_ascii_lowercase().replace('_', "")
+}
+
+/// Maps a dotted field to its canonical path: `["id"]` for root fields, or
+/// `["flags", <flag key>, ...rest]`. Only the top-level flag key is checked.
+fn resolve_path(field: &str) -> Option<Vec<String>> {
+    let mut segments: Vec<&str> = field.split('.').collect();
+    if segments.iter().any(|s| s.is_empty()) {
+        return None;
+    }
+
+    if segments.len() == 1 {
+        if let Some(root) = ROOT_FIELDS.iter().find(|r| normalize(r) == normalize(segments[0])) {
+            return Some(vec![root.to_string()]);
+        }
+    }
+
+    if segments.len() > 1 && segments[0].eq_ignore_ascii_case("flags") {
+        segments.remove(0);
+    }
+    let flag = flag_keys().iter().find(|k| normalize(k) == normalize(segments[0]))?;
+
+    let mut path = vec!["flags".to_string(), flag.clone()];
+    path.extend(segments[1..].iter().map(|s| s.to_string()));
+    Some(path)
+}
+
+/// Resolves a flag path (`light.brightness`, `flags.changedtoexpire`, ...) to its
+/// canonical segments below `flags`, using the same aliases as queries.
+pub fn resolve_flag_path(field: &str) -> Option<Vec<String>> {
+    let path = resolve_path(field)?;
+    (path[0] == "flags").then(|| path[1..].to_vec())
+}
+
+/// Per-appearance evaluation context. Flags are converted to JSON once, on first use.
+pub struct QueryTarget<'a> {
+    appearance: &'a Appearance,
+    flags: OnceCell<Value>,
+}
+
+impl<'a> QueryTarget<'a> {
+    pub fn new(appearance: &'a Appearance) -> Self {
+        Self {
+            appearance,
+            flags: OnceCell::new(),
+        }
+    }
+
+    fn flags(&self) -> &Value {
+        self.flags.get_or_init(|| self.appearance.flags.as_ref().and_then(|f| serde_json::to_value(CompleteFlags::from_protobuf(f)).ok()).unwrap_or(Value::Null))
+    }
+
+    fn resolve(&self, path: &[String]) -> Value {
+        let app = self.appearance;
+        match path[0].as_str() {
+            "id" => Value::from(app.id.unwrap_or(0)),
+            "name" => app.name.as_ref().map(|b| Value::String(String::from_utf8_lossy(b).into_owned())).unwrap_or(Value::Null),
+            "description" => app.description.as_ref().map(|b| Value::String(String::from_utf8_lossy(b).into_owned())).unwrap_or(Value::Null),
+            "animated" => Value::Bool(app.frame_group.iter().any(|fg| fg.sprite_info.as_ref().and_then(|si| si.animation.as_ref()).is_some_and(|an| an.sprite_phase.len() > 1))),
+            "frame_groups" => Value::from(app.frame_group.len()),
+            "sprite_count" => Value::from(app.frame_group.iter().filter_map(|fg| fg.sprite_info.as_ref()).map(|si| si.sprite_id.len()).sum::<usize>()),
+            _ => {
+                let mut current = self.flags();
+                for segment in &path[1..] {
+                    let next = match current {
+                        Value::Object(map) => map.get(segment.as_str()).or_else(|| map.iter().find(|(k, _)| normalize(k) == normalize(segment)).map(|(_, v)| v)),
+                        _ => None,
+                    };
+                    match next {
+                        Some(value) => current = value,
+                        None => return Value::Null,
+                    }
+                }
+                current.clone()
+            }
+        }
+    }
+}
+
+impl QueryExpr {
+    pub fn matches(&self, appearance: &Appearance) -> bool {
+        self.eval(&QueryTarget::new(appearance))
+    }
+
+    pub fn eval(&self, target: &QueryTarget<'_>) -> bool {
+        match self {
+            QueryExpr::And(a, b) => a.eval(target) && b.eval(target),
+            QueryExpr::Or(a, b) => a.eval(target) || b.eval(target),
+            QueryExpr::Not(inner) => !inner.eval(target),
+            QueryExpr::Predicate(predicate) => {
+                let value = target.resolve(&predicate.path);
+                match &predicate.comparison {
+                    None => truthy(&value),
+                    Some((op, literal)) => compare(&value, *op, literal),
+                }
+            }
+        }
+    }
+}
+
+fn truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64().is_some_and(|n| n != 0.0),
+        Value::String(s) => !s.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        Value::Object(_) => true,
+    }
+}
+
+fn compare(value: &Value, op: CompareOp, literal: &QueryValue) -> bool {
+    match value {
+        Value::Null | Value::Object(_) => false,
+        // Lists (restrict_to_vocation, ...) match when any element does
+        Value::Array(items) => items.iter().any(|item| compare(item, op, literal)),
+        Value::Bool(b) => match (op, literal) {
+            (CompareOp::Eq, QueryValue::Bool(l)) => b == l,
+            (CompareOp::Ne, QueryValue::Bool(l)) => b != l,
+            _ => false,
+        },
+        Value::Number(n) => {
+            let (Some(n), QueryValue::Number(l)) = (n.as_f64(), literal) else {
+                return false;
+            };
+            match op {
+                CompareOp::Eq => n == *l,
+                CompareOp::Ne => n != *l,
+                CompareOp::Gt => n > *l,
+                CompareOp::Ge => n >= *l,
+                CompareOp::Lt => n < *l,
+                CompareOp::Le => n <= *l,
+                CompareOp::Contains => false,
+            }
+        }
+        Value::String(s) => {
+            let literal = match literal {
+                QueryValue::Text(t) => t.to_lowercase(),
+                QueryValue::Number(n) => n.to_string(),
+                QueryValue::Bool(b) => b.to_string(),
+            };
+            let s = s.to_lowercase();
+            match op {
+                CompareOp::Eq => s == literal,
+                CompareOp::Ne => s != literal,
+                CompareOp::Contains => s.contains(&literal),
+                CompareOp::Gt => s > literal,
+                CompareOp::Ge => s >= literal,
+                CompareOp::Lt => s < literal,
+                CompareOp::Le => s <= literal,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::protobuf::{AppearanceFlagLight, AppearanceFlagMarket};
+
+    fn item(id: u32, name: &str, flags: AppearanceFlags) -> Appearance {
+        Appearance {
+            id: Some(id),
+            name: Some(name.as_bytes().to_vec()),
+            flags: Some(flags),
+            ..Default::default()
+        }
+    }
+
+    fn torch() -> Appearance {
+        item(
+            2050,
+            "Torch",
+            AppearanceFlags {
+                take: Some(true),
+                light: Some(AppearanceFlagLight {
+                    brightness: Some(4),
+                    color: Some(206),
+                }),
+                ..Default::default()
+            },
+        )
+    }
+
+    fn sword() -> Appearance {
+        item(
+            3264,
+            "Sword",
+            AppearanceFlags {
+                take: Some(true),
+                unpass: Some(true),
+                market: Some(AppearanceFlagMarket {
+                    category: Some(5),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        )
+    }
+
+    fn matches(query: &str, appearance: &Appearance) -> bool {
+        parse_query(query).unwrap().matches(appearance)
+    }
+
+    #[test]
+    fn parses_precedence_and_parentheses() {
+        let expr = parse_query("take or unpass and not market").unwrap();
+        assert!(matches!(expr, QueryExpr::Or(_, _)));
+        let expr = parse_query("(take or unpass) && !market").unwrap();
+        assert!(matches!(expr, QueryExpr::And(_, _)));
+    }
+
+    #[test]
+    fn evaluates_flags_and_sub_flags() {
+        let (torch, sword) = (torch(), sword());
+        let query = "take and light.brightness > 0 and not market";
+        assert!(matches(query, &torch));
+        assert!(!matches(query, &sword));
+
+        assert!(matches("flags.market.category = 5 and not light", &sword));
+        assert!(matches("market.category == 5", &sword));
+        assert!(!matches("market.category != 5", &torch)); // missing fields never match
+        assert!(matches("name ~ 'ORC' or name ~ sword", &sword));
+        assert!(matches("id >= 2000 and id < 3000", &torch));
+    }
+
+    #[test]
+    fn rejects_bad_queries() {
+        assert!(parse_query("").is_err());
+        assert!(parse_query("unknown_flag").is_err());
+        assert!(parse_query("take and").is_err());
+        assert!(parse_query("(take").is_err());
+        assert!(parse_query("id > ").is_err());
+        assert!(parse_query("name = \"open").is_err());
+    }
+}
@@ -0,0 +1,30 @@
+// Backups feature module
+// Listing, diffing and restoring the snapshots taken before each save
+
+use crate::core::backups::{manager, BackupDiff, BackupEntry, BackupManager};
+use std::path::Path;
+
+fn backup_manager() -> Result<&'static BackupManager, String> {
+    manager().ok_or_else(|| "Backups are not available".to_string())
+}
+
+/// Snapshots of `path`, or of every backed up file when None, newest first.
+#[tauri::command]
+pub async fn list_backups(path: Option<String>) -> Result<Vec<BackupEntry>, String> {
+    backup_manager()?.list(path.as_deref().map(Path::new)).map_err(|e| format!("Failed to list backups: {}", e))
+}
+
+/// Compare a snapshot with the current contents of its file.
+#[tauri::command]
+pub async fn diff_backup(backup_id: String) -> Result<BackupDiff, String> {
+    backup_manager()?.diff(&backup_id).map_err(|e| format!("Failed to diff backup: {}", e))
+}
+
+/// Write a snapshot back over its file. The frontend reloads whatever it had
+/// open from that file; the contents being replaced are snapshotted first.
+#[tauri::command]
+pub async fn restore_backup(backup_id: String) -> Result<BackupEntry, String> {
+    let entry = backup_manager()?.restore(&backup_id).map_err(|e| format!("Failed to restore backup: {}", e))?;
+    log::info!("Restored {} from backup {}", entry.original_path, entry.id);
+    Ok(entry)
+}
@@ -0,0 +1,414 @@
+// Semantic diff between two appearances.dat files.
+// Compares per category by id and reports field-level changes (name, description,
+// flags, frame groups) plus sprite id slot changes. Exportable as JSON or HTML.
+
+use crate::core::protobuf::{Appearance, Appearances};
+use crate::features::appearances::parsers::load_appearances;
+use crate::features::appearances::{AppearanceCategory, CompleteFlags, CompleteFrameGroup};
+use crate::state::AppState;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use tauri::State;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppearancesDiff {
+    pub old_path: Option<String>,
+    pub new_path: Option<String>,
+    pub categories: Vec<CategoryDiff>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CategoryDiff {
+    pub category: AppearanceCategory,
+    /// Ids only present in the new file.
+    pub added: Vec<u32>,
+    /// Ids only present in the old file.
+    pub removed: Vec<u32>,
+    pub modified: Vec<AppearanceDiff>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppearanceDiff {
+    pub id: u32,
+    pub fields: Vec<FieldChange>,
+    pub sprites: Vec<SpriteIdChange>,
+}
+
+/// A changed leaf value. `path` uses the JSON export field names,
+/// e.g. `flags.market.category` or `frame_groups[1].sprite_info.layers`.
+/// Missing values are `null`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldChange {
+    pub path: String,
+    pub old: Value,
+    pub new: Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpriteIdChange {
+    pub frame_group: usize,
+    pub index: usize,
+    pub old: Option<u32>,
+    pub new: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffExportFormat {
+    Json,
+    Html,
+}
+
+impl AppearancesDiff {
+    pub fn is_empty(&self) -> bool {
+        self.categories.iter().all(|c| c.added.is_empty() && c.removed.is_empty() && c.modified.is_empty())
+    }
+}
+
+/// Diffs two loaded appearance sets. Output ids are sorted ascending.
+pub fn diff_appearances(old: &Appearances, new: &Appearances) -> AppearancesDiff {
+    let categories = [
+        (AppearanceCategory::Objects, &old.object, &new.object),
+        (AppearanceCategory::Outfits, &old.outfit, &new.outfit),
+        (AppearanceCategory::Effects, &old.effect, &new.effect),
+        (AppearanceCategory::Missiles, &old.missile, &new.missile),
+    ];
+
+    AppearancesDiff {
+        old_path: None,
+        new_path: None,
+        categories: categories.into_iter().map(|(category, old_items, new_items)| diff_category(category, old_items, new_items)).collect(),
+    }
+}
+
+fn diff_category(category: AppearanceCategory, old_items: &[Appearance], new_items: &[Appearance]) -> CategoryDiff {
+    let old_by_id: BTreeMap<u32, &Appearance> = old_items.iter().filter_map(|a| a.id.map(|id| (id, a))).collect();
+    let new_by_id: BTreeMap<u32, &Appearance> = new_items.iter().filter_map(|a| a.id.map(|id| (id, a))).collect();
+
+    let added = new_by_id.keys().filter(|id| !old_by_id.contains_key(id)).copied().collect();
+    let removed = old_by_id.keys().filter(|id| !new_by_id.contains_key(id)).copied().collect();
+
+    let modified = old_by_id
+        .iter()
+        .filter_map(|(id, old_app)| {
+            let new_app = new_by_id.get(id)?;
+            if old_app == new_app {
+                return None;
+            }
+            let diff = diff_appearance(*id, old_app, new_app);
+            (!diff.fields.is_empty() || !diff.sprites.is_empty()).then_some(diff)
+        })
+        .collect();
+
+    CategoryDiff {
+        category,
+        added,
+        removed,
+        modified,
+    }
+}
+
+/// Field-level diff of two versions of the same appearance.
+pub fn diff_appearance(id: u32, old: &Appearance, new: &Appearance) -> AppearanceDiff {
+    let mut fields = Vec::new();
+    let mut sprites = Vec::new();
+
+    diff_values("name", &bytes_value(&old.name), &bytes_value(&new.name), &mut fields);
+    diff_values("description", &bytes_value(&old.description), &bytes_value(&new.description), &mut fields);
+
+    let old_flags = old.flags.as_ref().map(CompleteFlags::from_protobuf);
+    let new_flags = new.flags.as_ref().map(CompleteFlags::from_protobuf);
+    diff_values("flags", &to_value(&old_flags), &to_value(&new_flags), &mut fields);
+
+    let group_count = old.frame_group.len().max(new.frame_group.len());
+    for index in 0..group_count {
+        let old_group = old.frame_group.get(index).map(CompleteFrameGroup::from_protobuf);
+        let new_group = new.frame_group.get(index).map(CompleteFrameGroup::from_protobuf);
+
+        let old_ids = sprite_ids(&old_group);
+        let new_ids = sprite_ids(&new_group);
+        for slot in 0..old_ids.len().max(new_ids.len()) {
+            let (old_id, new_id) = (old_ids.get(slot).copied(), new_ids.get(slot).copied());
+            if old_id != new_id {
+                sprites.push(SpriteIdChange {
+                    frame_group: index,
+                    index: slot,
+                    old: old_id,
+                    new: new_id,
+                });
+            }
+        }
+
+        // Sprite ids are reported slot by slot above; keep them out of the field diff.
+        diff_values(&format!("frame_groups[{}]", index), &frame_group_value(&old_group), &frame_group_value(&new_group), &mut fields);
+    }
+
+    AppearanceDiff {
+        id,
+        fields,
+        sprites,
+    }
+}
+
+fn bytes_value(bytes: &Option<Vec<u8>>) -> Value {
+    bytes.as_ref().map(|b| Value::String(String::from_utf8_lossy(b).into_owned())).unwrap_or(Value::Null)
+}
+
+fn to_value<T: Serialize>(value: &T) -> Value {
+    serde_json::to_value(value).unwrap_or(Value::Null)
+}
+
+fn sprite_ids(group: &Option<CompleteFrameGroup>) -> &[u32] {
+    group.as_ref().and_then(|g| g.sprite_info.as_ref()).map(|info| info.sprite_ids.as_slice()).unwrap_or(&[])
+}
+
+fn frame_group_value(group: &Option<CompleteFrameGroup>) -> Value {
+    let mut value = to_value(group);
+    if let Some(info) = value.get_mut("sprite_info").and_then(Value::as_object_mut) {
+        info.remove("sprite_ids");
+    }
+    value
+}
+
+/// Recursively compares two JSON values and records every differing leaf.
+/// Objects are compared key by key (an absent key counts as `null`), arrays
+/// element by element when their lengths match and as a whole otherwise.
+fn diff_values(path: &str, old: &Value, new: &Value, out: &mut Vec<FieldChange>) {
+    if old == new {
+        return;
+    }
+
+    match (old, new) {
+        (Value::Object(old_map), Value::Object(new_map)) => {
+            let mut keys: Vec<&String> = old_map.keys().chain(new_map.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let old_child = old_map.get(key).unwrap_or(&Value::Null);
+                let new_child = new_map.get(key).unwrap_or(&Value::Null);
+                diff_values(&format!("{}.{}", path, key), old_child, new_child, out);
+            }
+        }
+        (Value::Object(old_map), Value::Null) => {
+            for (key, old_child) in old_map {
+                diff_values(&format!("{}.{}", path, key), old_child, &Value::Null, out);
+            }
+        }
+        (Value::Null, Value::Object(new_map)) => {
+            for (key, new_child) in new_map {
+                diff_values(&format!("{}.{}", path, key), &Value::Null, new_child, out);
+            }
+        }
+        (Value::Array(old_items), Value::Array(new_items)) if old_items.len() == new_items.len() => {
+            for (index, (old_child, new_child)) in old_items.iter().zip(new_items).enumerate() {
+                diff_values(&format!("{}[{}]", path, index), old_child, new_child, out);
+            }
+        }
+        _ => out.push(FieldChange {
+            path: path.to_string(),
+            old: old.clone(),
+            new: new.clone(),
+        }),
+    }
+}
+
+/// Loads both files and diffs them. Blocking; call from a worker thread.
+pub fn diff_appearances_paths(old_path: &str, new_path: &str) -> Result<AppearancesDiff, String> {
+    let old = load_appearances(old_path).map_err(|e| format!("Failed to load {}: {}", old_path, e))?;
+    let new = load_appearances(new_path).map_err(|e| format!("Failed to load {}: {}", new_path, e))?;
+
+    let mut diff = diff_appearances(&old, &new);
+    diff.old_path = Some(old_path.to_string());
+    diff.new_path = Some(new_path.to_string());
+    Ok(diff)
+}
+
+pub fn write_diff(diff: &AppearancesDiff, path: &str, format: DiffExportFormat) -> Result<(), String> {
+    let contents = match format {
+        DiffExportFormat::Json => serde_json::to_string_pretty(diff).map_err(|e| format!("Failed to serialize diff: {}", e))?,
+        DiffExportFormat::Html => render_diff_html(diff),
+    };
+    std::fs::write(path, contents).map_err(|e| format!("Failed to write {}: {}", path, e))
+}
+
+/// Renders a self-contained HTML report (no external assets).
+pub fn render_diff_html(diff: &AppearancesDiff) -> String {
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Appearances diff</title>\n");
+    html.push_str(
+        "<style>body{font-family:sans-serif;margin:24px}table{border-collapse:collapse;margin-bottom:16px}td,th{border:1px solid #ccc;padding:2px 8px;text-align:left}\
+         .old{color:#b00}.new{color:#070}code{font-size:12px}</style>\n</head>\n<body>\n",
+    );
+    html.push_str("<h1>Appearances diff</h1>\n");
+    if let (Some(old), Some(new)) = (&diff.old_path, &diff.new_path) {
+        let _ = writeln!(html, "<p><span class=\"old\">{}</span> &rarr; <span class=\"new\">{}</span></p>", escape_html(old), escape_html(new));
+    }
+
+    html.push_str("<table>\n<tr><th>Category</th><th>Added</th><th>Removed</th><th>Modified</th></tr>\n");
+    for category in &diff.categories {
+        let _ = writeln!(html, "<tr><td>{:?}</td><td>{}</td><td>{}</td><td>{}</td></tr>", category.category, category.added.len(), category.removed.len(), category.modified.len());
+    }
+    html.push_str("</table>\n");
+
+    for category in &diff.categories {
+        if category.added.is_empty() && category.removed.is_empty() && category.modified.is_empty() {
+            continue;
+        }
+        let _ = writeln!(html, "<h2>{:?}</h2>", category.category);
+        if !category.added.is_empty() {
+            let _ = writeln!(html, "<p><b>Added:</b> <span class=\"new\">{}</span></p>", join_ids(&category.added));
+        }
+        if !category.removed.is_empty() {
+            let _ = writeln!(html, "<p><b>Removed:</b> <span class=\"old\">{}</span></p>", join_ids(&category.removed));
+        }
+        for entry in &category.modified {
+            let _ = writeln!(html, "<h3>#{}</h3>", entry.id);
+            html.push_str("<table>\n<tr><th>Field</th><th>Old</th><th>New</th></tr>\n");
+            for field in &entry.fields {
+                let _ = writeln!(
+                    html,
+                    "<tr><td><code>{}</code></td><td class=\"old\">{}</td><td class=\"new\">{}</td></tr>",
+                    escape_html(&field.path),
+                    escape_html(&field.old.to_string()),
+                    escape_html(&field.new.to_string())
+                );
+            }
+            for sprite in &entry.sprites {
+                let _ = writeln!(
+                    html,
+                    "<tr><td><code>frame_groups[{}].sprite_ids[{}]</code></td><td class=\"old\">{}</td><td class=\"new\">{}</td></tr>",
+                    sprite.frame_group,
+                    sprite.index,
+                    sprite.old.map(|id| id.to_string()).unwrap_or_default(),
+                    sprite.new.map(|id| id.to_string()).unwrap_or_default()
+                );
+            }
+            html.push_str("</table>\n");
+        }
+    }
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn join_ids(ids: &[u32]) -> String {
+    ids.iter().map(u32::to_string).collect::<Vec<_>>().join(", ")
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Diff two appearances.dat files on disk (old -> new).
+#[tauri::command]
+pub async fn diff_appearances_files(old_path: String, new_path: String) -> Result<AppearancesDiff, String> {
+    tauri::async_runtime::spawn_blocking(move || diff_appearances_paths(&old_path, &new_path)).await.map_err(|e| format!("Task join error: {}", e))?
+}
+
+/// Diff the currently loaded dat (old custom) against the loaded merge source (new official).
+#[tauri::command]
+pub async fn diff_with_merge_source(state: State<'_, AppState>) -> Result<AppearancesDiff, String> {
+    let source_lock = state.merge_source.read();
+    let source = source_lock.as_ref().ok_or("No merge source loaded")?;
+
+    let appearances_lock = state.appearances.read();
+    let current = appearances_lock.as_ref().ok_or("No appearances loaded")?;
+
+    let mut diff = diff_appearances(current, source);
+    diff.old_path = state.tibia_path.lock().as_ref().map(|p| p.to_string_lossy().to_string());
+    Ok(diff)
+}
+
+/// Write a previously computed diff to `path` as JSON or HTML.
+#[tauri::command]
+pub async fn export_appearances_diff(diff: AppearancesDiff, path: String, format: DiffExportFormat) -> Result<String, String> {
+    write_diff(&diff, &path, format)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::protobuf::{AppearanceFlagMarket, AppearanceFlags, FrameGroup, SpriteInfo};
+
+    fn object(id: u32, sprite_ids: Vec<u32>) -> Appearance {
+        Appearance {
+            id: Some(id),
+            frame_group: vec![FrameGroup {
+                sprite_info: Some(SpriteInfo {
+                    sprite_id: sprite_ids,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn reports_added_removed_and_modified_ids() {
+        let old = Appearances {
+            object: vec![object(100, vec![1]), object(101, vec![2])],
+            ..Default::default()
+        };
+        let new = Appearances {
+            object: vec![object(100, vec![1]), object(102, vec![3])],
+            ..Default::default()
+        };
+
+        let diff = diff_appearances(&old, &new);
+        let objects = &diff.categories[0];
+        assert_eq!(objects.added, vec![102]);
+        assert_eq!(objects.removed, vec![101]);
+        assert!(objects.modified.is_empty());
+        assert!(diff.categories[1..].iter().all(|c| c.added.is_empty() && c.removed.is_empty()));
+    }
+
+    #[test]
+    fn reports_flag_fields_and_sprite_slots() {
+        let old_app = object(100, vec![10, 11]);
+        let mut new_app = object(100, vec![10, 12, 13]);
+        new_app.flags = Some(AppearanceFlags {
+            take: Some(true),
+            market: Some(AppearanceFlagMarket {
+                category: Some(3),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+
+        let diff = diff_appearance(100, &old_app, &new_app);
+        let paths: Vec<&str> = diff.fields.iter().map(|f| f.path.as_str()).collect();
+        assert!(paths.contains(&"flags.take"));
+        assert!(paths.contains(&"flags.market.category"));
+        assert!(!paths.iter().any(|p| p.contains("sprite_ids")));
+
+        let slots: Vec<(usize, Option<u32>, Option<u32>)> = diff.sprites.iter().map(|s| (s.index, s.old, s.new)).collect();
+        assert_eq!(slots, vec![(1, Some(11), Some(12)), (2, None, Some(13))]);
+    }
+
+    #[test]
+    fn html_escapes_values() {
+        let old = Appearances {
+            object: vec![object(1, vec![])],
+            ..Default::default()
+        };
+        let mut renamed = object(1, vec![]);
+        renamed.name = Some(b"<b>sword</b>".to_vec());
+        let new = Appearances {
+            object: vec![renamed],
+            ..Default::default()
+        };
+
+        let html = render_diff_html(&diff_appearances(&old, &new));
+        assert!(html.contains("&lt;b&gt;sword&lt;/b&gt;"));
+        assert!(!html.contains("<b>sword"));
+    }
+}
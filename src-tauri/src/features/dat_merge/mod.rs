@@ -1,15 +1,20 @@
+pub mod diff;
 pub mod staticdata_merge;
 pub use staticdata_merge::{get_staticdata_merge_preview, execute_staticdata_merge, StaticDataMergeThresholds, StaticDataMergePreview, StaticDataMergeResult};
 
+use crate::core::jobs::JobHandle;
+use crate::core::protobuf::unknown_fields::UnknownFields;
 use crate::core::protobuf::{Appearance, Appearances};
-use crate::features::appearances::parsers::load_appearances;
+use crate::features::appearances::parsers::{encode_appearances, load_appearances_preserving};
 use crate::features::appearances::commands::helpers::{rebuild_indexes, invalidate_search_cache};
+use crate::features::appearances::commands::AppearanceCategory;
+use crate::features::jobs::run_job;
 use crate::features::sprites::parsers::SpriteCatalogEntry;
 use crate::state::AppState;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
-use tauri::State;
+use tauri::{AppHandle, State};
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -97,7 +102,7 @@ pub async fn load_merge_folder(assets_path: String, state: State<'_, AppState>)
 
     // Auto-discover appearances file (appearances-{hash}.dat or appearances_latest.dat)
     let appearances_path = find_appearances_dat(&dir).ok_or_else(|| "Nenhum arquivo appearances-*.dat ou appearances_latest.dat encontrado na pasta selecionada".to_string())?;
-    let appearances = load_appearances(&appearances_path).map_err(|e: anyhow::Error| format!("Falha ao carregar appearances.dat: {}", e))?;
+    let (appearances, unknown_fields) = load_appearances_preserving(&appearances_path).map_err(|e: anyhow::Error| format!("Falha ao carregar appearances.dat: {}", e))?;
 
     let appearances_stats = MergeSourceStats {
         objects: appearances.object.len(),
@@ -107,6 +112,7 @@ pub async fn load_merge_folder(assets_path: String, state: State<'_, AppState>)
     };
 
     *state.merge_source.write() = Some(appearances);
+    *state.merge_source_unknown_fields.write() = unknown_fields;
     *state.merge_source_assets_dir.write() = Some(dir.clone());
 
     // catalog-content.json
@@ -143,7 +149,7 @@ pub async fn load_merge_folder(assets_path: String, state: State<'_, AppState>)
 /// Load a secondary .dat file to use as merge source (new official dat).
 #[tauri::command]
 pub async fn load_merge_source(path: String, state: State<'_, AppState>) -> Result<MergeSourceStats, String> {
-    let appearances = load_appearances(&path).map_err(|e: anyhow::Error| e.to_string())?;
+    let (appearances, unknown_fields) = load_appearances_preserving(&path).map_err(|e: anyhow::Error| e.to_string())?;
 
     let stats = MergeSourceStats {
         objects: appearances.object.len(),
@@ -153,6 +159,7 @@ pub async fn load_merge_source(path: String, state: State<'_, AppState>) -> Resu
     };
 
     *state.merge_source.write() = Some(appearances);
+    *state.merge_source_unknown_fields.write() = unknown_fields;
     Ok(stats)
 }
 
@@ -232,14 +239,53 @@ pub async fn execute_dat_merge(thresholds: MergeThresholds, state: State<'_, App
         lock.as_ref().ok_or("No appearances loaded")?.clone()
     };
 
-    fn merge_category(mut base: Vec<Appearance>, custom: &[Appearance], threshold: u32) -> (Vec<Appearance>, usize) {
+    let source_unknown = state.merge_source_unknown_fields.read().clone();
+    let (merged, unknown_fields, result_stats) = merge_appearances(source, source_unknown, &current, &state.appearance_unknown_fields.read(), &thresholds);
+
+    // Replace primary state with merged result
+    {
+        let mut lock = state.appearances.write();
+        *lock = Some(merged);
+        *state.appearance_unknown_fields.write() = unknown_fields;
+        // History refers to the pre-merge appearances
+        state.appearance_journal.lock().clear();
+
+        // Rebuild O(1) indexes
+        if let Some(appearances) = lock.as_ref() {
+            rebuild_indexes(&state, appearances);
+        }
+    }
+
+    // Invalidate all search caches
+    invalidate_search_cache(&state);
+
+    Ok(result_stats)
+}
+
+/// New official `source` plus the custom appearances (id >= threshold) of
+/// `current` it doesn't have. Unknown fields follow their appearances: the
+/// source's are kept and the current file's are carried over for the copied ids.
+fn merge_appearances(
+    source: Appearances,
+    source_unknown: UnknownFields,
+    current: &Appearances,
+    current_unknown: &UnknownFields,
+    thresholds: &MergeThresholds,
+) -> (Appearances, UnknownFields, MergeSourceStats) {
+    let mut unknown = source_unknown;
+
+    let mut merge_category = |mut base: Vec<Appearance>, custom: &[Appearance], category: AppearanceCategory, threshold: u32| {
         let base_ids: HashSet<u32> = base.iter().filter_map(|a| a.id).collect();
+        let field = category.appearances_field();
         let mut added = 0usize;
 
         for app in custom {
             if let Some(id) = app.id {
                 if id >= threshold && !base_ids.contains(&id) {
                     base.push(app.clone());
+                    if let Some(fields) = current_unknown.appearance(field, id) {
+                        unknown.set_appearance(field, id, fields.clone());
+                    }
                     added += 1;
                 }
             }
@@ -247,20 +293,13 @@ pub async fn execute_dat_merge(thresholds: MergeThresholds, state: State<'_, App
 
         base.sort_by_key(|a| a.id.unwrap_or(0));
         (base, added)
-    }
-
-    let (objects, obj_added) = merge_category(source.object, &current.object, thresholds.objects);
-    let (outfits, out_added) = merge_category(source.outfit, &current.outfit, thresholds.outfits);
-    let (effects, eff_added) = merge_category(source.effect, &current.effect, thresholds.effects);
-    let (missiles, mis_added) = merge_category(source.missile, &current.missile, thresholds.missiles);
-
-    let result_stats = MergeSourceStats {
-        objects: obj_added,
-        outfits: out_added,
-        effects: eff_added,
-        missiles: mis_added,
     };
 
+    let (objects, obj_added) = merge_category(source.object, &current.object, AppearanceCategory::Objects, thresholds.objects);
+    let (outfits, out_added) = merge_category(source.outfit, &current.outfit, AppearanceCategory::Outfits, thresholds.outfits);
+    let (effects, eff_added) = merge_category(source.effect, &current.effect, AppearanceCategory::Effects, thresholds.effects);
+    let (missiles, mis_added) = merge_category(source.missile, &current.missile, AppearanceCategory::Missiles, thresholds.missiles);
+
     let merged = Appearances {
         object: objects,
         outfit: outfits,
@@ -268,44 +307,35 @@ pub async fn execute_dat_merge(thresholds: MergeThresholds, state: State<'_, App
         missile: missiles,
         special_meaning_appearance_ids: source.special_meaning_appearance_ids,
     };
-
-    // Replace primary state with merged result
-    {
-        let mut lock = state.appearances.write();
-        *lock = Some(merged);
-
-        // Rebuild O(1) indexes
-        if let Some(appearances) = lock.as_ref() {
-            rebuild_indexes(&state, appearances);
-        }
-    }
-
-    // Invalidate all search caches
-    invalidate_search_cache(&state);
-
-    Ok(result_stats)
+    let stats = MergeSourceStats {
+        objects: obj_added,
+        outfits: out_added,
+        effects: eff_added,
+        missiles: mis_added,
+    };
+    (merged, unknown, stats)
 }
 
 /// Unload the merge source from memory.
 #[tauri::command]
 pub async fn unload_merge_source(state: State<'_, AppState>) -> Result<(), String> {
     *state.merge_source.write() = None;
+    *state.merge_source_unknown_fields.write() = UnknownFields::default();
     Ok(())
 }
 
 /// Save the current (merged) appearances to a specific path chosen by the caller.
 #[tauri::command]
 pub async fn save_merged_dat(path: String, state: State<'_, AppState>) -> Result<usize, String> {
-    use prost::Message;
-
     let appearances = {
         let lock = state.appearances.read();
         lock.as_ref().ok_or("No appearances loaded")?.clone()
     };
+    let unknown_fields = state.appearance_unknown_fields.read().clone();
 
     let size = tauri::async_runtime::spawn_blocking(move || {
-        let mut buf = Vec::new();
-        appearances.encode(&mut buf).map_err(|e| format!("Encode error: {}", e))?;
+        let buf = encode_appearances(&appearances, &unknown_fields).map_err(|e| format!("Encode error: {}", e))?;
+        crate::core::backups::snapshot_before_write(&path);
         std::fs::write(&path, &buf).map_err(|e| format!("Write error: {}", e))?;
         Ok::<usize, String>(buf.len())
     })
@@ -383,9 +413,14 @@ pub async fn get_sprite_merge_preview(thresholds: MergeThresholds, state: State<
 /// Entries with conflicting sprite ID ranges are automatically remapped to IDs above the
 /// official maximum — the LZMA content is unchanged, only the catalog entries and .dat
 /// sprite_id references are updated.
+/// Runs as a job; cancelling before the catalog is staged leaves the state untouched.
 #[tauri::command]
-pub async fn execute_sprite_merge(thresholds: MergeThresholds, state: State<'_, AppState>) -> Result<SpriteMergeResult, String> {
-    let old_assets_dir = old_assets_dir(&state)?;
+pub async fn execute_sprite_merge(thresholds: MergeThresholds, app: AppHandle, state: State<'_, AppState>) -> Result<SpriteMergeResult, String> {
+    run_job(&app, state.inner(), "sprite_merge", |job| execute_sprite_merge_impl(state.inner(), &thresholds, job))
+}
+
+fn execute_sprite_merge_impl(state: &AppState, thresholds: &MergeThresholds, job: &JobHandle) -> Result<SpriteMergeResult, String> {
+    let old_assets_dir = old_assets_dir(state)?;
     let new_assets_dir = {
         let lock = state.merge_source_assets_dir.read();
         lock.as_ref().ok_or("New official assets not loaded")?.clone()
@@ -399,7 +434,7 @@ pub async fn execute_sprite_merge(thresholds: MergeThresholds, state: State<'_,
 
     let appearances_lock = state.appearances.read();
     let current = appearances_lock.as_ref().ok_or("No appearances loaded")?;
-    let (mut custom_entries, _) = collect_custom_sprite_entries(current, &thresholds, &old_entries);
+    let (mut custom_entries, _) = collect_custom_sprite_entries(current, thresholds, &old_entries);
     drop(appearances_lock);
 
     // Sort by first_sprite_id for deterministic remap ordering
@@ -419,7 +454,10 @@ pub async fn execute_sprite_merge(thresholds: MergeThresholds, state: State<'_,
 
     let mut staged_files: Vec<(PathBuf, PathBuf)> = Vec::new();
 
+    job.set_total(custom_entries.len());
     for entry in &custom_entries {
+        job.check_cancelled()?;
+        job.advance(1);
         let src = old_assets_dir.join(&entry.file);
         let dst = new_assets_dir.join(&entry.file);
 
@@ -460,6 +498,7 @@ pub async fn execute_sprite_merge(thresholds: MergeThresholds, state: State<'_,
         new_catalog.push(serde_json::to_value(&catalog_entry).map_err(|e| format!("Serialize entry error: {}", e))?);
         catalog_entries_added += 1;
     }
+    job.check_cancelled()?;
 
     // Stage catalog and file copies in memory instead of writing to disk
     *state.staged_sprite_files.write() = staged_files;
@@ -471,13 +510,15 @@ pub async fn execute_sprite_merge(thresholds: MergeThresholds, state: State<'_,
     if !old_to_new.is_empty() {
         let mut lock = state.appearances.write();
         if let Some(appearances) = lock.as_mut() {
-            remap_sprite_ids_in_appearances(appearances, &old_to_new, &thresholds);
+            remap_sprite_ids_in_appearances(appearances, &old_to_new, thresholds);
         }
+        // History snapshots still hold the old sprite ids
+        state.appearance_journal.lock().clear();
         if let Some(appearances) = lock.as_ref() {
-            rebuild_indexes(&state, appearances);
+            rebuild_indexes(state, appearances);
         }
         drop(lock);
-        invalidate_search_cache(&state);
+        invalidate_search_cache(state);
     }
 
     Ok(SpriteMergeResult {
@@ -512,28 +553,43 @@ pub struct SaveAllMergeResult {
 
 /// Write all staged merge results to disk atomically.
 /// This is the only command that writes files — all execute_* commands only stage in memory.
+/// Runs as a job. Cancelling only works before the first file is written (the
+/// staged data is kept so it can be retried); once writing starts the save runs
+/// to the end, since a half-written set of files is worse than a slow save.
 #[tauri::command]
-pub async fn save_all_merge(dat_path: String, state: State<'_, AppState>) -> Result<SaveAllMergeResult, String> {
+pub async fn save_all_merge(dat_path: String, app: AppHandle, state: State<'_, AppState>) -> Result<SaveAllMergeResult, String> {
+    run_job(&app, state.inner(), "save_all_merge", |job| save_all_merge_impl(state.inner(), &dat_path, job))
+}
+
+fn save_all_merge_impl(state: &AppState, dat_path: &str, job: &JobHandle) -> Result<SaveAllMergeResult, String> {
     use crate::features::staticdata::parsers::save_staticdata;
-    use prost::Message;
+
+    // dat + sprite files + catalog + staticdata + staticmapdata
+    job.set_total(state.staged_sprite_files.read().len() + 4);
+    job.check_cancelled()?;
+    // Past this point the save is not cancellable: every file below belongs to one merge.
 
     // 1. Save appearances .dat
+    job.set_message("Saving appearances");
     let dat_bytes = {
         let lock = state.appearances.read();
         let appearances = lock.as_ref().ok_or("No appearances loaded")?;
-        let mut buf = Vec::new();
-        appearances.encode(&mut buf).map_err(|e| format!("Encode .dat error: {}", e))?;
-        std::fs::write(&dat_path, &buf).map_err(|e| format!("Write .dat error: {}", e))?;
+        let buf = encode_appearances(appearances, &state.appearance_unknown_fields.read()).map_err(|e| format!("Encode .dat error: {}", e))?;
+        crate::core::backups::snapshot_before_write(dat_path);
+        std::fs::write(dat_path, &buf).map_err(|e| format!("Write .dat error: {}", e))?;
         buf.len()
     };
+    job.advance(1);
 
     // 2. Copy staged LZMA sprite files
+    job.set_message("Copying sprite files");
     let sprite_files_copied = {
         let files = state.staged_sprite_files.read();
         let mut copied = 0usize;
         for (src, dst) in files.iter() {
             std::fs::copy(src, dst).map_err(|e| format!("Copy sprite file error: {}", e))?;
             copied += 1;
+            job.advance(1);
         }
         copied
     };
@@ -543,12 +599,14 @@ pub async fn save_all_merge(dat_path: String, state: State<'_, AppState>) -> Res
         let lock = state.staged_catalog.read();
         if let Some((path, catalog)) = lock.as_ref() {
             let json = serde_json::to_string_pretty(catalog).map_err(|e| format!("Serialize catalog error: {}", e))?;
+            crate::core::backups::snapshot_before_write(path);
             std::fs::write(path, json).map_err(|e| format!("Write catalog error: {}", e))?;
             true
         } else {
             false
         }
     };
+    job.advance(1);
 
     // 4. Save staged staticdata
     let staticdata_saved = {
@@ -560,17 +618,20 @@ pub async fn save_all_merge(dat_path: String, state: State<'_, AppState>) -> Res
             false
         }
     };
+    job.advance(1);
 
     // 5. Save staged staticmapdata
     let staticmapdata_saved = {
         let lock = state.staged_staticmapdata.read();
         if let Some((path, buf)) = lock.as_ref() {
+            crate::core::backups::snapshot_before_write(path);
             std::fs::write(path, buf).map_err(|e| format!("Write staticmapdata error: {}", e))?;
             true
         } else {
             false
         }
     };
+    job.advance(1);
 
     // Clear all staged data
     state.staged_sprite_files.write().clear();
@@ -711,3 +772,59 @@ fn collect_custom_sprite_entries(current: &Appearances, thresholds: &MergeThresh
 
     (entries, total_sprite_refs)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::protobuf::unknown_fields::{capture_unknown_fields, APPEARANCES_MESSAGE};
+
+    fn object(id: u32) -> Appearance {
+        Appearance {
+            id: Some(id),
+            ..Default::default()
+        }
+    }
+
+    /// Unknown varint field 900 = `value` on an appearance.
+    fn unknown_flag(value: u8) -> UnknownFields {
+        UnknownFields {
+            raw: vec![0xA0, 0x38, value],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn merged_dat_keeps_unknown_fields_of_both_sides() {
+        let source = Appearances {
+            object: vec![object(100), object(101)],
+            ..Default::default()
+        };
+        let mut source_unknown = UnknownFields::default();
+        source_unknown.set_appearance(1, 100, unknown_flag(1));
+
+        // Old custom dat: 100 was official there too, 200 and 201 are custom.
+        let current = Appearances {
+            object: vec![object(100), object(200), object(201)],
+            ..Default::default()
+        };
+        let mut current_unknown = UnknownFields::default();
+        current_unknown.set_appearance(1, 100, unknown_flag(2));
+        current_unknown.set_appearance(1, 200, unknown_flag(3));
+
+        let thresholds = MergeThresholds {
+            objects: 200,
+            outfits: u32::MAX,
+            effects: u32::MAX,
+            missiles: u32::MAX,
+        };
+        let (merged, unknown, stats) = merge_appearances(source, source_unknown, &current, &current_unknown, &thresholds);
+        assert_eq!(stats.objects, 2);
+        assert_eq!(merged.object.iter().filter_map(|a| a.id).collect::<Vec<_>>(), vec![100, 101, 200, 201]);
+
+        let saved = encode_appearances(&merged, &unknown).unwrap();
+        let recaptured = capture_unknown_fields(&saved, APPEARANCES_MESSAGE).unwrap();
+        assert_eq!(recaptured.count(), 2);
+        assert_eq!(recaptured.appearance(1, 100), Some(&unknown_flag(1)));
+        assert_eq!(recaptured.appearance(1, 200), Some(&unknown_flag(3)));
+    }
+}
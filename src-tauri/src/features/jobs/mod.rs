@@ -0,0 +1,52 @@
+// Jobs feature module
+// Progress events and cancellation for long-running commands
+
+use crate::core::jobs::{JobHandle, JobInfo};
+use crate::state::AppState;
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, State};
+
+/// Event carrying a `JobInfo` snapshot on start, progress and completion.
+pub const JOB_PROGRESS_EVENT: &str = "job-progress";
+
+/// Registers a job whose progress is emitted to the frontend.
+pub fn start_job(app: &AppHandle, state: &AppState, kind: &str) -> Arc<JobHandle> {
+    let app = app.clone();
+    state.jobs.start(
+        kind,
+        Some(Box::new(move |info: JobInfo| {
+            if let Err(e) = app.emit(JOB_PROGRESS_EVENT, info) {
+                log::warn!("Failed to emit job progress: {}", e);
+            }
+        })),
+    )
+}
+
+/// Records the outcome of `job` and passes `result` through.
+pub fn finish_job<T>(state: &AppState, job: &JobHandle, result: Result<T, String>) -> Result<T, String> {
+    state.jobs.finish(job, result.as_ref().err().map(String::as_str));
+    result
+}
+
+/// Runs `work` as a tracked job.
+pub fn run_job<T>(app: &AppHandle, state: &AppState, kind: &str, work: impl FnOnce(&JobHandle) -> Result<T, String>) -> Result<T, String> {
+    let job = start_job(app, state, kind);
+    let result = work(&job);
+    finish_job(state, &job, result)
+}
+
+/// Running and recently finished jobs, oldest first.
+#[tauri::command]
+pub async fn list_jobs(state: State<'_, AppState>) -> Result<Vec<JobInfo>, String> {
+    Ok(state.jobs.list())
+}
+
+/// Request cancellation of a running job. Returns false when it already finished.
+#[tauri::command]
+pub async fn cancel_job(job_id: u64, state: State<'_, AppState>) -> Result<bool, String> {
+    let cancelled = state.jobs.cancel(job_id);
+    if cancelled {
+        log::info!("Cancellation requested for job {}", job_id);
+    }
+    Ok(cancelled)
+}
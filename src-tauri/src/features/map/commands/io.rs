@@ -0,0 +1,114 @@
+use crate::features::map::parsers::{area_tree, get_statistics, load_map, remove_area, remove_npc, save_map, set_npc, upsert_area, MapAreaNode, MapStats};
+use crate::features::map::{Area, MapFile, Npc};
+use crate::state::AppState;
+use std::path::PathBuf;
+use tauri::State;
+
+// Edits apply to the loaded map (`state.map`) and reach disk on
+// `save_map_file`, like the staticdata browser.
+
+#[tauri::command]
+pub fn load_map_file(path: String, state: State<'_, AppState>) -> Result<MapStats, String> {
+    let path_buf = PathBuf::from(&path);
+    if !path_buf.exists() {
+        return Err(format!("File does not exist: {}", path));
+    }
+
+    let map = load_map(&path_buf).map_err(|e| format!("Failed to parse map: {}", e))?;
+    let stats = get_statistics(&map);
+    *state.map.write() = Some(map);
+    Ok(stats)
+}
+
+#[tauri::command]
+pub async fn list_map_files(tibia_path: String) -> Result<Vec<String>, String> {
+    use std::fs;
+    let assets_path = PathBuf::from(tibia_path).join("assets");
+    let entries = fs::read_dir(&assets_path).map_err(|e| format!("Failed to read assets directory: {}", e))?;
+    let mut files_data: Vec<(String, u64)> = Vec::new();
+
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
+        let path = entry.path();
+        if let Some(file_name) = path.file_name() {
+            let file_name_str = file_name.to_string_lossy().to_string();
+            if file_name_str.starts_with("map-") && file_name_str.ends_with(".dat") {
+                let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+                files_data.push((file_name_str, size));
+            }
+        }
+    }
+
+    files_data.sort_by(|(_, size_a), (_, size_b)| size_b.cmp(size_a));
+    Ok(files_data.into_iter().map(|(name, _)| name).collect())
+}
+
+#[tauri::command]
+pub fn save_map_file(path: String, state: State<'_, AppState>) -> Result<(), String> {
+    let lock = state.map.read();
+    match &*lock {
+        Some(map) => save_map(path, map).map_err(|e| format!("Failed to save map: {}", e)),
+        None => Err("No map loaded to save".to_string()),
+    }
+}
+
+#[tauri::command]
+pub fn get_map_areas(state: State<'_, AppState>) -> Result<Vec<Area>, String> {
+    let lock = state.map.read();
+    let map = lock.as_ref().ok_or("No map loaded")?;
+    Ok(map.areas.clone())
+}
+
+/// Areas nested by `subarea_ids`, with the NPC pin count of each.
+#[tauri::command]
+pub fn get_map_area_tree(state: State<'_, AppState>) -> Result<Vec<MapAreaNode>, String> {
+    let lock = state.map.read();
+    let map = lock.as_ref().ok_or("No map loaded")?;
+    Ok(area_tree(map))
+}
+
+/// Add or replace an area by `area_id`: name, type, subareas, label
+/// coordinate, `reject_donations` and alias.
+#[tauri::command]
+pub fn update_map_area(area: Area, state: State<'_, AppState>) -> Result<(), String> {
+    let mut lock = state.map.write();
+    let map = lock.as_mut().ok_or("No map loaded")?;
+    upsert_area(map, area)
+}
+
+#[tauri::command]
+pub fn remove_map_area(area_id: u32, state: State<'_, AppState>) -> Result<usize, String> {
+    let mut lock = state.map.write();
+    let map = lock.as_mut().ok_or("No map loaded")?;
+    remove_area(map, area_id)
+}
+
+/// NPC pins in file order; their position is the index the editors take.
+#[tauri::command]
+pub fn get_map_npcs(state: State<'_, AppState>) -> Result<Vec<Npc>, String> {
+    let lock = state.map.read();
+    let map = lock.as_ref().ok_or("No map loaded")?;
+    Ok(map.npcs.clone())
+}
+
+/// Replace the NPC pin at `index`, or add one when `index` is None.
+#[tauri::command]
+pub fn update_map_npc(index: Option<usize>, npc: Npc, state: State<'_, AppState>) -> Result<usize, String> {
+    let mut lock = state.map.write();
+    let map = lock.as_mut().ok_or("No map loaded")?;
+    set_npc(map, index, npc)
+}
+
+#[tauri::command]
+pub fn remove_map_npc(index: usize, state: State<'_, AppState>) -> Result<(), String> {
+    let mut lock = state.map.write();
+    let map = lock.as_mut().ok_or("No map loaded")?;
+    remove_npc(map, index).map(|_| ())
+}
+
+#[tauri::command]
+pub fn get_map_resource_files(state: State<'_, AppState>) -> Result<Vec<MapFile>, String> {
+    let lock = state.map.read();
+    let map = lock.as_ref().ok_or("No map loaded")?;
+    Ok(map.resource_files.clone())
+}
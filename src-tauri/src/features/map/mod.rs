@@ -0,0 +1,6 @@
+// Map feature: load, edit and save the Cyclopedia map file (`map.proto`) —
+// areas, subarea hierarchies, labels and NPC pins.
+pub mod commands;
+pub mod parsers;
+
+pub use crate::core::protobuf::map::{Area, AreaType, Map, MapFile, Npc};
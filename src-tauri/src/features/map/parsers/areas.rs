@@ -0,0 +1,219 @@
+// Area hierarchy and NPC pin edits on a loaded map. Areas reference their
+// children through `subarea_ids`; NPC pins point at a subarea. Edits keep both
+// consistent: no dangling ids, no cycles, no pins left on a removed area.
+
+use crate::core::protobuf::map::{Area, Map, Npc};
+use crate::core::protobuf::shared::Coordinate;
+use serde::Serialize;
+use std::collections::HashSet;
+
+/// An area with its subareas resolved, as shown in the Cyclopedia map tree.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MapAreaNode {
+    pub area_id: u32,
+    pub name: Option<String>,
+    pub alias: Option<String>,
+    pub area_type: Option<i32>,
+    pub label_coordinate: Option<Coordinate>,
+    pub reject_donations: bool,
+    pub npc_count: usize,
+    pub subareas: Vec<MapAreaNode>,
+}
+
+fn find_area(map: &Map, area_id: u32) -> Option<&Area> {
+    map.areas.iter().find(|a| a.area_id == Some(area_id))
+}
+
+/// True when `target` is reachable from `from` through `subarea_ids`, using
+/// `edited`'s children in place of the stored ones for that area.
+fn reaches(map: &Map, edited: &Area, from: u32, target: u32, seen: &mut HashSet<u32>) -> bool {
+    if from == target {
+        return true;
+    }
+    if !seen.insert(from) {
+        return false;
+    }
+    let children = if edited.area_id == Some(from) {
+        &edited.subarea_ids
+    } else {
+        match find_area(map, from) {
+            Some(area) => &area.subarea_ids,
+            None => return false,
+        }
+    };
+    children.iter().any(|child| reaches(map, edited, *child, target, seen))
+}
+
+/// Adds `area` or replaces the area with the same `area_id`.
+pub fn upsert_area(map: &mut Map, area: Area) -> Result<(), String> {
+    let area_id = area.area_id.ok_or("Area must have an area_id")?;
+    for child in &area.subarea_ids {
+        if *child == area_id {
+            return Err(format!("Area {} cannot be its own subarea", area_id));
+        }
+        if find_area(map, *child).is_none() {
+            return Err(format!("Subarea {} does not exist", child));
+        }
+        if reaches(map, &area, *child, area_id, &mut HashSet::new()) {
+            return Err(format!("Subarea {} already contains area {}", child, area_id));
+        }
+    }
+    if area.subarea_ids.iter().collect::<HashSet<_>>().len() != area.subarea_ids.len() {
+        return Err(format!("Area {} lists a subarea twice", area_id));
+    }
+
+    match map.areas.iter().position(|a| a.area_id == Some(area_id)) {
+        Some(pos) => map.areas[pos] = area,
+        None => map.areas.push(area),
+    }
+    Ok(())
+}
+
+/// Removes an area and detaches it from its parents. Refuses while NPC pins or
+/// subareas still point at it. Returns the number of parents it was detached from.
+pub fn remove_area(map: &mut Map, area_id: u32) -> Result<usize, String> {
+    let area = find_area(map, area_id).ok_or_else(|| format!("Area {} not found", area_id))?;
+    if !area.subarea_ids.is_empty() {
+        return Err(format!("Area {} still has {} subarea(s)", area_id, area.subarea_ids.len()));
+    }
+    let pins = map.npcs.iter().filter(|n| n.subarea_id == Some(area_id)).count();
+    if pins > 0 {
+        return Err(format!("{} NPC pin(s) still reference area {}", pins, area_id));
+    }
+
+    map.areas.retain(|a| a.area_id != Some(area_id));
+    let mut detached = 0;
+    for parent in &mut map.areas {
+        let before = parent.subarea_ids.len();
+        parent.subarea_ids.retain(|id| *id != area_id);
+        detached += before - parent.subarea_ids.len();
+    }
+    Ok(detached)
+}
+
+fn build_node(map: &Map, area: &Area, seen: &mut HashSet<u32>) -> Option<MapAreaNode> {
+    let area_id = area.area_id?;
+    if !seen.insert(area_id) {
+        return None;
+    }
+    let subareas = area.subarea_ids.iter().filter_map(|id| find_area(map, *id)).filter_map(|child| build_node(map, child, seen)).collect();
+    Some(MapAreaNode {
+        area_id,
+        name: area.name.clone(),
+        alias: area.alias.clone(),
+        area_type: area.area_type,
+        label_coordinate: area.label_coordinate,
+        reject_donations: area.reject_donations.unwrap_or(false),
+        npc_count: map.npcs.iter().filter(|n| n.subarea_id == Some(area_id)).count(),
+        subareas,
+    })
+}
+
+/// Top-level areas (those no other area lists as a subarea) with their subtrees.
+pub fn area_tree(map: &Map) -> Vec<MapAreaNode> {
+    let children: HashSet<u32> = map.areas.iter().flat_map(|a| a.subarea_ids.iter().copied()).collect();
+    let mut seen = HashSet::new();
+    map.areas.iter().filter(|a| a.area_id.is_some_and(|id| !children.contains(&id))).filter_map(|a| build_node(map, a, &mut seen)).collect()
+}
+
+/// Replaces the NPC pin at `index`, or appends when None. Returns its index.
+pub fn set_npc(map: &mut Map, index: Option<usize>, npc: Npc) -> Result<usize, String> {
+    if let Some(subarea_id) = npc.subarea_id {
+        if find_area(map, subarea_id).is_none() {
+            return Err(format!("Subarea {} does not exist", subarea_id));
+        }
+    }
+    match index {
+        Some(index) => {
+            let slot = map.npcs.get_mut(index).ok_or_else(|| format!("NPC pin {} not found", index))?;
+            *slot = npc;
+            Ok(index)
+        }
+        None => {
+            map.npcs.push(npc);
+            Ok(map.npcs.len() - 1)
+        }
+    }
+}
+
+pub fn remove_npc(map: &mut Map, index: usize) -> Result<Npc, String> {
+    if index >= map.npcs.len() {
+        return Err(format!("NPC pin {} not found", index));
+    }
+    Ok(map.npcs.remove(index))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::protobuf::map::AreaType;
+
+    fn area(id: u32, area_type: AreaType, subareas: &[u32]) -> Area {
+        Area {
+            area_id: Some(id),
+            name: Some(format!("Area {}", id)),
+            area_type: Some(area_type as i32),
+            subarea_ids: subareas.to_vec(),
+            ..Default::default()
+        }
+    }
+
+    fn sample() -> Map {
+        Map {
+            areas: vec![area(1, AreaType::Area, &[10, 11]), area(10, AreaType::Subarea, &[]), area(11, AreaType::Subarea, &[])],
+            npcs: vec![Npc {
+                name: Some("Rashid".to_string()),
+                tile_coordinate: Some(Coordinate {
+                    x: Some(32200),
+                    y: Some(31800),
+                    z: Some(7),
+                }),
+                subarea_id: Some(10),
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn upsert_rejects_dangling_and_cyclic_subareas() {
+        let mut map = sample();
+        assert!(upsert_area(&mut map, area(2, AreaType::Area, &[99])).is_err());
+        assert!(upsert_area(&mut map, area(10, AreaType::Subarea, &[1])).is_err());
+        assert!(upsert_area(&mut map, area(11, AreaType::Subarea, &[11])).is_err());
+
+        let mut renamed = area(11, AreaType::Subarea, &[]);
+        renamed.alias = Some("Port".to_string());
+        renamed.reject_donations = Some(true);
+        upsert_area(&mut map, renamed).unwrap();
+        assert_eq!(map.areas.len(), 3);
+        assert_eq!(find_area(&map, 11).unwrap().alias.as_deref(), Some("Port"));
+    }
+
+    #[test]
+    fn tree_and_removal_keep_hierarchy_consistent() {
+        let mut map = sample();
+        let tree = area_tree(&map);
+        assert_eq!(tree.len(), 1);
+        assert_eq!(tree[0].subareas.iter().map(|n| n.area_id).collect::<Vec<_>>(), vec![10, 11]);
+        assert_eq!(tree[0].subareas[0].npc_count, 1);
+
+        // Pinned NPCs and subareas block removal; a free subarea is detached.
+        assert!(remove_area(&mut map, 10).is_err());
+        assert!(remove_area(&mut map, 1).is_err());
+        assert_eq!(remove_area(&mut map, 11).unwrap(), 1);
+        assert_eq!(find_area(&map, 1).unwrap().subarea_ids, vec![10]);
+
+        assert!(set_npc(
+            &mut map,
+            None,
+            Npc {
+                subarea_id: Some(11),
+                ..Default::default()
+            }
+        )
+        .is_err());
+        assert_eq!(remove_npc(&mut map, 0).unwrap().name.as_deref(), Some("Rashid"));
+        assert_eq!(remove_area(&mut map, 10).unwrap(), 1);
+    }
+}
@@ -0,0 +1,89 @@
+use crate::core::protobuf::map::{AreaType, Map};
+use crate::core::protobuf::shared::Coordinate;
+use anyhow::{Context, Result};
+use prost::Message;
+use std::fs;
+use std::path::Path;
+
+pub fn load_map<P: AsRef<Path>>(path: P) -> Result<Map> {
+    let path = path.as_ref();
+    log::info!("Loading map file: {:?}", path);
+
+    let data = fs::read(path).context(format!("Failed to read map file: {:?}", path))?;
+
+    match Map::decode(&data[..]) {
+        Ok(map) => {
+            log::info!("Map decoded directly without decompression");
+            return Ok(map);
+        }
+        Err(e) => {
+            log::warn!("Direct protobuf decode failed: {}. Trying LZMA/XZ decompress fallback...", e);
+        }
+    }
+
+    let decompressed = crate::core::lzma::decompress(&data).context("Failed to decompress map data (LZMA/XZ)")?;
+    let map = Map::decode(&decompressed[..]).context("Failed to decode map protobuf data after decompression")?;
+
+    log::info!("Successfully parsed map: {} areas, {} npcs, {} resource files", map.areas.len(), map.npcs.len(), map.resource_files.len());
+
+    Ok(map)
+}
+
+/// On-disk encoding of a map file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum MapFormat {
+    Raw,
+    Xz,
+    Lzma,
+}
+
+fn detect_format(bytes: &[u8]) -> MapFormat {
+    if crate::core::lzma::is_xz(bytes) {
+        MapFormat::Xz
+    } else if Map::decode(bytes).is_ok() {
+        MapFormat::Raw
+    } else {
+        MapFormat::Lzma
+    }
+}
+
+/// Save `map` to `path`, keeping the existing file's encoding (raw / XZ / LZMA)
+/// so the client reads it back the same way. A new path defaults to raw.
+pub fn save_map<P: AsRef<Path>>(path: P, map: &Map) -> Result<()> {
+    let path = path.as_ref();
+    let buf = map.encode_to_vec();
+    let format = fs::read(path).ok().map(|existing| detect_format(&existing)).unwrap_or(MapFormat::Raw);
+
+    let out = match format {
+        MapFormat::Raw => buf,
+        MapFormat::Xz => crate::core::lzma::compress_xz(&buf).context("Failed to XZ-compress map")?,
+        MapFormat::Lzma => crate::core::lzma::compress(&buf).context("Failed to LZMA-compress map")?,
+    };
+
+    crate::core::backups::snapshot_before_write(path);
+    crate::core::fs_util::write_atomic(path, &out).context(format!("Failed to write map file: {:?}", path))?;
+    log::info!("Map saved ({:?}), on-disk: {} bytes", format, out.len());
+    Ok(())
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct MapStats {
+    pub total_areas: usize,
+    pub total_subareas: usize,
+    pub total_npcs: usize,
+    pub total_resource_files: usize,
+    pub top_left: Option<Coordinate>,
+    pub bottom_right: Option<Coordinate>,
+}
+
+pub fn get_statistics(map: &Map) -> MapStats {
+    let subareas = map.areas.iter().filter(|a| a.area_type == Some(AreaType::Subarea as i32)).count();
+    MapStats {
+        total_areas: map.areas.len() - subareas,
+        total_subareas: subareas,
+        total_npcs: map.npcs.len(),
+        total_resource_files: map.resource_files.len(),
+        top_left: map.top_left_tile_coordinate,
+        bottom_right: map.bottom_right_tile_coordinate,
+    }
+}
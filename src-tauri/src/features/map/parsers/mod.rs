@@ -0,0 +1,4 @@
+pub mod areas;
+pub mod map;
+pub use areas::*;
+pub use map::*;
@@ -1,21 +1,23 @@
-use crate::features::minimap::parsers::load_markers;
-use crate::features::minimap::parsers::otmm;
+use crate::core::jobs::{JobHandle, JOB_CANCELLED};
+use crate::core::protobuf::map::{MapFile, MapFileType};
+use crate::features::jobs::{finish_job, start_job};
+use crate::features::minimap::parsers::convert;
+use crate::features::minimap::parsers::generator::{AutomapLookup, EncodedMinimapTile, MinimapGenOptions, MinimapRaster};
+use crate::features::minimap::parsers::{
+    import_markers, load_markers, markers, markers_from_world, move_marker, read_markers, remove_marker, retype_marker, save_markers, set_marker, write_markers, MarkerFormat, MarkerImport,
+    MinimapMarkerDto, WorldMarkerSource,
+};
+use crate::features::minimap::MinimapMarkerFileContent;
+use crate::features::minimap::parsers::otmm::{self, OtmmMinimap};
+use crate::features::world::commands::loaded_world;
+use crate::state::AppState;
 use base64::Engine;
-use std::path::PathBuf;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, State};
 
 // ── Markers (minimap.proto) ────────────────────────────────────────────────
 
-/// A minimap marker flattened for the frontend.
-#[derive(serde::Serialize)]
-pub struct MinimapMarkerDto {
-    pub x: u32,
-    pub y: u32,
-    pub z: u32,
-    /// Marker icon type (Qt's minimap marker enum).
-    pub r#type: u32,
-    pub description: String,
-}
-
 /// Resolve the client's marker file, if present
 /// (`<client>/minimap/minimapmarkers.bin`). It only exists once the player has
 /// created markers in-game, so absence is normal.
@@ -33,20 +35,7 @@ pub fn minimap_find_markers_file(tibia_path: String) -> Option<String> {
 #[tauri::command]
 pub fn minimap_load_markers(path: String) -> Result<Vec<MinimapMarkerDto>, String> {
     let content = load_markers(&path).map_err(|e| format!("Failed to load minimap markers: {}", e))?;
-    Ok(content
-        .markers
-        .into_iter()
-        .map(|m| {
-            let pos = m.position.unwrap_or_default();
-            MinimapMarkerDto {
-                x: pos.x.unwrap_or(0),
-                y: pos.y.unwrap_or(0),
-                z: pos.z.unwrap_or(0),
-                r#type: m.r#type.unwrap_or(0),
-                description: m.description.unwrap_or_default(),
-            }
-        })
-        .collect())
+    Ok(markers(&content))
 }
 
 /// Convenience: find the client marker file and load it, returning an empty list
@@ -59,6 +48,91 @@ pub fn minimap_load_markers_auto(tibia_path: String) -> Result<Vec<MinimapMarker
     }
 }
 
+// Editing works on the markers held in `state.minimap_markers`; nothing
+// reaches disk until `minimap_save_markers`.
+
+fn with_markers<T>(state: &AppState, edit: impl FnOnce(&mut MinimapMarkerFileContent) -> anyhow::Result<T>) -> Result<T, String> {
+    let mut lock = state.minimap_markers.write();
+    let content = lock.as_mut().ok_or("No minimap markers open")?;
+    edit(content).map_err(|e| e.to_string())
+}
+
+/// Open a marker file for editing, or start an empty set when `path` is None
+/// (the client has no marker file until the player makes one).
+#[tauri::command]
+pub async fn minimap_open_markers(path: Option<String>, state: State<'_, AppState>) -> Result<Vec<MinimapMarkerDto>, String> {
+    let content = match path {
+        Some(path) => load_markers(&path).map_err(|e| format!("Failed to load minimap markers: {}", e))?,
+        None => MinimapMarkerFileContent::default(),
+    };
+    let list = markers(&content);
+    *state.minimap_markers.write() = Some(content);
+    Ok(list)
+}
+
+#[tauri::command]
+pub async fn minimap_get_markers(state: State<'_, AppState>) -> Result<Vec<MinimapMarkerDto>, String> {
+    with_markers(&state, |content| Ok(markers(content)))
+}
+
+/// Add a marker, or update the type/description of the one at its position.
+/// Returns true when an existing marker was updated.
+#[tauri::command]
+pub async fn minimap_set_marker(marker: MinimapMarkerDto, state: State<'_, AppState>) -> Result<bool, String> {
+    with_markers(&state, |content| set_marker(content, &marker))
+}
+
+#[tauri::command]
+pub async fn minimap_move_marker(x: u32, y: u32, z: u32, to_x: u32, to_y: u32, to_z: u32, state: State<'_, AppState>) -> Result<(), String> {
+    with_markers(&state, |content| move_marker(content, (x, y, z), (to_x, to_y, to_z)))
+}
+
+#[tauri::command]
+pub async fn minimap_retype_marker(x: u32, y: u32, z: u32, marker_type: u32, state: State<'_, AppState>) -> Result<(), String> {
+    with_markers(&state, |content| retype_marker(content, x, y, z, marker_type))
+}
+
+#[tauri::command]
+pub async fn minimap_remove_marker(x: u32, y: u32, z: u32, state: State<'_, AppState>) -> Result<MinimapMarkerDto, String> {
+    with_markers(&state, |content| remove_marker(content, x, y, z))
+}
+
+/// Write the edited markers to `path` (usually `<client>/minimap/minimapmarkers.bin`).
+#[tauri::command]
+pub async fn minimap_save_markers(path: String, state: State<'_, AppState>) -> Result<usize, String> {
+    let lock = state.minimap_markers.read();
+    let content = lock.as_ref().ok_or("No minimap markers open")?;
+    save_markers(&path, content).map_err(|e| format!("Failed to save minimap markers: {}", e))?;
+    Ok(content.markers.len())
+}
+
+#[tauri::command]
+pub async fn minimap_export_markers(path: String, format: MarkerFormat, state: State<'_, AppState>) -> Result<usize, String> {
+    let list = with_markers(&state, |content| Ok(markers(content)))?;
+    let text = write_markers(&list, format).map_err(|e| format!("Failed to export markers: {}", e))?;
+    crate::core::backups::snapshot_before_write(&path);
+    crate::core::fs_util::write_atomic(Path::new(&path), text.as_bytes()).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    Ok(list.len())
+}
+
+/// Merge markers from a CSV/JSON file by position; `replace` overwrites markers
+/// already at an imported position.
+#[tauri::command]
+pub async fn minimap_import_markers(path: String, format: MarkerFormat, replace: bool, state: State<'_, AppState>) -> Result<MarkerImport, String> {
+    let text = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let incoming = read_markers(&text, format).map_err(|e| format!("Failed to import markers: {}", e))?;
+    with_markers(&state, |content| import_markers(content, &incoming, replace))
+}
+
+/// Add a marker on every town temple or waypoint of the loaded world map, e.g.
+/// to ship a default marker set for new players.
+#[tauri::command]
+pub async fn minimap_import_world_markers(source: WorldMarkerSource, marker_type: u32, replace: bool, state: State<'_, AppState>) -> Result<MarkerImport, String> {
+    let world = loaded_world(&state)?;
+    let incoming = markers_from_world(source, world.towns(), world.waypoints(), marker_type);
+    with_markers(&state, |content| import_markers(content, &incoming, replace))
+}
+
 // ── Tiles (minimap-<scale>-<x>-<y>-<floor>-<hash>.bmp.lzma) ─────────────────
 
 /// One on-disk minimap tile, located by its sector coordinates and floor.
@@ -67,12 +141,14 @@ pub struct MinimapTile {
     pub x: u32,
     pub y: u32,
     pub floor: u32,
+    /// Fields per pixel.
+    pub scale: u32,
     /// Absolute path to the `.bmp.lzma` tile.
     pub path: String,
 }
 
-/// Parse `minimap-<scale>-<x>-<y>-<floor>-<hash>.bmp.lzma` → (x, y, floor).
-fn parse_tile_name(name: &str) -> Option<(u32, u32, u32)> {
+/// Parse `minimap-<scale>-<x>-<y>-<floor>-<hash>.bmp.lzma` → (x, y, floor, scale).
+fn parse_tile_name(name: &str) -> Option<(u32, u32, u32, u32)> {
     let stem = name.strip_prefix("minimap-")?.strip_suffix(".bmp.lzma")?;
     let parts: Vec<&str> = stem.split('-').collect();
     // [scale, x, y, floor, hash]
@@ -82,7 +158,8 @@ fn parse_tile_name(name: &str) -> Option<(u32, u32, u32)> {
     let x = parts[1].parse::<u32>().ok()?;
     let y = parts[2].parse::<u32>().ok()?;
     let floor = parts[3].parse::<u32>().ok()?;
-    Some((x, y, floor))
+    let scale = parts[0].parse::<u32>().ok()?;
+    Some((x, y, floor, scale))
 }
 
 /// List every minimap tile in the client's `assets/` folder, sorted by
@@ -101,11 +178,12 @@ pub fn minimap_list_tiles(tibia_path: String) -> Result<Vec<MinimapTile>, String
         if !name.starts_with("minimap-") || !name.ends_with(".bmp.lzma") {
             continue;
         }
-        if let Some((x, y, floor)) = parse_tile_name(name) {
+        if let Some((x, y, floor, scale)) = parse_tile_name(name) {
             tiles.push(MinimapTile {
                 x,
                 y,
                 floor,
+                scale,
                 path: path.to_string_lossy().to_string(),
             });
         }
@@ -123,6 +201,224 @@ pub fn minimap_get_tile(path: String) -> Result<Vec<u8>, String> {
     crate::core::lzma::decompress(&data).map_err(|e| format!("Failed to decompress tile: {}", e))
 }
 
+/// Outcome of `minimap_generate_tiles`.
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MinimapGeneration {
+    pub tiles: usize,
+    /// Tiles not already present in `assets/` under the same name.
+    pub written: usize,
+    pub map_files: Vec<MapFile>,
+    /// Whether the loaded map.dat had its minimap entries replaced (save it to apply).
+    pub map_updated: bool,
+    /// Tiles of the replaced entries that the new set no longer uses, deleted from `assets/`.
+    pub removed: usize,
+}
+
+/// Rasterise the loaded world map into client minimap tiles in `assets/`, using
+/// the loaded appearances (and items.otb for server ids). The loaded map.dat's
+/// minimap entries are replaced by the generated ones; `save_map_file` writes
+/// them out. Tile names are content hashes, so unchanged tiles aren't rewritten.
+/// Tiles the replaced entries pointed to and the new set doesn't use are
+/// deleted (after a backup). Only colours are written: the client's minimap
+/// has no pathing layer, so unpassable fields look like any other.
+#[tauri::command]
+pub async fn minimap_generate_tiles(tibia_path: String, options: Option<MinimapGenOptions>, app: AppHandle, state: State<'_, AppState>) -> Result<MinimapGeneration, String> {
+    let world = loaded_world(&state)?;
+    let lookup = {
+        let appearances = state.appearances.read();
+        let appearances = appearances.as_ref().ok_or("No appearances loaded")?;
+        AutomapLookup::new(appearances, state.items_otb.read().as_ref())
+    };
+    let mut raster = MinimapRaster::new(options.unwrap_or_default()).map_err(|e| e.to_string())?;
+    let assets = PathBuf::from(tibia_path).join("assets");
+    let tiles_dir = assets.clone();
+
+    let job = start_job(&app, state.inner(), "minimap_generate");
+    let worker_job = job.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || -> Result<(usize, usize, Vec<MapFile>), String> {
+        let job = worker_job;
+        world.for_each_tile(None, &job, |tile| raster.draw(tile, &lookup)).map_err(|e| {
+            if job.is_cancelled() {
+                JOB_CANCELLED.to_string()
+            } else {
+                format!("Failed to read world map: {}", e)
+            }
+        })?;
+        job.set_message("Encoding tiles");
+        let tiles = raster.encode().map_err(|e| format!("Failed to encode minimap tiles: {}", e))?;
+        let written = write_client_tiles(&assets, &tiles, &job)?;
+        Ok((tiles.len(), written, tiles.into_iter().map(|t| t.map_file).collect()))
+    })
+    .await
+    .map_err(|e| format!("Minimap generation task failed: {}", e))
+    .and_then(|result| result);
+    let (tiles, written, map_files) = finish_job(state.inner(), &job, result)?;
+    Ok(apply_generation(&state, &tiles_dir, tiles, written, map_files))
+}
+
+/// Writes the tiles not already in `assets/`; returns how many were written.
+fn write_client_tiles(assets: &Path, tiles: &[EncodedMinimapTile], job: &JobHandle) -> Result<usize, String> {
+    let mut written = 0;
+    for tile in tiles {
+        job.check_cancelled()?;
+        let path = assets.join(&tile.file_name);
+        if !path.exists() {
+            crate::core::fs_util::write_atomic(&path, &tile.data).map_err(|e| format!("Failed to write minimap tile: {}", e))?;
+            written += 1;
+        }
+    }
+    Ok(written)
+}
+
+/// Swaps the loaded map.dat's minimap entries for the generated ones and
+/// deletes the old tiles nothing points to any more.
+fn apply_generation(state: &AppState, assets: &Path, tiles: usize, written: usize, map_files: Vec<MapFile>) -> MinimapGeneration {
+    let (map_updated, old_files) = match state.map.write().as_mut() {
+        Some(map) => {
+            let (old, kept): (Vec<MapFile>, Vec<MapFile>) = map.resource_files.drain(..).partition(|f| f.file_type == Some(MapFileType::Minimap as i32));
+            map.resource_files = kept;
+            map.resource_files.extend(map_files.iter().cloned());
+            (true, old.into_iter().filter_map(|f| f.file_name).collect())
+        }
+        None => (false, Vec::new()),
+    };
+    let removed = remove_stale_tiles(assets, &old_files, &map_files);
+    MinimapGeneration {
+        tiles,
+        written,
+        map_files,
+        map_updated,
+        removed,
+    }
+}
+
+/// Deletes the `old` minimap tiles in `assets/` that `current` doesn't reference,
+/// snapshotting each first. Names that aren't plain tile file names are left alone.
+fn remove_stale_tiles(assets: &Path, old: &[String], current: &[MapFile]) -> usize {
+    let in_use: HashSet<&str> = current.iter().filter_map(|f| f.file_name.as_deref()).collect();
+    let mut removed = 0;
+    for name in old {
+        if in_use.contains(name.as_str()) || parse_tile_name(name).is_none() || Path::new(name).file_name() != Some(name.as_ref()) {
+            continue;
+        }
+        let path = assets.join(name);
+        if !path.is_file() {
+            continue;
+        }
+        crate::core::backups::snapshot_before_write(&path);
+        match std::fs::remove_file(&path) {
+            Ok(()) => removed += 1,
+            Err(e) => log::warn!("Failed to remove stale minimap tile {:?}: {}", path, e),
+        }
+    }
+    removed
+}
+
+/// Minimap tiles to read back: the loaded map.dat's minimap entries when there
+/// are any (stale tiles from earlier generations stay out), else every tile in `assets/`.
+fn client_tile_sources(state: &AppState, tibia_path: String) -> Result<Vec<MinimapTile>, String> {
+    let assets = PathBuf::from(&tibia_path).join("assets");
+    let from_map: Vec<MinimapTile> = state
+        .map
+        .read()
+        .iter()
+        .flat_map(|map| map.resource_files.iter())
+        .filter(|f| f.file_type == Some(MapFileType::Minimap as i32))
+        .filter_map(|f| {
+            let at = f.top_left_coordinate?;
+            let scale = f.scale_factor.filter(|s| *s > 0.0).map(|s| (1.0 / s).round().max(1.0) as u32).unwrap_or(1);
+            Some(MinimapTile {
+                x: at.x?,
+                y: at.y?,
+                floor: at.z?,
+                scale,
+                path: assets.join(f.file_name.as_deref()?).to_string_lossy().to_string(),
+            })
+        })
+        .collect();
+    if from_map.is_empty() {
+        minimap_list_tiles(tibia_path)
+    } else {
+        Ok(from_map)
+    }
+}
+
+/// Result of `minimap_client_tiles_to_otmm`.
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OtmmConversion {
+    pub tiles: usize,
+    pub fields: usize,
+    pub blocks: usize,
+}
+
+/// Convert an OTClient `.otmm` into client minimap tiles in `assets/` (same
+/// handling of map.dat entries as `minimap_generate_tiles`).
+#[tauri::command]
+pub async fn minimap_otmm_to_client_tiles(otmm_path: String, tibia_path: String, options: Option<MinimapGenOptions>, app: AppHandle, state: State<'_, AppState>) -> Result<MinimapGeneration, String> {
+    let options = options.unwrap_or_default();
+    let assets = PathBuf::from(tibia_path).join("assets");
+    let tiles_dir = assets.clone();
+    let job = start_job(&app, state.inner(), "minimap_from_otmm");
+    let worker_job = job.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || -> Result<(usize, usize, Vec<MapFile>), String> {
+        let job = worker_job;
+        let data = std::fs::read(&otmm_path).map_err(|e| format!("Failed to read .otmm: {}", e))?;
+        let otmm = OtmmMinimap::from_bytes(&data).map_err(|e| format!("Failed to parse .otmm: {}", e))?;
+        job.set_message("Encoding tiles");
+        let tiles = convert::client_tiles_from_otmm(&otmm, options).map_err(|e| format!("Failed to encode minimap tiles: {}", e))?;
+        let written = write_client_tiles(&assets, &tiles, &job)?;
+        Ok((tiles.len(), written, tiles.into_iter().map(|t| t.map_file).collect()))
+    })
+    .await
+    .map_err(|e| format!("Minimap conversion task failed: {}", e))
+    .and_then(|result| result);
+    let (tiles, written, map_files) = finish_job(state.inner(), &job, result)?;
+    Ok(apply_generation(&state, &tiles_dir, tiles, written, map_files))
+}
+
+/// Convert the client's minimap tiles into an OTClient `.otmm`. With `merge`,
+/// an existing file at `otmm_path` is updated in place (keeping its pathing
+/// flags) instead of replaced.
+#[tauri::command]
+pub async fn minimap_client_tiles_to_otmm(tibia_path: String, otmm_path: String, merge: bool, app: AppHandle, state: State<'_, AppState>) -> Result<OtmmConversion, String> {
+    let sources = client_tile_sources(&state, tibia_path)?;
+    let job = start_job(&app, state.inner(), "minimap_to_otmm");
+    let worker_job = job.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || -> Result<OtmmConversion, String> {
+        let job = worker_job;
+        let path = PathBuf::from(&otmm_path);
+        let mut otmm = if merge && path.is_file() {
+            let data = std::fs::read(&path).map_err(|e| format!("Failed to read .otmm: {}", e))?;
+            OtmmMinimap::from_bytes(&data).map_err(|e| format!("Failed to parse .otmm: {}", e))?
+        } else {
+            OtmmMinimap::new()
+        };
+        job.set_total(sources.len());
+        let mut fields = 0;
+        for tile in &sources {
+            job.check_cancelled()?;
+            let data = std::fs::read(&tile.path).map_err(|e| format!("Failed to read tile: {}", e))?;
+            let floor = u8::try_from(tile.floor).map_err(|_| "Invalid floor".to_string())?;
+            fields += convert::merge_client_tile(&mut otmm, tile.x, tile.y, floor, tile.scale, &data).map_err(|e| format!("Failed to convert {}: {}", tile.path, e))?;
+            job.advance(1);
+        }
+        let bytes = otmm.to_bytes().map_err(|e| format!("Failed to encode .otmm: {}", e))?;
+        crate::core::backups::snapshot_before_write(&path);
+        crate::core::fs_util::write_atomic(&path, &bytes).map_err(|e| format!("Failed to write .otmm: {}", e))?;
+        Ok(OtmmConversion {
+            tiles: sources.len(),
+            fields,
+            blocks: otmm.block_count(),
+        })
+    })
+    .await
+    .map_err(|e| format!("Minimap conversion task failed: {}", e))
+    .and_then(|result| result);
+    finish_job(state.inner(), &job, result)
+}
+
 // ── OTClient minimap (.otmm) ───────────────────────────────────────────────
 
 /// Per-floor summary of an `.otmm`.
@@ -173,15 +469,25 @@ pub fn minimap_otmm_info(path: String) -> Result<Vec<OtmmFloorInfo>, String> {
 }
 
 /// Render one floor of an `.otmm` to a PNG (downscaled so neither side exceeds
-/// `max_dim`). CPU heavy (zlib + raster) → runs on the blocking pool.
+/// `max_dim`). CPU heavy (zlib + raster) → runs on the blocking pool as a
+/// cancellable job.
 #[tauri::command]
-pub async fn minimap_render_otmm(path: String, floor: u32, max_dim: u32) -> Result<OtmmRender, String> {
+pub async fn minimap_render_otmm(path: String, floor: u32, max_dim: u32, app: AppHandle, state: State<'_, AppState>) -> Result<OtmmRender, String> {
     let max_dim = max_dim.clamp(256, 16384);
-    tauri::async_runtime::spawn_blocking(move || -> Result<OtmmRender, String> {
+    let job = start_job(&app, state.inner(), "render_otmm");
+    let worker_job = job.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || -> Result<OtmmRender, String> {
+        let job = worker_job;
         let data = std::fs::read(&path).map_err(|e| format!("Failed to read .otmm: {}", e))?;
         let index = otmm::parse_index(&data).map_err(|e| format!("Failed to parse .otmm: {}", e))?;
         let floor_u8 = u8::try_from(floor).map_err(|_| "Invalid floor".to_string())?;
-        let r = otmm::render_floor(&data, &index, floor_u8, max_dim).map_err(|e| format!("Failed to render floor: {}", e))?;
+        let r = otmm::render_floor(&data, &index, floor_u8, max_dim, &job).map_err(|e| {
+            if job.is_cancelled() {
+                JOB_CANCELLED.to_string()
+            } else {
+                format!("Failed to render floor: {}", e)
+            }
+        })?;
         Ok(OtmmRender {
             image_base64: base64::engine::general_purpose::STANDARD.encode(&r.png),
             width: r.width,
@@ -192,5 +498,7 @@ pub async fn minimap_render_otmm(path: String, floor: u32, max_dim: u32) -> Resu
         })
     })
     .await
-    .map_err(|e| format!("Render task failed: {}", e))?
+    .map_err(|e| format!("Render task failed: {}", e))
+    .and_then(|result| result);
+    finish_job(state.inner(), &job, result)
 }
@@ -1,5 +1,7 @@
-// Minimap feature: parse the marker file (`minimap.proto`) and list the on-disk
-// minimap tiles (`minimap-<scale>-<x>-<y>-<floor>-<hash>.bmp.lzma`).
+// Minimap feature: read, edit and write the marker file (`minimap.proto`), list
+// and generate the on-disk minimap tiles
+// (`minimap-<scale>-<x>-<y>-<floor>-<hash>.bmp.lzma`) and convert them to and
+// from OTClient's `.otmm`.
 pub mod commands;
 pub mod parsers;
 
@@ -0,0 +1,103 @@
+// Conversion between OTClient `.otmm` blocks and the client's `.bmp.lzma`
+// minimap tiles. Both sides share the 6x6x6 colour cube, so colours map 1:1;
+// client tiles are RGB and go through `color_to_8bit`. Black client pixels are
+// "no data" (that's what the generator writes), and client tiles carry no
+// pathing: it is dropped on the way out, and on the way in fields keep
+// whatever walkability the `.otmm` already had.
+
+use anyhow::{Context, Result};
+
+use super::generator::{EncodedMinimapTile, MinimapGenOptions, MinimapRaster};
+use super::otmm::{color_to_8bit, OtmmField, OtmmMinimap};
+
+/// Client tiles covering every seen field of `otmm`.
+pub fn client_tiles_from_otmm(otmm: &OtmmMinimap, options: MinimapGenOptions) -> Result<Vec<EncodedMinimapTile>> {
+    let mut raster = MinimapRaster::new(options)?;
+    for (x, y, z, field) in otmm.fields() {
+        let color = if field.color < 216 {
+            field.color
+        } else {
+            0
+        };
+        raster.set(x, y, z, color);
+    }
+    raster.encode()
+}
+
+/// Decompresses a `.bmp.lzma` tile to RGB.
+pub fn decode_client_tile(data: &[u8]) -> Result<image::RgbImage> {
+    let bmp = crate::core::lzma::decompress(data).context("Failed to decompress minimap tile")?;
+    Ok(image::load_from_memory_with_format(&bmp, image::ImageFormat::Bmp).context("Failed to decode minimap BMP")?.to_rgb8())
+}
+
+/// Marks the fields of one client tile (top-left field `x`/`y`, `scale` fields
+/// per pixel) as seen in `otmm`. Returns how many fields were set.
+pub fn merge_client_tile(otmm: &mut OtmmMinimap, x: u32, y: u32, z: u8, scale: u32, data: &[u8]) -> Result<usize> {
+    let image = decode_client_tile(data)?;
+    let scale = scale.max(1);
+    let mut set = 0;
+    for (px, py, pixel) in image.enumerate_pixels() {
+        if pixel.0 == [0, 0, 0] {
+            continue;
+        }
+        let color = color_to_8bit(pixel.0);
+        for dy in 0..scale {
+            for dx in 0..scale {
+                let (Ok(fx), Ok(fy)) = (u16::try_from(x + px * scale + dx), u16::try_from(y + py * scale + dy)) else {
+                    continue;
+                };
+                let unpassable = otmm.get(fx, fy, z).is_some_and(|f| f.unpassable);
+                otmm.set(
+                    fx,
+                    fy,
+                    z,
+                    OtmmField {
+                        color,
+                        unpassable,
+                    },
+                );
+                set += 1;
+            }
+        }
+    }
+    Ok(set)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn otmm_round_trips_through_client_tiles() {
+        let mut otmm = OtmmMinimap::new();
+        let grass = OtmmField {
+            color: 24,
+            unpassable: false,
+        };
+        let wall = OtmmField {
+            color: 186,
+            unpassable: true,
+        };
+        otmm.set(1000, 1000, 7, grass);
+        otmm.set(1001, 1000, 7, wall);
+
+        let options = MinimapGenOptions {
+            fields: 64,
+            scale: 1,
+        };
+        let tiles = client_tiles_from_otmm(&otmm, options).unwrap();
+        assert_eq!(tiles.len(), 1);
+        assert!(tiles[0].file_name.starts_with("minimap-1-960-960-7-"));
+
+        // Into a fresh .otmm the colours survive; walkability isn't in the BMP.
+        let mut back = OtmmMinimap::new();
+        assert_eq!(merge_client_tile(&mut back, 960, 960, 7, 1, &tiles[0].data).unwrap(), 2);
+        assert_eq!(back.get(1000, 1000, 7), Some(grass));
+        assert_eq!(back.get(1001, 1000, 7).map(|f| f.color), Some(186));
+        assert_eq!(back.get(1002, 1000, 7), None);
+
+        // Merging over the original keeps its pathing flags.
+        merge_client_tile(&mut otmm, 960, 960, 7, 1, &tiles[0].data).unwrap();
+        assert_eq!(otmm.get(1001, 1000, 7), Some(wall));
+    }
+}
@@ -0,0 +1,268 @@
+// Client minimap tiles generated from an OTBM world.
+//
+// Each field takes the `automap.color` of its topmost coloured item (an 8-bit
+// index into the 6x6x6 colour cube). Fields are grouped into square tiles
+// aligned to `fields`, written as 8-bit palettised BMPs, LZMA-compressed and
+// named
+//   minimap-<scale>-<x>-<y>-<floor>-<sha256 of the file>.bmp.lzma
+// to match what `minimap_list_tiles` reads back. The client's tiles are colour
+// only: it has no pathing layer, so the `unpass` flag has nowhere to go.
+
+use anyhow::{bail, Context, Result};
+use image::codecs::bmp::BmpEncoder;
+use image::ExtendedColorType;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+
+use super::otmm::color_from_8bit;
+use crate::core::protobuf::map::{MapFile, MapFileType};
+use crate::core::protobuf::shared::Coordinate;
+use crate::core::protobuf::Appearances;
+use crate::features::otb::parsers::ItemsOtb;
+use crate::features::world::parsers::MapTile;
+
+/// Tile edge used by the client's own minimap (fields).
+pub const DEFAULT_TILE_FIELDS: u32 = 512;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MinimapGenOptions {
+    /// Tile edge in fields; tiles start at multiples of this.
+    pub fields: u32,
+    /// Fields per pixel (1 = full resolution).
+    pub scale: u32,
+}
+
+impl Default for MinimapGenOptions {
+    fn default() -> Self {
+        Self {
+            fields: DEFAULT_TILE_FIELDS,
+            scale: 1,
+        }
+    }
+}
+
+impl MinimapGenOptions {
+    fn validate(&self) -> Result<()> {
+        if self.fields == 0 || self.scale == 0 || !self.fields.is_multiple_of(self.scale) || self.fields > 4096 {
+            bail!("Invalid tile size {} at scale {}", self.fields, self.scale);
+        }
+        Ok(())
+    }
+}
+
+/// Server id -> automap colour, resolved through items.otb. Colour 0 means the
+/// item isn't drawn.
+pub struct AutomapLookup {
+    items: HashMap<u16, u8>,
+}
+
+impl AutomapLookup {
+    /// Without an `items_otb`, map item ids are taken to be client ids.
+    pub fn new(appearances: &Appearances, items_otb: Option<&ItemsOtb>) -> Self {
+        let by_client: HashMap<u32, u8> = appearances
+            .object
+            .iter()
+            .filter_map(|object| {
+                let color = object.flags.as_ref()?.automap.as_ref()?.color?.min(255) as u8;
+                (color != 0).then_some((object.id?, color))
+            })
+            .collect();
+        let items = match items_otb {
+            Some(otb) => otb.items.iter().filter_map(|item| Some((item.server_id, *by_client.get(&(item.client_id as u32))?))).collect(),
+            None => by_client.into_iter().filter_map(|(id, info)| Some((u16::try_from(id).ok()?, info))).collect(),
+        };
+        Self {
+            items,
+        }
+    }
+
+    pub fn get(&self, server_id: u16) -> u8 {
+        self.items.get(&server_id).copied().unwrap_or(0)
+    }
+
+    /// A tile's colour: that of its topmost coloured item.
+    pub fn field(&self, tile: &MapTile) -> u8 {
+        tile.items.iter().rev().map(|item| self.get(item.id)).find(|c| *c != 0).unwrap_or(0)
+    }
+}
+
+/// Field colours of every tile touched so far, keyed by (x, y, floor) of the tile origin.
+pub struct MinimapRaster {
+    options: MinimapGenOptions,
+    tiles: BTreeMap<(u16, u16, u8), Vec<u8>>,
+}
+
+impl MinimapRaster {
+    pub fn new(options: MinimapGenOptions) -> Result<Self> {
+        options.validate()?;
+        Ok(Self {
+            options,
+            tiles: BTreeMap::new(),
+        })
+    }
+
+    fn pixels(&self) -> u32 {
+        self.options.fields / self.options.scale
+    }
+
+    pub fn draw(&mut self, tile: &MapTile, lookup: &AutomapLookup) {
+        let color = lookup.field(tile);
+        if color != 0 {
+            self.set(tile.position.x, tile.position.y, tile.position.z, color);
+        }
+    }
+
+    /// Merges one field into its pixel; a coloured field wins over an uncoloured one.
+    pub fn set(&mut self, x: u16, y: u16, z: u8, color: u8) {
+        let (fields, scale, pixels) = (self.options.fields, self.options.scale, self.pixels());
+        let (x, y) = (x as u32, y as u32);
+        let origin = ((x - x % fields) as u16, (y - y % fields) as u16, z);
+        let index = ((y % fields) / scale * pixels + (x % fields) / scale) as usize;
+        let cells = self.tiles.entry(origin).or_insert_with(|| vec![0; (pixels * pixels) as usize]);
+        if color != 0 {
+            cells[index] = color;
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.tiles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+
+    /// Encodes every tile, in (x, y, floor) order.
+    pub fn encode(&self) -> Result<Vec<EncodedMinimapTile>> {
+        let palette: Vec<[u8; 3]> = (0..=215u8).map(color_from_8bit).collect();
+        let pixels = self.pixels();
+        self.tiles
+            .iter()
+            .map(|(&(x, y, z), cells)| {
+                let indices: Vec<u8> = cells.iter().map(|c| (*c).min(215)).collect();
+                let mut bmp = Vec::new();
+                BmpEncoder::new(&mut bmp).encode_with_palette(&indices, pixels, pixels, ExtendedColorType::L8, Some(&palette)).context("Failed to encode minimap BMP")?;
+                let data = crate::core::lzma::compress(&bmp).context("Failed to LZMA-compress minimap tile")?;
+                let hash: String = Sha256::digest(&data).iter().map(|b| format!("{:02x}", b)).collect();
+                let file_name = format!("minimap-{}-{}-{}-{}-{}.bmp.lzma", self.options.scale, x, y, z, hash);
+                Ok(EncodedMinimapTile {
+                    map_file: MapFile {
+                        file_type: Some(MapFileType::Minimap as i32),
+                        top_left_coordinate: Some(Coordinate {
+                            x: Some(x as u32),
+                            y: Some(y as u32),
+                            z: Some(z as u32),
+                        }),
+                        file_name: Some(file_name.clone()),
+                        fields_width: Some(self.options.fields),
+                        fields_height: Some(self.options.fields),
+                        area_id: None,
+                        scale_factor: Some(1.0 / self.options.scale as f64),
+                    },
+                    file_name,
+                    data,
+                })
+            })
+            .collect()
+    }
+}
+
+pub struct EncodedMinimapTile {
+    pub file_name: String,
+    /// Compressed file contents.
+    pub data: Vec<u8>,
+    /// The map.dat entry pointing at this file.
+    pub map_file: MapFile,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::protobuf::{Appearance, AppearanceFlagAutomap, AppearanceFlags};
+    use crate::features::otb::parsers::OtbItem;
+    use crate::features::world::parsers::{MapItem, Position};
+
+    fn object(id: u32, color: Option<u32>) -> Appearance {
+        Appearance {
+            id: Some(id),
+            flags: Some(AppearanceFlags {
+                automap: color.map(|c| AppearanceFlagAutomap {
+                    color: Some(c),
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn tile(x: u16, y: u16, ids: &[u16]) -> MapTile {
+        MapTile {
+            position: Position::new(x, y, 7),
+            flags: 0,
+            house_id: None,
+            items: ids
+                .iter()
+                .map(|&id| MapItem {
+                    id,
+                    ..Default::default()
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn rasterises_top_colour_through_items_otb() {
+        let appearances = Appearances {
+            object: vec![object(100, Some(24)), object(101, None), object(102, Some(186))],
+            ..Default::default()
+        };
+        let otb = ItemsOtb {
+            items: [(1, 100), (2, 101), (3, 102)]
+                .into_iter()
+                .map(|(server_id, client_id)| OtbItem {
+                    server_id,
+                    client_id,
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let lookup = AutomapLookup::new(&appearances, Some(&otb));
+        // Ground 24 under an uncoloured item keeps the ground colour.
+        assert_eq!(lookup.field(&tile(0, 0, &[1, 2])), 24);
+        assert_eq!(lookup.field(&tile(0, 0, &[1, 3])), 186);
+        // Without an otb the ids are client ids.
+        assert_eq!(AutomapLookup::new(&appearances, None).get(100), 24);
+    }
+
+    #[test]
+    fn encodes_named_bmp_tiles_with_map_entries() {
+        let appearances = Appearances {
+            object: vec![object(100, Some(24))],
+            ..Default::default()
+        };
+        let lookup = AutomapLookup::new(&appearances, None);
+        let mut raster = MinimapRaster::new(MinimapGenOptions {
+            fields: 8,
+            scale: 1,
+        })
+        .unwrap();
+        raster.draw(&tile(17, 10, &[100]), &lookup);
+        raster.draw(&tile(20, 30, &[999]), &lookup);
+        assert_eq!(raster.len(), 1);
+
+        let tiles = raster.encode().unwrap();
+        let tile = &tiles[0];
+        let hash = tile.file_name.strip_prefix("minimap-1-16-8-7-").unwrap().strip_suffix(".bmp.lzma").unwrap();
+        assert_eq!(hash.len(), 64);
+        assert_eq!(tile.map_file.file_name.as_deref(), Some(tile.file_name.as_str()));
+        assert_eq!(tile.map_file.fields_width, Some(8));
+
+        let bmp = crate::core::lzma::decompress(&tile.data).unwrap();
+        let image = image::load_from_memory_with_format(&bmp, image::ImageFormat::Bmp).unwrap().to_rgb8();
+        assert_eq!(image.get_pixel(1, 2).0, color_from_8bit(24));
+        assert_eq!(image.get_pixel(0, 0).0, [0, 0, 0]);
+    }
+}
@@ -0,0 +1,258 @@
+// Editing, CSV/JSON exchange and bulk import for minimap markers.
+//
+// The client keeps at most one marker per field, so markers are addressed by
+// position: setting a marker where one exists replaces its type/description,
+// and imports merge by position the same way.
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::core::protobuf::minimap::{MinimapMarker, MinimapMarkerFileContent};
+use crate::core::protobuf::shared::Coordinate;
+use crate::features::world::parsers::{Position, Town, Waypoint};
+
+/// Number of marker icons the client offers (types 0..=19).
+pub const MARKER_TYPES: u32 = 20;
+
+/// A minimap marker flattened for the frontend and for CSV/JSON files.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MinimapMarkerDto {
+    pub x: u32,
+    pub y: u32,
+    pub z: u32,
+    /// Marker icon type (Qt's minimap marker enum).
+    pub r#type: u32,
+    #[serde(default)]
+    pub description: String,
+}
+
+impl From<&MinimapMarker> for MinimapMarkerDto {
+    fn from(marker: &MinimapMarker) -> Self {
+        let pos = marker.position.unwrap_or_default();
+        Self {
+            x: pos.x.unwrap_or(0),
+            y: pos.y.unwrap_or(0),
+            z: pos.z.unwrap_or(0),
+            r#type: marker.r#type.unwrap_or(0),
+            description: marker.description.clone().unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MarkerFormat {
+    Csv,
+    Json,
+}
+
+/// Where `markers_from_world` takes positions from.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WorldMarkerSource {
+    /// Town temple positions, described by town name.
+    Temples,
+    /// Map waypoints, described by waypoint name.
+    Waypoints,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MarkerImport {
+    pub added: usize,
+    pub updated: usize,
+    /// Positions that already had a marker, left alone without `replace`.
+    pub skipped: usize,
+}
+
+pub fn markers(content: &MinimapMarkerFileContent) -> Vec<MinimapMarkerDto> {
+    content.markers.iter().map(MinimapMarkerDto::from).collect()
+}
+
+pub fn find_marker(content: &MinimapMarkerFileContent, x: u32, y: u32, z: u32) -> Option<usize> {
+    content.markers.iter().position(|m| m.position.is_some_and(|p| (p.x, p.y, p.z) == (Some(x), Some(y), Some(z))))
+}
+
+fn check_type(marker_type: u32) -> Result<()> {
+    if marker_type >= MARKER_TYPES {
+        bail!("Unknown marker type {} (expected 0-{})", marker_type, MARKER_TYPES - 1);
+    }
+    Ok(())
+}
+
+/// Adds a marker, or updates the one already at its position. Returns true when
+/// an existing marker was updated.
+pub fn set_marker(content: &mut MinimapMarkerFileContent, marker: &MinimapMarkerDto) -> Result<bool> {
+    check_type(marker.r#type)?;
+    if marker.z > 15 {
+        bail!("Invalid floor {}", marker.z);
+    }
+    if let Some(index) = find_marker(content, marker.x, marker.y, marker.z) {
+        let existing = &mut content.markers[index];
+        existing.r#type = Some(marker.r#type);
+        existing.description = Some(marker.description.clone());
+        return Ok(true);
+    }
+    content.markers.push(MinimapMarker {
+        position: Some(Coordinate {
+            x: Some(marker.x),
+            y: Some(marker.y),
+            z: Some(marker.z),
+        }),
+        r#type: Some(marker.r#type),
+        description: Some(marker.description.clone()),
+        unknown_4: None,
+    });
+    Ok(false)
+}
+
+/// Moves the marker at `from` to `to`, which must be free.
+pub fn move_marker(content: &mut MinimapMarkerFileContent, from: (u32, u32, u32), to: (u32, u32, u32)) -> Result<()> {
+    let index = find_marker(content, from.0, from.1, from.2).ok_or_else(|| anyhow!("No marker at {}, {}, {}", from.0, from.1, from.2))?;
+    if from == to {
+        return Ok(());
+    }
+    if to.2 > 15 {
+        bail!("Invalid floor {}", to.2);
+    }
+    if find_marker(content, to.0, to.1, to.2).is_some() {
+        bail!("A marker already exists at {}, {}, {}", to.0, to.1, to.2);
+    }
+    content.markers[index].position = Some(Coordinate {
+        x: Some(to.0),
+        y: Some(to.1),
+        z: Some(to.2),
+    });
+    Ok(())
+}
+
+pub fn retype_marker(content: &mut MinimapMarkerFileContent, x: u32, y: u32, z: u32, marker_type: u32) -> Result<()> {
+    check_type(marker_type)?;
+    let index = find_marker(content, x, y, z).ok_or_else(|| anyhow!("No marker at {}, {}, {}", x, y, z))?;
+    content.markers[index].r#type = Some(marker_type);
+    Ok(())
+}
+
+pub fn remove_marker(content: &mut MinimapMarkerFileContent, x: u32, y: u32, z: u32) -> Result<MinimapMarkerDto> {
+    let index = find_marker(content, x, y, z).ok_or_else(|| anyhow!("No marker at {}, {}, {}", x, y, z))?;
+    Ok(MinimapMarkerDto::from(&content.markers.remove(index)))
+}
+
+/// Merges `incoming` by position. With `replace`, markers already at a position
+/// are updated; otherwise they are kept and counted as skipped.
+pub fn import_markers(content: &mut MinimapMarkerFileContent, incoming: &[MinimapMarkerDto], replace: bool) -> Result<MarkerImport> {
+    let mut result = MarkerImport::default();
+    for marker in incoming {
+        if !replace && find_marker(content, marker.x, marker.y, marker.z).is_some() {
+            result.skipped += 1;
+        } else if set_marker(content, marker).with_context(|| format!("Marker at {}, {}, {}", marker.x, marker.y, marker.z))? {
+            result.updated += 1;
+        } else {
+            result.added += 1;
+        }
+    }
+    Ok(result)
+}
+
+pub fn write_markers(markers: &[MinimapMarkerDto], format: MarkerFormat) -> Result<String> {
+    match format {
+        MarkerFormat::Json => serde_json::to_string_pretty(markers).context("Failed to serialize markers"),
+        MarkerFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            for marker in markers {
+                writer.serialize(marker).context("Failed to write CSV row")?;
+            }
+            let bytes = writer.into_inner().map_err(|e| anyhow!("Failed to flush CSV: {}", e))?;
+            String::from_utf8(bytes).context("CSV is not valid UTF-8")
+        }
+    }
+}
+
+/// Reads markers written by `write_markers` (CSV columns are matched by header).
+pub fn read_markers(contents: &str, format: MarkerFormat) -> Result<Vec<MinimapMarkerDto>> {
+    match format {
+        MarkerFormat::Json => serde_json::from_str(contents).context("Failed to parse markers JSON"),
+        MarkerFormat::Csv => csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_reader(contents.as_bytes())
+            .deserialize()
+            .enumerate()
+            .map(|(row, marker)| marker.with_context(|| format!("Failed to parse CSV row {}", row + 1)))
+            .collect(),
+    }
+}
+
+fn marker_at(position: Position, marker_type: u32, description: &str) -> MinimapMarkerDto {
+    MinimapMarkerDto {
+        x: position.x as u32,
+        y: position.y as u32,
+        z: position.z as u32,
+        r#type: marker_type,
+        description: description.to_string(),
+    }
+}
+
+/// One marker per town temple or waypoint, described by its name.
+pub fn markers_from_world(source: WorldMarkerSource, towns: &[Town], waypoints: &[Waypoint], marker_type: u32) -> Vec<MinimapMarkerDto> {
+    match source {
+        WorldMarkerSource::Temples => towns.iter().map(|t| marker_at(t.temple, marker_type, &t.name)).collect(),
+        WorldMarkerSource::Waypoints => waypoints.iter().map(|w| marker_at(w.position, marker_type, &w.name)).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dto(x: u32, r#type: u32, description: &str) -> MinimapMarkerDto {
+        MinimapMarkerDto {
+            x,
+            y: 31900,
+            z: 7,
+            r#type,
+            description: description.into(),
+        }
+    }
+
+    #[test]
+    fn edits_markers_by_position() {
+        let mut content = MinimapMarkerFileContent::default();
+        assert!(!set_marker(&mut content, &dto(32100, 3, "Temple")).unwrap());
+        assert!(set_marker(&mut content, &dto(32100, 4, "Temple, again")).unwrap());
+        assert!(set_marker(&mut content, &dto(32101, 20, "")).is_err());
+        set_marker(&mut content, &dto(32050, 11, "Depot")).unwrap();
+
+        assert!(move_marker(&mut content, (32050, 31900, 7), (32100, 31900, 7)).is_err());
+        move_marker(&mut content, (32050, 31900, 7), (32051, 31900, 7)).unwrap();
+        retype_marker(&mut content, 32051, 31900, 7, 12).unwrap();
+        assert_eq!(remove_marker(&mut content, 32051, 31900, 7).unwrap(), dto(32051, 12, "Depot"));
+        assert_eq!(markers(&content), vec![dto(32100, 4, "Temple, again")]);
+    }
+
+    #[test]
+    fn csv_and_json_round_trip_and_merge() {
+        let list = vec![dto(32100, 3, "Temple, \"main\""), dto(32050, 11, "")];
+        for format in [MarkerFormat::Csv, MarkerFormat::Json] {
+            let text = write_markers(&list, format).unwrap();
+            assert_eq!(read_markers(&text, format).unwrap(), list);
+        }
+        // Hand-written CSV: reordered columns, no description column.
+        let csv = "type, z, y, x\n1, 7, 31900, 32100\n";
+        let imported = read_markers(csv, MarkerFormat::Csv).unwrap();
+
+        let mut content = MinimapMarkerFileContent::default();
+        import_markers(&mut content, &list, false).unwrap();
+        let kept = import_markers(&mut content, &imported, false).unwrap();
+        assert_eq!(kept.skipped, 1);
+        let replaced = import_markers(&mut content, &imported, true).unwrap();
+        assert_eq!(replaced.updated, 1);
+        assert_eq!(markers(&content)[0].r#type, 1);
+
+        let towns = [Town {
+            id: 1,
+            name: "Thais".into(),
+            temple: Position::new(32369, 32241, 7),
+        }];
+        assert_eq!(markers_from_world(WorldMarkerSource::Temples, &towns, &[], 5)[0].description, "Thais");
+    }
+}
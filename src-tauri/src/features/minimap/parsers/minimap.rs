@@ -28,6 +28,17 @@ pub fn load_markers<P: AsRef<Path>>(path: P) -> Result<MinimapMarkerFileContent>
     MinimapMarkerFileContent::decode(&decompressed[..]).context("Failed to decode minimap markers protobuf")
 }
 
+/// Write markers as raw protobuf, the form the client writes itself. The
+/// `minimap/` folder is created when the client hasn't made it yet.
+pub fn save_markers<P: AsRef<Path>>(path: P, content: &MinimapMarkerFileContent) -> Result<()> {
+    let path = path.as_ref();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).context("Failed to create minimap directory")?;
+    }
+    crate::core::backups::snapshot_before_write(path);
+    crate::core::fs_util::write_atomic(path, &content.encode_to_vec()).context("Failed to write minimap markers file")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -75,4 +86,28 @@ mod tests {
         assert_eq!(loaded.markers.len(), 2);
         let _ = std::fs::remove_file(&tmp);
     }
+
+    #[test]
+    fn saved_markers_load_back() {
+        let content = MinimapMarkerFileContent {
+            markers: vec![MinimapMarker {
+                position: Some(Coordinate {
+                    x: Some(32369),
+                    y: Some(32241),
+                    z: Some(7),
+                }),
+                r#type: Some(5),
+                description: Some("Thais".into()),
+                unknown_4: None,
+            }],
+        };
+
+        // The client's minimap/ folder may not exist yet.
+        let dir = std::env::temp_dir().join("canary_minimapmarkers_save_test");
+        let _ = std::fs::remove_dir_all(&dir);
+        let path = dir.join("minimap").join("minimapmarkers.bin");
+        save_markers(&path, &content).unwrap();
+        assert_eq!(load_markers(&path).unwrap(), content);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 }
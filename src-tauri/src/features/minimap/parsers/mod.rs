@@ -1,3 +1,7 @@
+pub mod convert;
+pub mod generator;
+mod markers;
 mod minimap;
 pub mod otmm;
+pub use markers::*;
 pub use minimap::*;
@@ -11,16 +11,33 @@
 //
 // A MinimapTile is 3 bytes: [flags][color][speed]. `color` is an 8-bit palette
 // index (6x6x6 colour cube); 255 means "unseen". 64x64 tiles per block, one map
-// tile per pixel.
+// tile per pixel. Block x/y are absolute map coordinates aligned to 64, and
+// OTClient ends the block list with an invalid position (0xFFFF, 0xFFFF, 0xFF).
 
+use crate::core::jobs::{JobHandle, JOB_CANCELLED};
 use anyhow::{bail, Context, Result};
 use flate2::read::ZlibDecoder;
-use std::io::Read;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
 
 const BLOCK: usize = 64;
 const TILE_BYTES: usize = 3;
 const COLOR_BYTE: usize = 1;
 
+const VERSION: u16 = 1;
+const DESCRIPTION: &str = "OTMM 1.0";
+
+/// OTClient `MinimapTileFlags`.
+pub const FLAG_WAS_SEEN: u8 = 1;
+pub const FLAG_NOT_PATHABLE: u8 = 2;
+pub const FLAG_NOT_WALKABLE: u8 = 4;
+/// Colour of a tile never seen.
+pub const UNSEEN_COLOR: u8 = 255;
+/// OTClient's default ground speed for minimap pathing.
+const DEFAULT_SPEED: u8 = 10;
+
 /// A block's position and where its compressed tile data lives in the file.
 pub struct OtmmBlock {
     pub x: u16,
@@ -71,6 +88,12 @@ pub fn color_from_8bit(c: u8) -> [u8; 3] {
     [((c / 36) % 6 * 51) as u8, ((c / 6) % 6 * 51) as u8, (c % 6 * 51) as u8]
 }
 
+/// Nearest 6x6x6 cube index of an RGB colour (inverse of `color_from_8bit`).
+pub fn color_to_8bit([r, g, b]: [u8; 3]) -> u8 {
+    let level = |c: u8| (c as u32 + 25) / 51;
+    (level(r) * 36 + level(g) * 6 + level(b)) as u8
+}
+
 fn zlib_decompress(data: &[u8]) -> Result<Vec<u8>> {
     let mut dec = ZlibDecoder::new(data);
     let mut out = Vec::new();
@@ -116,6 +139,106 @@ pub fn floor_bounds(blocks: &[OtmmBlock], floor: u8) -> Option<(u32, u32, u32, u
     Some((x0, y0, x1 + BLOCK as u32 - x0, y1 + BLOCK as u32 - y0))
 }
 
+/// A seen field: its colour and whether it blocks walking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OtmmField {
+    pub color: u8,
+    pub unpassable: bool,
+}
+
+/// Whole-minimap model for editing and writing `.otmm` files. Blocks are kept
+/// as raw 64x64 tile bytes keyed by (x, y, z) of their top-left tile.
+#[derive(Default)]
+pub struct OtmmMinimap {
+    blocks: BTreeMap<(u16, u16, u8), Vec<u8>>,
+}
+
+impl OtmmMinimap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decodes every block; corrupt or short blocks are skipped like `render_floor` does.
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let mut minimap = Self::new();
+        for b in parse_index(data)? {
+            let Ok(raw) = zlib_decompress(&data[b.data_offset..b.data_offset + b.clen]) else {
+                continue;
+            };
+            if raw.len() >= BLOCK * BLOCK * TILE_BYTES {
+                minimap.blocks.insert((b.x, b.y, b.z), raw[..BLOCK * BLOCK * TILE_BYTES].to_vec());
+            }
+        }
+        Ok(minimap)
+    }
+
+    fn slot(x: u16, y: u16) -> ((u16, u16), usize) {
+        let block = BLOCK as u16;
+        let origin = (x - x % block, y - y % block);
+        (origin, ((y % block) as usize * BLOCK + (x % block) as usize) * TILE_BYTES)
+    }
+
+    pub fn get(&self, x: u16, y: u16, z: u8) -> Option<OtmmField> {
+        let ((bx, by), at) = Self::slot(x, y);
+        let tile = &self.blocks.get(&(bx, by, z))?[at..at + TILE_BYTES];
+        (tile[0] & FLAG_WAS_SEEN != 0 && tile[COLOR_BYTE] != UNSEEN_COLOR).then_some(OtmmField {
+            color: tile[COLOR_BYTE],
+            unpassable: tile[0] & FLAG_NOT_WALKABLE != 0,
+        })
+    }
+
+    pub fn set(&mut self, x: u16, y: u16, z: u8, field: OtmmField) {
+        let ((bx, by), at) = Self::slot(x, y);
+        let block = self.blocks.entry((bx, by, z)).or_insert_with(|| [0, UNSEEN_COLOR, DEFAULT_SPEED].repeat(BLOCK * BLOCK));
+        let mut flags = block[at] | FLAG_WAS_SEEN;
+        if field.unpassable {
+            flags |= FLAG_NOT_WALKABLE | FLAG_NOT_PATHABLE;
+        } else {
+            flags &= !(FLAG_NOT_WALKABLE | FLAG_NOT_PATHABLE);
+        }
+        block[at] = flags;
+        block[at + COLOR_BYTE] = field.color;
+    }
+
+    /// Every seen field as (x, y, z, field), block by block.
+    pub fn fields(&self) -> impl Iterator<Item = (u16, u16, u8, OtmmField)> + '_ {
+        self.blocks.keys().flat_map(move |&(bx, by, z)| {
+            (0..BLOCK * BLOCK).filter_map(move |i| {
+                let (x, y) = (bx + (i % BLOCK) as u16, by + (i / BLOCK) as u16);
+                self.get(x, y, z).map(|field| (x, y, z, field))
+            })
+        })
+    }
+
+    pub fn block_count(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Serialises in OTClient's layout: header, zlib'd blocks, invalid-position terminator.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut out = b"OTMM".to_vec();
+        let data_start = (4 + 2 + 2 + 4 + 2 + DESCRIPTION.len()) as u16;
+        out.extend_from_slice(&data_start.to_le_bytes());
+        out.extend_from_slice(&VERSION.to_le_bytes());
+        out.extend_from_slice(&0u32.to_le_bytes());
+        out.extend_from_slice(&(DESCRIPTION.len() as u16).to_le_bytes());
+        out.extend_from_slice(DESCRIPTION.as_bytes());
+        for (&(x, y, z), tiles) in &self.blocks {
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(tiles).context("OTMM block zlib compress")?;
+            let compressed = encoder.finish().context("OTMM block zlib compress")?;
+            let clen = u16::try_from(compressed.len()).map_err(|_| anyhow::anyhow!("OTMM block at {},{},{} too large", x, y, z))?;
+            out.extend_from_slice(&x.to_le_bytes());
+            out.extend_from_slice(&y.to_le_bytes());
+            out.push(z);
+            out.extend_from_slice(&clen.to_le_bytes());
+            out.extend_from_slice(&compressed);
+        }
+        out.extend_from_slice(&[0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
+        Ok(out)
+    }
+}
+
 /// A rendered floor: PNG bytes + placement metadata.
 pub struct OtmmFloorRender {
     pub png: Vec<u8>,
@@ -128,7 +251,7 @@ pub struct OtmmFloorRender {
 
 /// Render one floor of the minimap into a PNG, downscaling so neither dimension
 /// exceeds `max_dim` (the explored area can span the whole world).
-pub fn render_floor(data: &[u8], blocks: &[OtmmBlock], floor: u8, max_dim: u32) -> Result<OtmmFloorRender> {
+pub fn render_floor(data: &[u8], blocks: &[OtmmBlock], floor: u8, max_dim: u32, job: &JobHandle) -> Result<OtmmFloorRender> {
     let (min_x, min_y, w, h) = floor_bounds(blocks, floor).ok_or_else(|| anyhow::anyhow!("No blocks on floor {}", floor))?;
 
     let scale = (max_dim as f32 / w as f32).min(max_dim as f32 / h as f32).min(1.0);
@@ -136,7 +259,12 @@ pub fn render_floor(data: &[u8], blocks: &[OtmmBlock], floor: u8, max_dim: u32)
     let out_h = ((h as f32 * scale).round() as u32).max(1);
 
     let mut img = image::RgbaImage::new(out_w, out_h);
+    job.set_total(blocks.iter().filter(|b| b.z == floor).count());
     for b in blocks.iter().filter(|b| b.z == floor) {
+        if job.is_cancelled() {
+            bail!(JOB_CANCELLED);
+        }
+        job.advance(1);
         // Skip outlier blocks outside the main cluster bbox (keeps subtraction
         // below non-negative and avoids drawing stray dots far from the map).
         let (bx, by) = (b.x as u32, b.y as u32);
@@ -168,9 +296,7 @@ pub fn render_floor(data: &[u8], blocks: &[OtmmBlock], floor: u8, max_dim: u32)
     }
 
     let mut png = Vec::new();
-    image::DynamicImage::ImageRgba8(img)
-        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
-        .context("Encode OTMM floor PNG")?;
+    image::DynamicImage::ImageRgba8(img).write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png).context("Encode OTMM floor PNG")?;
 
     Ok(OtmmFloorRender {
         png,
@@ -186,6 +312,37 @@ pub fn render_floor(data: &[u8], blocks: &[OtmmBlock], floor: u8, max_dim: u32)
 mod tests {
     use super::*;
 
+    #[test]
+    fn writes_blocks_that_read_back() {
+        let mut minimap = OtmmMinimap::new();
+        let wall = OtmmField {
+            color: 186,
+            unpassable: true,
+        };
+        minimap.set(32000, 31999, 7, wall);
+        minimap.set(
+            100,
+            100,
+            6,
+            OtmmField {
+                color: 0,
+                unpassable: false,
+            },
+        );
+        let bytes = minimap.to_bytes().unwrap();
+        assert_eq!(u16::from_le_bytes([bytes[4], bytes[5]]), 22);
+
+        let index = parse_index(&bytes).unwrap();
+        assert_eq!(index.iter().map(|b| (b.x, b.y, b.z)).collect::<Vec<_>>(), vec![(64, 64, 6), (32000, 31936, 7)]);
+        let read = OtmmMinimap::from_bytes(&bytes).unwrap();
+        assert_eq!(read.get(32000, 31999, 7), Some(wall));
+        assert_eq!(read.get(32001, 31999, 7), None);
+        assert_eq!(read.fields().count(), 2);
+
+        assert_eq!(color_to_8bit(color_from_8bit(186)), 186);
+        assert_eq!(color_to_8bit([250, 10, 60]), 5 * 36 + 1);
+    }
+
     #[test]
     fn real_otmm_when_env_set() {
         let Ok(path) = std::env::var("CANARY_OTMM") else {
@@ -198,7 +355,7 @@ mod tests {
         assert!(!floors.is_empty());
         // Render a floor and confirm we produced a valid, non-trivial PNG.
         let floor = *floors.iter().next().unwrap();
-        let r = render_floor(&data, &index, floor, 2048).expect("render");
+        let r = render_floor(&data, &index, floor, 2048, &JobHandle::detached("render_otmm")).expect("render");
         println!("floor {} -> {}x{} scale {} png {} bytes", floor, r.width, r.height, r.scale, r.png.len());
         assert!(r.png.len() > 8 && &r.png[1..4] == b"PNG");
     }
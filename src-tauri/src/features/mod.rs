@@ -2,15 +2,21 @@
 // Organized by feature domain
 
 pub mod appearances;
+pub mod backups;
 pub mod dat_merge;
+pub mod jobs;
+pub mod map;
 pub mod minimap;
 pub mod monsters;
 pub mod npcs;
+pub mod otb;
 pub mod proficiency;
 pub mod qm;
 pub mod rcc;
+pub mod references;
 pub mod settings;
 pub mod sounds;
 pub mod sprites;
 pub mod staticdata;
 pub mod staticmapdata;
+pub mod world;
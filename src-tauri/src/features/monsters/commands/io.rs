@@ -22,7 +22,7 @@ pub async fn list_monster_files(monsters_path: String) -> Result<Vec<MonsterList
     list_monsters_recursive(Path::new(&monsters_path), &base_path).map_err(|e| format!("Failed to list monster files: {}", e))
 }
 
-fn list_monsters_recursive(dir: &Path, base: &Path) -> Result<Vec<MonsterListEntry>> {
+pub(crate) fn list_monsters_recursive(dir: &Path, base: &Path) -> Result<Vec<MonsterListEntry>> {
     let mut monsters = Vec::new();
 
     if !dir.exists() {
@@ -102,6 +102,7 @@ pub async fn save_monster_file(file_path: String, monster: Monster) -> Result<()
 
     // Atomic write (temp + rename) so a mid-write failure can't truncate the
     // existing monster file.
+    crate::core::backups::snapshot_before_write(&file_path);
     crate::core::fs_util::write_atomic(Path::new(&file_path), lua_content.as_bytes()).map_err(|e| format!("Failed to write monster file: {}", e))?;
 
     Ok(())
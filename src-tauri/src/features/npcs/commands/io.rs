@@ -85,6 +85,7 @@ pub async fn save_npc_file(file_path: String, npc: Npc) -> Result<(), String> {
 
     // Atomic write (temp + rename) so a mid-write failure can't truncate the
     // existing NPC file.
+    crate::core::backups::snapshot_before_write(&file_path);
     crate::core::fs_util::write_atomic(Path::new(&file_path), lua_content.as_bytes()).map_err(|e| format!("Failed to write npc file: {}", e))?;
 
     Ok(())
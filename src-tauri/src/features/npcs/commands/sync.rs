@@ -1,3 +1,5 @@
+use crate::core::jobs::JobHandle;
+use crate::features::jobs::run_job;
 use crate::features::npcs::commands::io::list_npcs_recursive;
 use crate::features::npcs::parsers::lua_parser::LuaNpcParser;
 use crate::features::npcs::types::NpcShopItem;
@@ -398,7 +400,7 @@ fn strip_leading_data_component(path: &Path) -> Option<PathBuf> {
 }
 
 /// Resolve items.xml path from explicit input (absolute/relative) or infer from npcs path.
-fn resolve_items_xml_path(npcs_path: &Path, items_xml_path: Option<&str>) -> Result<Option<PathBuf>, String> {
+pub(crate) fn resolve_items_xml_path(npcs_path: &Path, items_xml_path: Option<&str>) -> Result<Option<PathBuf>, String> {
     let data_dir = infer_data_dir_from_npcs_path(npcs_path);
     let server_root = infer_server_root_from_npcs_path(npcs_path);
 
@@ -631,7 +633,7 @@ fn remove_auto_sync_block(content: &str) -> String {
     result
 }
 
-fn find_matching_brace(content: &str, open_brace: usize) -> Option<usize> {
+pub(crate) fn find_matching_brace(content: &str, open_brace: usize) -> Option<usize> {
     let bytes = content.as_bytes();
     let len = bytes.len();
     if open_brace >= len || bytes[open_brace] != b'{' {
@@ -1776,9 +1778,42 @@ pub async fn sync_npc_shops_from_proto(
     items_xml_path: Option<String>,
     shop_source: Option<String>,
     fandom_shop_map: Option<HashMap<String, Vec<ExternalProtoShopEntryInput>>>,
+    app: tauri::AppHandle,
     state: tauri::State<'_, AppState>,
 ) -> Result<SyncNpcShopsResult, String> {
-    let base_path = PathBuf::from(&npcs_path);
+    // Runs as a job; cancelling stops before the next NPC file (files already written stay written).
+    run_job(&app, state.inner(), "sync_npc_shops", |job| {
+        let options = NpcShopSyncOptions {
+            ignore_item_ids,
+            ignore_item_names,
+            keep_custom_items,
+            items_xml_path,
+            shop_source,
+            fandom_shop_map,
+        };
+        sync_npc_shops_impl(state.inner(), &npcs_path, options, job)
+    })
+}
+
+struct NpcShopSyncOptions {
+    ignore_item_ids: Vec<u32>,
+    ignore_item_names: Vec<String>,
+    keep_custom_items: bool,
+    items_xml_path: Option<String>,
+    shop_source: Option<String>,
+    fandom_shop_map: Option<HashMap<String, Vec<ExternalProtoShopEntryInput>>>,
+}
+
+fn sync_npc_shops_impl(state: &AppState, npcs_path: &str, options: NpcShopSyncOptions, job: &JobHandle) -> Result<SyncNpcShopsResult, String> {
+    let NpcShopSyncOptions {
+        ignore_item_ids,
+        ignore_item_names,
+        keep_custom_items,
+        items_xml_path,
+        shop_source,
+        fandom_shop_map,
+    } = options;
+    let base_path = PathBuf::from(npcs_path);
     if !base_path.exists() {
         return Err("NPC directory does not exist".to_string());
     }
@@ -1919,7 +1954,10 @@ pub async fn sync_npc_shops_from_proto(
         npc_details: Vec::new(),
     };
 
+    job.set_total(npc_entries.len());
     for entry in &npc_entries {
+        job.check_cancelled()?;
+        job.advance(1);
         let file_content = match std::fs::read_to_string(&entry.file_path) {
             Ok(c) => c,
             Err(e) => {
@@ -2006,6 +2044,7 @@ pub async fn sync_npc_shops_from_proto(
                 status: "updated_dynamic".to_string(),
             });
 
+            crate::core::backups::snapshot_before_write(&entry.file_path);
             if let Err(e) = std::fs::write(&entry.file_path, new_content) {
                 result.errors.push(format!("{}: Failed to save - {}", entry.name, e));
             }
@@ -2040,6 +2079,7 @@ pub async fn sync_npc_shops_from_proto(
             status: "updated".to_string(),
         });
 
+        crate::core::backups::snapshot_before_write(&entry.file_path);
         if let Err(e) = std::fs::write(&entry.file_path, new_content) {
             result.errors.push(format!("{}: Failed to save - {}", entry.name, e));
         }
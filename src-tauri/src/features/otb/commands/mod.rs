@@ -0,0 +1,3 @@
+mod otb;
+
+pub use otb::*;
@@ -0,0 +1,420 @@
+use crate::core::protobuf::{Appearance, HookType};
+use crate::features::otb::parsers::{flag, group, parse_items_otb, write_items_otb, ItemsOtb, OtbItem, OtbVersion, ATTR_LIGHT2, ATTR_MINIMAP_COLOR, ATTR_SPEED, ATTR_TOP_ORDER, ATTR_WARE_ID};
+use crate::state::AppState;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use tauri::State;
+
+/// Flag bits recomputed from the appearance on sync; every other bit (floor
+/// change, decay, distance read, ...) is server-only and left untouched.
+const DERIVED_FLAGS: u32 = flag::BLOCK_SOLID
+    | flag::BLOCK_PROJECTILE
+    | flag::BLOCK_PATHFIND
+    | flag::HAS_HEIGHT
+    | flag::USEABLE
+    | flag::PICKUPABLE
+    | flag::MOVEABLE
+    | flag::STACKABLE
+    | flag::ALWAYS_ON_TOP
+    | flag::READABLE
+    | flag::ROTATABLE
+    | flag::HANGABLE
+    | flag::VERTICAL
+    | flag::HORIZONTAL
+    | flag::LOOK_THROUGH
+    | flag::ANIMATION
+    | flag::FULL_TILE
+    | flag::FORCE_USE;
+
+const DERIVED_GROUPS: [u8; 4] = [group::GROUND, group::CONTAINER, group::SPLASH, group::FLUID];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OtbSummary {
+    pub version: OtbVersion,
+    pub item_count: usize,
+    pub max_server_id: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OtbMappingStatus {
+    /// Client id resolves to a loaded object appearance.
+    Mapped,
+    /// Client id points at an object that does not exist.
+    MissingAppearance,
+    /// Server-only item (no client id).
+    ServerOnly,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OtbMappingEntry {
+    pub server_id: u16,
+    pub client_id: u16,
+    pub group: u8,
+    pub status: OtbMappingStatus,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OtbMapping {
+    pub entries: Vec<OtbMappingEntry>,
+    /// Object ids with no server item.
+    pub unmapped_client_ids: Vec<u32>,
+    /// Client ids shared by several server items (legal, but usually stale copies).
+    pub shared_client_ids: Vec<u16>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OtbSyncResult {
+    /// `(server_id, client_id)` of the items that were appended.
+    pub added: Vec<(u16, u16)>,
+    /// Existing items whose group, flags or attributes were refreshed.
+    pub updated: usize,
+    /// Object ids above 65535 that cannot be represented.
+    pub skipped: Vec<u32>,
+}
+
+fn u16_attr(value: Option<u32>) -> Option<Vec<u8>> {
+    value.map(|v| (v.min(u16::MAX as u32) as u16).to_le_bytes().to_vec())
+}
+
+/// Recomputes the group, derived flags and appearance-backed attributes of `item`.
+pub fn apply_appearance_to_item(item: &mut OtbItem, appearance: &Appearance) {
+    let flags = appearance.flags.clone().unwrap_or_default();
+    let set = |value: Option<bool>| value == Some(true);
+
+    let derived_group = if flags.bank.is_some() {
+        group::GROUND
+    } else if set(flags.container) {
+        group::CONTAINER
+    } else if set(flags.liquidpool) {
+        group::SPLASH
+    } else if set(flags.liquidcontainer) {
+        group::FLUID
+    } else {
+        group::NONE
+    };
+    if derived_group != group::NONE || DERIVED_GROUPS.contains(&item.group) {
+        item.group = derived_group;
+    }
+
+    let animated = appearance.frame_group.iter().filter_map(|fg| fg.sprite_info.as_ref()).any(|info| info.animation.as_ref().is_some_and(|a| a.sprite_phase.len() > 1));
+    let hook = flags.hook.and_then(|h| h.direction).and_then(|d| HookType::try_from(d).ok());
+    let derived = [
+        (set(flags.unpass), flag::BLOCK_SOLID),
+        (set(flags.unsight), flag::BLOCK_PROJECTILE),
+        (set(flags.avoid), flag::BLOCK_PATHFIND),
+        (flags.height.is_some(), flag::HAS_HEIGHT),
+        (set(flags.multiuse), flag::USEABLE),
+        (set(flags.take), flag::PICKUPABLE),
+        (!set(flags.unmove), flag::MOVEABLE),
+        (set(flags.cumulative), flag::STACKABLE),
+        (set(flags.clip) || set(flags.bottom) || set(flags.top), flag::ALWAYS_ON_TOP),
+        (flags.write.is_some() || flags.write_once.is_some(), flag::READABLE),
+        (set(flags.rotate), flag::ROTATABLE),
+        (set(flags.hang), flag::HANGABLE),
+        (hook == Some(HookType::South), flag::VERTICAL),
+        (hook == Some(HookType::East), flag::HORIZONTAL),
+        (set(flags.ignore_look), flag::LOOK_THROUGH),
+        (animated, flag::ANIMATION),
+        (set(flags.fullbank), flag::FULL_TILE),
+        (set(flags.forceuse), flag::FORCE_USE),
+    ]
+    .into_iter()
+    .filter(|(on, _)| *on)
+    .fold(0, |bits, (_, bit)| bits | bit);
+    item.flags = (item.flags & !DERIVED_FLAGS) | derived;
+
+    let top_order = if set(flags.clip) {
+        Some(1)
+    } else if set(flags.bottom) {
+        Some(2)
+    } else if set(flags.top) {
+        Some(3)
+    } else {
+        None
+    };
+    item.set_attribute(ATTR_SPEED, u16_attr(flags.bank.and_then(|b| b.waypoints)));
+    item.set_attribute(ATTR_MINIMAP_COLOR, u16_attr(flags.automap.and_then(|a| a.color)));
+    item.set_attribute(
+        ATTR_LIGHT2,
+        flags.light.map(|light| {
+            let mut data = u16_attr(Some(light.brightness.unwrap_or(0))).unwrap_or_default();
+            data.extend(u16_attr(Some(light.color.unwrap_or(0))).unwrap_or_default());
+            data
+        }),
+    );
+    item.set_attribute(ATTR_TOP_ORDER, top_order.map(|order| vec![order]));
+    item.set_attribute(ATTR_WARE_ID, u16_attr(flags.market.and_then(|m| m.trade_as_object_id)));
+}
+
+/// Appends a server item for every object without one and, when `refresh` is
+/// set, re-derives existing items from their appearance. New items reuse the
+/// client id as server id when it is free, otherwise take the next free id.
+pub fn sync_otb_with_objects(otb: &mut ItemsOtb, objects: &[Appearance], refresh: bool) -> OtbSyncResult {
+    let mut result = OtbSyncResult::default();
+    let by_client: BTreeMap<u32, &Appearance> = objects.iter().filter_map(|a| a.id.map(|id| (id, a))).collect();
+
+    if refresh {
+        for item in otb.items.iter_mut().filter(|i| i.client_id != 0) {
+            if let Some(appearance) = by_client.get(&(item.client_id as u32)) {
+                let before = item.clone();
+                apply_appearance_to_item(item, appearance);
+                if *item != before {
+                    result.updated += 1;
+                }
+            }
+        }
+    }
+
+    let mapped: HashSet<u16> = otb.items.iter().map(|i| i.client_id).collect();
+    let mut used: HashSet<u16> = otb.items.iter().map(|i| i.server_id).collect();
+    let mut next_free = otb.max_server_id().max(99);
+
+    for (client_id, appearance) in by_client {
+        let Ok(client_id_u16) = u16::try_from(client_id) else {
+            result.skipped.push(client_id);
+            continue;
+        };
+        if mapped.contains(&client_id_u16) {
+            continue;
+        }
+        let server_id = if used.contains(&client_id_u16) {
+            while used.contains(&next_free) && next_free < u16::MAX {
+                next_free += 1;
+            }
+            if used.contains(&next_free) {
+                result.skipped.push(client_id);
+                continue;
+            }
+            next_free
+        } else {
+            client_id_u16
+        };
+        used.insert(server_id);
+
+        let mut item = OtbItem {
+            server_id,
+            client_id: client_id_u16,
+            ..Default::default()
+        };
+        apply_appearance_to_item(&mut item, appearance);
+        otb.items.push(item);
+        result.added.push((server_id, client_id_u16));
+    }
+
+    otb.items.sort_by_key(|i| i.server_id);
+    result
+}
+
+pub fn build_otb_mapping(otb: &ItemsOtb, objects: &[Appearance]) -> OtbMapping {
+    let object_ids: HashSet<u32> = objects.iter().filter_map(|a| a.id).collect();
+    let mut seen = HashSet::new();
+    let mut shared = BTreeSet::new();
+
+    let entries = otb
+        .items
+        .iter()
+        .map(|item| {
+            let status = if item.client_id == 0 {
+                OtbMappingStatus::ServerOnly
+            } else if object_ids.contains(&(item.client_id as u32)) {
+                OtbMappingStatus::Mapped
+            } else {
+                OtbMappingStatus::MissingAppearance
+            };
+            if item.client_id != 0 && !seen.insert(item.client_id) {
+                shared.insert(item.client_id);
+            }
+            OtbMappingEntry {
+                server_id: item.server_id,
+                client_id: item.client_id,
+                group: item.group,
+                status,
+            }
+        })
+        .collect();
+
+    let mut unmapped_client_ids: Vec<u32> = object_ids.into_iter().filter(|id| u16::try_from(*id).map_or(true, |id| !seen.contains(&id))).collect();
+    unmapped_client_ids.sort_unstable();
+
+    OtbMapping {
+        entries,
+        unmapped_client_ids,
+        shared_client_ids: shared.into_iter().collect(),
+    }
+}
+
+fn summary(otb: &ItemsOtb) -> OtbSummary {
+    OtbSummary {
+        version: otb.version.clone(),
+        item_count: otb.items.len(),
+        max_server_id: otb.max_server_id(),
+    }
+}
+
+/// Adds server items for freshly created objects when an items.otb is loaded.
+/// Called by the appearance create/import paths; a no-op without an OTB.
+pub fn register_new_objects(state: &AppState, objects: &[Appearance]) {
+    if objects.is_empty() {
+        return;
+    }
+    if let Some(otb) = state.items_otb.write().as_mut() {
+        let result = sync_otb_with_objects(otb, objects, false);
+        if !result.added.is_empty() {
+            log::info!("items.otb: added {} server items for new objects", result.added.len());
+        }
+    }
+}
+
+pub fn load_items_otb_file(state: &AppState, path: &str) -> Result<OtbSummary, String> {
+    let data = std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let otb = parse_items_otb(&data).map_err(|e| format!("Failed to parse items.otb: {}", e))?;
+    let summary = summary(&otb);
+    *state.items_otb.write() = Some(otb);
+    *state.items_otb_path.lock() = Some(path.into());
+    Ok(summary)
+}
+
+pub fn save_items_otb_file(state: &AppState, path: &str) -> Result<OtbSummary, String> {
+    let otb_lock = state.items_otb.read();
+    let otb = otb_lock.as_ref().ok_or_else(|| "No items.otb loaded".to_string())?;
+    let bytes = write_items_otb(otb).map_err(|e| format!("Failed to encode items.otb: {}", e))?;
+    crate::core::backups::snapshot_before_write(path);
+    std::fs::write(path, bytes).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    *state.items_otb_path.lock() = Some(path.into());
+    Ok(summary(otb))
+}
+
+pub fn sync_loaded_items_otb(state: &AppState, refresh: bool) -> Result<OtbSyncResult, String> {
+    let appearances_lock = state.appearances.read();
+    let appearances = appearances_lock.as_ref().ok_or_else(|| "No appearances loaded".to_string())?;
+    let mut otb_lock = state.items_otb.write();
+    let otb = otb_lock.as_mut().ok_or_else(|| "No items.otb loaded".to_string())?;
+    Ok(sync_otb_with_objects(otb, &appearances.object, refresh))
+}
+
+pub fn loaded_items_otb_mapping(state: &AppState) -> Result<OtbMapping, String> {
+    let appearances_lock = state.appearances.read();
+    let appearances = appearances_lock.as_ref().ok_or_else(|| "No appearances loaded".to_string())?;
+    let otb_lock = state.items_otb.read();
+    let otb = otb_lock.as_ref().ok_or_else(|| "No items.otb loaded".to_string())?;
+    Ok(build_otb_mapping(otb, &appearances.object))
+}
+
+/// Load an items.otb into memory.
+#[tauri::command]
+pub async fn load_items_otb(path: String, state: State<'_, AppState>) -> Result<OtbSummary, String> {
+    load_items_otb_file(&state, &path)
+}
+
+/// Write the in-memory items.otb to `path`.
+#[tauri::command]
+pub async fn save_items_otb(path: String, state: State<'_, AppState>) -> Result<OtbSummary, String> {
+    save_items_otb_file(&state, &path)
+}
+
+/// Server id → client id table checked against the loaded appearances.
+#[tauri::command]
+pub async fn get_items_otb_mapping(state: State<'_, AppState>) -> Result<OtbMapping, String> {
+    loaded_items_otb_mapping(&state)
+}
+
+/// Extend the loaded items.otb with missing objects; `refresh_flags` also
+/// re-derives flags and attributes of existing items from their appearance.
+#[tauri::command]
+pub async fn sync_items_otb(refresh_flags: bool, state: State<'_, AppState>) -> Result<OtbSyncResult, String> {
+    sync_loaded_items_otb(&state, refresh_flags)
+}
+
+/// Start a new items.otb generated from the loaded objects.
+#[tauri::command]
+pub async fn generate_items_otb(version: OtbVersion, state: State<'_, AppState>) -> Result<OtbSyncResult, String> {
+    *state.items_otb.write() = Some(ItemsOtb {
+        version,
+        ..Default::default()
+    });
+    *state.items_otb_path.lock() = None;
+    sync_loaded_items_otb(&state, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::protobuf::{AppearanceFlagBank, AppearanceFlags};
+
+    fn object(id: u32, flags: AppearanceFlags) -> Appearance {
+        Appearance {
+            id: Some(id),
+            flags: Some(flags),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn sync_adds_missing_objects_and_keeps_server_flags() {
+        let mut otb = ItemsOtb {
+            items: vec![OtbItem {
+                server_id: 101,
+                client_id: 100,
+                flags: 1 << 19, // cannot decay: server-only
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let objects = vec![
+            object(
+                100,
+                AppearanceFlags {
+                    bank: Some(AppearanceFlagBank {
+                        waypoints: Some(150),
+                    }),
+                    unmove: Some(true),
+                    ..Default::default()
+                },
+            ),
+            object(101, AppearanceFlags::default()),
+            object(102, AppearanceFlags::default()),
+        ];
+
+        let result = sync_otb_with_objects(&mut otb, &objects, true);
+        assert_eq!(result.updated, 1);
+        // Server id 101 is taken, so client 101 moves to 102 and client 102 to 103.
+        assert_eq!(result.added, vec![(102, 101), (103, 102)]);
+        let ground = &otb.items[0];
+        assert_eq!(ground.group, group::GROUND);
+        assert_eq!(ground.flags, 1 << 19);
+        assert_eq!(ground.attribute(ATTR_SPEED), Some(&150u16.to_le_bytes()[..]));
+        assert_eq!(otb.items[1].flags & flag::MOVEABLE, flag::MOVEABLE);
+    }
+
+    #[test]
+    fn mapping_reports_missing_and_unmapped() {
+        let otb = ItemsOtb {
+            items: vec![
+                OtbItem {
+                    server_id: 100,
+                    client_id: 100,
+                    ..Default::default()
+                },
+                OtbItem {
+                    server_id: 101,
+                    client_id: 500,
+                    ..Default::default()
+                },
+                OtbItem {
+                    server_id: 102,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let mapping = build_otb_mapping(&otb, &[object(100, AppearanceFlags::default()), object(103, AppearanceFlags::default())]);
+        let statuses: Vec<OtbMappingStatus> = mapping.entries.iter().map(|e| e.status).collect();
+        assert_eq!(statuses, vec![OtbMappingStatus::Mapped, OtbMappingStatus::MissingAppearance, OtbMappingStatus::ServerOnly]);
+        assert_eq!(mapping.unmapped_client_ids, vec![103]);
+    }
+}
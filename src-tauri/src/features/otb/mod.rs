@@ -0,0 +1,6 @@
+// Items OTB feature: read/write the server's `items.otb` (server id -> client id
+// table) and keep it in step with the loaded object appearances.
+pub mod commands;
+pub mod parsers;
+
+pub use parsers::ItemsOtb;
@@ -0,0 +1,371 @@
+// Server item list (`items.otb`).
+//
+// Layout: u32 identifier (0), then a node tree. A node is
+//   0xFE <type u8> <data...> <child nodes...> 0xFF
+// and any 0xFD/0xFE/0xFF byte inside node data is escaped with a leading 0xFD.
+// The root node carries u32 flags plus a version attribute; each child is one
+// item: <group u8> <flags u32> then attributes as [attr u8][len u16][data].
+
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+
+const NODE_START: u8 = 0xFE;
+const NODE_END: u8 = 0xFF;
+const ESCAPE: u8 = 0xFD;
+
+const ROOT_ATTR_VERSION: u8 = 0x01;
+const CSD_VERSION_LEN: usize = 128;
+
+pub const ATTR_SERVER_ID: u8 = 0x10;
+pub const ATTR_CLIENT_ID: u8 = 0x11;
+pub const ATTR_SPEED: u8 = 0x14;
+pub const ATTR_MINIMAP_COLOR: u8 = 0x21;
+pub const ATTR_LIGHT2: u8 = 0x2A;
+pub const ATTR_TOP_ORDER: u8 = 0x2B;
+pub const ATTR_WARE_ID: u8 = 0x2D;
+
+/// Item groups (`itemgroup_t` in the server sources).
+pub mod group {
+    pub const NONE: u8 = 0;
+    pub const GROUND: u8 = 1;
+    pub const CONTAINER: u8 = 2;
+    pub const SPLASH: u8 = 11;
+    pub const FLUID: u8 = 12;
+}
+
+/// Item flag bits (`itemflags_t` in the server sources).
+pub mod flag {
+    pub const BLOCK_SOLID: u32 = 1 << 0;
+    pub const BLOCK_PROJECTILE: u32 = 1 << 1;
+    pub const BLOCK_PATHFIND: u32 = 1 << 2;
+    pub const HAS_HEIGHT: u32 = 1 << 3;
+    pub const USEABLE: u32 = 1 << 4;
+    pub const PICKUPABLE: u32 = 1 << 5;
+    pub const MOVEABLE: u32 = 1 << 6;
+    pub const STACKABLE: u32 = 1 << 7;
+    pub const ALWAYS_ON_TOP: u32 = 1 << 13;
+    pub const READABLE: u32 = 1 << 14;
+    pub const ROTATABLE: u32 = 1 << 15;
+    pub const HANGABLE: u32 = 1 << 16;
+    pub const VERTICAL: u32 = 1 << 17;
+    pub const HORIZONTAL: u32 = 1 << 18;
+    pub const LOOK_THROUGH: u32 = 1 << 23;
+    pub const ANIMATION: u32 = 1 << 24;
+    pub const FULL_TILE: u32 = 1 << 25;
+    pub const FORCE_USE: u32 = 1 << 26;
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OtbVersion {
+    pub major_version: u32,
+    pub minor_version: u32,
+    pub build_number: u32,
+    /// Free-form description, e.g. "OTB 3.65-12.00".
+    pub csd_version: String,
+}
+
+/// An attribute the editor does not interpret, kept byte-for-byte.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OtbAttribute {
+    pub kind: u8,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OtbItem {
+    pub group: u8,
+    pub flags: u32,
+    pub server_id: u16,
+    /// Client (appearance object) id; 0 for server-only items.
+    pub client_id: u16,
+    pub attributes: Vec<OtbAttribute>,
+}
+
+impl OtbItem {
+    pub fn attribute(&self, kind: u8) -> Option<&[u8]> {
+        self.attributes.iter().find(|a| a.kind == kind).map(|a| a.data.as_slice())
+    }
+
+    /// Replaces (or removes, when `data` is `None`) one attribute.
+    pub fn set_attribute(&mut self, kind: u8, data: Option<Vec<u8>>) {
+        match (self.attributes.iter().position(|a| a.kind == kind), data) {
+            (Some(index), Some(data)) => self.attributes[index].data = data,
+            (Some(index), None) => {
+                self.attributes.remove(index);
+            }
+            (None, Some(data)) => self.attributes.push(OtbAttribute {
+                kind,
+                data,
+            }),
+            (None, None) => {}
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemsOtb {
+    pub root_flags: u32,
+    pub version: OtbVersion,
+    pub items: Vec<OtbItem>,
+}
+
+impl ItemsOtb {
+    pub fn max_server_id(&self) -> u16 {
+        self.items.iter().map(|i| i.server_id).max().unwrap_or(0)
+    }
+}
+
+struct Node {
+    kind: u8,
+    data: Vec<u8>,
+    children: Vec<Node>,
+}
+
+/// Reads one node starting at `data[*pos]` (which must be `NODE_START`).
+fn read_node(data: &[u8], pos: &mut usize) -> Result<Node> {
+    if data.get(*pos) != Some(&NODE_START) {
+        bail!("Expected node start at offset {}", *pos);
+    }
+    *pos += 1;
+    let kind = *data.get(*pos).ok_or_else(|| anyhow!("Truncated node at offset {}", *pos))?;
+    *pos += 1;
+
+    let mut node = Node {
+        kind,
+        data: Vec::new(),
+        children: Vec::new(),
+    };
+    loop {
+        let byte = *data.get(*pos).ok_or_else(|| anyhow!("Unterminated node (type {})", kind))?;
+        match byte {
+            NODE_START => node.children.push(read_node(data, pos)?),
+            NODE_END => {
+                *pos += 1;
+                return Ok(node);
+            }
+            ESCAPE => {
+                let escaped = *data.get(*pos + 1).ok_or_else(|| anyhow!("Dangling escape at offset {}", *pos))?;
+                node.data.push(escaped);
+                *pos += 2;
+            }
+            other => {
+                node.data.push(other);
+                *pos += 1;
+            }
+        }
+    }
+}
+
+fn write_escaped(out: &mut Vec<u8>, bytes: &[u8]) {
+    for &byte in bytes {
+        if matches!(byte, NODE_START | NODE_END | ESCAPE) {
+            out.push(ESCAPE);
+        }
+        out.push(byte);
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, len: usize) -> Result<&[u8]> {
+        let end = self.pos.checked_add(len).filter(|end| *end <= self.data.len()).ok_or_else(|| anyhow!("Node data truncated at offset {}", self.pos))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+}
+
+pub fn parse_items_otb(data: &[u8]) -> Result<ItemsOtb> {
+    if data.len() < 5 {
+        bail!("File too small to be an items.otb");
+    }
+    let mut pos = 4; // identifier, always 0
+    let root = read_node(data, &mut pos)?;
+
+    let mut reader = Reader {
+        data: &root.data,
+        pos: 0,
+    };
+    let root_flags = reader.u32()?;
+    let mut version = OtbVersion::default();
+    while !reader.is_empty() {
+        let attr = reader.u8()?;
+        let len = reader.u16()? as usize;
+        let value = reader.take(len)?;
+        if attr == ROOT_ATTR_VERSION {
+            if len < 12 {
+                bail!("Version attribute too short ({} bytes)", len);
+            }
+            let mut version_reader = Reader {
+                data: value,
+                pos: 0,
+            };
+            version.major_version = version_reader.u32()?;
+            version.minor_version = version_reader.u32()?;
+            version.build_number = version_reader.u32()?;
+            let csd = &value[12..];
+            version.csd_version = String::from_utf8_lossy(&csd[..csd.iter().position(|b| *b == 0).unwrap_or(csd.len())]).to_string();
+        }
+    }
+
+    let items = root.children.iter().map(parse_item).collect::<Result<Vec<_>>>()?;
+    Ok(ItemsOtb {
+        root_flags,
+        version,
+        items,
+    })
+}
+
+fn parse_item(node: &Node) -> Result<OtbItem> {
+    let mut reader = Reader {
+        data: &node.data,
+        pos: 0,
+    };
+    let mut item = OtbItem {
+        group: node.kind,
+        flags: reader.u32()?,
+        ..Default::default()
+    };
+    while !reader.is_empty() {
+        let kind = reader.u8()?;
+        let len = reader.u16()? as usize;
+        let value = reader.take(len)?;
+        match kind {
+            ATTR_SERVER_ID if len == 2 => item.server_id = u16::from_le_bytes([value[0], value[1]]),
+            ATTR_CLIENT_ID if len == 2 => item.client_id = u16::from_le_bytes([value[0], value[1]]),
+            _ => item.attributes.push(OtbAttribute {
+                kind,
+                data: value.to_vec(),
+            }),
+        }
+    }
+    if item.server_id == 0 {
+        bail!("Item node without a server id");
+    }
+    Ok(item)
+}
+
+fn push_attribute(data: &mut Vec<u8>, kind: u8, value: &[u8]) -> Result<()> {
+    let len = u16::try_from(value.len()).map_err(|_| anyhow!("Attribute 0x{:02X} too large", kind))?;
+    data.push(kind);
+    data.extend_from_slice(&len.to_le_bytes());
+    data.extend_from_slice(value);
+    Ok(())
+}
+
+pub fn write_items_otb(otb: &ItemsOtb) -> Result<Vec<u8>> {
+    let mut out = vec![0, 0, 0, 0];
+
+    let mut root_data = otb.root_flags.to_le_bytes().to_vec();
+    let mut version = Vec::with_capacity(12 + CSD_VERSION_LEN);
+    version.extend_from_slice(&otb.version.major_version.to_le_bytes());
+    version.extend_from_slice(&otb.version.minor_version.to_le_bytes());
+    version.extend_from_slice(&otb.version.build_number.to_le_bytes());
+    let mut csd = otb.version.csd_version.as_bytes().to_vec();
+    csd.resize(CSD_VERSION_LEN, 0);
+    version.extend_from_slice(&csd);
+    push_attribute(&mut root_data, ROOT_ATTR_VERSION, &version)?;
+
+    out.push(NODE_START);
+    out.push(0);
+    write_escaped(&mut out, &root_data);
+
+    for item in &otb.items {
+        let mut data = item.flags.to_le_bytes().to_vec();
+        push_attribute(&mut data, ATTR_SERVER_ID, &item.server_id.to_le_bytes())?;
+        if item.client_id != 0 {
+            push_attribute(&mut data, ATTR_CLIENT_ID, &item.client_id.to_le_bytes())?;
+        }
+        for attribute in &item.attributes {
+            push_attribute(&mut data, attribute.kind, &attribute.data)?;
+        }
+
+        out.push(NODE_START);
+        // The node type is not escaped: it follows NODE_START directly.
+        out.push(item.group);
+        write_escaped(&mut out, &data);
+        out.push(NODE_END);
+    }
+
+    out.push(NODE_END);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> ItemsOtb {
+        ItemsOtb {
+            root_flags: 0,
+            version: OtbVersion {
+                major_version: 3,
+                minor_version: 57,
+                build_number: 62,
+                csd_version: "OTB 3.57-10.98".to_string(),
+            },
+            items: vec![
+                OtbItem {
+                    group: group::GROUND,
+                    flags: flag::MOVEABLE,
+                    server_id: 100,
+                    client_id: 100,
+                    attributes: vec![OtbAttribute {
+                        kind: ATTR_SPEED,
+                        data: 150u16.to_le_bytes().to_vec(),
+                    }],
+                },
+                // 0xFE/0xFF/0xFD in ids and flags must be escaped.
+                OtbItem {
+                    group: group::NONE,
+                    flags: 0xFDFE_FF00,
+                    server_id: 0x01FE,
+                    client_id: 0xFFFD,
+                    attributes: Vec::new(),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn otb_roundtrips_with_escaped_bytes() {
+        let otb = sample();
+        let bytes = write_items_otb(&otb).unwrap();
+        assert_eq!(parse_items_otb(&bytes).unwrap(), otb);
+    }
+
+    #[test]
+    fn set_attribute_replaces_and_removes() {
+        let mut item = sample().items.remove(0);
+        item.set_attribute(ATTR_SPEED, Some(vec![1, 0]));
+        assert_eq!(item.attribute(ATTR_SPEED), Some(&[1u8, 0][..]));
+        item.set_attribute(ATTR_SPEED, None);
+        assert!(item.attribute(ATTR_SPEED).is_none());
+        assert_eq!(sample().max_server_id(), 0x01FE);
+    }
+}
@@ -0,0 +1,3 @@
+mod items_otb;
+
+pub use items_otb::*;
@@ -169,6 +169,7 @@ pub async fn save_proficiency_file(file_path: String, data: Vec<ProficiencyEntry
     }
 
     let json = serde_json::to_string_pretty(&new_value).map_err(|e| format!("Falha ao formatar JSON: {}", e))?;
+    crate::core::backups::snapshot_before_write(&file_path);
     fs::write(&file_path, json).map_err(|e| format!("Falha ao salvar arquivo: {}", e))?;
     Ok(())
 }
@@ -187,7 +188,11 @@ pub struct ItemProficiencyInfo {
 #[command]
 pub async fn scan_proficiency_items_xml(xml_path: String) -> Result<Vec<ItemProficiencyInfo>, String> {
     let content = fs::read_to_string(&xml_path).map_err(|e| format!("Falha ao ler items.xml: {}", e))?;
+    parse_items_xml_proficiency(&content)
+}
 
+/// Items of an items.xml document that carry a proficiency attribute.
+pub fn parse_items_xml_proficiency(content: &str) -> Result<Vec<ItemProficiencyInfo>, String> {
     let item_re = Regex::new(r#"<item\s+(?:fromid|id)="(\d+)"(?:\s[^>]*?name="([^"]*)")?"#).map_err(|e| format!("Regex error: {}", e))?;
     let prof_re = Regex::new(r#"<attribute\s+key="proficiency"\s+value="(\d+)""#).map_err(|e| format!("Regex error: {}", e))?;
 
@@ -303,6 +308,7 @@ pub async fn update_item_proficiency_xml(xml_path: String, item_id: u32, profici
         output.push('\n');
     }
 
+    crate::core::backups::snapshot_before_write(&xml_path);
     fs::write(&xml_path, output).map_err(|e| format!("Falha ao salvar items.xml: {}", e))?;
 
     Ok(())
@@ -417,6 +423,7 @@ pub async fn sync_proficiency_items_xml(xml_path: String, mappings: Vec<ItemProf
         output.push('\n');
     }
 
+    crate::core::backups::snapshot_before_write(&xml_path);
     fs::write(&xml_path, output).map_err(|e| format!("Falha ao salvar items.xml: {}", e))?;
 
     Ok(SyncResult {
@@ -121,14 +121,7 @@ pub fn qm_update_translations(updates: Vec<(usize, Option<String>)>) -> Result<u
     let mut state = QM_STATE.lock();
     let file = state.file.as_mut().ok_or("No QM file loaded")?;
 
-    let mut count = 0usize;
-    for (index, translation) in updates {
-        if let Some(entry) = file.entries.iter_mut().find(|e| e.index == index) {
-            entry.translation = translation;
-            count += 1;
-        }
-    }
-    Ok(count)
+    Ok(apply_translations(file, updates))
 }
 
 /// Save the QM file to disk (original path or a new one)
@@ -145,6 +138,7 @@ pub fn qm_save(output_path: Option<String>) -> Result<String, String> {
         let file = state.file.as_ref().ok_or("No QM file loaded")?;
         qm_writer::write_qm(file)
     };
+    crate::core::backups::snapshot_before_write(&dest);
     std::fs::write(&dest, &bytes).map_err(|e| format!("Failed to write {}: {e}", dest.display()))?;
 
     // "Save As": adopt the new path as the active source so a later
@@ -161,8 +155,12 @@ pub fn qm_save(output_path: Option<String>) -> Result<String, String> {
 pub fn qm_export_csv(output_path: String) -> Result<usize, String> {
     let state = QM_STATE.lock();
     let file = state.file.as_ref().ok_or("No QM file loaded")?;
+    write_entries_csv(file, &output_path)
+}
 
-    let mut wtr = csv::Writer::from_path(&output_path).map_err(|e| format!("Failed to open CSV for writing: {e}"))?;
+/// Writes every entry of `file` as a CSV row (shared with the headless CLI)
+pub fn write_entries_csv(file: &QmFile, output_path: &str) -> Result<usize, String> {
+    let mut wtr = csv::Writer::from_path(output_path).map_err(|e| format!("Failed to open CSV for writing: {e}"))?;
     wtr.write_record(["index", "hash", "context", "source_text", "comment", "translation"]).map_err(|e| format!("Failed to write CSV header: {e}"))?;
     for entry in &file.entries {
         // csv::Writer quotes/escapes fields (including embedded newlines) so the
@@ -179,7 +177,20 @@ pub fn qm_export_csv(output_path: String) -> Result<usize, String> {
 /// Only the `index` and `translation` columns are used; others are ignored.
 #[command]
 pub fn qm_import_csv(file_path: String) -> Result<usize, String> {
-    let mut rdr = csv::ReaderBuilder::new().has_headers(true).flexible(true).from_path(&file_path).map_err(|e| format!("Failed to read CSV: {e}"))?;
+    let updates = read_csv_translations(&file_path)?;
+    let count = updates.len();
+
+    // Apply
+    let mut state = QM_STATE.lock();
+    let file = state.file.as_mut().ok_or("No QM file loaded")?;
+    apply_translations(file, updates);
+
+    Ok(count)
+}
+
+/// Read `(index, translation)` pairs from a CSV produced by `qm_export_csv`
+pub fn read_csv_translations(file_path: &str) -> Result<Vec<(usize, Option<String>)>, String> {
+    let mut rdr = csv::ReaderBuilder::new().has_headers(true).flexible(true).from_path(file_path).map_err(|e| format!("Failed to read CSV: {e}"))?;
 
     // Resolve the index/translation columns from the header (defaults match the
     // export order) so reordered columns still map correctly.
@@ -207,18 +218,19 @@ pub fn qm_import_csv(file_path: String) -> Result<usize, String> {
         }
     }
 
-    let count = updates.len();
+    Ok(updates)
+}
 
-    // Apply
-    let mut state = QM_STATE.lock();
-    let file = state.file.as_mut().ok_or("No QM file loaded")?;
+/// Apply translation updates by entry index; unknown indices are ignored
+pub fn apply_translations(file: &mut QmFile, updates: Vec<(usize, Option<String>)>) -> usize {
+    let mut count = 0usize;
     for (index, translation) in updates {
         if let Some(entry) = file.entries.iter_mut().find(|e| e.index == index) {
             entry.translation = translation;
+            count += 1;
         }
     }
-
-    Ok(count)
+    count
 }
 
 /// Debug: return raw hex dumps of the first few messages to diagnose parse issues
@@ -150,6 +150,7 @@ pub fn rcc_save(output_path: Option<String>) -> Result<String, String> {
 
     let output = rcc_writer::write_rcc(&rcc.entries)?;
 
+    crate::core::backups::snapshot_before_write(&save_path);
     std::fs::write(&save_path, &output).map_err(|e| format!("Failed to write RCC: {}", e))?;
 
     Ok(save_path.to_string_lossy().to_string())
@@ -178,6 +179,7 @@ pub fn rcc_install_to_client() -> Result<String, String> {
         std::fs::copy(&source, &backup).map_err(|e| format!("Failed to back up original RCC: {}", e))?;
     }
 
+    crate::core::backups::snapshot_before_write(&source);
     crate::core::fs_util::write_atomic(&source, &output).map_err(|e| format!("Failed to install RCC: {}", e))?;
     Ok(source.to_string_lossy().to_string())
 }
@@ -204,7 +206,11 @@ pub fn rcc_extract_all(output_dir: String) -> Result<usize, String> {
     let state = RCC_STATE.lock();
     let rcc = state.file.as_ref().ok_or("No RCC file loaded")?;
 
-    let base = PathBuf::from(&output_dir);
+    extract_entries(rcc, &PathBuf::from(&output_dir))
+}
+
+/// Write every file entry of `rcc` under `base`, keeping the resource tree layout
+pub fn extract_entries(rcc: &RccFile, base: &Path) -> Result<usize, String> {
     let mut count = 0;
 
     for entry in &rcc.entries {
@@ -212,7 +218,7 @@ pub fn rcc_extract_all(output_dir: String) -> Result<usize, String> {
             continue;
         }
 
-        let file_path = safe_output_path(&base, &entry.path)?;
+        let file_path = safe_output_path(base, &entry.path)?;
 
         // Create parent directories
         if let Some(parent) = file_path.parent() {
@@ -644,6 +650,7 @@ pub async fn rcc_install_to_client_qt(rcc_exe: Option<String>) -> Result<String,
         if !backup.exists() {
             std::fs::copy(&source, &backup).map_err(|e| format!("Failed to back up original RCC: {}", e))?;
         }
+        crate::core::backups::snapshot_before_write(&source);
         crate::core::fs_util::write_atomic(&source, &compiled).map_err(|e| format!("Failed to install RCC: {}", e))?;
         Ok(source.to_string_lossy().to_string())
     })
@@ -257,6 +257,7 @@ pub async fn exe_apply_spell_to_disk(name: String, content: String) -> Result<Sp
                 let _ = std::fs::copy(&target, &bak);
             }
         }
+        crate::core::backups::snapshot_before_write(&target);
         crate::core::fs_util::write_atomic(&target, content.as_bytes()).map_err(|e| format!("Failed to write {}: {}", target.display(), e))?;
 
         // Point the client at the disk copy.
@@ -400,8 +400,10 @@ fn replace_once(buf: &mut [u8], old: &[u8], new: &[u8]) -> Result<(), String> {
     Ok(())
 }
 
-/// Copy `path` to a sibling named `backup_name` once (no overwrite).
+/// Copy `path` to a sibling named `backup_name` once (no overwrite), and take
+/// a versioned snapshot of the current binary.
 fn backup_once(path: &Path, backup_name: &str) -> Result<(), String> {
+    crate::core::backups::snapshot_before_write(path);
     let backup = path.with_file_name(backup_name);
     if !backup.exists() {
         std::fs::copy(path, &backup).map_err(|e| format!("Failed to create backup {}: {}", backup.display(), e))?;
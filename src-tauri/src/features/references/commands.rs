@@ -0,0 +1,181 @@
+// Tauri commands for the project-wide reference index
+
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use tauri::{AppHandle, State};
+
+use super::index::{IdReference, ReferenceIndex, ReferenceSource};
+use crate::core::jobs::JobHandle;
+use crate::features::appearances::AppearanceCategory;
+use crate::features::jobs::run_job;
+use crate::features::monsters::commands::list_monsters_recursive;
+use crate::features::monsters::parsers::lua_parser::LuaMonsterParser;
+use crate::features::npcs::commands::io::list_npcs_recursive;
+use crate::features::npcs::commands::sync::resolve_items_xml_path;
+use crate::features::npcs::parsers::lua_parser::LuaNpcParser;
+use crate::features::proficiency::{parse_items_xml_proficiency, ProficiencyEntry};
+use crate::features::settings::read_settings;
+use crate::state::AppState;
+
+/// Server datapack locations scanned besides the loaded client data.
+#[derive(Debug, Clone, Default)]
+pub struct ReferencePaths {
+    pub tibia_path: Option<PathBuf>,
+    pub monster_path: Option<PathBuf>,
+    pub npc_path: Option<PathBuf>,
+    pub items_xml_path: Option<PathBuf>,
+    pub proficiency_path: Option<PathBuf>,
+}
+
+impl ReferencePaths {
+    /// Tibia/monster/NPC paths from settings; items.xml falls back to the copy next to the NPC folder.
+    pub fn from_settings(app: &AppHandle, items_xml_path: Option<String>, proficiency_path: Option<String>) -> Result<Self, String> {
+        let settings = read_settings(app)?;
+        let tibia_path = settings.tibia_base_path.filter(|p| !p.trim().is_empty()).map(PathBuf::from);
+        let monster_path = settings.monster_base_path.filter(|p| !p.trim().is_empty()).map(PathBuf::from);
+        let npc_path = settings.npc_base_path.filter(|p| !p.trim().is_empty()).map(PathBuf::from);
+        let items_xml_path = match (&npc_path, items_xml_path.filter(|p| !p.trim().is_empty())) {
+            (Some(npc_path), explicit) => resolve_items_xml_path(npc_path, explicit.as_deref())?,
+            (None, explicit) => explicit.map(PathBuf::from),
+        };
+        Ok(Self {
+            tibia_path,
+            monster_path,
+            npc_path,
+            items_xml_path,
+            proficiency_path: proficiency_path.filter(|p| !p.trim().is_empty()).map(PathBuf::from),
+        })
+    }
+
+    /// Monster Lua files under `monster_path` (empty when not configured).
+    pub fn monster_files(&self) -> Result<Vec<String>, String> {
+        let Some(root) = &self.monster_path else {
+            return Ok(Vec::new());
+        };
+        let monsters = list_monsters_recursive(root, root).map_err(|e| format!("Failed to list monster files: {}", e))?;
+        Ok(monsters.into_iter().map(|m| m.file_path).collect())
+    }
+
+    /// NPC Lua files under `npc_path` (empty when not configured).
+    pub fn npc_files(&self) -> Result<Vec<String>, String> {
+        let Some(root) = &self.npc_path else {
+            return Ok(Vec::new());
+        };
+        let npcs = list_npcs_recursive(root, root).map_err(|e| format!("Failed to list npc files: {}", e))?;
+        Ok(npcs.into_iter().map(|n| n.file_path).collect())
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReferenceIndexSummary {
+    pub total_references: usize,
+    pub by_source: HashMap<ReferenceSource, usize>,
+    pub monster_files: usize,
+    pub npc_files: usize,
+    pub items_xml_path: Option<String>,
+    pub proficiency_path: Option<String>,
+}
+
+/// Parses every Lua file in `files` in parallel, skipping the ones `parse` rejects.
+fn parse_lua_files<T: Send>(files: &[String], job: &JobHandle, parse: impl Fn(String) -> anyhow::Result<T> + Sync) -> Vec<(String, T)> {
+    files
+        .par_iter()
+        .filter_map(|path| {
+            if job.is_cancelled() {
+                return None;
+            }
+            let parsed = fs::read_to_string(path).map_err(anyhow::Error::from).and_then(&parse);
+            job.advance(1);
+            match parsed {
+                Ok(value) => Some((path.clone(), value)),
+                Err(e) => {
+                    log::debug!("Skipping {} in reference index: {}", path, e);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// Scans the loaded client data and the server datapack into a fresh index.
+pub fn build_reference_index_impl(state: &AppState, paths: &ReferencePaths, job: &JobHandle) -> Result<(ReferenceIndex, ReferenceIndexSummary), String> {
+    let mut index = ReferenceIndex::new(state.items_otb.read().as_ref());
+
+    job.set_message("Indexing client data");
+    let appearances_guard = state.appearances.read();
+    if let Some(appearances) = appearances_guard.as_ref() {
+        index.index_appearances(appearances);
+    }
+    if let Some(doc) = state.staticdata_doc.read().as_ref() {
+        index.index_static_data(doc);
+    }
+    if let Some(data) = state.staticmapdata.read().as_ref() {
+        index.index_static_map_data(data);
+    }
+
+    if let Some(path) = &paths.proficiency_path {
+        let content = fs::read_to_string(path).map_err(|e| format!("Failed to read proficiency file: {}", e))?;
+        let entries: Vec<ProficiencyEntry> = serde_json::from_str(&content).map_err(|e| format!("Failed to parse proficiency file: {}", e))?;
+        if let Some(appearances) = appearances_guard.as_ref() {
+            index.index_proficiencies(appearances, &entries, &path.to_string_lossy());
+        }
+    }
+    drop(appearances_guard);
+
+    if let Some(path) = &paths.items_xml_path {
+        let content = fs::read_to_string(path).map_err(|e| format!("Failed to read items.xml: {}", e))?;
+        let items = parse_items_xml_proficiency(&content)?;
+        index.index_items_xml(&items, &path.to_string_lossy());
+    }
+
+    let monster_files = paths.monster_files()?;
+    let npc_files = paths.npc_files()?;
+
+    job.set_total(monster_files.len() + npc_files.len());
+    job.set_message("Scanning monsters");
+    let monsters = parse_lua_files(&monster_files, job, |content| LuaMonsterParser::new(content).parse());
+    job.check_cancelled()?;
+    job.set_message("Scanning NPCs");
+    let npcs = parse_lua_files(&npc_files, job, |content| LuaNpcParser::new(content).parse());
+    job.check_cancelled()?;
+
+    for (path, monster) in &monsters {
+        index.index_monster(monster, path);
+    }
+    for (path, npc) in &npcs {
+        index.index_npc(npc, path);
+    }
+
+    let summary = ReferenceIndexSummary {
+        total_references: index.len(),
+        by_source: index.counts(),
+        monster_files: monsters.len(),
+        npc_files: npcs.len(),
+        items_xml_path: paths.items_xml_path.as_ref().map(|p| p.to_string_lossy().to_string()),
+        proficiency_path: paths.proficiency_path.as_ref().map(|p| p.to_string_lossy().to_string()),
+    };
+    Ok((index, summary))
+}
+
+/// (Re)build the reference index from the loaded data and the configured
+/// monster/NPC paths. Call again after editing to refresh it.
+#[tauri::command]
+pub async fn build_reference_index(items_xml_path: Option<String>, proficiency_path: Option<String>, app: AppHandle, state: State<'_, AppState>) -> Result<ReferenceIndexSummary, String> {
+    let paths = ReferencePaths::from_settings(&app, items_xml_path, proficiency_path)?;
+    let (index, summary) = run_job(&app, state.inner(), "reference_index", |job| build_reference_index_impl(state.inner(), &paths, job))?;
+    log::info!("Reference index built: {} references ({} monsters, {} NPCs)", summary.total_references, summary.monster_files, summary.npc_files);
+    *state.reference_index.write() = Some(index);
+    Ok(summary)
+}
+
+/// Everything that points at `id` (client id for objects, looktype for outfits).
+#[tauri::command]
+pub async fn find_references(category: AppearanceCategory, id: u32, state: State<'_, AppState>) -> Result<Vec<IdReference>, String> {
+    let guard = state.reference_index.read();
+    let index = guard.as_ref().ok_or("Reference index not built")?;
+    Ok(index.find(&category, id))
+}
@@ -0,0 +1,329 @@
+// Reference index: every place in the loaded datasets that points at an
+// object (client id) or outfit id. Server-side ids (monster loot, NPC shop
+// `itemId`, items.xml) are translated through items.otb when one is loaded;
+// canary-style datapacks that use client ids directly need no translation.
+
+use crate::features::appearances::{AppearanceCategory, Appearances};
+use crate::features::monsters::types::Monster;
+use crate::features::npcs::types::Npc;
+use crate::features::otb::ItemsOtb;
+use crate::features::proficiency::{ItemProficiencyInfo, ProficiencyEntry};
+use crate::features::staticdata::parsers::StaticDataDoc;
+use crate::features::staticmapdata::StaticMapData;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Dataset a reference was found in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ReferenceSource {
+    Appearances,
+    Monsters,
+    Npcs,
+    ItemsXml,
+    ItemsOtb,
+    Proficiency,
+    StaticData,
+    StaticMapData,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdReference {
+    pub source: ReferenceSource,
+    /// What holds the reference: "Object 3031", a monster or NPC name, "House 12".
+    pub owner: String,
+    /// Field holding the id, e.g. `flags.market.trade_as_object_id`.
+    pub field: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_path: Option<String>,
+    /// How many times `owner` uses the id in `field` (house tiles, repeated loot).
+    pub occurrences: u32,
+}
+
+fn at(source: ReferenceSource, owner: &str, field: &str, file_path: Option<&str>) -> IdReference {
+    IdReference {
+        source,
+        owner: owner.to_string(),
+        field: field.to_string(),
+        file_path: file_path.map(str::to_string),
+        occurrences: 1,
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ReferenceIndex {
+    refs: HashMap<(AppearanceCategory, u32), Vec<IdReference>>,
+    /// Server id -> client id from items.otb; ids missing here are taken as client ids.
+    server_to_client: HashMap<u32, u32>,
+}
+
+impl ReferenceIndex {
+    pub fn new(items_otb: Option<&ItemsOtb>) -> Self {
+        let server_to_client = items_otb.map(|otb| otb.items.iter().filter(|item| item.client_id != 0).map(|item| (item.server_id as u32, item.client_id as u32)).collect()).unwrap_or_default();
+        Self {
+            refs: HashMap::new(),
+            server_to_client,
+        }
+    }
+
+    /// References to `id` in `category`, grouped by source.
+    pub fn find(&self, category: &AppearanceCategory, id: u32) -> Vec<IdReference> {
+        let mut refs = self.refs.get(&(category.clone(), id)).cloned().unwrap_or_default();
+        refs.sort_by_key(|r| r.source as u8);
+        refs
+    }
+
+    /// Total number of references in the index.
+    pub fn len(&self) -> usize {
+        self.refs.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.refs.is_empty()
+    }
+
+    /// Number of references found per source.
+    pub fn counts(&self) -> HashMap<ReferenceSource, usize> {
+        let mut counts = HashMap::new();
+        for r in self.refs.values().flatten() {
+            *counts.entry(r.source).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    fn client_id(&self, server_id: u32) -> u32 {
+        self.server_to_client.get(&server_id).copied().unwrap_or(server_id)
+    }
+
+    /// Records `reference`, merging it into an existing entry with the same owner and field.
+    fn add(&mut self, category: AppearanceCategory, id: u32, reference: IdReference) {
+        if id == 0 {
+            return;
+        }
+        let refs = self.refs.entry((category, id)).or_default();
+        match refs.iter_mut().find(|r| r.source == reference.source && r.owner == reference.owner && r.field == reference.field) {
+            Some(existing) => existing.occurrences += reference.occurrences,
+            None => refs.push(reference),
+        }
+    }
+
+    /// Object-to-object links inside appearances.dat (self references are skipped).
+    pub fn index_appearances(&mut self, appearances: &Appearances) {
+        for object in &appearances.object {
+            let (Some(own_id), Some(flags)) = (object.id, object.flags.as_ref()) else {
+                continue;
+            };
+            let owner = format!("Object {}", own_id);
+            let mut links = Vec::new();
+            if let Some(market) = &flags.market {
+                links.push((market.trade_as_object_id, "flags.market.trade_as_object_id"));
+                links.push((market.show_as_object_id, "flags.market.show_as_object_id"));
+            }
+            if let Some(expire) = &flags.changedtoexpire {
+                links.push((expire.former_object_typeid, "flags.changedtoexpire.former_object_typeid"));
+            }
+            for (target, field) in links {
+                if let Some(target) = target.filter(|t| *t != own_id) {
+                    self.add(AppearanceCategory::Objects, target, at(ReferenceSource::Appearances, &owner, field, None));
+                }
+            }
+        }
+    }
+
+    /// Links each object with a proficiency flag to its entry in the proficiency file.
+    pub fn index_proficiencies(&mut self, appearances: &Appearances, entries: &[ProficiencyEntry], file_path: &str) {
+        let by_id: HashMap<u32, &ProficiencyEntry> = entries.iter().map(|entry| (entry.proficiency_id, entry)).collect();
+        for object in &appearances.object {
+            let Some(proficiency_id) = object.flags.as_ref().and_then(|f| f.proficiency.as_ref()).and_then(|p| p.proficiency_id) else {
+                continue;
+            };
+            if let Some(entry) = by_id.get(&proficiency_id) {
+                let owner = format!("{} (ProficiencyId {})", entry.name, proficiency_id);
+                self.add(AppearanceCategory::Objects, object.id.unwrap_or(0), at(ReferenceSource::Proficiency, &owner, "ProficiencyId", Some(file_path)));
+            }
+        }
+    }
+
+    /// items.xml entries (server ids) carrying a proficiency attribute.
+    pub fn index_items_xml(&mut self, items: &[ItemProficiencyInfo], file_path: &str) {
+        for item in items {
+            let owner = if item.item_name.is_empty() {
+                format!("Item {}", item.item_id)
+            } else {
+                item.item_name.clone()
+            };
+            let client_id = self.client_id(item.item_id);
+            self.add(AppearanceCategory::Objects, client_id, at(ReferenceSource::ItemsXml, &owner, "attribute.proficiency", Some(file_path)));
+        }
+    }
+
+    pub fn index_monster(&mut self, monster: &Monster, file_path: &str) {
+        let outfit = &monster.outfit;
+        self.add(AppearanceCategory::Outfits, outfit.look_type, at(ReferenceSource::Monsters, &monster.name, "outfit.lookType", Some(file_path)));
+        self.add(AppearanceCategory::Outfits, outfit.look_mount as u32, at(ReferenceSource::Monsters, &monster.name, "outfit.lookMount", Some(file_path)));
+        for entry in &monster.loot {
+            if let Some(id) = entry.id {
+                let client_id = self.client_id(id);
+                self.add(AppearanceCategory::Objects, client_id, at(ReferenceSource::Monsters, &monster.name, "loot.id", Some(file_path)));
+            }
+        }
+    }
+
+    pub fn index_npc(&mut self, npc: &Npc, file_path: &str) {
+        let outfit = &npc.outfit;
+        self.add(AppearanceCategory::Outfits, outfit.look_type, at(ReferenceSource::Npcs, &npc.name, "outfit.lookType", Some(file_path)));
+        self.add(AppearanceCategory::Outfits, outfit.look_mount as u32, at(ReferenceSource::Npcs, &npc.name, "outfit.lookMount", Some(file_path)));
+        for item in npc.shop.iter().flatten() {
+            if let Some(client_id) = item.client_id {
+                self.add(AppearanceCategory::Objects, client_id, at(ReferenceSource::Npcs, &npc.name, "shop.clientId", Some(file_path)));
+            }
+            if let Some(item_id) = item.item_id {
+                let client_id = self.client_id(item_id);
+                self.add(AppearanceCategory::Objects, client_id, at(ReferenceSource::Npcs, &npc.name, "shop.itemId", Some(file_path)));
+            }
+        }
+    }
+
+    /// Creature and boss outfits (looktype and mount are outfit ids).
+    pub fn index_static_data(&mut self, doc: &StaticDataDoc) {
+        let mut outfits = Vec::new();
+        match doc {
+            StaticDataDoc::Old(data) => {
+                for creature in &data.creatures {
+                    outfits.push((format!("Creature {}", creature.name()), "creatures", creature.outfit.as_ref().map(|o| (o.looktype, o.mount))));
+                }
+                for boss in &data.bosses {
+                    outfits.push((format!("Boss {}", boss.name()), "bosses", boss.outfit.as_ref().map(|o| (o.looktype, o.mount))));
+                }
+            }
+            StaticDataDoc::New(data) => {
+                for monster in &data.monsters {
+                    outfits.push((format!("Monster {}", monster.name()), "monsters", monster.outfit.as_ref().map(|o| (o.looktype, o.mount))));
+                }
+                for boss in &data.bosses {
+                    outfits.push((format!("Boss {}", boss.name()), "bosses", boss.outfit.as_ref().map(|o| (o.looktype, o.mount))));
+                }
+            }
+        }
+        for (owner, list, outfit) in outfits {
+            let Some((looktype, mount)) = outfit else {
+                continue;
+            };
+            if let Some(looktype) = looktype {
+                self.add(AppearanceCategory::Outfits, looktype, at(ReferenceSource::StaticData, &owner, &format!("{}.outfit.looktype", list), None));
+            }
+            if let Some(mount) = mount {
+                self.add(AppearanceCategory::Outfits, mount, at(ReferenceSource::StaticData, &owner, &format!("{}.outfit.mount", list), None));
+            }
+        }
+    }
+
+    /// Objects placed on house tiles.
+    pub fn index_static_map_data(&mut self, data: &StaticMapData) {
+        for house in &data.houses {
+            let mut counts: HashMap<u32, u32> = HashMap::new();
+            let rows = house.layout.as_ref().and_then(|l| l.tiles.as_ref()).and_then(|t| t.floor_data.as_ref()).map(|f| f.rows.as_slice()).unwrap_or_default();
+            for tile in rows.iter().flat_map(|row| &row.tiles) {
+                if let Some(object_id) = tile.object_id {
+                    *counts.entry(object_id).or_insert(0) += 1;
+                }
+            }
+            let owner = format!("House {}", house.house_id());
+            for (object_id, count) in counts {
+                self.add(
+                    AppearanceCategory::Objects,
+                    object_id,
+                    IdReference {
+                        occurrences: count,
+                        ..at(ReferenceSource::StaticMapData, &owner, "layout.tiles.object_id", None)
+                    },
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::protobuf::{Appearance, AppearanceFlagMarket, AppearanceFlags};
+    use crate::features::monsters::types::LootEntry;
+    use crate::features::npcs::types::NpcShopItem;
+    use crate::features::otb::parsers::OtbItem;
+
+    fn object(id: u32, trade_as: u32) -> Appearance {
+        Appearance {
+            id: Some(id),
+            flags: Some(AppearanceFlags {
+                market: Some(AppearanceFlagMarket {
+                    trade_as_object_id: Some(trade_as),
+                    show_as_object_id: Some(trade_as),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn appearance_links_skip_self_references() {
+        let appearances = Appearances {
+            object: vec![object(100, 100), object(101, 100)],
+            ..Default::default()
+        };
+        let mut index = ReferenceIndex::new(None);
+        index.index_appearances(&appearances);
+
+        let refs = index.find(&AppearanceCategory::Objects, 100);
+        assert_eq!(refs.len(), 2);
+        assert!(refs.iter().all(|r| r.owner == "Object 101"));
+        assert!(index.find(&AppearanceCategory::Objects, 101).is_empty());
+    }
+
+    #[test]
+    fn server_ids_are_translated_through_items_otb() {
+        let otb = ItemsOtb {
+            items: vec![OtbItem {
+                server_id: 2148,
+                client_id: 3031,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let mut index = ReferenceIndex::new(Some(&otb));
+        let monster = Monster {
+            name: "Rat".to_string(),
+            loot: vec![
+                LootEntry {
+                    id: Some(2148),
+                    chance: 100,
+                    ..Default::default()
+                },
+                LootEntry {
+                    id: Some(2148),
+                    chance: 50,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        index.index_monster(&monster, "monster/rat.lua");
+        let npc = Npc {
+            name: "Trader".to_string(),
+            shop: Some(vec![NpcShopItem {
+                client_id: Some(3031),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        };
+        index.index_npc(&npc, "npc/trader.lua");
+
+        let refs = index.find(&AppearanceCategory::Objects, 3031);
+        assert_eq!(refs.len(), 2);
+        assert_eq!((refs[0].source, refs[0].occurrences), (ReferenceSource::Monsters, 2));
+        assert_eq!((refs[1].source, refs[1].field.as_str()), (ReferenceSource::Npcs, "shop.clientId"));
+        assert!(index.find(&AppearanceCategory::Objects, 2148).is_empty());
+    }
+}
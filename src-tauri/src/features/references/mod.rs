@@ -0,0 +1,11 @@
+// References feature module
+// Project-wide "find references" for object and outfit ids, and cascading
+// object id remaps across client and server files
+
+pub mod commands;
+pub mod index;
+pub mod remap;
+
+pub use commands::*;
+pub use index::*;
+pub use remap::*;
@@ -0,0 +1,667 @@
+// Cascading object id remap
+// `change_appearance_id` only renames inside appearances.dat. This moves
+// objects and rewrites the references the reference index knows about:
+// appearances.dat, staticmapdata house tiles, items.otb, monster loot, NPC
+// shops and items.xml. Every file is planned in memory first, snapshotted by
+// the backup manager, then written; a failed write puts back the files already
+// replaced.
+//
+// With items.otb loaded, server files keep their server ids and only the otb
+// client ids move (items.otb is written with the rest). Without one, server
+// ids are client ids and are rewritten too, splitting items.xml `fromid`/`toid`
+// ranges around moved ids. The proficiency file is keyed by ProficiencyId, so
+// it never needs changes. The remap is not journaled: undo history is cleared.
+
+use prost::Message;
+use rayon::prelude::*;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+use tauri::{AppHandle, State};
+
+use super::commands::ReferencePaths;
+use super::index::ReferenceSource;
+use crate::core::backups::{manager, BackupEntry, BackupManager};
+use crate::core::fs_util::write_atomic;
+use crate::core::jobs::JobHandle;
+use crate::core::protobuf::unknown_fields::UnknownFields;
+use crate::core::protobuf::Appearance;
+use crate::features::appearances::commands::helpers::{invalidate_search_cache, rebuild_indexes};
+use crate::features::appearances::commands::remap_object_references;
+use crate::features::appearances::parsers::encode_appearances;
+use crate::features::appearances::Appearances;
+use crate::features::dat_merge::staticdata_merge::find_largest_dat;
+use crate::features::jobs::run_job;
+use crate::features::npcs::commands::sync::find_matching_brace;
+use crate::features::otb::parsers::write_items_otb;
+use crate::features::otb::ItemsOtb;
+use crate::features::staticmapdata::parsers::load_staticmapdata;
+use crate::features::staticmapdata::StaticMapData;
+use crate::state::AppState;
+
+/// Repeated field holding objects in `Appearances`.
+const OBJECT_FIELD: u32 = 1;
+
+static MONSTER_LOOT_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"monster\.loot\s*=\s*\{").unwrap());
+static LOOT_ID_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\bid\s*=\s*(?P<id>\d+)").unwrap());
+static SHOP_CLIENT_ID_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\bclientId\s*=\s*(?P<id>\d+)").unwrap());
+static SHOP_ITEM_ID_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\bitemId\s*=\s*(?P<id>\d+)").unwrap());
+static XML_ITEM_TAG_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"<item\b[^>]*>").unwrap());
+static XML_ID_ATTR_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"\sid="(?P<id>\d+)""#).unwrap());
+static XML_ID_RANGE_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"\sfromid="(?P<from>\d+)"\s+toid="(?P<to>\d+)""#).unwrap());
+static XML_CLIENT_ID_ATTR_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"\bclientid="(?P<id>\d+)""#).unwrap());
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdRemap {
+    pub old_id: u32,
+    pub new_id: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemapLineChange {
+    pub line: usize,
+    pub before: String,
+    pub after: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemapFileChange {
+    pub path: String,
+    pub source: ReferenceSource,
+    pub replacements: usize,
+    /// Changed lines for text files; empty for binary files.
+    pub lines: Vec<RemapLineChange>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemapPreview {
+    pub objects_renamed: usize,
+    pub appearance_references: usize,
+    /// Entries of the loaded items.otb pointed at a new client id.
+    pub otb_entries: usize,
+    pub files: Vec<RemapFileChange>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemapResult {
+    pub preview: RemapPreview,
+    /// Snapshots taken before writing, restorable with `restore_backup`.
+    pub backups: Vec<BackupEntry>,
+}
+
+struct PlannedFile {
+    path: PathBuf,
+    original: Vec<u8>,
+    updated: Vec<u8>,
+}
+
+struct RemapPlan {
+    appearances: Appearances,
+    unknown: UnknownFields,
+    items_otb: Option<ItemsOtb>,
+    static_map_data: Option<StaticMapData>,
+    files: Vec<PlannedFile>,
+    preview: RemapPreview,
+}
+
+/// Validated old -> new map. Pairs with equal ids are dropped.
+pub fn build_mapping(remaps: &[IdRemap], objects: &[Appearance]) -> Result<HashMap<u32, u32>, String> {
+    let existing: HashSet<u32> = objects.iter().filter_map(|o| o.id).collect();
+    let mut mapping = HashMap::new();
+    let mut targets = HashSet::new();
+    for remap in remaps.iter().filter(|r| r.old_id != r.new_id) {
+        if remap.new_id == 0 {
+            return Err("New id must be greater than 0".to_string());
+        }
+        if !existing.contains(&remap.old_id) {
+            return Err(format!("Object {} not found", remap.old_id));
+        }
+        if mapping.insert(remap.old_id, remap.new_id).is_some() {
+            return Err(format!("Object {} is remapped more than once", remap.old_id));
+        }
+        if !targets.insert(remap.new_id) {
+            return Err(format!("More than one object is moved to id {}", remap.new_id));
+        }
+    }
+    // A target may only be taken by an object that moves away in the same remap.
+    if let Some(taken) = targets.iter().find(|id| existing.contains(id) && !mapping.contains_key(id)) {
+        return Err(format!("Object with id {} already exists", taken));
+    }
+    Ok(mapping)
+}
+
+/// Renames objects and rewrites object references between them.
+/// Returns (objects renamed, references rewritten).
+pub fn remap_objects(appearances: &mut Appearances, mapping: &HashMap<u32, u32>) -> (usize, usize) {
+    let mut renamed = 0;
+    let mut references = 0;
+    for object in &mut appearances.object {
+        if let Some(new_id) = object.id.and_then(|id| mapping.get(&id)) {
+            object.id = Some(*new_id);
+            renamed += 1;
+        }
+        references += remap_object_references(object, |id| mapping.get(&id).copied());
+    }
+    appearances.object.sort_by_key(|o| o.id.unwrap_or(u32::MAX));
+    (renamed, references)
+}
+
+/// Rewrites object ids placed on house tiles. Returns the number of tiles changed.
+pub fn remap_static_map_data(data: &mut StaticMapData, mapping: &HashMap<u32, u32>) -> usize {
+    let mut changed = 0;
+    for house in &mut data.houses {
+        let rows = house.layout.as_mut().and_then(|l| l.tiles.as_mut()).and_then(|t| t.floor_data.as_mut()).map(|f| f.rows.as_mut_slice()).unwrap_or_default();
+        for tile in rows.iter_mut().flat_map(|row| row.tiles.iter_mut()) {
+            if let Some(new_id) = tile.object_id.and_then(|id| mapping.get(&id)) {
+                tile.object_id = Some(*new_id);
+                changed += 1;
+            }
+        }
+    }
+    changed
+}
+
+/// Points items.otb entries at the new client ids. Returns the number of entries changed.
+pub fn remap_items_otb(otb: &mut ItemsOtb, mapping: &HashMap<u32, u32>) -> usize {
+    let mut changed = 0;
+    for item in &mut otb.items {
+        if let Some(new_id) = mapping.get(&(item.client_id as u32)) {
+            if let Ok(new_id) = u16::try_from(*new_id) {
+                item.client_id = new_id;
+                changed += 1;
+            }
+        }
+    }
+    changed
+}
+
+/// Replaces the `id` group of every `re` match inside `ranges` (sorted, disjoint).
+fn rewrite_ids(content: &str, re: &Regex, ranges: impl IntoIterator<Item = Range<usize>>, mapping: &HashMap<u32, u32>, count: &mut usize) -> String {
+    let mut result = String::with_capacity(content.len());
+    let mut last = 0;
+    for range in ranges {
+        for caps in re.captures_iter(&content[range.clone()]) {
+            let Some(id) = caps.name("id") else {
+                continue;
+            };
+            let Some(new_id) = id.as_str().parse::<u32>().ok().and_then(|old| mapping.get(&old)) else {
+                continue;
+            };
+            result.push_str(&content[last..range.start + id.start()]);
+            result.push_str(&new_id.to_string());
+            last = range.start + id.end();
+            *count += 1;
+        }
+    }
+    result.push_str(&content[last..]);
+    result
+}
+
+/// Loot ids of a monster script, which are only client ids when the datapack uses them.
+pub fn remap_monster_lua(content: &str, mapping: &HashMap<u32, u32>, datapack_uses_client_ids: bool) -> (String, usize) {
+    let mut count = 0;
+    if !datapack_uses_client_ids {
+        return (content.to_string(), count);
+    }
+    let Some(loot) = MONSTER_LOOT_RE.find(content) else {
+        return (content.to_string(), count);
+    };
+    let open_brace = loot.end() - 1;
+    let Some(close_brace) = find_matching_brace(content, open_brace) else {
+        return (content.to_string(), count);
+    };
+    let updated = rewrite_ids(content, &LOOT_ID_RE, Some(open_brace..close_brace), mapping, &mut count);
+    (updated, count)
+}
+
+/// Shop `clientId`s, plus `itemId`s when the datapack uses client ids.
+pub fn remap_npc_lua(content: &str, mapping: &HashMap<u32, u32>, datapack_uses_client_ids: bool) -> (String, usize) {
+    let mut count = 0;
+    let mut updated = rewrite_ids(content, &SHOP_CLIENT_ID_RE, Some(0..content.len()), mapping, &mut count);
+    if datapack_uses_client_ids {
+        updated = rewrite_ids(&updated, &SHOP_ITEM_ID_RE, Some(0..updated.len()), mapping, &mut count);
+    }
+    (updated, count)
+}
+
+/// `<item clientid=..>`, plus `<item id=..>` and `fromid`/`toid` ranges when
+/// the datapack uses client ids.
+pub fn remap_items_xml(content: &str, mapping: &HashMap<u32, u32>, datapack_uses_client_ids: bool) -> (String, usize) {
+    let tags = |text: &str| XML_ITEM_TAG_RE.find_iter(text).map(|m| m.range()).collect::<Vec<_>>();
+    let mut count = 0;
+    let mut updated = rewrite_ids(content, &XML_CLIENT_ID_ATTR_RE, tags(content), mapping, &mut count);
+    if datapack_uses_client_ids {
+        updated = rewrite_ids(&updated, &XML_ID_ATTR_RE, tags(&updated), mapping, &mut count);
+        // After the `id` pass, so ids moved out of a range aren't remapped twice.
+        updated = split_item_ranges(&updated, mapping, &mut count);
+    }
+    (updated, count)
+}
+
+fn id_attrs(from: u64, to: u64) -> String {
+    if from == to {
+        format!(" id=\"{}\"", from)
+    } else {
+        format!(" fromid=\"{}\" toid=\"{}\"", from, to)
+    }
+}
+
+/// Splits every `<item fromid=.. toid=..>` element holding a remapped id into
+/// the remaining ranges plus one element per moved id, each a copy of the
+/// original element (children included) on its own line.
+fn split_item_ranges(content: &str, mapping: &HashMap<u32, u32>, count: &mut usize) -> String {
+    let mut result = String::with_capacity(content.len());
+    let mut last = 0;
+    for tag in XML_ITEM_TAG_RE.find_iter(content) {
+        let Some(range) = XML_ID_RANGE_RE.captures(tag.as_str()) else {
+            continue;
+        };
+        let (Ok(from), Ok(to)) = (range["from"].parse::<u32>(), range["to"].parse::<u32>()) else {
+            continue;
+        };
+        let mut moved: Vec<u32> = mapping.keys().copied().filter(|id| (from..=to).contains(id)).collect();
+        if moved.is_empty() || tag.start() < last {
+            continue;
+        }
+        moved.sort_unstable();
+        let end = if tag.as_str().ends_with("/>") {
+            tag.end()
+        } else {
+            match content[tag.end()..].find("</item>") {
+                Some(close) => tag.end() + close + "</item>".len(),
+                None => continue,
+            }
+        };
+
+        let attrs = range.get(0).map(|m| m.range()).unwrap_or_default();
+        let element = |ids: String| format!("{}{}{}{}", &tag.as_str()[..attrs.start], ids, &tag.as_str()[attrs.end..], &content[tag.end()..end]);
+        let line_start = content[..tag.start()].rfind('\n').map_or(0, |newline| newline + 1);
+        let indent = Some(&content[line_start..tag.start()]).filter(|indent| indent.trim().is_empty()).unwrap_or("");
+
+        let mut elements = Vec::new();
+        let mut next = from as u64;
+        for id in &moved {
+            if (*id as u64) > next {
+                elements.push(element(id_attrs(next, *id as u64 - 1)));
+            }
+            elements.push(element(id_attrs(mapping[id] as u64, mapping[id] as u64)));
+            next = *id as u64 + 1;
+        }
+        if next <= to as u64 {
+            elements.push(element(id_attrs(next, to as u64)));
+        }
+
+        result.push_str(&content[last..tag.start()]);
+        result.push_str(&elements.join(&format!("\n{}", indent)));
+        last = end;
+        *count += moved.len();
+    }
+    result.push_str(&content[last..]);
+    result
+}
+
+fn line_change(line: usize, before: &[&str], after: &[&str]) -> RemapLineChange {
+    let join = |lines: &[&str]| lines.iter().map(|l| l.trim()).collect::<Vec<_>>().join("\n");
+    RemapLineChange {
+        line,
+        before: join(before),
+        after: join(after),
+    }
+}
+
+/// Line diff of a rewrite that replaced numbers in place and may have added
+/// lines (split items.xml ranges). Added lines are reported against the line
+/// they follow, with an empty `before` when that line itself is unchanged.
+fn changed_lines(before: &str, after: &str) -> Vec<RemapLineChange> {
+    let (before, after): (Vec<&str>, Vec<&str>) = (before.lines().collect(), after.lines().collect());
+    let (mut i, mut j) = (0, 0);
+    let mut changes = Vec::new();
+    while i < before.len() && j < after.len() {
+        if before[i] == after[j] {
+            i += 1;
+            j += 1;
+            continue;
+        }
+        let extra = (after.len() - j).saturating_sub(before.len() - i);
+        // Inserted lines: the original resumes unchanged a little further on.
+        if let Some(d) = (1..=extra).find(|d| after[j + d] == before[i] && after.get(j + d + 1) == before.get(i + 1)) {
+            changes.push(line_change(i, &[], &after[j..j + d]));
+            j += d;
+            continue;
+        }
+        // A changed line, possibly followed by inserted ones.
+        let span = (0..=extra).find(|d| after.get(j + 1 + d) == before.get(i + 1)).unwrap_or(0);
+        changes.push(line_change(i + 1, &before[i..=i], &after[j..=j + span]));
+        i += 1;
+        j += 1 + span;
+    }
+    if j < after.len() {
+        changes.push(line_change(before.len(), &[], &after[j..]));
+    }
+    changes
+}
+
+/// Rewrites every text file in `paths` in parallel; only changed files are returned.
+fn plan_text_files(paths: &[PathBuf], source: ReferenceSource, job: &JobHandle, rewrite: impl Fn(&str) -> (String, usize) + Sync) -> Result<Vec<(PlannedFile, RemapFileChange)>, String> {
+    let planned: Vec<Option<(PlannedFile, RemapFileChange)>> = paths
+        .par_iter()
+        .map(|path| -> Result<_, String> {
+            job.check_cancelled()?;
+            let original = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            job.advance(1);
+            let Ok(text) = std::str::from_utf8(&original) else {
+                log::warn!("Skipping non UTF-8 file in id remap: {}", path.display());
+                return Ok(None);
+            };
+            let (updated, replacements) = rewrite(text);
+            if replacements == 0 {
+                return Ok(None);
+            }
+            let change = RemapFileChange {
+                path: path.to_string_lossy().to_string(),
+                source,
+                replacements,
+                lines: changed_lines(text, &updated),
+            };
+            Ok(Some((
+                PlannedFile {
+                    path: path.clone(),
+                    original,
+                    updated: updated.into_bytes(),
+                },
+                change,
+            )))
+        })
+        .collect::<Result<_, String>>()?;
+    Ok(planned.into_iter().flatten().collect())
+}
+
+fn plan_id_remap(state: &AppState, paths: &ReferencePaths, remaps: &[IdRemap], job: &JobHandle) -> Result<(HashMap<u32, u32>, RemapPlan), String> {
+    let mut appearances = state.appearances.read().clone().ok_or("No appearances loaded")?;
+    let mapping = build_mapping(remaps, &appearances.object)?;
+    if mapping.is_empty() {
+        return Err("Nothing to remap".to_string());
+    }
+    let appearances_path = state.tibia_path.lock().clone().ok_or("No appearances file path available")?;
+
+    job.set_message("Remapping appearances");
+    let (objects_renamed, appearance_references) = remap_objects(&mut appearances, &mapping);
+    let mut unknown = state.appearance_unknown_fields.read().clone();
+    unknown.remap_appearance_ids(OBJECT_FIELD, &mapping);
+
+    let mut items_otb = state.items_otb.read().clone();
+    let otb_entries = items_otb.as_mut().map(|otb| remap_items_otb(otb, &mapping)).unwrap_or(0);
+    let datapack_uses_client_ids = items_otb.is_none();
+
+    let mut static_map_data = state.staticmapdata.read().clone();
+    if let Some(data) = static_map_data.as_mut() {
+        remap_static_map_data(data, &mapping);
+    }
+
+    let mut files = Vec::new();
+    let mut changes = Vec::new();
+
+    let original = fs::read(&appearances_path).map_err(|e| format!("Failed to read appearances file: {}", e))?;
+    let updated = encode_appearances(&appearances, &unknown).map_err(|e| format!("Failed to encode appearances: {}", e))?;
+    changes.push(RemapFileChange {
+        path: appearances_path.to_string_lossy().to_string(),
+        source: ReferenceSource::Appearances,
+        replacements: objects_renamed + appearance_references,
+        lines: Vec::new(),
+    });
+    files.push(PlannedFile {
+        path: appearances_path,
+        original,
+        updated,
+    });
+
+    if let Some(otb) = items_otb.as_ref().filter(|_| otb_entries > 0) {
+        let path = state.items_otb_path.lock().clone().ok_or("The loaded items.otb has no file yet; save it before remapping")?;
+        let original = fs::read(&path).map_err(|e| format!("Failed to read items.otb: {}", e))?;
+        let updated = write_items_otb(otb).map_err(|e| format!("Failed to encode items.otb: {}", e))?;
+        changes.push(RemapFileChange {
+            path: path.to_string_lossy().to_string(),
+            source: ReferenceSource::ItemsOtb,
+            replacements: otb_entries,
+            lines: Vec::new(),
+        });
+        files.push(PlannedFile {
+            path,
+            original,
+            updated,
+        });
+    }
+
+    if let Some(path) = paths.tibia_path.as_ref().and_then(|tibia| find_largest_dat(&tibia.join("assets"), "staticmapdata-")) {
+        let mut data = load_staticmapdata(&path).map_err(|e| format!("Failed to load staticmapdata: {}", e))?;
+        let replacements = remap_static_map_data(&mut data, &mapping);
+        if replacements > 0 {
+            let original = fs::read(&path).map_err(|e| format!("Failed to read staticmapdata: {}", e))?;
+            changes.push(RemapFileChange {
+                path: path.to_string_lossy().to_string(),
+                source: ReferenceSource::StaticMapData,
+                replacements,
+                lines: Vec::new(),
+            });
+            files.push(PlannedFile {
+                path,
+                original,
+                updated: data.encode_to_vec(),
+            });
+        }
+    }
+
+    let monster_files: Vec<PathBuf> = paths.monster_files()?.into_iter().map(PathBuf::from).collect();
+    let npc_files: Vec<PathBuf> = paths.npc_files()?.into_iter().map(PathBuf::from).collect();
+    let items_xml: Vec<PathBuf> = paths.items_xml_path.iter().cloned().collect();
+    job.set_total(monster_files.len() + npc_files.len() + items_xml.len());
+
+    job.set_message("Scanning server files");
+    let text_files = [
+        plan_text_files(&monster_files, ReferenceSource::Monsters, job, |text| remap_monster_lua(text, &mapping, datapack_uses_client_ids))?,
+        plan_text_files(&npc_files, ReferenceSource::Npcs, job, |text| remap_npc_lua(text, &mapping, datapack_uses_client_ids))?,
+        plan_text_files(&items_xml, ReferenceSource::ItemsXml, job, |text| remap_items_xml(text, &mapping, datapack_uses_client_ids))?,
+    ];
+    for (file, change) in text_files.into_iter().flatten() {
+        files.push(file);
+        changes.push(change);
+    }
+
+    let preview = RemapPreview {
+        objects_renamed,
+        appearance_references,
+        otb_entries,
+        files: changes,
+    };
+    Ok((
+        mapping,
+        RemapPlan {
+            appearances,
+            unknown,
+            items_otb,
+            static_map_data,
+            files,
+            preview,
+        },
+    ))
+}
+
+/// Lists every change an id remap would make, without writing anything.
+pub fn preview_id_remap_impl(state: &AppState, paths: &ReferencePaths, remaps: &[IdRemap], job: &JobHandle) -> Result<RemapPreview, String> {
+    plan_id_remap(state, paths, remaps, job).map(|(_, plan)| plan.preview)
+}
+
+/// Applies an id remap to every planned file as one transaction, then updates
+/// the loaded state. Every file is snapshotted in `backups` before anything is written.
+pub fn apply_id_remap_impl(state: &AppState, paths: &ReferencePaths, remaps: &[IdRemap], backups: &BackupManager, job: &JobHandle) -> Result<RemapResult, String> {
+    let (mapping, plan) = plan_id_remap(state, paths, remaps, job)?;
+    job.check_cancelled()?;
+
+    job.set_message("Backing up files");
+    let mut snapshots = Vec::new();
+    for file in &plan.files {
+        let snapshot = backups.snapshot(&file.path).map_err(|e| format!("Failed to back up {}: {}", file.path.display(), e))?;
+        snapshots.extend(snapshot);
+    }
+
+    // Past this point the remap is not cancellable: a half-applied remap is worse than a slow one.
+    job.set_message("Writing files");
+    for (written, file) in plan.files.iter().enumerate() {
+        if let Err(e) = write_atomic(&file.path, &file.updated) {
+            for done in plan.files[..written].iter().rev() {
+                if let Err(restore_error) = write_atomic(&done.path, &done.original) {
+                    log::error!("Failed to restore {} after aborted remap: {}", done.path.display(), restore_error);
+                }
+            }
+            return Err(format!("Failed to write {}: {} (changes rolled back)", file.path.display(), e));
+        }
+    }
+
+    {
+        let mut appearances_lock = state.appearances.write();
+        rebuild_indexes(state, &plan.appearances);
+        *appearances_lock = Some(plan.appearances);
+    }
+    *state.appearance_unknown_fields.write() = plan.unknown;
+    // Undo entries would swap back appearances whose references now live in files.
+    state.appearance_journal.lock().clear();
+    if plan.items_otb.is_some() {
+        *state.items_otb.write() = plan.items_otb;
+    }
+    if plan.static_map_data.is_some() {
+        *state.staticmapdata.write() = plan.static_map_data;
+    }
+    invalidate_search_cache(state);
+    state.sprite_cache.clear();
+    state.preview_cache.clear();
+    *state.reference_index.write() = None;
+
+    log::info!("Remapped {} object id(s) across {} file(s)", mapping.len(), plan.preview.files.len());
+    Ok(RemapResult {
+        preview: plan.preview,
+        backups: snapshots,
+    })
+}
+
+/// Preview of moving objects to new ids across client and server files.
+#[tauri::command]
+pub async fn preview_id_remap(remaps: Vec<IdRemap>, items_xml_path: Option<String>, app: AppHandle, state: State<'_, AppState>) -> Result<RemapPreview, String> {
+    let paths = ReferencePaths::from_settings(&app, items_xml_path, None)?;
+    run_job(&app, state.inner(), "id_remap_preview", |job| preview_id_remap_impl(state.inner(), &paths, &remaps, job))
+}
+
+/// Moves objects to new ids and rewrites every reference, backing up each file first.
+#[tauri::command]
+pub async fn apply_id_remap(remaps: Vec<IdRemap>, items_xml_path: Option<String>, app: AppHandle, state: State<'_, AppState>) -> Result<RemapResult, String> {
+    let paths = ReferencePaths::from_settings(&app, items_xml_path, None)?;
+    let backups = manager().ok_or("Backups are not available; refusing to remap without them")?;
+    run_job(&app, state.inner(), "id_remap", |job| apply_id_remap_impl(state.inner(), &paths, &remaps, backups, job))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::protobuf::{AppearanceFlagMarket, AppearanceFlags};
+
+    fn object(id: u32, trade_as: u32) -> Appearance {
+        Appearance {
+            id: Some(id),
+            flags: Some(AppearanceFlags {
+                market: Some(AppearanceFlagMarket {
+                    trade_as_object_id: Some(trade_as),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn swapping_ids_rewrites_each_reference_once() {
+        let mut appearances = Appearances {
+            object: vec![object(100, 101), object(101, 100), object(102, 100)],
+            ..Default::default()
+        };
+        let remaps = [
+            IdRemap {
+                old_id: 100,
+                new_id: 101,
+            },
+            IdRemap {
+                old_id: 101,
+                new_id: 100,
+            },
+        ];
+        let mapping = build_mapping(&remaps, &appearances.object).unwrap();
+        assert_eq!(remap_objects(&mut appearances, &mapping), (2, 3));
+
+        let trade_as: Vec<(u32, u32)> = appearances.object.iter().map(|o| (o.id.unwrap(), o.flags.as_ref().unwrap().market.as_ref().unwrap().trade_as_object_id.unwrap())).collect();
+        assert_eq!(trade_as, vec![(100, 101), (101, 100), (102, 101)]);
+
+        let collision = [IdRemap {
+            old_id: 100,
+            new_id: 102,
+        }];
+        assert!(build_mapping(&collision, &appearances.object).is_err());
+    }
+
+    #[test]
+    fn server_files_only_change_ids_in_their_own_fields() {
+        let mapping = HashMap::from([(3031, 3035)]);
+
+        let monster = "monster.raceId = 3031\nmonster.loot = {\n\t{ id = 3031, chance = 100000, maxCount = 7 },\n\t{ name = \"gold coin\", chance = 50 },\n}\n";
+        let (updated, count) = remap_monster_lua(monster, &mapping, true);
+        assert_eq!(count, 1);
+        assert!(updated.starts_with("monster.raceId = 3031\n"));
+        assert!(updated.contains("{ id = 3035, chance = 100000"));
+        assert_eq!(changed_lines(monster, &updated)[0].line, 3);
+        assert_eq!(remap_monster_lua(monster, &mapping, false).1, 0);
+
+        let npc = "npcConfig.shop = {\n\t{ itemName = \"gold coin\", clientId = 3031, itemId = 3031, buy = 1 },\n}\n";
+        assert_eq!(remap_npc_lua(npc, &mapping, false).0, npc.replacen("clientId = 3031", "clientId = 3035", 1));
+
+        let xml = "<item id=\"3031\" name=\"gold coin\">\n\t<attribute key=\"weight\" value=\"3031\"/>\n</item>\n";
+        let (updated, count) = remap_items_xml(xml, &mapping, true);
+        assert_eq!(count, 1);
+        assert!(updated.starts_with("<item id=\"3035\"") && updated.contains("value=\"3031\""));
+    }
+
+    #[test]
+    fn items_xml_ranges_split_around_moved_ids() {
+        let mapping = HashMap::from([(3032, 5000), (3040, 3041)]);
+        let xml =
+            "<items>\n\t<item fromid=\"3031\" toid=\"3034\" name=\"coin\">\n\t\t<attribute key=\"weight\" value=\"10\"/>\n\t</item>\n\t<item fromid=\"3039\" toid=\"3040\" name=\"wall\"/>\n</items>\n";
+        let (updated, count) = remap_items_xml(xml, &mapping, true);
+        assert_eq!(count, 2);
+        assert_eq!(
+            updated,
+            "<items>\n\t<item id=\"3031\" name=\"coin\">\n\t\t<attribute key=\"weight\" value=\"10\"/>\n\t</item>\n\t\
+             <item id=\"5000\" name=\"coin\">\n\t\t<attribute key=\"weight\" value=\"10\"/>\n\t</item>\n\t\
+             <item fromid=\"3033\" toid=\"3034\" name=\"coin\">\n\t\t<attribute key=\"weight\" value=\"10\"/>\n\t</item>\n\t\
+             <item id=\"3039\" name=\"wall\"/>\n\t<item id=\"3041\" name=\"wall\"/>\n</items>\n"
+        );
+        // Ranges are client ids only when there is no items.otb.
+        assert_eq!(remap_items_xml(xml, &mapping, false).1, 0);
+
+        // The copies land before the changed wall line and are reported with it.
+        let lines = changed_lines(xml, &updated);
+        assert_eq!(lines.iter().map(|l| l.line).collect::<Vec<_>>(), vec![2, 5]);
+        assert_eq!(lines[0].after, "<item id=\"3031\" name=\"coin\">");
+        assert_eq!(lines[1].before, "<item fromid=\"3039\" toid=\"3040\" name=\"wall\"/>");
+        assert!(lines[1].after.starts_with("<item id=\"5000\"") && lines[1].after.ends_with("<item id=\"3041\" name=\"wall\"/>"));
+
+        // Lines added after an unchanged line are reported against it.
+        let added = changed_lines("a\nb\nc\n", "a\nb\nx\ny\nc\n");
+        assert_eq!((added[0].line, added[0].before.as_str(), added[0].after.as_str()), (2, "", "x\ny"));
+    }
+}
@@ -47,7 +47,7 @@ fn settings_file_path(app: &AppHandle) -> Result<PathBuf, String> {
     Ok(base_dir.join("settings.json"))
 }
 
-fn read_settings(app: &AppHandle) -> Result<AppSettings, String> {
+pub(crate) fn read_settings(app: &AppHandle) -> Result<AppSettings, String> {
     let path = settings_file_path(app)?;
     if !path.exists() {
         return Ok(AppSettings::default());
@@ -697,6 +697,7 @@ impl SoundsParser {
             raw
         };
 
+        crate::core::backups::snapshot_before_write(&dat_path);
         fs::write(&dat_path, &to_write).context("Failed to write sounds dat")?;
         Ok(dat_path)
     }
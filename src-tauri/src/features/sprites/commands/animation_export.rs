@@ -0,0 +1,359 @@
+// Animated GIF / APNG export
+// Renders one frame group of an appearance into an animation per direction
+// (pattern x), following the SpriteAnimation phase durations and loop type.
+
+use super::sprites::{resolve_appearance, resolve_imported_sprite_bytes};
+use crate::core::protobuf::{AnimationLoopType, SpriteInfo};
+use crate::features::appearances::AppearanceCategory;
+use crate::features::sprites::parsers::SpriteLoader;
+use crate::state::AppState;
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{imageops, Delay, Frame, RgbaImage};
+use serde::Deserialize;
+use std::path::Path;
+use tauri::State;
+
+/// Delay used for groups without animation data (single static frame).
+const DEFAULT_FRAME_DELAY_MS: u32 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnimationFormat {
+    Gif,
+    Apng,
+}
+
+impl AnimationFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            AnimationFormat::Gif => "gif",
+            AnimationFormat::Apng => "png",
+        }
+    }
+}
+
+/// Pattern y/z to export (addon and mount variants); every direction is written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct AnimationPattern {
+    pub y: u32,
+    pub z: u32,
+}
+
+/// Playback order of sprite phases plus how many times it plays (0 = forever).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnimationTimeline {
+    /// `(phase index, delay in ms)` in playback order.
+    pub frames: Vec<(u32, u32)>,
+    pub plays: u32,
+}
+
+/// Builds the playback timeline for a frame group. The client picks a random
+/// duration between `duration_min` and `duration_max`; exports use the midpoint.
+/// Ping-pong animations are unrolled (0,1,2,1) so they loop seamlessly.
+pub fn animation_timeline(info: &SpriteInfo) -> AnimationTimeline {
+    let Some(animation) = info.animation.as_ref().filter(|a| !a.sprite_phase.is_empty()) else {
+        return AnimationTimeline {
+            frames: vec![(0, DEFAULT_FRAME_DELAY_MS)],
+            plays: 0,
+        };
+    };
+
+    let delays: Vec<u32> = animation
+        .sprite_phase
+        .iter()
+        .map(|phase| {
+            let min = phase.duration_min.unwrap_or(0);
+            let max = phase.duration_max.unwrap_or(min).max(min);
+            ((min + max) / 2).max(1)
+        })
+        .collect();
+
+    let mut frames: Vec<(u32, u32)> = delays.iter().enumerate().map(|(i, delay)| (i as u32, *delay)).collect();
+    let plays = match AnimationLoopType::try_from(animation.loop_type.unwrap_or(0)).unwrap_or(AnimationLoopType::Infinite) {
+        AnimationLoopType::Pingpong => {
+            let back: Vec<(u32, u32)> = frames.iter().rev().skip(1).take(frames.len().saturating_sub(2)).copied().collect();
+            frames.extend(back);
+            0
+        }
+        AnimationLoopType::Infinite => 0,
+        AnimationLoopType::Counted => animation.loop_count.unwrap_or(1).max(1),
+    };
+
+    AnimationTimeline {
+        frames,
+        plays,
+    }
+}
+
+/// Index into `sprite_id` for the given layer/pattern/phase (same layout as the client).
+pub fn sprite_slot(info: &SpriteInfo, layer: u32, x: u32, y: u32, z: u32, phase: u32) -> usize {
+    let layers = info.layers.unwrap_or(1).max(1);
+    let pw = info.pattern_width.unwrap_or(1).max(1);
+    let ph = info.pattern_height.unwrap_or(1).max(1);
+    let pd = info.pattern_depth.unwrap_or(1).max(1);
+    let phases = info.animation.as_ref().map(|a| a.sprite_phase.len() as u32).filter(|count| *count > 0).unwrap_or(1);
+
+    let mut idx = (phase % phases) as usize;
+    idx = idx * pd as usize + z as usize;
+    idx = idx * ph as usize + y as usize;
+    idx = idx * pw as usize + x as usize;
+    idx * layers as usize + layer as usize
+}
+
+/// Composes every phase for direction `x` at pattern `y`/`z` (addon, mount). Layers are
+/// drawn in order, anchored bottom-right like the client does for large sprites.
+/// `layers` limits how many layers are drawn (outfits only use the base layer;
+/// the second one is the colour mask).
+pub fn render_direction(info: &SpriteInfo, x: u32, y: u32, z: u32, layers: u32, fetch: &dyn Fn(u32) -> Option<RgbaImage>) -> Vec<RgbaImage> {
+    let phases = info.animation.as_ref().map(|a| a.sprite_phase.len() as u32).filter(|count| *count > 0).unwrap_or(1);
+
+    let sprites: Vec<Vec<RgbaImage>> = (0..phases)
+        .map(|phase| (0..layers).filter_map(|layer| info.sprite_id.get(sprite_slot(info, layer, x, y, z, phase)).copied()).filter(|sprite_id| *sprite_id != 0).filter_map(fetch).collect())
+        .collect();
+
+    let width = sprites.iter().flatten().map(|s| s.width()).max().unwrap_or(32);
+    let height = sprites.iter().flatten().map(|s| s.height()).max().unwrap_or(32);
+
+    sprites
+        .into_iter()
+        .map(|phase_sprites| {
+            let mut canvas = RgbaImage::new(width, height);
+            for sprite in phase_sprites {
+                imageops::overlay(&mut canvas, &sprite, (width - sprite.width()) as i64, (height - sprite.height()) as i64);
+            }
+            canvas
+        })
+        .collect()
+}
+
+pub fn encode_gif(phases: &[RgbaImage], timeline: &AnimationTimeline) -> Result<Vec<u8>, String> {
+    let mut buffer = Vec::new();
+    {
+        let mut encoder = GifEncoder::new_with_speed(&mut buffer, 10);
+        // GIF counts repetitions after the first play.
+        let repeat = match timeline.plays {
+            0 => Repeat::Infinite,
+            plays => Repeat::Finite((plays - 1).min(u16::MAX as u32) as u16),
+        };
+        encoder.set_repeat(repeat).map_err(|e| format!("Failed to encode GIF: {}", e))?;
+
+        for (phase, delay) in &timeline.frames {
+            let image = phases.get(*phase as usize).ok_or_else(|| format!("Missing rendered phase {}", phase))?;
+            let frame = Frame::from_parts(image.clone(), 0, 0, Delay::from_numer_denom_ms(*delay, 1));
+            encoder.encode_frame(frame).map_err(|e| format!("Failed to encode GIF: {}", e))?;
+        }
+    }
+    Ok(buffer)
+}
+
+pub fn encode_apng(phases: &[RgbaImage], timeline: &AnimationTimeline) -> Result<Vec<u8>, String> {
+    let first = phases.first().ok_or_else(|| "Nothing to encode".to_string())?;
+    let mut buffer = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut buffer, first.width(), first.height());
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_animated(timeline.frames.len() as u32, timeline.plays).map_err(|e| format!("Failed to encode APNG: {}", e))?;
+        // Every frame covers the full canvas, so replace instead of blending.
+        encoder.set_blend_op(png::BlendOp::Source).map_err(|e| format!("Failed to encode APNG: {}", e))?;
+
+        let mut writer = encoder.write_header().map_err(|e| format!("Failed to encode APNG: {}", e))?;
+        for (phase, delay) in &timeline.frames {
+            let image = phases.get(*phase as usize).ok_or_else(|| format!("Missing rendered phase {}", phase))?;
+            writer.set_frame_delay((*delay).min(u16::MAX as u32) as u16, 1000).map_err(|e| format!("Failed to encode APNG: {}", e))?;
+            writer.write_image_data(image.as_raw()).map_err(|e| format!("Failed to encode APNG: {}", e))?;
+        }
+        writer.finish().map_err(|e| format!("Failed to encode APNG: {}", e))?;
+    }
+    Ok(buffer)
+}
+
+pub(super) fn load_sprite_image(state: &AppState, loader: &SpriteLoader, sprite_id: u32) -> Option<RgbaImage> {
+    // Imported sprites override the catalog, same as the PNG preview path.
+    let result = match resolve_imported_sprite_bytes(state, sprite_id) {
+        Some(bytes) => image::load_from_memory(&bytes).map(|img| img.to_rgba8()).map_err(|e| e.to_string()),
+        None => loader.get_sprite(sprite_id).and_then(|sprite| sprite.to_image()).map(|img| img.to_rgba8()).map_err(|e| e.to_string()),
+    };
+    match result {
+        Ok(image) => Some(image),
+        Err(e) => {
+            log::warn!("Skipping sprite {} in animation export: {}", sprite_id, e);
+            None
+        }
+    }
+}
+
+/// Writes one animation per direction of a frame group into `destination_dir`.
+/// Files are named `<category>_<id>_fg<group>_dir<x>.<gif|png>`, with `_y<y>_z<z>` added
+/// before the extension when a non-zero pattern y/z is exported. Returns the written paths.
+pub fn export_animation(
+    state: &AppState,
+    category: &AppearanceCategory,
+    appearance_id: u32,
+    frame_group_index: usize,
+    pattern: AnimationPattern,
+    format: AnimationFormat,
+    destination_dir: &str,
+) -> Result<Vec<String>, String> {
+    let AnimationPattern {
+        y: pattern_y,
+        z: pattern_z,
+    } = pattern;
+    let dir = Path::new(destination_dir);
+    if !dir.is_dir() {
+        return Err(format!("Destination is not a directory: {}", destination_dir));
+    }
+
+    let appearances_lock = state.appearances.read();
+    let appearances = appearances_lock.as_ref().ok_or_else(|| "No appearances loaded".to_string())?;
+    let sprite_loader_lock = state.sprite_loader.read();
+    let loader = sprite_loader_lock.as_ref().ok_or_else(|| "No sprites loaded".to_string())?;
+
+    let items = match category {
+        AppearanceCategory::Objects => &appearances.object,
+        AppearanceCategory::Outfits => &appearances.outfit,
+        AppearanceCategory::Effects => &appearances.effect,
+        AppearanceCategory::Missiles => &appearances.missile,
+    };
+    let appearance = resolve_appearance(state, items, category, appearance_id).ok_or_else(|| format!("Appearance with ID {} not found in {:?}", appearance_id, category))?;
+
+    let info = appearance
+        .frame_group
+        .get(frame_group_index)
+        .and_then(|fg| fg.sprite_info.as_ref())
+        .ok_or_else(|| format!("Appearance {} has no sprite info in frame group {}", appearance_id, frame_group_index))?;
+
+    let pattern_height = info.pattern_height.unwrap_or(1).max(1);
+    let pattern_depth = info.pattern_depth.unwrap_or(1).max(1);
+    if pattern_y >= pattern_height || pattern_z >= pattern_depth {
+        return Err(format!("Pattern y {} / z {} out of range (appearance {} has {} x {})", pattern_y, pattern_z, appearance_id, pattern_height, pattern_depth));
+    }
+
+    let layers = match category {
+        AppearanceCategory::Outfits => 1,
+        _ => info.layers.unwrap_or(1).max(1),
+    };
+    let timeline = animation_timeline(info);
+    let fetch = |sprite_id: u32| load_sprite_image(state, loader, sprite_id);
+
+    let mut written = Vec::new();
+    for x in 0..info.pattern_width.unwrap_or(1).max(1) {
+        let phases = render_direction(info, x, pattern_y, pattern_z, layers, &fetch);
+        let bytes = match format {
+            AnimationFormat::Gif => encode_gif(&phases, &timeline)?,
+            AnimationFormat::Apng => encode_apng(&phases, &timeline)?,
+        };
+
+        let pattern_suffix = if pattern_y != 0 || pattern_z != 0 {
+            format!("_y{}_z{}", pattern_y, pattern_z)
+        } else {
+            String::new()
+        };
+        let file_name = format!("{}_{}_fg{}_dir{}{}.{}", format!("{:?}", category).to_lowercase(), appearance_id, frame_group_index, x, pattern_suffix, format.extension());
+        let path = dir.join(file_name);
+        std::fs::write(&path, &bytes).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        written.push(path.to_string_lossy().to_string());
+    }
+
+    Ok(written)
+}
+
+/// Export the animation of a frame group (default: the first) as GIF or APNG, one file per direction.
+/// `pattern` picks the addon/mount variant and defaults to y = z = 0.
+#[tauri::command]
+pub async fn export_appearance_animation(
+    category: AppearanceCategory,
+    appearance_id: u32,
+    frame_group_index: Option<usize>,
+    pattern: Option<AnimationPattern>,
+    format: AnimationFormat,
+    destination_dir: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, String> {
+    export_animation(&state, &category, appearance_id, frame_group_index.unwrap_or(0), pattern.unwrap_or_default(), format, &destination_dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::protobuf::{SpriteAnimation, SpritePhase};
+
+    fn info(phases: &[(u32, u32)], loop_type: AnimationLoopType, loop_count: Option<u32>) -> SpriteInfo {
+        SpriteInfo {
+            pattern_width: Some(4),
+            layers: Some(2),
+            animation: Some(SpriteAnimation {
+                loop_type: Some(loop_type as i32),
+                loop_count,
+                sprite_phase: phases
+                    .iter()
+                    .map(|(min, max)| SpritePhase {
+                        duration_min: Some(*min),
+                        duration_max: Some(*max),
+                    })
+                    .collect(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn timeline_honours_durations_and_loop_type() {
+        let counted = animation_timeline(&info(&[(100, 200), (50, 50)], AnimationLoopType::Counted, Some(3)));
+        assert_eq!(counted.frames, vec![(0, 150), (1, 50)]);
+        assert_eq!(counted.plays, 3);
+
+        let pingpong = animation_timeline(&info(&[(10, 10), (20, 20), (30, 30), (40, 40)], AnimationLoopType::Pingpong, None));
+        let order: Vec<u32> = pingpong.frames.iter().map(|(phase, _)| *phase).collect();
+        assert_eq!(order, vec![0, 1, 2, 3, 2, 1]);
+        assert_eq!(pingpong.plays, 0);
+
+        let stat = animation_timeline(&SpriteInfo::default());
+        assert_eq!(stat.frames, vec![(0, DEFAULT_FRAME_DELAY_MS)]);
+    }
+
+    #[test]
+    fn sprite_slot_matches_client_layout() {
+        let info = info(&[(1, 1), (1, 1)], AnimationLoopType::Infinite, None);
+        // layers=2, pw=4: slot = (phase * 4 + x) * 2 + layer
+        assert_eq!(sprite_slot(&info, 0, 0, 0, 0, 0), 0);
+        assert_eq!(sprite_slot(&info, 1, 2, 0, 0, 0), 5);
+        assert_eq!(sprite_slot(&info, 1, 3, 0, 0, 1), 15);
+    }
+
+    #[test]
+    fn renders_and_encodes_each_phase() {
+        let mut info = info(&[(100, 100), (100, 100)], AnimationLoopType::Infinite, None);
+        info.pattern_width = Some(1);
+        info.layers = Some(1);
+        info.sprite_id = vec![1, 2];
+        let fetch = |id: u32| Some(RgbaImage::from_pixel(32, 32, image::Rgba([id as u8, 0, 0, 255])));
+
+        let phases = render_direction(&info, 0, 0, 0, 1, &fetch);
+        assert_eq!(phases.len(), 2);
+        assert_eq!(phases[1].get_pixel(0, 0)[0], 2);
+
+        let timeline = animation_timeline(&info);
+        let gif = encode_gif(&phases, &timeline).unwrap();
+        assert!(gif.starts_with(b"GIF89a"));
+        let apng = encode_apng(&phases, &timeline).unwrap();
+        assert!(apng.windows(4).any(|w| w == b"acTL"));
+    }
+
+    #[test]
+    fn renders_the_requested_pattern_y_and_z() {
+        let mut info = info(&[(100, 100)], AnimationLoopType::Infinite, None);
+        info.pattern_width = Some(1);
+        info.pattern_height = Some(2);
+        info.pattern_depth = Some(2);
+        info.layers = Some(1);
+        // slot = z * 2 + y
+        info.sprite_id = vec![1, 2, 3, 4];
+        let fetch = |id: u32| Some(RgbaImage::from_pixel(32, 32, image::Rgba([id as u8, 0, 0, 255])));
+
+        assert_eq!(render_direction(&info, 0, 1, 0, 1, &fetch)[0].get_pixel(0, 0)[0], 2);
+        assert_eq!(render_direction(&info, 0, 1, 1, 1, &fetch)[0].get_pixel(0, 0)[0], 4);
+    }
+}
@@ -0,0 +1,322 @@
+// Sprite sheet atlas export / re-import
+// Lays out every sprite slot of an appearance in one PNG so it can be edited in
+// an external tool, with a JSON sidecar mapping each cell back to its slot.
+// Re-import only registers tiles whose pixels changed and rewires those slots.
+
+use super::animation_export::load_sprite_image;
+use super::image_import::register_imported_tile;
+use super::sprites::resolve_appearance;
+use crate::core::protobuf::Appearance;
+use crate::features::appearances::commands::helpers::{get_index_for_category, get_items_by_category, get_items_by_category_mut};
+use crate::features::appearances::commands::record_edit;
+use crate::features::appearances::AppearanceCategory;
+use crate::state::AppState;
+use image::{imageops, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+/// Transparent pixels between cells so slot boundaries stay visible while editing.
+const ATLAS_GUTTER: u32 = 1;
+const DEFAULT_CELL: u32 = 32;
+
+/// One sprite slot in the atlas. `slot` indexes `frame_group[frame_group].sprite_info.sprite_id`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpriteAtlasCell {
+    pub label: String,
+    pub frame_group: usize,
+    pub slot: usize,
+    pub sprite_id: u32,
+    pub layer: u32,
+    pub pattern_x: u32,
+    pub pattern_y: u32,
+    pub pattern_z: u32,
+    pub phase: u32,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// JSON sidecar written next to the atlas PNG.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpriteAtlasManifest {
+    pub category: AppearanceCategory,
+    pub appearance_id: u32,
+    pub columns: u32,
+    pub rows: u32,
+    pub cell_width: u32,
+    pub cell_height: u32,
+    pub cells: Vec<SpriteAtlasCell>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AtlasImportResult {
+    pub cells_checked: usize,
+    /// `(slot label, old sprite id, new sprite id)` for every rewired slot.
+    pub changed: Vec<(String, u32, u32)>,
+}
+
+/// Computes the atlas grid: one row per (frame group, phase, z, y) and one column
+/// per (x, layer), i.e. directions side by side with their layers next to each other.
+pub fn atlas_layout(appearance: &Appearance, cell_width: u32, cell_height: u32) -> (Vec<SpriteAtlasCell>, u32, u32) {
+    let mut cells = Vec::new();
+    let mut columns = 1;
+    let mut row = 0;
+
+    for (group_index, group) in appearance.frame_group.iter().enumerate() {
+        let Some(info) = group.sprite_info.as_ref() else {
+            continue;
+        };
+        let layers = info.layers.unwrap_or(1).max(1);
+        let pw = info.pattern_width.unwrap_or(1).max(1);
+        let ph = info.pattern_height.unwrap_or(1).max(1);
+        let pd = info.pattern_depth.unwrap_or(1).max(1);
+        columns = columns.max(pw * layers);
+
+        // Slot order is ((((phase * pd + z) * ph + y) * pw + x) * layers + layer),
+        // so walking it sequentially fills rows of pw * layers cells.
+        for (slot, sprite_id) in info.sprite_id.iter().enumerate() {
+            let index = slot as u32;
+            let layer = index % layers;
+            let x = (index / layers) % pw;
+            let y = (index / layers / pw) % ph;
+            let z = (index / layers / pw / ph) % pd;
+            let phase = index / layers / pw / ph / pd;
+            let column = x * layers + layer;
+            let cell_row = row + index / (layers * pw);
+
+            cells.push(SpriteAtlasCell {
+                label: format!("fg{} phase{} z{} y{} x{} layer{}", group_index, phase, z, y, x, layer),
+                frame_group: group_index,
+                slot,
+                sprite_id: *sprite_id,
+                layer,
+                pattern_x: x,
+                pattern_y: y,
+                pattern_z: z,
+                phase,
+                x: column * (cell_width + ATLAS_GUTTER),
+                y: cell_row * (cell_height + ATLAS_GUTTER),
+                width: cell_width,
+                height: cell_height,
+            });
+        }
+        row += (info.sprite_id.len() as u32).div_ceil(layers * pw);
+    }
+
+    (cells, columns, row)
+}
+
+/// Returns the cells whose pixels in `atlas` differ from the original sprite.
+/// Empty slots (sprite id 0) compare against a fully transparent tile.
+pub fn changed_cells<'a>(atlas: &RgbaImage, manifest: &'a SpriteAtlasManifest, fetch: &dyn Fn(u32) -> Option<RgbaImage>) -> Result<Vec<(&'a SpriteAtlasCell, RgbaImage)>, String> {
+    let mut changed = Vec::new();
+    for cell in &manifest.cells {
+        if cell.x + cell.width > atlas.width() || cell.y + cell.height > atlas.height() {
+            return Err(format!("Atlas is too small for cell {}", cell.label));
+        }
+        let edited = imageops::crop_imm(atlas, cell.x, cell.y, cell.width, cell.height).to_image();
+        let original = match cell.sprite_id {
+            0 => RgbaImage::new(cell.width, cell.height),
+            // Without the original there is nothing to diff against.
+            id => match fetch(id) {
+                Some(image) => image,
+                None => continue,
+            },
+        };
+        if original != edited {
+            changed.push((cell, edited));
+        }
+    }
+    Ok(changed)
+}
+
+fn sidecar_path(atlas_path: &str) -> PathBuf {
+    Path::new(atlas_path).with_extension("json")
+}
+
+/// Writes `<atlas_path>` (PNG) and a `.json` sidecar for every sprite slot of an appearance.
+pub fn export_atlas(state: &AppState, category: &AppearanceCategory, appearance_id: u32, atlas_path: &str) -> Result<SpriteAtlasManifest, String> {
+    let appearances_lock = state.appearances.read();
+    let appearances = appearances_lock.as_ref().ok_or_else(|| "No appearances loaded".to_string())?;
+    let sprite_loader_lock = state.sprite_loader.read();
+    let loader = sprite_loader_lock.as_ref().ok_or_else(|| "No sprites loaded".to_string())?;
+
+    let items = get_items_by_category(appearances, category);
+    let appearance = resolve_appearance(state, items, category, appearance_id).ok_or_else(|| format!("Appearance with ID {} not found in {:?}", appearance_id, category))?;
+
+    let mut sprites = std::collections::HashMap::new();
+    for sprite_id in appearance.frame_group.iter().filter_map(|fg| fg.sprite_info.as_ref()).flat_map(|info| info.sprite_id.iter().copied()) {
+        if sprite_id != 0 && !sprites.contains_key(&sprite_id) {
+            if let Some(image) = load_sprite_image(state, loader, sprite_id) {
+                sprites.insert(sprite_id, image);
+            }
+        }
+    }
+    if sprites.is_empty() {
+        return Err(format!("Appearance {} has no sprites to export", appearance_id));
+    }
+
+    let cell_width = sprites.values().map(|s| s.width()).max().unwrap_or(DEFAULT_CELL);
+    let cell_height = sprites.values().map(|s| s.height()).max().unwrap_or(DEFAULT_CELL);
+    let (mut cells, columns, rows) = atlas_layout(appearance, cell_width, cell_height);
+
+    let mut atlas = RgbaImage::new(columns * (cell_width + ATLAS_GUTTER) - ATLAS_GUTTER, rows * (cell_height + ATLAS_GUTTER) - ATLAS_GUTTER);
+    for cell in cells.iter_mut() {
+        // Cells keep the original sprite size so re-imported tiles match it.
+        if let Some(sprite) = sprites.get(&cell.sprite_id) {
+            cell.width = sprite.width();
+            cell.height = sprite.height();
+            imageops::replace(&mut atlas, sprite, cell.x as i64, cell.y as i64);
+        }
+    }
+
+    let manifest = SpriteAtlasManifest {
+        category: category.clone(),
+        appearance_id,
+        columns,
+        rows,
+        cell_width,
+        cell_height,
+        cells,
+    };
+
+    atlas.save_with_format(atlas_path, image::ImageFormat::Png).map_err(|e| format!("Failed to write atlas {}: {}", atlas_path, e))?;
+    let sidecar = sidecar_path(atlas_path);
+    let json = serde_json::to_string_pretty(&manifest).map_err(|e| format!("Failed to serialize atlas manifest: {}", e))?;
+    std::fs::write(&sidecar, json).map_err(|e| format!("Failed to write {}: {}", sidecar.display(), e))?;
+
+    Ok(manifest)
+}
+
+/// Diffs an edited atlas against the current sprites, registers the changed
+/// tiles as imported sprites and points the affected slots at them. The whole
+/// import is one undoable edit.
+pub fn import_atlas(state: &AppState, atlas_path: &str) -> Result<AtlasImportResult, String> {
+    let sidecar = sidecar_path(atlas_path);
+    let json = std::fs::read_to_string(&sidecar).map_err(|e| format!("Failed to read {}: {}", sidecar.display(), e))?;
+    let manifest: SpriteAtlasManifest = serde_json::from_str(&json).map_err(|e| format!("Failed to parse atlas manifest: {}", e))?;
+    let atlas = image::open(atlas_path).map_err(|e| format!("Failed to open atlas {}: {}", atlas_path, e))?.to_rgba8();
+
+    let changed = {
+        let sprite_loader_lock = state.sprite_loader.read();
+        let loader = sprite_loader_lock.as_ref().ok_or_else(|| "No sprites loaded".to_string())?;
+        let fetch = |sprite_id: u32| load_sprite_image(state, loader, sprite_id);
+        changed_cells(&atlas, &manifest, &fetch)?
+    };
+
+    let category = &manifest.category;
+    let mut appearances_lock = state.appearances.write();
+    let appearances = appearances_lock.as_mut().ok_or_else(|| "No appearances loaded".to_string())?;
+    let index = get_index_for_category(state, category).get(&manifest.appearance_id).map(|idx| *idx);
+    let items = get_items_by_category_mut(appearances, category);
+    let appearance = match index {
+        Some(idx) => items.get_mut(idx),
+        None => items.iter_mut().find(|app| app.id == Some(manifest.appearance_id)),
+    }
+    .ok_or_else(|| format!("Appearance with ID {} not found in {:?}", manifest.appearance_id, category))?;
+
+    // Refuse to rewire slots that were edited since the atlas was exported.
+    for (cell, _) in &changed {
+        let current = appearance.frame_group.get(cell.frame_group).and_then(|fg| fg.sprite_info.as_ref()).and_then(|info| info.sprite_id.get(cell.slot)).copied();
+        if current != Some(cell.sprite_id) {
+            return Err(format!("Slot {} no longer holds sprite {}; export the atlas again", cell.label, cell.sprite_id));
+        }
+    }
+
+    let before = appearance.clone();
+    let mut rewired = Vec::with_capacity(changed.len());
+    for (cell, tile) in changed {
+        let new_id = register_imported_tile(state, tile)?;
+        if let Some(info) = appearance.frame_group[cell.frame_group].sprite_info.as_mut() {
+            info.sprite_id[cell.slot] = new_id;
+        }
+        rewired.push((cell.label.clone(), cell.sprite_id, new_id));
+    }
+
+    record_edit(state, "Import sprite atlas", category, before, appearance);
+    let cache_key = format!("{:?}:{}", category, manifest.appearance_id);
+    state.sprite_cache.remove(&cache_key);
+    state.preview_cache.remove(&cache_key);
+
+    Ok(AtlasImportResult {
+        cells_checked: manifest.cells.len(),
+        changed: rewired,
+    })
+}
+
+/// Export every sprite of an appearance into one PNG atlas plus a `.json` sidecar.
+#[tauri::command]
+pub async fn export_sprite_atlas(category: AppearanceCategory, appearance_id: u32, atlas_path: String, state: State<'_, AppState>) -> Result<SpriteAtlasManifest, String> {
+    export_atlas(&state, &category, appearance_id, &atlas_path)
+}
+
+/// Re-import an edited atlas written by `export_sprite_atlas`; only changed tiles become new sprites.
+#[tauri::command]
+pub async fn import_sprite_atlas(atlas_path: String, state: State<'_, AppState>) -> Result<AtlasImportResult, String> {
+    import_atlas(&state, &atlas_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::protobuf::{FrameGroup, SpriteAnimation, SpriteInfo, SpritePhase};
+    use image::Rgba;
+
+    fn appearance() -> Appearance {
+        Appearance {
+            id: Some(1),
+            frame_group: vec![FrameGroup {
+                sprite_info: Some(SpriteInfo {
+                    pattern_width: Some(2),
+                    layers: Some(2),
+                    animation: Some(SpriteAnimation {
+                        sprite_phase: vec![SpritePhase::default(); 2],
+                        ..Default::default()
+                    }),
+                    sprite_id: (10..18).collect(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn layout_puts_directions_and_layers_in_columns() {
+        let (cells, columns, rows) = atlas_layout(&appearance(), 32, 32);
+        assert_eq!((columns, rows), (4, 2));
+        assert_eq!(cells.len(), 8);
+        // slot 3 = x1 layer1 of phase 0; slot 4 = x0 layer0 of phase 1
+        assert_eq!((cells[3].pattern_x, cells[3].layer, cells[3].x, cells[3].y), (1, 1, 99, 0));
+        assert_eq!((cells[4].phase, cells[4].x, cells[4].y), (1, 0, 33));
+    }
+
+    #[test]
+    fn only_edited_cells_are_reported() {
+        let (cells, columns, rows) = atlas_layout(&appearance(), 2, 2);
+        let manifest = SpriteAtlasManifest {
+            category: AppearanceCategory::Objects,
+            appearance_id: 1,
+            columns,
+            rows,
+            cell_width: 2,
+            cell_height: 2,
+            cells,
+        };
+        let tile = |id: u32| Some(RgbaImage::from_pixel(2, 2, Rgba([id as u8, 0, 0, 255])));
+
+        let mut atlas = RgbaImage::new(columns * 3 - 1, rows * 3 - 1);
+        for cell in &manifest.cells {
+            imageops::replace(&mut atlas, &tile(cell.sprite_id).unwrap(), cell.x as i64, cell.y as i64);
+        }
+        assert!(changed_cells(&atlas, &manifest, &tile).unwrap().is_empty());
+
+        atlas.put_pixel(manifest.cells[5].x, manifest.cells[5].y, Rgba([0, 255, 0, 255]));
+        let changed = changed_cells(&atlas, &manifest, &tile).unwrap();
+        assert_eq!(changed.len(), 1);
+        assert_eq!(changed[0].0.slot, 5);
+    }
+}
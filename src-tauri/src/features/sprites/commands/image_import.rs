@@ -11,21 +11,27 @@
 //! verifies OUR reader/writer agree; whether the live Tibia client accepts the
 //! written `.cwm` must still be confirmed in the running app on real assets.
 
+use crate::core::jobs::JobHandle;
 use crate::core::lzma;
+use crate::features::jobs::run_job;
 use crate::features::sprites::parsers::SpriteCatalogEntry;
 use crate::state::AppState;
 use ahash::AHasher;
 use image::{DynamicImage, ImageBuffer, ImageFormat, Rgba};
 use serde::Serialize;
+use std::collections::BTreeMap;
 use std::hash::{Hash, Hasher};
 use std::io::Cursor;
 use std::path::Path;
-use tauri::State;
+use tauri::{AppHandle, State};
 
 const DEFAULT_TILE: u32 = 32;
 const MAGENTA: [u8; 3] = [0xFF, 0x00, 0xFF];
 const MAX_TILES: u32 = 10_000;
-const DEFAULT_COLS: u32 = 12;
+/// Edge of a client sprite sheet; sheets hold 384 / tile width columns.
+const SHEET_PIXELS: u32 = 384;
+/// Tile size per catalog `spriteType` (see `SpriteLoader`).
+const SPRITE_TYPE_SIZES: [(u32, u32); 4] = [(32, 32), (32, 64), (64, 32), (64, 64)];
 
 fn parse_hex_rgb(hex: &str) -> Option<[u8; 3]> {
     let s = hex.trim().trim_start_matches('#');
@@ -90,7 +96,13 @@ pub async fn import_image_as_tiles(
     let th = tile_height.unwrap_or(DEFAULT_TILE).max(1);
     let key = chroma_key_color.as_deref().and_then(parse_hex_rgb).unwrap_or(MAGENTA);
 
-    let img = image::open(&file_path).map_err(|e| format!("Failed to open image {}: {}", file_path, e))?;
+    import_image_tiles(state.inner(), &file_path, tw, th, chroma_key_enabled.then_some(key))
+}
+
+/// Non-command body of [`import_image_as_tiles`]; `chroma_key` is the colour
+/// made transparent, if any. Also used by the headless CLI.
+pub fn import_image_tiles(state: &AppState, file_path: &str, tw: u32, th: u32, chroma_key: Option<[u8; 3]>) -> Result<Vec<u32>, String> {
+    let img = image::open(file_path).map_err(|e| format!("Failed to open image {}: {}", file_path, e))?;
     let rgba = img.to_rgba8();
     let (w, h) = rgba.dimensions();
     if w < tw || h < th {
@@ -109,33 +121,38 @@ pub async fn import_image_as_tiles(
             for y in 0..th {
                 for x in 0..tw {
                     let mut p = *rgba.get_pixel(col * tw + x, row * th + y);
-                    if chroma_key_enabled && p[0] == key[0] && p[1] == key[1] && p[2] == key[2] {
+                    if chroma_key.is_some_and(|key| p[0] == key[0] && p[1] == key[1] && p[2] == key[2]) {
                         p = Rgba([0, 0, 0, 0]);
                     }
                     tile.put_pixel(x, y, p);
                 }
             }
 
-            let mut png = Vec::new();
-            DynamicImage::ImageRgba8(tile).write_to(&mut Cursor::new(&mut png), ImageFormat::Png).map_err(|e| format!("Failed to encode tile: {}", e))?;
-
-            let mut hasher = AHasher::default();
-            png.hash(&mut hasher);
-            let hash = hasher.finish();
-            if let Some(existing) = state.imported_sprite_hashes.get(&hash) {
-                ids.push(*existing.value());
-                continue;
-            }
-            let id = next_imported_sprite_id(&state)?;
-            state.imported_sprites.insert(id, png);
-            state.imported_sprite_hashes.insert(hash, id);
-            ids.push(id);
+            ids.push(register_imported_tile(state, tile)?);
         }
     }
 
     Ok(ids)
 }
 
+/// Registers one RGBA tile as an imported sprite and returns its id. Identical
+/// tiles (same encoded PNG) reuse the id they were first registered under.
+pub fn register_imported_tile(state: &AppState, tile: ImageBuffer<Rgba<u8>, Vec<u8>>) -> Result<u32, String> {
+    let mut png = Vec::new();
+    DynamicImage::ImageRgba8(tile).write_to(&mut Cursor::new(&mut png), ImageFormat::Png).map_err(|e| format!("Failed to encode tile: {}", e))?;
+
+    let mut hasher = AHasher::default();
+    png.hash(&mut hasher);
+    let hash = hasher.finish();
+    if let Some(existing) = state.imported_sprite_hashes.get(&hash) {
+        return Ok(*existing.value());
+    }
+    let id = next_imported_sprite_id(state)?;
+    state.imported_sprites.insert(id, png);
+    state.imported_sprite_hashes.insert(hash, id);
+    Ok(id)
+}
+
 /// Encodes `n` as a 7-bit little-endian varint (the CIP "size" field, which the
 /// reader skips without interpreting).
 fn encode_7bit(mut n: u64) -> Vec<u8> {
@@ -156,7 +173,7 @@ fn encode_7bit(mut n: u64) -> Vec<u8> {
 
 /// Wraps a plain LZMA stream with the CIP header the sprite loader expects:
 /// `0x70 0x0A 0xFA 0x80 0x24` + 7-bit size + LZMA.
-fn wrap_cip_lzma(lzma: &[u8]) -> Vec<u8> {
+pub(super) fn wrap_cip_lzma(lzma: &[u8]) -> Vec<u8> {
     let mut out = Vec::with_capacity(lzma.len() + 16);
     out.extend_from_slice(&[0x70, 0x0A, 0xFA, 0x80, 0x24]);
     out.extend_from_slice(&encode_7bit(lzma.len() as u64));
@@ -167,7 +184,7 @@ fn wrap_cip_lzma(lzma: &[u8]) -> Vec<u8> {
 /// Builds a row-major RGBA spritesheet from tile buffers (each `tile_w*tile_h*4`
 /// RGBA bytes), padding the last row with transparent tiles, and encodes it as a
 /// BMP. Returns `(bmp_bytes, cols, rows)`.
-fn build_sheet_bmp(tiles: &[Vec<u8>], tile_w: u32, tile_h: u32, cols: u32) -> Result<(Vec<u8>, u32, u32), String> {
+pub(super) fn build_sheet_bmp(tiles: &[Vec<u8>], tile_w: u32, tile_h: u32, cols: u32) -> Result<(Vec<u8>, u32, u32), String> {
     let cols = cols.max(1);
     let n = tiles.len() as u32;
     let rows = n.div_ceil(cols).max(1);
@@ -194,25 +211,47 @@ fn build_sheet_bmp(tiles: &[Vec<u8>], tile_w: u32, tile_h: u32, cols: u32) -> Re
     Ok((bmp, cols, rows))
 }
 
+fn sprite_type_for(width: u32, height: u32) -> Option<u32> {
+    SPRITE_TYPE_SIZES.iter().position(|size| *size == (width, height)).map(|index| index as u32)
+}
+
+#[derive(Serialize)]
+pub struct CompiledSheet {
+    pub sheet_file: String,
+    pub sprite_type: u32,
+    pub first_sprite_id: u32,
+    pub last_sprite_id: u32,
+}
+
 #[derive(Serialize)]
 pub struct CompileResult {
+    /// First written sheet; see `sheets` for all of them.
     pub sheet_file: String,
     pub first_sprite_id: u32,
     pub last_sprite_id: u32,
     pub sprites_compiled: usize,
     /// `(imported_id, new_catalog_id)` pairs.
     pub remap: Vec<(u32, u32)>,
+    /// One sheet per imported sprite size, in ascending id order.
+    pub sheets: Vec<CompiledSheet>,
 }
 
-/// Packs all currently imported sprites into a single 32x32 spritesheet, writes
-/// it next to the catalog as `custom_imported_<firstid>.cwm`, appends a catalog
-/// entry (backing up `catalog-content.json` to `.bak` first), and remaps in-memory
-/// appearance sprite references from imported ids to the new catalog ids.
+/// Packs all currently imported sprites into one spritesheet per sprite size
+/// (32x32, 32x64, 64x32, 64x64), writes each next to the catalog as
+/// `custom_imported_<firstid>.cwm`, appends their catalog entries (backing up
+/// `catalog-content.json` to `.bak` first), and remaps in-memory appearance
+/// sprite references from imported ids to the new catalog ids.
 ///
 /// Destructive (writes to the user's assets). Verify in the running app before
-/// trusting on production assets.
+/// trusting on production assets. Runs as a job; cancelling is honoured until
+/// the sheet is written.
 #[tauri::command]
-pub async fn compile_imported_sprites(assets_dir: String, catalog_path: String, state: State<'_, AppState>) -> Result<CompileResult, String> {
+pub async fn compile_imported_sprites(assets_dir: String, catalog_path: String, app: AppHandle, state: State<'_, AppState>) -> Result<CompileResult, String> {
+    run_job(&app, state.inner(), "compile_imported_sprites", |job| compile_imported_sprite_sheet(state.inner(), &assets_dir, &catalog_path, job))
+}
+
+/// Non-command body of [`compile_imported_sprites`], shared with the headless CLI.
+pub fn compile_imported_sprite_sheet(state: &AppState, assets_dir: &str, catalog_path: &str, job: &JobHandle) -> Result<CompileResult, String> {
     // Snapshot imported sprites, sorted by id for a stable layout.
     let mut items: Vec<(u32, Vec<u8>)> = state.imported_sprites.iter().map(|e| (*e.key(), e.value().clone())).collect();
     items.sort_by_key(|(id, _)| *id);
@@ -220,48 +259,68 @@ pub async fn compile_imported_sprites(assets_dir: String, catalog_path: String,
         return Err("No imported sprites to compile".to_string());
     }
 
-    // Decode each imported PNG to a 32x32 RGBA tile.
-    let mut tiles: Vec<Vec<u8>> = Vec::with_capacity(items.len());
+    // Decode each imported PNG and group the tiles by catalog sprite type (size).
+    job.set_message("Decoding imported sprites");
+    job.set_total(items.len());
+    let mut groups: BTreeMap<u32, Vec<(u32, Vec<u8>)>> = BTreeMap::new();
     for (id, png) in &items {
+        job.check_cancelled()?;
         let img = image::load_from_memory(png).map_err(|e| format!("Failed to decode imported sprite {}: {}", id, e))?;
         let rgba = img.to_rgba8();
-        if rgba.width() != DEFAULT_TILE || rgba.height() != DEFAULT_TILE {
-            return Err(format!("Imported sprite {} is {}x{}, expected {}x{}", id, rgba.width(), rgba.height(), DEFAULT_TILE, DEFAULT_TILE));
-        }
-        tiles.push(rgba.into_raw());
+        let sprite_type =
+            sprite_type_for(rgba.width(), rgba.height()).ok_or_else(|| format!("Imported sprite {} is {}x{}; sprites must be 32 or 64 pixels on each side", id, rgba.width(), rgba.height()))?;
+        groups.entry(sprite_type).or_default().push((*id, rgba.into_raw()));
+        job.advance(1);
     }
 
-    let (bmp, cols, rows) = build_sheet_bmp(&tiles, DEFAULT_TILE, DEFAULT_TILE, DEFAULT_COLS)?;
-    let lzma = lzma::compress(&bmp).map_err(|e| format!("Failed to LZMA-compress sheet: {}", e))?;
-    let cwm = wrap_cip_lzma(&lzma);
+    job.set_message("Compressing sprite sheets");
+    let mut encoded = Vec::with_capacity(groups.len());
+    for (sprite_type, tiles) in &groups {
+        let (tile_w, tile_h) = SPRITE_TYPE_SIZES[*sprite_type as usize];
+        let pixels: Vec<Vec<u8>> = tiles.iter().map(|(_, rgba)| rgba.clone()).collect();
+        let (bmp, cols, rows) = build_sheet_bmp(&pixels, tile_w, tile_h, SHEET_PIXELS / tile_w)?;
+        let lzma = lzma::compress(&bmp).map_err(|e| format!("Failed to LZMA-compress sheet: {}", e))?;
+        encoded.push((*sprite_type, cols * rows, wrap_cip_lzma(&lzma)));
+        job.check_cancelled()?;
+    }
 
     // Load the catalog, find the next free id range.
-    let catalog_text = std::fs::read_to_string(&catalog_path).map_err(|e| format!("Failed to read catalog {}: {}", catalog_path, e))?;
+    let catalog_text = std::fs::read_to_string(catalog_path).map_err(|e| format!("Failed to read catalog {}: {}", catalog_path, e))?;
     let mut entries: Vec<SpriteCatalogEntry> = serde_json::from_str(&catalog_text).map_err(|e| format!("Failed to parse catalog: {}", e))?;
     let max_id = entries.iter().filter_map(|e| e.last_sprite_id).max().unwrap_or(0);
 
-    let first = max_id.checked_add(1).ok_or_else(|| "Sprite id overflow".to_string())?;
-    let total = cols * rows;
-    let last = first + total - 1;
-    let filename = format!("custom_imported_{}.cwm", first);
-
-    // Backup the catalog, then write the sheet and the updated catalog.
-    let _ = std::fs::copy(&catalog_path, format!("{}.bak", catalog_path));
-    std::fs::write(Path::new(&assets_dir).join(&filename), &cwm).map_err(|e| format!("Failed to write sheet: {}", e))?;
-
-    entries.push(SpriteCatalogEntry {
-        entry_type: "sprite".to_string(),
-        file: filename.clone(),
-        sprite_type: Some(0),
-        first_sprite_id: Some(first),
-        last_sprite_id: Some(last),
-        area: None,
-    });
-    let new_json = serde_json::to_string_pretty(&entries).map_err(|e| format!("Failed to serialize catalog: {}", e))?;
-    std::fs::write(&catalog_path, new_json).map_err(|e| format!("Failed to write catalog: {}", e))?;
+    // Sheets take consecutive id ranges, one per sprite size.
+    let mut sheets = Vec::with_capacity(encoded.len());
+    let mut remap: Vec<(u32, u32)> = Vec::with_capacity(items.len());
+    let mut next = max_id.checked_add(1).ok_or_else(|| "Sprite id overflow".to_string())?;
+    for ((sprite_type, total, cwm), (_, tiles)) in encoded.iter().zip(&groups) {
+        let first = next;
+        let last = first.checked_add(total - 1).ok_or_else(|| "Sprite id overflow".to_string())?;
+        next = last + 1;
+        let filename = format!("custom_imported_{}.cwm", first);
+        std::fs::write(Path::new(assets_dir).join(&filename), cwm).map_err(|e| format!("Failed to write sheet: {}", e))?;
+        entries.push(SpriteCatalogEntry {
+            entry_type: "sprite".to_string(),
+            file: filename.clone(),
+            sprite_type: Some(*sprite_type),
+            first_sprite_id: Some(first),
+            last_sprite_id: Some(last),
+            area: None,
+        });
+        remap.extend(tiles.iter().enumerate().map(|(i, (old_id, _))| (*old_id, first + i as u32)));
+        sheets.push(CompiledSheet {
+            sheet_file: filename,
+            sprite_type: *sprite_type,
+            first_sprite_id: first,
+            last_sprite_id: last,
+        });
+    }
 
-    // Build remap imported_id -> new catalog id (row-major == sorted order).
-    let remap: Vec<(u32, u32)> = items.iter().enumerate().map(|(i, (old_id, _))| (*old_id, first + i as u32)).collect();
+    // Backup the catalog, then write it pointing at the new sheets.
+    let _ = std::fs::copy(catalog_path, format!("{}.bak", catalog_path));
+    let new_json = serde_json::to_string_pretty(&entries).map_err(|e| format!("Failed to serialize catalog: {}", e))?;
+    crate::core::backups::snapshot_before_write(catalog_path);
+    std::fs::write(catalog_path, new_json).map_err(|e| format!("Failed to write catalog: {}", e))?;
 
     // Remap in-memory appearance sprite references so a later save persists them.
     {
@@ -293,11 +352,12 @@ pub async fn compile_imported_sprites(assets_dir: String, catalog_path: String,
     *state.imported_sprite_next_id.lock() = None;
 
     Ok(CompileResult {
-        sheet_file: filename,
-        first_sprite_id: first,
-        last_sprite_id: last,
+        sheet_file: sheets.first().map(|sheet| sheet.sheet_file.clone()).unwrap_or_default(),
+        first_sprite_id: sheets.first().map(|sheet| sheet.first_sprite_id).unwrap_or(0),
+        last_sprite_id: sheets.last().map(|sheet| sheet.last_sprite_id).unwrap_or(0),
         sprites_compiled: items.len(),
         remap,
+        sheets,
     })
 }
 
@@ -0,0 +1,512 @@
+// Perceptual image search
+// Keeps a 64-bit difference hash (dHash) of every sprite, persisted in the app
+// data directory and refreshed incrementally: only sprites whose source file
+// (catalog sheet or legacy .spr) changed since the last build are re-hashed,
+// and sprites that fail to decode are remembered so they aren't retried until
+// their source changes. Appearances whose idle frame is drawn from several
+// sprites (layers) also get a hash of the composed frame, so a screenshot crop
+// of the whole thing can match. A query image is hashed the same way and
+// matched by Hamming distance.
+
+use super::animation_export::{load_sprite_image, sprite_slot};
+use crate::core::fs_util::write_atomic;
+use crate::core::protobuf::Appearance;
+use crate::features::appearances::commands::helpers::get_items_by_category;
+use crate::features::appearances::AppearanceCategory;
+use crate::features::sprites::parsers::SpriteLoader;
+use crate::state::AppState;
+use image::{imageops, DynamicImage, GenericImageView, Rgba, RgbaImage};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tauri::{AppHandle, Manager, State};
+
+const INDEX_FILE: &str = "sprite_hash_index.bin";
+const INDEX_MAGIC: &[u8; 4] = b"SPHI";
+const INDEX_VERSION: u32 = 3;
+const HASH_BITS: u32 = 64;
+const DEFAULT_LIMIT: usize = 20;
+const DEFAULT_MIN_SIMILARITY: f32 = 0.75;
+
+const CATEGORIES: [AppearanceCategory; 4] = [AppearanceCategory::Objects, AppearanceCategory::Outfits, AppearanceCategory::Effects, AppearanceCategory::Missiles];
+
+/// Sprite hashes plus the `(size, mtime)` of each source file they came from.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SpriteHashIndex {
+    pub sources: BTreeMap<String, (u64, u64)>,
+    pub hashes: HashMap<u32, u64>,
+    /// Sprites that could not be decoded, skipped until their source changes.
+    pub failed: BTreeSet<u32>,
+    /// Hashes of composed idle frames, keyed by the sprite ids drawn (in order).
+    pub composed: HashMap<Vec<u32>, u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpriteHashIndexStats {
+    pub sprites: usize,
+    pub rehashed: usize,
+    pub reused: usize,
+    /// Composed idle frames hashed in this build.
+    pub composed: usize,
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageSearchMatch {
+    pub category: AppearanceCategory,
+    pub appearance_id: u32,
+    /// Best matching sprite of the appearance (the first one drawn when the
+    /// composed idle frame matched best).
+    pub sprite_id: u32,
+    /// 1.0 = identical hash, 0.0 = every bit differs.
+    pub similarity: f32,
+}
+
+/// Crops to the visible pixels and flattens onto black, so a transparent
+/// sprite and a screenshot crop of it hash alike.
+fn normalize(image: &RgbaImage) -> RgbaImage {
+    let mut bounds: Option<(u32, u32, u32, u32)> = None;
+    for (x, y, pixel) in image.enumerate_pixels() {
+        if pixel.0[3] != 0 {
+            bounds = Some(match bounds {
+                Some((x0, y0, x1, y1)) => (x0.min(x), y0.min(y), x1.max(x), y1.max(y)),
+                None => (x, y, x, y),
+            });
+        }
+    }
+    let Some((x0, y0, x1, y1)) = bounds else {
+        return RgbaImage::from_pixel(1, 1, Rgba([0, 0, 0, 255]));
+    };
+    let mut cropped = imageops::crop_imm(image, x0, y0, x1 - x0 + 1, y1 - y0 + 1).to_image();
+    for pixel in cropped.pixels_mut() {
+        let alpha = pixel.0[3] as u32;
+        for channel in &mut pixel.0[..3] {
+            *channel = (*channel as u32 * alpha / 255) as u8;
+        }
+        pixel.0[3] = 255;
+    }
+    cropped
+}
+
+/// 64-bit dHash: 9x8 greyscale thumbnail, one bit per horizontal gradient.
+pub fn difference_hash(image: &RgbaImage) -> u64 {
+    let normalized = normalize(image);
+    let thumb = DynamicImage::ImageRgba8(normalized).resize_exact(9, 8, imageops::FilterType::Triangle).to_luma8();
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if thumb.get_pixel(x, y).0[0] < thumb.get_pixel(x + 1, y).0[0] {
+                hash |= 1;
+            }
+        }
+    }
+    hash
+}
+
+#[inline]
+pub fn hash_similarity(a: u64, b: u64) -> f32 {
+    1.0 - (a ^ b).count_ones() as f32 / HASH_BITS as f32
+}
+
+fn file_fingerprint(path: &Path) -> Option<(u64, u64)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?.as_secs();
+    Some((metadata.len(), modified))
+}
+
+pub fn encode_hash_index(index: &SpriteHashIndex) -> Vec<u8> {
+    let mut out = Vec::with_capacity(16 + index.hashes.len() * 12);
+    out.extend_from_slice(INDEX_MAGIC);
+    out.extend_from_slice(&INDEX_VERSION.to_le_bytes());
+    out.extend_from_slice(&(index.sources.len() as u32).to_le_bytes());
+    for (path, (size, modified)) in &index.sources {
+        out.extend_from_slice(&(path.len() as u32).to_le_bytes());
+        out.extend_from_slice(path.as_bytes());
+        out.extend_from_slice(&size.to_le_bytes());
+        out.extend_from_slice(&modified.to_le_bytes());
+    }
+    out.extend_from_slice(&(index.hashes.len() as u32).to_le_bytes());
+    let mut ids: Vec<&u32> = index.hashes.keys().collect();
+    ids.sort_unstable();
+    for id in ids {
+        out.extend_from_slice(&id.to_le_bytes());
+        out.extend_from_slice(&index.hashes[id].to_le_bytes());
+    }
+    out.extend_from_slice(&(index.failed.len() as u32).to_le_bytes());
+    for id in &index.failed {
+        out.extend_from_slice(&id.to_le_bytes());
+    }
+    out.extend_from_slice(&(index.composed.len() as u32).to_le_bytes());
+    let mut frames: Vec<(&Vec<u32>, &u64)> = index.composed.iter().collect();
+    frames.sort_unstable();
+    for (ids, hash) in frames {
+        out.extend_from_slice(&(ids.len() as u32).to_le_bytes());
+        for id in ids {
+            out.extend_from_slice(&id.to_le_bytes());
+        }
+        out.extend_from_slice(&hash.to_le_bytes());
+    }
+    out
+}
+
+/// Decodes a persisted index; anything unreadable yields `None` (full rebuild).
+pub fn decode_hash_index(data: &[u8]) -> Option<SpriteHashIndex> {
+    let mut pos = 0usize;
+    let mut take = |len: usize| -> Option<&[u8]> {
+        let bytes = data.get(pos..pos.checked_add(len)?)?;
+        pos += len;
+        Some(bytes)
+    };
+    if take(4)? != INDEX_MAGIC {
+        return None;
+    }
+    let read_u32 = |bytes: &[u8]| u32::from_le_bytes(bytes.try_into().unwrap_or_default());
+    let read_u64 = |bytes: &[u8]| u64::from_le_bytes(bytes.try_into().unwrap_or_default());
+    if read_u32(take(4)?) != INDEX_VERSION {
+        return None;
+    }
+
+    let mut index = SpriteHashIndex::default();
+    for _ in 0..read_u32(take(4)?) {
+        let len = read_u32(take(4)?) as usize;
+        let path = String::from_utf8(take(len)?.to_vec()).ok()?;
+        let size = read_u64(take(8)?);
+        let modified = read_u64(take(8)?);
+        index.sources.insert(path, (size, modified));
+    }
+    for _ in 0..read_u32(take(4)?) {
+        let id = read_u32(take(4)?);
+        let hash = read_u64(take(8)?);
+        index.hashes.insert(id, hash);
+    }
+    for _ in 0..read_u32(take(4)?) {
+        index.failed.insert(read_u32(take(4)?));
+    }
+    for _ in 0..read_u32(take(4)?) {
+        let ids = (0..read_u32(take(4)?)).map(|_| Some(read_u32(take(4)?))).collect::<Option<Vec<u32>>>()?;
+        let hash = read_u64(take(8)?);
+        index.composed.insert(ids, hash);
+    }
+    Some(index)
+}
+
+/// Brings `index` up to date with `loader`: drops sprites that no longer exist
+/// and re-hashes those whose source file changed. Returns `(rehashed, reused)`.
+/// Imported overrides are left out; they are hashed at query time.
+pub fn refresh_hash_index(index: &mut SpriteHashIndex, loader: &SpriteLoader) -> (usize, usize) {
+    let ids = loader.get_all_sprite_ids();
+    let mut current_sources: BTreeMap<String, (u64, u64)> = BTreeMap::new();
+    let mut stale = Vec::new();
+    let mut fresh: HashMap<u32, u64> = HashMap::with_capacity(ids.len());
+    let mut failed = BTreeSet::new();
+
+    for id in ids {
+        let source = loader.sprite_source(id).map(|path| path.to_string_lossy().to_string());
+        let fingerprint = source.as_ref().and_then(|path| {
+            if let Some(known) = current_sources.get(path) {
+                return Some(*known);
+            }
+            let fingerprint = file_fingerprint(Path::new(path))?;
+            current_sources.insert(path.clone(), fingerprint);
+            Some(fingerprint)
+        });
+        let unchanged = source.as_ref().zip(fingerprint).is_some_and(|(path, fingerprint)| index.sources.get(path) == Some(&fingerprint));
+        match index.hashes.get(&id) {
+            Some(hash) if unchanged => {
+                fresh.insert(id, *hash);
+            }
+            None if unchanged && index.failed.contains(&id) => {
+                failed.insert(id);
+            }
+            _ => stale.push(id),
+        }
+    }
+
+    let reused = fresh.len();
+    let rehashed: Vec<(u32, Option<u64>)> = stale
+        .par_iter()
+        .map(|id| {
+            let image = loader.get_sprite(*id).and_then(|sprite| sprite.to_image()).ok();
+            (*id, image.map(|image| difference_hash(&image.to_rgba8())))
+        })
+        .collect();
+    let mut rehashed_count = 0;
+    for (id, hash) in rehashed {
+        match hash {
+            Some(hash) => {
+                fresh.insert(id, hash);
+                rehashed_count += 1;
+            }
+            None => {
+                failed.insert(id);
+            }
+        }
+    }
+
+    // A composed frame is only current while all of its sprites are.
+    let rehashed_ids: BTreeSet<u32> = stale.into_iter().collect();
+    index.composed.retain(|ids, _| ids.iter().all(|id| fresh.contains_key(id) && !rehashed_ids.contains(id)));
+    index.hashes = fresh;
+    index.failed = failed;
+    index.sources = current_sources;
+    (rehashed_count, reused)
+}
+
+/// Sprite ids drawn for an appearance's idle frame: first frame group and
+/// phase, every layer (outfits: base layer facing south; the second layer is
+/// the colour mask). Empty slots are left out.
+pub fn idle_frame_sprites(appearance: &Appearance, category: &AppearanceCategory) -> Vec<u32> {
+    let Some(info) = appearance.frame_group.first().and_then(|fg| fg.sprite_info.as_ref()) else {
+        return Vec::new();
+    };
+    let (direction, layers) = match category {
+        AppearanceCategory::Outfits => (2.min(info.pattern_width.unwrap_or(1).max(1) - 1), 1),
+        _ => (0, info.layers.unwrap_or(1).max(1)),
+    };
+    (0..layers).filter_map(|layer| info.sprite_id.get(sprite_slot(info, layer, direction, 0, 0, 0)).copied()).filter(|id| *id != 0).collect()
+}
+
+/// Draws `sprites` in order onto one canvas, anchored bottom-right like the client.
+fn compose_frame(sprites: &[RgbaImage]) -> RgbaImage {
+    let width = sprites.iter().map(|s| s.width()).max().unwrap_or(32);
+    let height = sprites.iter().map(|s| s.height()).max().unwrap_or(32);
+    let mut canvas = RgbaImage::new(width, height);
+    for sprite in sprites {
+        imageops::overlay(&mut canvas, sprite, (width - sprite.width()) as i64, (height - sprite.height()) as i64);
+    }
+    canvas
+}
+
+/// Hash of the frame drawn from `ids`, or `None` if any sprite can't be loaded.
+fn composed_hash(ids: &[u32], fetch: &(dyn Fn(u32) -> Option<RgbaImage> + Sync)) -> Option<u64> {
+    let sprites = ids.iter().map(|id| fetch(*id)).collect::<Option<Vec<_>>>()?;
+    Some(difference_hash(&compose_frame(&sprites)))
+}
+
+/// Hashes the composed idle frames of `appearances` that draw more than one
+/// indexed sprite and aren't cached yet. Returns how many were added.
+pub fn refresh_composed_hashes(index: &mut SpriteHashIndex, appearances: &crate::core::protobuf::Appearances, fetch: &(dyn Fn(u32) -> Option<RgbaImage> + Sync)) -> usize {
+    let mut missing: Vec<Vec<u32>> = CATEGORIES
+        .iter()
+        .flat_map(|category| get_items_by_category(appearances, category).iter().map(move |appearance| idle_frame_sprites(appearance, category)))
+        .filter(|ids| ids.len() > 1 && !index.composed.contains_key(ids) && ids.iter().all(|id| index.hashes.contains_key(id)))
+        .collect();
+    missing.sort_unstable();
+    missing.dedup();
+
+    let hashed: Vec<(Vec<u32>, u64)> = missing.into_par_iter().filter_map(|ids| composed_hash(&ids, fetch).map(|hash| (ids, hash))).collect();
+    let added = hashed.len();
+    index.composed.extend(hashed);
+    added
+}
+
+/// Ranks appearances by their best sprite or composed idle frame match against `query`.
+pub fn search_by_hash(state: &AppState, index: &SpriteHashIndex, query: u64, category: Option<&AppearanceCategory>, limit: usize, min_similarity: f32) -> Result<Vec<ImageSearchMatch>, String> {
+    let appearances_lock = state.appearances.read();
+    let appearances = appearances_lock.as_ref().ok_or_else(|| "No appearances loaded".to_string())?;
+
+    // Imported sprites are not in the on-disk index; hash them on the fly.
+    let imported: HashMap<u32, u64> =
+        state.imported_sprites.iter().filter_map(|entry| image::load_from_memory(entry.value()).ok().map(|img| (*entry.key(), difference_hash(&img.to_rgba8())))).collect();
+    let hash_of = |id: u32| imported.get(&id).or_else(|| index.hashes.get(&id)).copied();
+    // Frames not cached in the index (edited since the build, or drawing
+    // imported sprites) are composed on the fly, unless a sprite is known not to decode.
+    let sprite_loader_lock = state.sprite_loader.read();
+    let composed_of = |ids: &[u32]| {
+        index.composed.get(ids).copied().or_else(|| {
+            let loader = sprite_loader_lock.as_ref().filter(|_| !ids.iter().any(|id| index.failed.contains(id)))?;
+            composed_hash(ids, &|id| load_sprite_image(state, loader, id))
+        })
+    };
+
+    let mut matches = Vec::new();
+    for current in CATEGORIES.iter().filter(|c| category.is_none_or(|wanted| wanted == *c)) {
+        for appearance in get_items_by_category(appearances, current) {
+            let best = appearance
+                .frame_group
+                .iter()
+                .filter_map(|fg| fg.sprite_info.as_ref())
+                .flat_map(|info| info.sprite_id.iter().copied())
+                .filter_map(|id| hash_of(id).map(|hash| (id, hash_similarity(hash, query))))
+                .max_by(|a, b| a.1.total_cmp(&b.1));
+            let frame = idle_frame_sprites(appearance, current);
+            let composed = (frame.len() > 1).then(|| composed_of(&frame)).flatten().map(|hash| (frame[0], hash_similarity(hash, query)));
+            let best = match (best, composed) {
+                (Some(sprite), Some(frame)) if frame.1 > sprite.1 => Some(frame),
+                (None, frame) => frame,
+                (sprite, _) => sprite,
+            };
+            if let Some((sprite_id, similarity)) = best.filter(|(_, similarity)| *similarity >= min_similarity) {
+                matches.push(ImageSearchMatch {
+                    category: current.clone(),
+                    appearance_id: appearance.id.unwrap_or(0),
+                    sprite_id,
+                    similarity,
+                });
+            }
+        }
+    }
+
+    matches.sort_by(|a, b| b.similarity.total_cmp(&a.similarity).then(a.appearance_id.cmp(&b.appearance_id)));
+    matches.truncate(limit);
+    Ok(matches)
+}
+
+fn index_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let base_dir = app.path().app_data_dir().map_err(|e| format!("Failed to resolve app data directory: {}", e))?;
+    std::fs::create_dir_all(&base_dir).map_err(|e| format!("Failed to create app data directory {:?}: {}", base_dir, e))?;
+    Ok(base_dir.join(INDEX_FILE))
+}
+
+/// Loads the persisted index (once), refreshes it against the loaded sprites and saves it back.
+pub fn build_hash_index(state: &AppState, path: &Path) -> Result<SpriteHashIndexStats, String> {
+    let sprite_loader_lock = state.sprite_loader.read();
+    let loader = sprite_loader_lock.as_ref().ok_or_else(|| "No sprites loaded".to_string())?;
+
+    let mut index_lock = state.sprite_hash_index.write();
+    let mut index = index_lock.take().or_else(|| std::fs::read(path).ok().and_then(|data| decode_hash_index(&data))).unwrap_or_default();
+    let failed_before = index.failed.clone();
+    let (rehashed, reused) = refresh_hash_index(&mut index, loader);
+    let composed = match state.appearances.read().as_ref() {
+        Some(appearances) => refresh_composed_hashes(&mut index, appearances, &|id| loader.get_sprite(id).and_then(|sprite| sprite.to_image()).ok().map(|image| image.to_rgba8())),
+        None => 0,
+    };
+    if rehashed > 0 || composed > 0 || index.failed != failed_before {
+        write_atomic(path, &encode_hash_index(&index)).map_err(|e| format!("Failed to save sprite hash index: {}", e))?;
+    }
+
+    let stats = SpriteHashIndexStats {
+        sprites: index.hashes.len(),
+        rehashed,
+        reused,
+        composed,
+        path: path.to_string_lossy().to_string(),
+    };
+    *index_lock = Some(index);
+    Ok(stats)
+}
+
+/// Build or incrementally refresh the perceptual hash index of all sprites.
+#[tauri::command]
+pub async fn build_sprite_hash_index(app: AppHandle, state: State<'_, AppState>) -> Result<SpriteHashIndexStats, String> {
+    let path = index_path(&app)?;
+    build_hash_index(&state, &path)
+}
+
+/// Find the appearances whose sprites look most like `image_bytes` (PNG, JPEG, ...).
+/// Builds the hash index on first use; `build_sprite_hash_index` picks up
+/// sprites changed on disk since.
+#[tauri::command]
+pub async fn find_appearances_by_image(
+    image_bytes: Vec<u8>,
+    category: Option<AppearanceCategory>,
+    limit: Option<usize>,
+    min_similarity: Option<f32>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<Vec<ImageSearchMatch>, String> {
+    let image = image::load_from_memory(&image_bytes).map_err(|e| format!("Failed to decode image: {}", e))?;
+    if image.dimensions().0 == 0 || image.dimensions().1 == 0 {
+        return Err("Image is empty".to_string());
+    }
+    let query = difference_hash(&image.to_rgba8());
+
+    if state.sprite_hash_index.read().is_none() {
+        build_hash_index(&state, &index_path(&app)?)?;
+    }
+    let index_lock = state.sprite_hash_index.read();
+    let index = index_lock.as_ref().ok_or_else(|| "Sprite hash index not built".to_string())?;
+    search_by_hash(&state, index, query, category.as_ref(), limit.unwrap_or(DEFAULT_LIMIT), min_similarity.unwrap_or(DEFAULT_MIN_SIMILARITY))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient(width: u32, height: u32, offset: u32) -> RgbaImage {
+        RgbaImage::from_fn(width, height, |x, y| {
+            let v = ((x * 255 / width) ^ (y * 40)) as u8;
+            if x < offset {
+                Rgba([0, 0, 0, 0])
+            } else {
+                Rgba([v, v / 2, 255 - v, 255])
+            }
+        })
+    }
+
+    #[test]
+    fn transparent_margins_do_not_change_the_hash() {
+        let sprite = gradient(32, 32, 0);
+        let mut padded = RgbaImage::new(64, 64);
+        imageops::overlay(&mut padded, &sprite, 32, 32);
+        assert_eq!(difference_hash(&sprite), difference_hash(&padded));
+
+        let scaled = imageops::resize(&sprite, 64, 64, imageops::FilterType::Nearest);
+        assert!(hash_similarity(difference_hash(&sprite), difference_hash(&scaled)) > 0.9);
+    }
+
+    #[test]
+    fn crops_of_layered_appearances_match_the_composed_frame() {
+        use crate::core::protobuf::{Appearances, FrameGroup, SpriteInfo};
+
+        // A 64x64 base with a 32x32 layer on top of its lower right quarter.
+        let base = gradient(64, 64, 0);
+        let top = RgbaImage::from_fn(32, 32, |x, _| {
+            if x % 8 < 4 {
+                Rgba([255, 255, 0, 255])
+            } else {
+                Rgba([0, 0, 0, 0])
+            }
+        });
+        let appearance = Appearance {
+            id: Some(500),
+            frame_group: vec![FrameGroup {
+                sprite_info: Some(SpriteInfo {
+                    layers: Some(2),
+                    sprite_id: vec![1, 2],
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        assert_eq!(idle_frame_sprites(&appearance, &AppearanceCategory::Objects), vec![1, 2]);
+
+        let sprites = [base.clone(), top.clone()];
+        let fetch = move |id: u32| sprites.get(id as usize - 1).cloned();
+        let mut index = SpriteHashIndex::default();
+        index.hashes.insert(1, difference_hash(&base));
+        index.hashes.insert(2, difference_hash(&top));
+        let appearances = Appearances {
+            object: vec![appearance],
+            ..Default::default()
+        };
+        assert_eq!(refresh_composed_hashes(&mut index, &appearances, &fetch), 1);
+
+        // A screenshot of the drawn thing, on an opaque background.
+        let mut screenshot = RgbaImage::from_pixel(80, 80, Rgba([0, 0, 0, 255]));
+        imageops::overlay(&mut screenshot, &compose_frame(&[base.clone(), top.clone()]), 8, 8);
+        let query = difference_hash(&imageops::crop_imm(&screenshot, 8, 8, 64, 64).to_image());
+        let frame = hash_similarity(index.composed[&vec![1, 2]], query);
+        assert_eq!(frame, 1.0);
+        assert!(frame > hash_similarity(index.hashes[&1], query));
+        assert!(frame > hash_similarity(index.hashes[&2], query));
+    }
+
+    #[test]
+    fn index_roundtrips() {
+        let mut index = SpriteHashIndex::default();
+        index.sources.insert("sprites-1.bmp.lzma".to_string(), (1234, 99));
+        index.hashes.insert(7, 0xDEAD_BEEF_0000_0001);
+        index.hashes.insert(3, u64::MAX);
+        index.failed.insert(12);
+        index.composed.insert(vec![7, 3], 42);
+        assert_eq!(decode_hash_index(&encode_hash_index(&index)), Some(index));
+        assert_eq!(decode_hash_index(b"SPHI\x02\x00\x00\x00"), None);
+    }
+}
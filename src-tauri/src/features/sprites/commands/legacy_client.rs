@@ -0,0 +1,389 @@
+// Legacy client export / import
+// Writes the loaded appearances and sprites as a versioned Tibia.dat/Tibia.spr
+// pair for OTClient-based clients, and loads a legacy pair back into the
+// editor. Sprites larger than 32x32 are split into client tiles on export and
+// recomposed from them on import.
+
+use super::animation_export::load_sprite_image;
+use super::image_import::register_imported_tile;
+use crate::core::jobs::JobHandle;
+use crate::core::protobuf::{Appearance, FixedFrameGroup};
+use crate::features::appearances::commands::helpers::{invalidate_search_cache, rebuild_indexes};
+use crate::features::appearances::parsers::{
+    legacy_to_appearances, protobuf_group_to_legacy, read_legacy_dat, write_legacy_dat, LegacyClientVersion, LegacyDat, LegacyDatLayout, LegacyFrameGroup, LegacyThing, LEGACY_FIRST_ITEM_ID,
+    LEGACY_TILE_SIZE,
+};
+use crate::features::jobs::run_job;
+use crate::features::sprites::parsers::{write_legacy_spr, LegacySprFile, LEGACY_TILE_BYTES};
+use crate::state::AppState;
+use anyhow::anyhow;
+use image::{imageops, RgbaImage};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use tauri::{AppHandle, State};
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LegacyExportResult {
+    pub dat_path: String,
+    pub spr_path: String,
+    pub version: u32,
+    pub items: usize,
+    pub outfits: usize,
+    pub effects: usize,
+    pub missiles: usize,
+    /// Distinct 32x32 tiles written to the `.spr`.
+    pub sprite_count: usize,
+    /// Sprite ids that could not be loaded and were exported blank.
+    pub missing_sprites: Vec<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LegacyImportResult {
+    pub signature: u32,
+    /// Client version the dat signature belongs to, when it is a built-in one.
+    pub version: Option<u32>,
+    pub spr_signature: u32,
+    pub objects: usize,
+    pub outfits: usize,
+    pub effects: usize,
+    pub missiles: usize,
+    /// Distinct sprites added to the imported (not yet compiled) sprites.
+    pub sprites_imported: usize,
+}
+
+/// Splits sprites into deduplicated 32x32 client tiles. Tile id 0 is the blank tile.
+struct TileSheet<'a> {
+    fetch: Box<dyn Fn(u32) -> Option<RgbaImage> + 'a>,
+    tiles: Vec<Vec<u8>>,
+    by_pixels: HashMap<Vec<u8>, u32>,
+    images: HashMap<u32, Option<RgbaImage>>,
+    missing: Vec<u32>,
+}
+
+impl<'a> TileSheet<'a> {
+    fn new(fetch: impl Fn(u32) -> Option<RgbaImage> + 'a) -> Self {
+        Self {
+            fetch: Box::new(fetch),
+            tiles: Vec::new(),
+            by_pixels: HashMap::new(),
+            images: HashMap::new(),
+            missing: Vec::new(),
+        }
+    }
+
+    fn image(&mut self, sprite_id: u32) -> Option<&RgbaImage> {
+        if sprite_id == 0 {
+            return None;
+        }
+        if !self.images.contains_key(&sprite_id) {
+            let image = (self.fetch)(sprite_id);
+            if image.is_none() {
+                self.missing.push(sprite_id);
+            }
+            self.images.insert(sprite_id, image);
+        }
+        self.images.get(&sprite_id).and_then(|image| image.as_ref())
+    }
+
+    /// Size in tiles of the first loadable sprite of a group, at least 1x1.
+    fn group_size(&mut self, sprite_ids: &[u32]) -> (u8, u8) {
+        let size = sprite_ids.iter().find_map(|id| self.image(*id).map(|image| image.dimensions()));
+        let (width, height) = size.unwrap_or((LEGACY_TILE_SIZE, LEGACY_TILE_SIZE));
+        (width.div_ceil(LEGACY_TILE_SIZE).clamp(1, 255) as u8, height.div_ceil(LEGACY_TILE_SIZE).clamp(1, 255) as u8)
+    }
+
+    /// Tile ids for one sprite, ordered `hh * width + ww` with (0, 0) the bottom-right tile.
+    fn tiles_for(&mut self, sprite_id: u32, width: u8, height: u8) -> Vec<u32> {
+        let (width, height) = (width as u32, height as u32);
+        let Some(image) = self.image(sprite_id).cloned() else {
+            return vec![0; (width * height) as usize];
+        };
+
+        let (canvas_width, canvas_height) = (width * LEGACY_TILE_SIZE, height * LEGACY_TILE_SIZE);
+        let mut canvas = RgbaImage::new(canvas_width, canvas_height);
+        imageops::overlay(&mut canvas, &image, canvas_width as i64 - image.width() as i64, canvas_height as i64 - image.height() as i64);
+
+        let mut ids = Vec::with_capacity((width * height) as usize);
+        for hh in 0..height {
+            for ww in 0..width {
+                let x = (width - 1 - ww) * LEGACY_TILE_SIZE;
+                let y = (height - 1 - hh) * LEGACY_TILE_SIZE;
+                let tile = imageops::crop_imm(&canvas, x, y, LEGACY_TILE_SIZE, LEGACY_TILE_SIZE).to_image().into_raw();
+                ids.push(self.intern(tile));
+            }
+        }
+        ids
+    }
+
+    fn intern(&mut self, tile: Vec<u8>) -> u32 {
+        debug_assert_eq!(tile.len(), LEGACY_TILE_BYTES);
+        if tile.chunks_exact(4).all(|pixel| pixel[3] == 0) {
+            return 0;
+        }
+        if let Some(id) = self.by_pixels.get(&tile) {
+            return *id;
+        }
+        self.tiles.push(tile.clone());
+        let id = self.tiles.len() as u32;
+        self.by_pixels.insert(tile, id);
+        id
+    }
+}
+
+/// Converts one category into contiguous legacy things starting at `first_id`.
+/// Gaps become empty things, as the legacy format has no id table.
+fn legacy_things(appearances: &[Appearance], first_id: u32, outfit: bool, tiles: &mut TileSheet) -> Vec<LegacyThing> {
+    let by_id: HashMap<u32, &Appearance> = appearances.iter().filter_map(|a| a.id.map(|id| (id, a))).collect();
+    let last_id = by_id.keys().copied().max().unwrap_or(0);
+
+    (first_id..=last_id)
+        .map(|id| {
+            let Some(appearance) = by_id.get(&id) else {
+                return LegacyThing {
+                    id,
+                    groups: vec![LegacyFrameGroup::default()],
+                    ..Default::default()
+                };
+            };
+
+            let frame_groups = if outfit {
+                &appearance.frame_group[..]
+            } else {
+                &appearance.frame_group[..appearance.frame_group.len().min(1)]
+            };
+            let groups: Vec<LegacyFrameGroup> = frame_groups
+                .iter()
+                .filter_map(|fg| {
+                    let info = fg.sprite_info.as_ref()?;
+                    let group_type = u8::from(fg.fixed_frame_group == Some(FixedFrameGroup::OutfitMoving as i32));
+                    let (width, height) = tiles.group_size(&info.sprite_id);
+                    Some(protobuf_group_to_legacy(info, group_type, width, height, &mut |sprite_id| tiles.tiles_for(sprite_id, width, height)))
+                })
+                .collect();
+
+            LegacyThing {
+                id,
+                flags: appearance.flags.clone().unwrap_or_default(),
+                name: appearance.name.clone(),
+                groups: if groups.is_empty() {
+                    vec![LegacyFrameGroup::default()]
+                } else {
+                    groups
+                },
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+/// Exports the loaded appearances and sprites as a legacy dat/spr pair.
+pub fn export_legacy_files(state: &AppState, dat_path: &str, spr_path: &str, client: LegacyClientVersion, transparency: bool) -> Result<LegacyExportResult, String> {
+    let appearances_lock = state.appearances.read();
+    let appearances = appearances_lock.as_ref().ok_or_else(|| "No appearances loaded".to_string())?;
+    let sprite_loader_lock = state.sprite_loader.read();
+    let loader = sprite_loader_lock.as_ref().ok_or_else(|| "No sprites loaded".to_string())?;
+
+    let mut tiles = TileSheet::new(|sprite_id| load_sprite_image(state, loader, sprite_id));
+    let dat = LegacyDat {
+        signature: client.dat_signature,
+        items: legacy_things(&appearances.object, LEGACY_FIRST_ITEM_ID, false, &mut tiles),
+        outfits: legacy_things(&appearances.outfit, 1, true, &mut tiles),
+        effects: legacy_things(&appearances.effect, 1, false, &mut tiles),
+        missiles: legacy_things(&appearances.missile, 1, false, &mut tiles),
+    };
+
+    let dat_bytes = write_legacy_dat(&dat, client.layout).map_err(|e| format!("Failed to build legacy dat: {}", e))?;
+    let spr_bytes = write_legacy_spr(client.spr_signature, &tiles.tiles, transparency).map_err(|e| format!("Failed to build legacy spr: {}", e))?;
+    crate::core::backups::snapshot_before_write(dat_path);
+    std::fs::write(dat_path, dat_bytes).map_err(|e| format!("Failed to write {}: {}", dat_path, e))?;
+    crate::core::backups::snapshot_before_write(spr_path);
+    std::fs::write(spr_path, spr_bytes).map_err(|e| format!("Failed to write {}: {}", spr_path, e))?;
+
+    let mut missing_sprites = tiles.missing;
+    missing_sprites.sort_unstable();
+
+    Ok(LegacyExportResult {
+        dat_path: dat_path.to_string(),
+        spr_path: spr_path.to_string(),
+        version: client.version,
+        items: dat.items.len(),
+        outfits: dat.outfits.len(),
+        effects: dat.effects.len(),
+        missiles: dat.missiles.len(),
+        sprite_count: tiles.tiles.len(),
+        missing_sprites,
+    })
+}
+
+/// Draws the tiles of one legacy slot (ordered like [`TileSheet::tiles_for`])
+/// back into a single sprite. Slots without visible pixels yield `None`.
+fn compose_slot(spr: &LegacySprFile, width: u8, height: u8, tile_ids: &[u32]) -> anyhow::Result<Option<RgbaImage>> {
+    if tile_ids.iter().all(|id| *id == 0) {
+        return Ok(None);
+    }
+    let (width, height) = (width.max(1) as u32, height.max(1) as u32);
+    let mut canvas = RgbaImage::new(width * LEGACY_TILE_SIZE, height * LEGACY_TILE_SIZE);
+    for (index, id) in tile_ids.iter().enumerate() {
+        let (ww, hh) = (index as u32 % width, index as u32 / width);
+        let tile = RgbaImage::from_raw(LEGACY_TILE_SIZE, LEGACY_TILE_SIZE, spr.tile(*id)?).ok_or_else(|| anyhow!("Sprite {} is not a 32x32 tile", id))?;
+        imageops::replace(&mut canvas, &tile, ((width - 1 - ww) * LEGACY_TILE_SIZE) as i64, ((height - 1 - hh) * LEGACY_TILE_SIZE) as i64);
+    }
+    Ok(canvas.pixels().any(|pixel| pixel[3] != 0).then_some(canvas))
+}
+
+/// Loads a legacy Tibia.dat/Tibia.spr pair as the current appearances. Every
+/// slot is recomposed from its `.spr` tiles into an imported sprite (identical
+/// sprites share one id), ready for `compile_imported_sprites`. Things larger
+/// than 64x64 are refused. `transparency` must match how the `.spr` was written.
+pub fn import_legacy_files(state: &AppState, dat_path: &str, spr_path: &str, transparency: bool, job: &JobHandle) -> Result<LegacyImportResult, String> {
+    let data = std::fs::read(dat_path).map_err(|e| format!("Failed to read {}: {}", dat_path, e))?;
+    let dat = read_legacy_dat(&data).map_err(|e| format!("Failed to parse legacy dat: {}", e))?;
+    let spr_data = std::fs::read(spr_path).map_err(|e| format!("Failed to read {}: {}", spr_path, e))?;
+    let spr = LegacySprFile::parse(&spr_data, transparency).map_err(|e| format!("Failed to parse legacy spr: {}", e))?;
+
+    job.set_message("Recomposing legacy sprites");
+    job.set_total(
+        [&dat.items, &dat.outfits, &dat.effects, &dat.missiles]
+            .iter()
+            .flat_map(|things| things.iter())
+            .flat_map(|thing| &thing.groups)
+            .map(|group| group.sprite_ids.len() / (group.width as usize * group.height as usize).max(1))
+            .sum(),
+    );
+    let existing: HashSet<u32> = state.imported_sprites.iter().map(|entry| *entry.key()).collect();
+    let converted = legacy_to_appearances(&dat, &mut |group, tile_ids| {
+        job.check_cancelled().map_err(|e| anyhow!(e))?;
+        job.advance(1);
+        match compose_slot(&spr, group.width, group.height, tile_ids)? {
+            Some(image) => register_imported_tile(state, image).map_err(|e| anyhow!(e)),
+            None => Ok(0),
+        }
+    });
+    let added: HashSet<u32> = state.imported_sprites.iter().map(|entry| *entry.key()).filter(|id| !existing.contains(id)).collect();
+    let appearances = match converted {
+        Ok(appearances) => appearances,
+        Err(e) => {
+            // Nothing references the sprites registered so far; drop them again.
+            state.imported_sprites.retain(|id, _| !added.contains(id));
+            state.imported_sprite_hashes.retain(|_, id| !added.contains(id));
+            return Err(format!("Failed to convert legacy dat: {:#}", e));
+        }
+    };
+
+    let result = LegacyImportResult {
+        signature: dat.signature,
+        version: LegacyClientVersion::from_dat_signature(dat.signature).map(|client| client.version),
+        spr_signature: spr.signature,
+        objects: appearances.object.len(),
+        outfits: appearances.outfit.len(),
+        effects: appearances.effect.len(),
+        missiles: appearances.missile.len(),
+        sprites_imported: added.len(),
+    };
+
+    {
+        let mut appearances_lock = state.appearances.write();
+        *appearances_lock = Some(appearances);
+        *state.appearance_unknown_fields.write() = Default::default();
+        rebuild_indexes(state, appearances_lock.as_ref().unwrap());
+        invalidate_search_cache(state);
+    }
+    state.appearance_journal.lock().clear();
+    // Not a protobuf file: saving must pick a new destination.
+    *state.tibia_path.lock() = None;
+
+    Ok(result)
+}
+
+/// Export a legacy Tibia.dat/Tibia.spr pair for a 10.98 or 12.x client.
+/// Signatures default to the built-in ones for `version`; other versions need
+/// them passed explicitly and use the 12.x layout from 1200 on.
+#[tauri::command]
+pub async fn export_legacy_client(
+    dat_path: String,
+    spr_path: String,
+    version: u32,
+    dat_signature: Option<u32>,
+    spr_signature: Option<u32>,
+    transparency: bool,
+    state: State<'_, AppState>,
+) -> Result<LegacyExportResult, String> {
+    let known = LegacyClientVersion::known(version);
+    let client = LegacyClientVersion {
+        version,
+        layout: known.map(|k| k.layout).unwrap_or(if version >= 1200 {
+            LegacyDatLayout::V12
+        } else {
+            LegacyDatLayout::V1098
+        }),
+        dat_signature: dat_signature.or(known.map(|k| k.dat_signature)).ok_or_else(|| format!("Unknown client version {}: dat signature required", version))?,
+        spr_signature: spr_signature.or(known.map(|k| k.spr_signature)).ok_or_else(|| format!("Unknown client version {}: spr signature required", version))?,
+    };
+    export_legacy_files(&state, &dat_path, &spr_path, client, transparency)
+}
+
+/// Load a legacy Tibia.dat/Tibia.spr pair into the editor.
+#[tauri::command]
+pub async fn import_legacy_dat(dat_path: String, spr_path: String, transparency: bool, app: AppHandle, state: State<'_, AppState>) -> Result<LegacyImportResult, String> {
+    run_job(&app, state.inner(), "import_legacy_dat", |job| import_legacy_files(state.inner(), &dat_path, &spr_path, transparency, job))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::protobuf::{FrameGroup, SpriteInfo};
+    use image::Rgba;
+
+    #[test]
+    fn large_sprites_split_bottom_right_first() {
+        let mut image = RgbaImage::new(64, 32);
+        image.put_pixel(63, 0, Rgba([1, 2, 3, 255]));
+        image.put_pixel(0, 0, Rgba([4, 5, 6, 255]));
+        let mut sheet = TileSheet::new(move |_| Some(image.clone()));
+
+        assert_eq!(sheet.group_size(&[7]), (2, 1));
+        assert_eq!(sheet.tiles_for(7, 2, 1), vec![1, 2]);
+        assert_eq!(&sheet.tiles[0][31 * 4..32 * 4], &[1, 2, 3, 255]);
+        assert_eq!(&sheet.tiles[1][..4], &[4, 5, 6, 255]);
+        // Same pixels reuse the same tile ids.
+        assert_eq!(sheet.tiles_for(8, 2, 1), vec![1, 2]);
+    }
+
+    #[test]
+    fn split_tiles_compose_back_into_the_sprite() {
+        let mut image = RgbaImage::new(64, 64);
+        image.put_pixel(63, 63, Rgba([1, 2, 3, 255]));
+        image.put_pixel(0, 40, Rgba([4, 5, 6, 255]));
+        let original = image.clone();
+        let mut sheet = TileSheet::new(move |_| Some(image.clone()));
+        let ids = sheet.tiles_for(7, 2, 2);
+
+        let spr_bytes = write_legacy_spr(0x57BB_D603, &sheet.tiles, false).unwrap();
+        let spr = LegacySprFile::parse(&spr_bytes, false).unwrap();
+        assert_eq!(compose_slot(&spr, 2, 2, &ids).unwrap(), Some(original));
+        assert_eq!(compose_slot(&spr, 2, 2, &[0, 0, 0, 0]).unwrap(), None);
+    }
+
+    #[test]
+    fn categories_are_filled_contiguously() {
+        let appearance = Appearance {
+            id: Some(102),
+            frame_group: vec![FrameGroup {
+                sprite_info: Some(SpriteInfo {
+                    sprite_id: vec![0],
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let mut sheet = TileSheet::new(|_| None);
+        let things = legacy_things(&[appearance], LEGACY_FIRST_ITEM_ID, false, &mut sheet);
+
+        assert_eq!(things.iter().map(|t| t.id).collect::<Vec<_>>(), vec![100, 101, 102]);
+        assert_eq!(things[2].groups[0].sprite_ids, vec![0]);
+        assert!(sheet.tiles.is_empty());
+    }
+}
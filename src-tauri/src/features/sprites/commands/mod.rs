@@ -1,8 +1,22 @@
 // Sprite commands module
 // All Tauri commands for sprites
 
+mod animation_export;
+mod atlas;
 mod image_import;
+mod image_search;
+mod legacy_client;
+mod outfit_render;
+mod sprite_gc;
+mod sprite_metrics;
 mod sprites;
 
+pub use animation_export::*;
+pub use atlas::*;
 pub use image_import::*;
+pub use image_search::*;
+pub use legacy_client::*;
+pub use outfit_render::*;
+pub use sprite_gc::*;
+pub use sprite_metrics::*;
 pub use sprites::*;
@@ -0,0 +1,234 @@
+// Outfit compositor
+// Renders a looktype the way the client does: base layer tinted through the
+// template (mask) layer with the 133-colour HSI palette, plus addons and mount.
+
+use super::animation_export::{load_sprite_image, sprite_slot};
+use super::sprites::resolve_appearance;
+use crate::core::protobuf::SpriteInfo;
+use crate::features::appearances::AppearanceCategory;
+use crate::state::AppState;
+use image::{imageops, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::io::Cursor;
+use tauri::State;
+
+const HSI_SI_VALUES: u32 = 7;
+const HSI_H_STEPS: u32 = 19;
+
+/// Number of entries in the outfit colour palette (0..=132).
+pub const OUTFIT_COLOR_COUNT: u32 = HSI_H_STEPS * HSI_SI_VALUES;
+
+/// Looktype plus colours, same field names as `MonsterOutfit`/`NpcOutfit`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutfitLook {
+    pub look_type: u32,
+    pub look_head: u8,
+    pub look_body: u8,
+    pub look_legs: u8,
+    pub look_feet: u8,
+    /// Bit 1 = first addon, bit 2 = second addon.
+    pub look_addons: u8,
+    /// Mount looktype, 0 for none.
+    pub look_mount: u16,
+}
+
+/// Converts an outfit colour index to RGB. Out-of-range indices map to 0 (white).
+pub fn outfit_color_to_rgb(color: u32) -> [u8; 3] {
+    let color = if color >= OUTFIT_COLOR_COUNT {
+        0
+    } else {
+        color
+    };
+
+    let (hue, saturation, intensity) = if color % HSI_H_STEPS != 0 {
+        let hue = (color % HSI_H_STEPS) as f32 / 18.0;
+        let (saturation, intensity) = match color / HSI_H_STEPS {
+            0 => (0.25, 1.0),
+            1 => (0.25, 0.75),
+            2 => (0.5, 0.75),
+            3 => (2.0 / 3.0, 0.75),
+            4 => (1.0, 1.0),
+            5 => (1.0, 0.75),
+            _ => (1.0, 0.5),
+        };
+        (hue, saturation, intensity)
+    } else {
+        (0.0, 0.0, 1.0 - color as f32 / HSI_H_STEPS as f32 / HSI_SI_VALUES as f32)
+    };
+
+    if intensity == 0.0 {
+        return [0, 0, 0];
+    }
+    if saturation == 0.0 {
+        let grey = to_byte(intensity);
+        return [grey, grey, grey];
+    }
+
+    let low = intensity * (1.0 - saturation);
+    let (red, green, blue) = if hue < 1.0 / 6.0 {
+        (intensity, low + (intensity - low) * 6.0 * hue, low)
+    } else if hue < 2.0 / 6.0 {
+        (intensity - (intensity - low) * (6.0 * hue - 1.0), intensity, low)
+    } else if hue < 3.0 / 6.0 {
+        (low, intensity, low + (intensity - low) * (6.0 * hue - 2.0))
+    } else if hue < 4.0 / 6.0 {
+        (low, intensity - (intensity - low) * (6.0 * hue - 3.0), intensity)
+    } else if hue < 5.0 / 6.0 {
+        (low + (intensity - low) * (6.0 * hue - 4.0), low, intensity)
+    } else {
+        (intensity, low, intensity - (intensity - low) * (6.0 * hue - 5.0))
+    };
+
+    [to_byte(red), to_byte(green), to_byte(blue)]
+}
+
+#[inline]
+fn to_byte(value: f32) -> u8 {
+    (value * 255.0).round().clamp(0.0, 255.0) as u8
+}
+
+/// Tints `base` in place using the template mask: yellow = head, red = body,
+/// green = legs, blue = feet. Both images must have the same size.
+pub fn apply_outfit_mask(base: &mut RgbaImage, mask: &RgbaImage, look: &OutfitLook) {
+    let colors = [look.look_head, look.look_body, look.look_legs, look.look_feet].map(|c| outfit_color_to_rgb(c as u32));
+
+    for (pixel, mask_pixel) in base.pixels_mut().zip(mask.pixels()) {
+        let [r, g, b, a] = mask_pixel.0;
+        if a == 0 {
+            continue;
+        }
+        let color = match (r > 0, g > 0, b > 0) {
+            (true, true, false) => colors[0],
+            (true, false, false) => colors[1],
+            (false, true, false) => colors[2],
+            (false, false, true) => colors[3],
+            _ => continue,
+        };
+        for (channel, tint) in pixel.0.iter_mut().zip(color) {
+            *channel = ((*channel as u32 * tint as u32) / 255) as u8;
+        }
+    }
+}
+
+/// Pattern rows to draw: the base outfit (y = 0) plus each enabled addon.
+pub fn addon_rows(info: &SpriteInfo, addons: u8) -> Vec<u32> {
+    let pattern_height = info.pattern_height.unwrap_or(1).max(1);
+    let mut rows = vec![0];
+    rows.extend((1..pattern_height.min(3)).filter(|y| addons & (1 << (y - 1)) != 0));
+    rows
+}
+
+fn draw_bottom_right(canvas: &mut RgbaImage, sprite: &RgbaImage) {
+    let x = canvas.width() as i64 - sprite.width() as i64;
+    let y = canvas.height() as i64 - sprite.height() as i64;
+    imageops::overlay(canvas, sprite, x, y);
+}
+
+/// Composes one outfit frame: every addon row at pattern depth `z`, each tinted
+/// through its mask layer when the looktype has one.
+pub fn compose_outfit(info: &SpriteInfo, look: &OutfitLook, direction: u32, z: u32, phase: u32, fetch: &dyn Fn(u32) -> Option<RgbaImage>) -> Vec<RgbaImage> {
+    let layers = info.layers.unwrap_or(1).max(1);
+    let direction = direction % info.pattern_width.unwrap_or(1).max(1);
+    let z = z.min(info.pattern_depth.unwrap_or(1).max(1) - 1);
+    let sprite_at = |layer: u32, y: u32| info.sprite_id.get(sprite_slot(info, layer, direction, y, z, phase)).copied().filter(|id| *id != 0).and_then(fetch);
+
+    addon_rows(info, look.look_addons)
+        .into_iter()
+        .filter_map(|y| {
+            let mut base = sprite_at(0, y)?;
+            if layers > 1 {
+                if let Some(mask) = sprite_at(1, y).filter(|mask| mask.dimensions() == base.dimensions()) {
+                    apply_outfit_mask(&mut base, &mask, look);
+                }
+            }
+            Some(base)
+        })
+        .collect()
+}
+
+/// Renders the full outfit (mount first, then the rider) into a single RGBA frame.
+pub fn render_outfit_image(state: &AppState, look: &OutfitLook, direction: u32, phase: u32) -> Result<RgbaImage, String> {
+    let appearances_lock = state.appearances.read();
+    let appearances = appearances_lock.as_ref().ok_or_else(|| "No appearances loaded".to_string())?;
+    let sprite_loader_lock = state.sprite_loader.read();
+    let loader = sprite_loader_lock.as_ref().ok_or_else(|| "No sprites loaded".to_string())?;
+    let fetch = |sprite_id: u32| load_sprite_image(state, loader, sprite_id);
+
+    let outfit_info = |look_type: u32| -> Result<&SpriteInfo, String> {
+        let appearance = resolve_appearance(state, &appearances.outfit, &AppearanceCategory::Outfits, look_type).ok_or_else(|| format!("Outfit {} not found", look_type))?;
+        appearance.frame_group.first().and_then(|fg| fg.sprite_info.as_ref()).ok_or_else(|| format!("Outfit {} has no sprite info", look_type))
+    };
+
+    let mut layers: Vec<RgbaImage> = Vec::new();
+    let mut rider_z = 0;
+    if look.look_mount != 0 {
+        let mount_look = OutfitLook {
+            look_type: look.look_mount as u32,
+            ..Default::default()
+        };
+        layers.extend(compose_outfit(outfit_info(mount_look.look_type)?, &mount_look, direction, 0, phase, &fetch));
+        rider_z = 1;
+    }
+    layers.extend(compose_outfit(outfit_info(look.look_type)?, look, direction, rider_z, phase, &fetch));
+
+    let width = layers.iter().map(|l| l.width()).max().unwrap_or(32);
+    let height = layers.iter().map(|l| l.height()).max().unwrap_or(32);
+    let mut canvas = RgbaImage::from_pixel(width, height, Rgba([0, 0, 0, 0]));
+    for layer in &layers {
+        draw_bottom_right(&mut canvas, layer);
+    }
+    Ok(canvas)
+}
+
+/// Render a coloured outfit preview as PNG bytes. `direction` is 0 = north,
+/// 1 = east, 2 = south, 3 = west; `phase` selects the animation frame (default 0).
+#[tauri::command]
+pub async fn render_outfit(look: OutfitLook, direction: u32, phase: Option<u32>, state: State<'_, AppState>) -> Result<Vec<u8>, String> {
+    let image = render_outfit_image(&state, &look, direction, phase.unwrap_or(0))?;
+    let mut buffer = Vec::new();
+    image.write_to(&mut Cursor::new(&mut buffer), image::ImageFormat::Png).map_err(|e| format!("Failed to encode outfit PNG: {}", e))?;
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn palette_matches_client_colors() {
+        assert_eq!(outfit_color_to_rgb(0), [255, 255, 255]);
+        assert_eq!(outfit_color_to_rgb(114), [36, 36, 36]);
+        // Row 4 is full saturation and intensity; hue step 1 leans red.
+        assert_eq!(outfit_color_to_rgb(77), [255, 85, 0]);
+        assert_eq!(outfit_color_to_rgb(500), outfit_color_to_rgb(0));
+    }
+
+    #[test]
+    fn mask_tints_only_marked_regions() {
+        let mut base = RgbaImage::from_pixel(2, 1, Rgba([200, 200, 200, 255]));
+        let mut mask = RgbaImage::new(2, 1);
+        mask.put_pixel(0, 0, Rgba([255, 0, 0, 255])); // body
+        let look = OutfitLook {
+            look_body: 114, // darkest grey
+            ..Default::default()
+        };
+
+        apply_outfit_mask(&mut base, &mask, &look);
+        assert_eq!(base.get_pixel(0, 0).0, [28, 28, 28, 255]);
+        assert_eq!(base.get_pixel(1, 0).0, [200, 200, 200, 255]);
+    }
+
+    #[test]
+    fn addon_rows_follow_addon_bits() {
+        let info = SpriteInfo {
+            pattern_height: Some(3),
+            ..Default::default()
+        };
+        assert_eq!(addon_rows(&info, 0), vec![0]);
+        assert_eq!(addon_rows(&info, 2), vec![0, 2]);
+        assert_eq!(addon_rows(&info, 3), vec![0, 1, 2]);
+        let no_addons = SpriteInfo::default();
+        assert_eq!(addon_rows(&no_addons, 3), vec![0]);
+    }
+}
@@ -0,0 +1,479 @@
+// Sprite deduplication and unused-sprite garbage collection
+// Dedup hashes the decoded pixels of every referenced sprite and points all
+// references at the lowest id with identical pixels. GC drops catalog sheets no
+// appearance uses any more and repacks partially used sheets with the unused
+// slots blanked (transparent tiles compress to almost nothing).
+
+use super::image_import::{build_sheet_bmp, wrap_cip_lzma};
+use crate::core::lzma;
+use crate::core::protobuf::Appearances;
+use crate::features::appearances::commands::helpers::get_items_by_category;
+use crate::features::appearances::commands::{record_changes, AppearanceChange};
+use crate::features::appearances::AppearanceCategory;
+use crate::features::sprites::parsers::{SpriteCatalogEntry, SpriteLoader};
+use crate::state::AppState;
+use ahash::AHasher;
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+/// Sheets written by the client are 384x384 pixels.
+const SHEET_SIZE: u32 = 384;
+/// Removed sheets are moved here instead of being deleted.
+const GC_BACKUP_DIR: &str = "sprite_gc_backup";
+
+const CATEGORIES: [AppearanceCategory; 4] = [AppearanceCategory::Objects, AppearanceCategory::Outfits, AppearanceCategory::Effects, AppearanceCategory::Missiles];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DuplicateSpriteGroup {
+    /// Lowest id with these pixels; every duplicate is rewritten to it.
+    pub canonical: u32,
+    pub duplicates: Vec<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SpriteDedupResult {
+    pub sprites_hashed: usize,
+    pub groups: Vec<DuplicateSpriteGroup>,
+    /// Number of `sprite_id` slots pointed at a canonical id (0 on dry run).
+    pub references_rewritten: usize,
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SheetAction {
+    Keep,
+    /// No sprite in the sheet is referenced; drop it from the catalog.
+    Remove,
+    /// Some sprites are unreferenced; rewrite the sheet with them blanked.
+    Repack,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SheetPlan {
+    pub file: String,
+    pub first_sprite_id: u32,
+    pub last_sprite_id: u32,
+    pub unused: usize,
+    pub action: SheetAction,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SpriteGcResult {
+    pub referenced_sprites: usize,
+    pub unused_sprites: usize,
+    pub sheets: Vec<SheetPlan>,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+    pub dry_run: bool,
+}
+
+/// Every sprite id referenced by any appearance (0 excluded).
+pub fn referenced_sprite_ids(appearances: &Appearances) -> BTreeSet<u32> {
+    CATEGORIES
+        .iter()
+        .flat_map(|category| get_items_by_category(appearances, category).iter())
+        .flat_map(|appearance| appearance.frame_group.iter())
+        .filter_map(|fg| fg.sprite_info.as_ref())
+        .flat_map(|info| info.sprite_id.iter().copied())
+        .filter(|id| *id != 0)
+        .collect()
+}
+
+/// Groups sprites with identical size and pixels. Input is `(id, width, height, rgba)`.
+pub fn find_duplicate_groups(sprites: &[(u32, u32, u32, &[u8])]) -> Vec<DuplicateSpriteGroup> {
+    let mut buckets: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (index, (_, width, height, data)) in sprites.iter().enumerate() {
+        let mut hasher = AHasher::default();
+        (width, height, data).hash(&mut hasher);
+        buckets.entry(hasher.finish()).or_default().push(index);
+    }
+
+    let mut groups = Vec::new();
+    for indices in buckets.into_values().filter(|indices| indices.len() > 1) {
+        // Confirm byte equality so a hash collision can never merge different sprites.
+        let mut remaining = indices;
+        while let Some(first) = remaining.first().copied() {
+            let (same, rest): (Vec<usize>, Vec<usize>) =
+                remaining.into_iter().partition(|i| sprites[*i].1 == sprites[first].1 && sprites[*i].2 == sprites[first].2 && sprites[*i].3 == sprites[first].3);
+            remaining = rest;
+            if same.len() > 1 {
+                let mut ids: Vec<u32> = same.iter().map(|i| sprites[*i].0).collect();
+                ids.sort_unstable();
+                groups.push(DuplicateSpriteGroup {
+                    canonical: ids[0],
+                    duplicates: ids[1..].to_vec(),
+                });
+            }
+        }
+    }
+    groups.sort_by_key(|group| group.canonical);
+    groups
+}
+
+/// Rewrites every `sprite_id` found in `remap`. Returns the journal changes per
+/// category and the number of rewritten slots.
+pub fn rewrite_sprite_references(appearances: &mut Appearances, remap: &HashMap<u32, u32>) -> (Vec<(AppearanceCategory, Vec<AppearanceChange>)>, usize) {
+    let mut changes = Vec::new();
+    let mut rewritten = 0;
+    for (category, items) in CATEGORIES.iter().zip([&mut appearances.object, &mut appearances.outfit, &mut appearances.effect, &mut appearances.missile]) {
+        let mut category_changes = Vec::new();
+        for appearance in items.iter_mut() {
+            let hits = appearance.frame_group.iter().filter_map(|fg| fg.sprite_info.as_ref()).flat_map(|info| info.sprite_id.iter()).filter(|id| remap.contains_key(id)).count();
+            if hits == 0 {
+                continue;
+            }
+            let before = appearance.clone();
+            for info in appearance.frame_group.iter_mut().filter_map(|fg| fg.sprite_info.as_mut()) {
+                for sprite_id in info.sprite_id.iter_mut() {
+                    if let Some(canonical) = remap.get(sprite_id) {
+                        *sprite_id = *canonical;
+                    }
+                }
+            }
+            rewritten += hits;
+            category_changes.push(AppearanceChange {
+                before: Some(before),
+                after: Some(appearance.clone()),
+            });
+        }
+        if !category_changes.is_empty() {
+            changes.push((category.clone(), category_changes));
+        }
+    }
+    (changes, rewritten)
+}
+
+/// Decides per catalog sprite sheet whether it can be removed or repacked.
+pub fn plan_sheets(entries: &[SpriteCatalogEntry], referenced: &BTreeSet<u32>) -> Vec<SheetPlan> {
+    entries
+        .iter()
+        .filter(|entry| entry.entry_type == "sprite")
+        .filter_map(|entry| {
+            let (first, last) = (entry.first_sprite_id?, entry.last_sprite_id?);
+            let used = referenced.range(first..=last).count();
+            let total = (last - first + 1) as usize;
+            let action = match used {
+                0 => SheetAction::Remove,
+                used if used < total => SheetAction::Repack,
+                _ => SheetAction::Keep,
+            };
+            Some(SheetPlan {
+                file: entry.file.clone(),
+                first_sprite_id: first,
+                last_sprite_id: last,
+                unused: total - used,
+                action,
+            })
+        })
+        .collect()
+}
+
+/// Hashes all referenced sprites and, unless `dry_run`, rewrites references to
+/// duplicates. The rewrite is journaled (one undo entry per category).
+pub fn deduplicate_loaded_sprites(state: &AppState, dry_run: bool) -> Result<SpriteDedupResult, String> {
+    let referenced: Vec<u32> = {
+        let appearances_lock = state.appearances.read();
+        let appearances = appearances_lock.as_ref().ok_or_else(|| "No appearances loaded".to_string())?;
+        referenced_sprite_ids(appearances).into_iter().collect()
+    };
+
+    let decoded: Vec<(u32, u32, u32, Vec<u8>)> = {
+        let sprite_loader_lock = state.sprite_loader.read();
+        let loader = sprite_loader_lock.as_ref().ok_or_else(|| "No sprites loaded".to_string())?;
+        referenced.par_iter().filter_map(|id| decode_sprite(state, loader, *id)).collect()
+    };
+    let views: Vec<(u32, u32, u32, &[u8])> = decoded.iter().map(|(id, w, h, data)| (*id, *w, *h, data.as_slice())).collect();
+    let groups = find_duplicate_groups(&views);
+
+    let mut references_rewritten = 0;
+    if !dry_run && !groups.is_empty() {
+        let remap: HashMap<u32, u32> = groups.iter().flat_map(|group| group.duplicates.iter().map(move |id| (*id, group.canonical))).collect();
+        let mut appearances_lock = state.appearances.write();
+        let appearances = appearances_lock.as_mut().ok_or_else(|| "No appearances loaded".to_string())?;
+        let (changes, rewritten) = rewrite_sprite_references(appearances, &remap);
+        for (category, category_changes) in changes {
+            record_changes(state, "Deduplicate sprites", &category, category_changes);
+        }
+        references_rewritten = rewritten;
+        drop(appearances_lock);
+        state.sprite_cache.clear();
+        state.preview_cache.clear();
+    }
+
+    Ok(SpriteDedupResult {
+        sprites_hashed: decoded.len(),
+        groups,
+        references_rewritten,
+        dry_run,
+    })
+}
+
+fn decode_sprite(state: &AppState, loader: &SpriteLoader, sprite_id: u32) -> Option<(u32, u32, u32, Vec<u8>)> {
+    if let Some(png) = state.imported_sprites.get(&sprite_id) {
+        let image = image::load_from_memory(&png).ok()?.to_rgba8();
+        return Some((sprite_id, image.width(), image.height(), image.into_raw()));
+    }
+    let sprite = loader.get_sprite(sprite_id).ok()?;
+    Some((sprite_id, sprite.width, sprite.height, (*sprite.data).clone()))
+}
+
+/// Removes fully unused sheets from the catalog and repacks partially used
+/// ones, based on the appearances currently in memory (save them afterwards).
+/// Removed sheets go to `<assets_dir>/sprite_gc_backup/`; repacked sheets and
+/// the catalog are snapshotted by the backup manager before being replaced.
+pub fn collect_unused_catalog_sprites(state: &AppState, assets_dir: &str, catalog_path: &str, dry_run: bool) -> Result<SpriteGcResult, String> {
+    let referenced = {
+        let appearances_lock = state.appearances.read();
+        let appearances = appearances_lock.as_ref().ok_or_else(|| "No appearances loaded".to_string())?;
+        referenced_sprite_ids(appearances)
+    };
+
+    let catalog_text = std::fs::read_to_string(catalog_path).map_err(|e| format!("Failed to read catalog {}: {}", catalog_path, e))?;
+    let entries: Vec<SpriteCatalogEntry> = serde_json::from_str(&catalog_text).map_err(|e| format!("Failed to parse catalog: {}", e))?;
+    let sheets = plan_sheets(&entries, &referenced);
+
+    let assets = Path::new(assets_dir);
+    let file_size = |file: &str| std::fs::metadata(assets.join(file)).map(|m| m.len()).unwrap_or(0);
+    let bytes_before: u64 = sheets.iter().map(|sheet| file_size(&sheet.file)).sum();
+    let unused_sprites = sheets.iter().map(|sheet| sheet.unused).sum();
+
+    if dry_run || sheets.iter().all(|sheet| sheet.action == SheetAction::Keep) {
+        return Ok(SpriteGcResult {
+            referenced_sprites: referenced.len(),
+            unused_sprites,
+            sheets,
+            bytes_before,
+            bytes_after: bytes_before,
+            dry_run,
+        });
+    }
+
+    // Encode every repacked sheet before touching the disk so a decode failure leaves nothing half-written.
+    let mut repacked: Vec<(String, Vec<u8>)> = Vec::new();
+    {
+        let sprite_loader_lock = state.sprite_loader.read();
+        let loader = sprite_loader_lock.as_ref().ok_or_else(|| "No sprites loaded".to_string())?;
+        for sheet in sheets.iter().filter(|sheet| sheet.action == SheetAction::Repack) {
+            repacked.push((sheet.file.clone(), repack_sheet(loader, sheet, &referenced)?));
+        }
+    }
+
+    // Rewrite the catalog as raw JSON so non-sprite entries keep every field.
+    let removed: BTreeSet<&str> = sheets.iter().filter(|sheet| sheet.action == SheetAction::Remove).map(|sheet| sheet.file.as_str()).collect();
+    let mut raw: Vec<serde_json::Value> = serde_json::from_str(&catalog_text).map_err(|e| format!("Failed to parse catalog: {}", e))?;
+    raw.retain(|entry| !(entry.get("type").and_then(|t| t.as_str()) == Some("sprite") && entry.get("file").and_then(|f| f.as_str()).is_some_and(|f| removed.contains(f))));
+    let new_json = serde_json::to_string_pretty(&raw).map_err(|e| format!("Failed to serialize catalog: {}", e))?;
+
+    apply_sheet_changes(assets, &removed, &repacked, Path::new(catalog_path), new_json.as_bytes())?;
+    // Reload so the in-memory loader doesn't serve pixels from the old sheets.
+    let loader = SpriteLoader::new(catalog_path, assets_dir).map_err(|e| format!("Failed to reload sprite catalog: {}", e))?;
+    *state.sprite_loader.write() = Some(loader);
+    state.clear_caches();
+
+    let bytes_after: u64 = sheets.iter().filter(|sheet| sheet.action != SheetAction::Remove).map(|sheet| file_size(&sheet.file)).sum();
+    Ok(SpriteGcResult {
+        referenced_sprites: referenced.len(),
+        unused_sprites,
+        sheets,
+        bytes_before,
+        bytes_after,
+        dry_run,
+    })
+}
+
+/// Staged suffixes for repacked sheets: the new contents, and the original
+/// moved aside until the catalog is written.
+const STAGED_SUFFIX: &str = ".gc-new";
+const REPLACED_SUFFIX: &str = ".gc-old";
+
+/// Renames done so far, undone newest first when a later step fails.
+struct RenameLog(Vec<(PathBuf, PathBuf)>);
+
+impl RenameLog {
+    fn rename(&mut self, from: PathBuf, to: PathBuf) -> Result<(), String> {
+        std::fs::rename(&from, &to).map_err(|e| format!("Failed to move {} to {}: {}", from.display(), to.display(), e))?;
+        self.0.push((from, to));
+        Ok(())
+    }
+
+    fn undo(self) {
+        for (from, to) in self.0.into_iter().rev() {
+            if let Err(e) = std::fs::rename(&to, &from) {
+                log::error!("Failed to restore {} from {}: {}", from.display(), to.display(), e);
+            }
+        }
+    }
+}
+
+/// Applies a GC plan to disk with the catalog written last. Repacked sheets are
+/// staged next to the originals first; if any move or the catalog write fails,
+/// every move is undone so the catalog never points at a missing or partial sheet.
+fn apply_sheet_changes(assets: &Path, removed: &BTreeSet<&str>, repacked: &[(String, Vec<u8>)], catalog_path: &Path, catalog: &[u8]) -> Result<(), String> {
+    let staged = |file: &str| assets.join(format!("{}{}", file, STAGED_SUFFIX));
+    let replaced = |file: &str| assets.join(format!("{}{}", file, REPLACED_SUFFIX));
+    let discard_staged = || {
+        for (file, _) in repacked {
+            let _ = std::fs::remove_file(staged(file));
+        }
+    };
+
+    for (file, bytes) in repacked {
+        if let Err(e) = crate::core::fs_util::write_atomic(&staged(file), bytes) {
+            discard_staged();
+            return Err(format!("Failed to stage {}: {}", file, e));
+        }
+    }
+
+    let backup_dir = assets.join(GC_BACKUP_DIR);
+    let mut log = RenameLog(Vec::new());
+    let result = (|| {
+        std::fs::create_dir_all(&backup_dir).map_err(|e| format!("Failed to create {}: {}", backup_dir.display(), e))?;
+        for file in removed {
+            log.rename(assets.join(file), backup_dir.join(file))?;
+        }
+        for (file, _) in repacked {
+            crate::core::backups::snapshot_before_write(assets.join(file));
+            log.rename(assets.join(file), replaced(file))?;
+            log.rename(staged(file), assets.join(file))?;
+        }
+        crate::core::backups::snapshot_before_write(catalog_path);
+        crate::core::fs_util::write_atomic(catalog_path, catalog).map_err(|e| format!("Failed to write catalog: {}", e))
+    })();
+
+    match result {
+        Ok(()) => {
+            for (file, _) in repacked {
+                let _ = std::fs::remove_file(replaced(file));
+            }
+            Ok(())
+        }
+        Err(e) => {
+            log.undo();
+            discard_staged();
+            Err(e)
+        }
+    }
+}
+
+fn repack_sheet(loader: &SpriteLoader, sheet: &SheetPlan, referenced: &BTreeSet<u32>) -> Result<Vec<u8>, String> {
+    let mut tiles = Vec::new();
+    let (mut tile_w, mut tile_h) = (32, 32);
+    for sprite_id in sheet.first_sprite_id..=sheet.last_sprite_id {
+        let sprite = loader.get_sprite(sprite_id).map_err(|e| format!("Failed to read sprite {} from {}: {}", sprite_id, sheet.file, e))?;
+        (tile_w, tile_h) = (sprite.width, sprite.height);
+        if referenced.contains(&sprite_id) {
+            tiles.push((*sprite.data).clone());
+        } else {
+            tiles.push(vec![0; sprite.data.len()]);
+        }
+    }
+
+    let (bmp, _, _) = build_sheet_bmp(&tiles, tile_w, tile_h, SHEET_SIZE / tile_w)?;
+    let compressed = lzma::compress(&bmp).map_err(|e| format!("Failed to LZMA-compress {}: {}", sheet.file, e))?;
+    Ok(wrap_cip_lzma(&compressed))
+}
+
+/// Find sprites with identical pixels and point every reference at one canonical id.
+#[tauri::command]
+pub async fn deduplicate_sprites(dry_run: bool, state: State<'_, AppState>) -> Result<SpriteDedupResult, String> {
+    deduplicate_loaded_sprites(&state, dry_run)
+}
+
+/// Report (dry run) or drop/repack catalog sheets holding sprites no appearance references.
+#[tauri::command]
+pub async fn collect_unused_sprites(assets_dir: String, catalog_path: String, dry_run: bool, state: State<'_, AppState>) -> Result<SpriteGcResult, String> {
+    collect_unused_catalog_sprites(&state, &assets_dir, &catalog_path, dry_run)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::protobuf::{Appearance, FrameGroup, SpriteInfo};
+
+    fn appearance(id: u32, sprite_ids: Vec<u32>) -> Appearance {
+        Appearance {
+            id: Some(id),
+            frame_group: vec![FrameGroup {
+                sprite_info: Some(SpriteInfo {
+                    sprite_id: sprite_ids,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn duplicates_group_under_lowest_id() {
+        let red = [255u8, 0, 0, 255];
+        let blue = [0u8, 0, 255, 255];
+        let sprites: Vec<(u32, u32, u32, &[u8])> = vec![(9, 1, 1, &red), (4, 1, 1, &red), (5, 1, 1, &blue), (7, 1, 1, &red)];
+        assert_eq!(
+            find_duplicate_groups(&sprites),
+            vec![DuplicateSpriteGroup {
+                canonical: 4,
+                duplicates: vec![7, 9],
+            }]
+        );
+    }
+
+    #[test]
+    fn rewrite_records_only_touched_appearances() {
+        let mut appearances = Appearances {
+            object: vec![appearance(100, vec![4, 7, 9]), appearance(101, vec![5])],
+            outfit: vec![appearance(1, vec![9])],
+            ..Default::default()
+        };
+        let remap: HashMap<u32, u32> = [(7, 4), (9, 4)].into_iter().collect();
+
+        let (changes, rewritten) = rewrite_sprite_references(&mut appearances, &remap);
+        assert_eq!(rewritten, 3);
+        assert_eq!(changes.iter().map(|(c, list)| (c.clone(), list.len())).collect::<Vec<_>>(), vec![(AppearanceCategory::Objects, 1), (AppearanceCategory::Outfits, 1)]);
+        assert_eq!(appearances.object[0].frame_group[0].sprite_info.as_ref().unwrap().sprite_id, vec![4, 4, 4]);
+    }
+
+    #[test]
+    fn sheets_are_kept_repacked_or_removed() {
+        let entry = |file: &str, first: u32, last: u32| SpriteCatalogEntry {
+            entry_type: "sprite".to_string(),
+            file: file.to_string(),
+            sprite_type: Some(0),
+            first_sprite_id: Some(first),
+            last_sprite_id: Some(last),
+            area: None,
+        };
+        let entries = vec![entry("a.cwm", 1, 3), entry("b.cwm", 4, 6), entry("c.cwm", 7, 9)];
+        let referenced: BTreeSet<u32> = [1, 2, 3, 5].into_iter().collect();
+
+        let actions: Vec<(SheetAction, usize)> = plan_sheets(&entries, &referenced).iter().map(|s| (s.action, s.unused)).collect();
+        assert_eq!(actions, vec![(SheetAction::Keep, 0), (SheetAction::Repack, 2), (SheetAction::Remove, 3)]);
+    }
+
+    #[test]
+    fn failed_catalog_write_restores_every_sheet() {
+        let dir = std::env::temp_dir().join(format!("sprite-gc-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("b.cwm"), b"old b").unwrap();
+        std::fs::write(dir.join("c.cwm"), b"old c").unwrap();
+        let removed: BTreeSet<&str> = ["c.cwm"].into_iter().collect();
+        let repacked = vec![("b.cwm".to_string(), b"new b".to_vec())];
+
+        // The catalog's directory doesn't exist, so the last step fails.
+        assert!(apply_sheet_changes(&dir, &removed, &repacked, &dir.join("missing").join("catalog.json"), b"[]").is_err());
+        assert_eq!(std::fs::read(dir.join("b.cwm")).unwrap(), b"old b");
+        assert_eq!(std::fs::read(dir.join("c.cwm")).unwrap(), b"old c");
+        assert!(!dir.join("b.cwm.gc-new").exists() && !dir.join("b.cwm.gc-old").exists());
+
+        apply_sheet_changes(&dir, &removed, &repacked, &dir.join("catalog.json"), b"[]").unwrap();
+        assert_eq!(std::fs::read(dir.join("b.cwm")).unwrap(), b"new b");
+        assert!(!dir.join("c.cwm").exists() && dir.join(GC_BACKUP_DIR).join("c.cwm").exists());
+        assert!(!dir.join("b.cwm.gc-old").exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
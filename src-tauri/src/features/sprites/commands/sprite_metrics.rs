@@ -0,0 +1,376 @@
+// Sprite metrics (bounding_square / is_opaque / bounding_box_per_direction)
+// Recomputes the `SpriteInfo` fields the client uses for picking and drawing
+// order from the decoded pixels, and reports where the stored values differ.
+
+use super::animation_export::{load_sprite_image, sprite_slot};
+use crate::core::protobuf::{Box as SpriteBox, SpriteInfo};
+use crate::features::appearances::commands::helpers::{get_index_for_category, get_items_by_category, get_items_by_category_mut};
+use crate::features::appearances::commands::{record_changes, AppearanceChange};
+use crate::features::appearances::AppearanceCategory;
+use crate::state::AppState;
+use image::RgbaImage;
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use tauri::State;
+
+const CATEGORIES: [AppearanceCategory; 4] = [AppearanceCategory::Objects, AppearanceCategory::Outfits, AppearanceCategory::Effects, AppearanceCategory::Missiles];
+
+/// Size, visible area and opacity of one decoded sprite.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpritePixelStats {
+    pub width: u32,
+    pub height: u32,
+    /// `[x0, y0, x1, y1)` of the non-transparent pixels, `None` when fully transparent.
+    pub visible: Option<[u32; 4]>,
+    pub opaque: bool,
+}
+
+impl SpritePixelStats {
+    pub fn from_image(image: &RgbaImage) -> Self {
+        let mut visible: Option<[u32; 4]> = None;
+        let mut opaque = true;
+        for (x, y, pixel) in image.enumerate_pixels() {
+            let alpha = pixel.0[3];
+            if alpha != 0xFF {
+                opaque = false;
+            }
+            if alpha == 0 {
+                continue;
+            }
+            visible = Some(match visible {
+                Some([x0, y0, x1, y1]) => [x0.min(x), y0.min(y), x1.max(x + 1), y1.max(y + 1)],
+                None => [x, y, x + 1, y + 1],
+            });
+        }
+        Self {
+            width: image.width(),
+            height: image.height(),
+            visible,
+            opaque: opaque && image.width() > 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpriteMetrics {
+    pub bounding_square: Option<u32>,
+    pub is_opaque: Option<bool>,
+    pub bounding_box_per_direction: Vec<SpriteBox>,
+}
+
+impl SpriteMetrics {
+    fn stored(info: &SpriteInfo) -> Self {
+        Self {
+            bounding_square: info.bounding_square,
+            is_opaque: info.is_opaque,
+            bounding_box_per_direction: info.bounding_box_per_direction.clone(),
+        }
+    }
+
+    /// Compares as the client reads the fields: a missing `is_opaque` is false,
+    /// and missing boxes (or box fields) are empty.
+    fn matches(&self, other: &Self) -> bool {
+        let empty = SpriteBox::default();
+        let boxes = self.bounding_box_per_direction.len().max(other.bounding_box_per_direction.len());
+        let same_box = |a: &SpriteBox, b: &SpriteBox| [a.x, a.y, a.width, a.height].map(|v| v.unwrap_or(0)) == [b.x, b.y, b.width, b.height].map(|v| v.unwrap_or(0));
+        self.bounding_square == other.bounding_square
+            && self.is_opaque.unwrap_or(false) == other.is_opaque.unwrap_or(false)
+            && (0..boxes).all(|i| same_box(self.bounding_box_per_direction.get(i).unwrap_or(&empty), other.bounding_box_per_direction.get(i).unwrap_or(&empty)))
+    }
+
+    fn apply(&self, info: &mut SpriteInfo) {
+        info.bounding_square = self.bounding_square;
+        info.is_opaque = self.is_opaque;
+        info.bounding_box_per_direction = self.bounding_box_per_direction.clone();
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpriteMetricsMismatch {
+    pub category: AppearanceCategory,
+    pub appearance_id: u32,
+    pub frame_group: usize,
+    pub stored: SpriteMetrics,
+    pub computed: SpriteMetrics,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpriteMetricsReport {
+    pub frame_groups_checked: usize,
+    pub mismatches: Vec<SpriteMetricsMismatch>,
+    pub applied: bool,
+}
+
+/// Computes the metrics of one frame group. Sprites are anchored bottom-right
+/// in a canvas as large as the biggest sprite, like the client draws them; one
+/// box is produced per pattern-x direction, covering every layer, pattern and phase.
+pub fn compute_group_metrics(info: &SpriteInfo, stats: &dyn Fn(u32) -> Option<SpritePixelStats>) -> SpriteMetrics {
+    let layers = info.layers.unwrap_or(1).max(1);
+    let pw = info.pattern_width.unwrap_or(1).max(1);
+    let ph = info.pattern_height.unwrap_or(1).max(1);
+    let pd = info.pattern_depth.unwrap_or(1).max(1);
+    let phases = info.animation.as_ref().map(|a| a.sprite_phase.len() as u32).filter(|count| *count > 0).unwrap_or(1);
+
+    let sprite_stats = |slot: usize| info.sprite_id.get(slot).copied().filter(|id| *id != 0).and_then(stats);
+    let all: Vec<SpritePixelStats> = info.sprite_id.iter().enumerate().filter_map(|(slot, _)| sprite_stats(slot)).collect();
+    if all.is_empty() {
+        return SpriteMetrics::default();
+    }
+    let canvas_w = all.iter().map(|s| s.width).max().unwrap_or(0);
+    let canvas_h = all.iter().map(|s| s.height).max().unwrap_or(0);
+
+    let mut boxes: Vec<Option<[u32; 4]>> = vec![None; pw as usize];
+    let mut opaque = true;
+    for x in 0..pw {
+        for layer in 0..layers {
+            for y in 0..ph {
+                for z in 0..pd {
+                    for phase in 0..phases {
+                        let Some(sprite) = sprite_stats(sprite_slot(info, layer, x, y, z, phase)) else {
+                            if layer == 0 {
+                                opaque = false;
+                            }
+                            continue;
+                        };
+                        if layer == 0 && !sprite.opaque {
+                            opaque = false;
+                        }
+                        let Some([x0, y0, x1, y1]) = sprite.visible else {
+                            continue;
+                        };
+                        let (dx, dy) = (canvas_w - sprite.width, canvas_h - sprite.height);
+                        let area = [x0 + dx, y0 + dy, x1 + dx, y1 + dy];
+                        let merged = &mut boxes[x as usize];
+                        *merged = Some(match *merged {
+                            Some([a0, b0, a1, b1]) => [a0.min(area[0]), b0.min(area[1]), a1.max(area[2]), b1.max(area[3])],
+                            None => area,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    // Side of the bottom-right anchored square that holds every visible pixel.
+    let bounding_square = boxes.iter().flatten().map(|[x0, y0, _, _]| (canvas_w - x0).max(canvas_h - y0)).max();
+
+    SpriteMetrics {
+        bounding_square,
+        is_opaque: Some(opaque),
+        bounding_box_per_direction: boxes
+            .into_iter()
+            .map(|area| {
+                let [x0, y0, x1, y1] = area.unwrap_or_default();
+                SpriteBox {
+                    x: Some(x0),
+                    y: Some(y0),
+                    width: Some(x1 - x0),
+                    height: Some(y1 - y0),
+                }
+            })
+            .collect(),
+    }
+}
+
+/// Recomputes metrics for one appearance, one category or (both `None`) the
+/// whole file. With `apply`, mismatching groups are updated in one journal entry
+/// per category.
+pub fn compute_appearance_metrics(state: &AppState, category: Option<AppearanceCategory>, appearance_id: Option<u32>, apply: bool) -> Result<SpriteMetricsReport, String> {
+    if appearance_id.is_some() && category.is_none() {
+        return Err("An appearance id needs a category".to_string());
+    }
+    let categories: Vec<AppearanceCategory> = match &category {
+        Some(category) => vec![category.clone()],
+        None => CATEGORIES.to_vec(),
+    };
+
+    // Snapshot the sprite infos so pixels are decoded without holding the lock.
+    let targets: Vec<(AppearanceCategory, u32, usize, SpriteInfo)> = {
+        let appearances_lock = state.appearances.read();
+        let appearances = appearances_lock.as_ref().ok_or_else(|| "No appearances loaded".to_string())?;
+        let mut targets = Vec::new();
+        for category in &categories {
+            for appearance in get_items_by_category(appearances, category) {
+                let id = appearance.id.unwrap_or(0);
+                if appearance_id.is_some_and(|wanted| wanted != id) {
+                    continue;
+                }
+                for (index, fg) in appearance.frame_group.iter().enumerate() {
+                    if let Some(info) = &fg.sprite_info {
+                        targets.push((category.clone(), id, index, info.clone()));
+                    }
+                }
+            }
+        }
+        if let Some(id) = appearance_id {
+            if targets.is_empty() {
+                return Err(format!("Appearance {} not found in {:?} or has no sprites", id, category.unwrap_or(AppearanceCategory::Objects)));
+            }
+        }
+        targets
+    };
+
+    let sprite_ids: BTreeSet<u32> = targets.iter().flat_map(|(_, _, _, info)| info.sprite_id.iter().copied()).filter(|id| *id != 0).collect();
+    let stats: HashMap<u32, SpritePixelStats> = {
+        let sprite_loader_lock = state.sprite_loader.read();
+        let loader = sprite_loader_lock.as_ref().ok_or_else(|| "No sprites loaded".to_string())?;
+        sprite_ids.par_iter().filter_map(|id| load_sprite_image(state, loader, *id).map(|image| (*id, SpritePixelStats::from_image(&image)))).collect()
+    };
+    let lookup = |id: u32| stats.get(&id).copied();
+
+    let mismatches: Vec<SpriteMetricsMismatch> = targets
+        .iter()
+        .filter_map(|(category, id, index, info)| {
+            let computed = compute_group_metrics(info, &lookup);
+            let stored = SpriteMetrics::stored(info);
+            (!computed.matches(&stored)).then(|| SpriteMetricsMismatch {
+                category: category.clone(),
+                appearance_id: *id,
+                frame_group: *index,
+                stored,
+                computed,
+            })
+        })
+        .collect();
+
+    let applied = apply && !mismatches.is_empty();
+    if applied {
+        let mut appearances_lock = state.appearances.write();
+        let appearances = appearances_lock.as_mut().ok_or_else(|| "No appearances loaded".to_string())?;
+        for category in &categories {
+            let mut by_appearance: BTreeMap<u32, Vec<&SpriteMetricsMismatch>> = BTreeMap::new();
+            for mismatch in mismatches.iter().filter(|m| &m.category == category) {
+                by_appearance.entry(mismatch.appearance_id).or_default().push(mismatch);
+            }
+            let index = get_index_for_category(state, category);
+            let items = get_items_by_category_mut(appearances, category);
+            let mut changes = Vec::new();
+            for (id, group_mismatches) in by_appearance {
+                let Some(appearance) = index.get(&id).and_then(|position| items.get_mut(*position)) else {
+                    continue;
+                };
+                let before = appearance.clone();
+                for mismatch in group_mismatches {
+                    if let Some(info) = appearance.frame_group.get_mut(mismatch.frame_group).and_then(|fg| fg.sprite_info.as_mut()) {
+                        mismatch.computed.apply(info);
+                    }
+                }
+                changes.push(AppearanceChange {
+                    before: Some(before),
+                    after: Some(appearance.clone()),
+                });
+            }
+            if !changes.is_empty() {
+                record_changes(state, "Recompute sprite metrics", category, changes);
+            }
+        }
+    }
+
+    Ok(SpriteMetricsReport {
+        frame_groups_checked: targets.len(),
+        mismatches,
+        applied,
+    })
+}
+
+/// Recompute bounding_square / is_opaque / bounding_box_per_direction from the
+/// sprite pixels. Without `apply` this only reports what differs.
+#[tauri::command]
+pub async fn compute_sprite_metrics(category: Option<AppearanceCategory>, appearance_id: Option<u32>, apply: bool, state: State<'_, AppState>) -> Result<SpriteMetricsReport, String> {
+    compute_appearance_metrics(&state, category, appearance_id, apply)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    fn stats_for(image: &RgbaImage) -> SpritePixelStats {
+        SpritePixelStats::from_image(image)
+    }
+
+    #[test]
+    fn pixel_stats_find_visible_area() {
+        let mut image = RgbaImage::new(32, 32);
+        image.put_pixel(4, 6, Rgba([1, 1, 1, 255]));
+        image.put_pixel(10, 20, Rgba([1, 1, 1, 128]));
+        let stats = stats_for(&image);
+        assert_eq!(stats.visible, Some([4, 6, 11, 21]));
+        assert!(!stats.opaque);
+        assert!(stats_for(&RgbaImage::from_pixel(32, 32, Rgba([0, 0, 0, 255]))).opaque);
+    }
+
+    #[test]
+    fn group_metrics_per_direction() {
+        // Two directions: a full opaque tile and a small sprite in the lower-right corner.
+        let full = stats_for(&RgbaImage::from_pixel(32, 32, Rgba([9, 9, 9, 255])));
+        let mut corner = RgbaImage::new(32, 32);
+        corner.put_pixel(30, 31, Rgba([1, 1, 1, 255]));
+        let corner = stats_for(&corner);
+        let info = SpriteInfo {
+            pattern_width: Some(2),
+            sprite_id: vec![1, 2],
+            ..Default::default()
+        };
+
+        let metrics = compute_group_metrics(&info, &|id| {
+            Some(if id == 1 {
+                full
+            } else {
+                corner
+            })
+        });
+        assert_eq!(metrics.bounding_square, Some(32));
+        assert_eq!(metrics.is_opaque, Some(false));
+        assert_eq!(
+            metrics.bounding_box_per_direction[1],
+            SpriteBox {
+                x: Some(30),
+                y: Some(31),
+                width: Some(1),
+                height: Some(1),
+            }
+        );
+    }
+
+    #[test]
+    fn unset_fields_match_their_defaults() {
+        let empty_box = SpriteBox {
+            x: Some(0),
+            y: Some(0),
+            width: Some(0),
+            height: Some(0),
+        };
+        let computed = SpriteMetrics {
+            bounding_square: Some(32),
+            is_opaque: Some(false),
+            bounding_box_per_direction: vec![empty_box, empty_box],
+        };
+        let stored = SpriteMetrics {
+            bounding_square: Some(32),
+            ..Default::default()
+        };
+        assert!(computed.matches(&stored));
+
+        let visible = SpriteMetrics {
+            bounding_box_per_direction: vec![
+                empty_box,
+                SpriteBox {
+                    width: Some(4),
+                    ..Default::default()
+                },
+            ],
+            ..computed.clone()
+        };
+        assert!(!visible.matches(&stored));
+        assert!(!SpriteMetrics {
+            is_opaque: Some(true),
+            ..computed
+        }
+        .matches(&stored));
+    }
+}
@@ -1,10 +1,13 @@
+use crate::core::disk_cache::DiskCacheStats;
+use crate::core::jobs::{JobHandle, JOB_CANCELLED};
 use crate::core::protobuf::Appearance;
 use crate::features::appearances::AppearanceCategory;
+use crate::features::jobs::run_job;
 use crate::features::sprites::parsers::SpriteLoader;
 use crate::state::AppState;
 use std::collections::HashMap;
 use std::path::PathBuf;
-use tauri::State;
+use tauri::{AppHandle, State};
 use rayon::prelude::*;
 
 #[inline]
@@ -19,20 +22,31 @@ fn resolve_sprite_bytes(state: &AppState, sprite_loader: &SpriteLoader, sprite_i
         return Ok((*cached_png).clone());
     }
 
-    // 3. Cache miss: decode + encode + cache
+    // 3. Check disk cache (survives restarts; keyed by the sheet's content hash)
+    let disk_cache_lock = state.sprite_disk_cache.read();
+    let disk_key = disk_cache_lock.as_ref().and_then(|cache| Some((cache, cache.source_hash(&sprite_loader.sprite_source(sprite_id)?)?)));
+    if let Some(png_bytes) = disk_key.and_then(|(cache, source_hash)| cache.get(source_hash, sprite_id)) {
+        state.png_cache.insert(sprite_id, png_bytes.clone());
+        return Ok(png_bytes);
+    }
+
+    // 4. Cache miss: decode + encode + cache
     let sprite = sprite_loader.get_sprite(sprite_id).map_err(|e| format!("Failed to get sprite {}: {}", sprite_id, e))?;
     let png_bytes = sprite.to_png_bytes().map_err(|e| format!("Failed to convert sprite to PNG: {}", e))?;
+    if let Some((cache, source_hash)) = disk_key {
+        cache.put(source_hash, sprite_id, &png_bytes);
+    }
     state.png_cache.insert(sprite_id, png_bytes.clone());
     Ok(png_bytes)
 }
 
 #[inline]
-fn resolve_imported_sprite_bytes(state: &AppState, sprite_id: u32) -> Option<Vec<u8>> {
+pub(super) fn resolve_imported_sprite_bytes(state: &AppState, sprite_id: u32) -> Option<Vec<u8>> {
     state.imported_sprites.get(&sprite_id).map(|entry| entry.clone())
 }
 
 #[inline(always)]
-fn resolve_appearance<'a>(state: &'a AppState, items: &'a [Appearance], category: &AppearanceCategory, appearance_id: u32) -> Option<&'a Appearance> {
+pub(super) fn resolve_appearance<'a>(state: &'a AppState, items: &'a [Appearance], category: &AppearanceCategory, appearance_id: u32) -> Option<&'a Appearance> {
     let index_map = match category {
         AppearanceCategory::Objects => &state.object_index,
         AppearanceCategory::Outfits => &state.outfit_index,
@@ -64,27 +78,34 @@ pub async fn load_sprites_catalog(catalog_path: String, assets_dir: String, stat
 }
 
 /// Auto-detect and load sprites from Tibia directory
+/// Runs as a job: the sheet preload reports progress and can be cancelled.
 #[tauri::command]
-pub async fn auto_load_sprites(tibia_path: String, state: State<'_, AppState>) -> Result<usize, String> {
+pub async fn auto_load_sprites(tibia_path: String, app: AppHandle, state: State<'_, AppState>) -> Result<usize, String> {
+    run_job(&app, state.inner(), "load_sprites", |job| auto_load_sprites_impl(state.inner(), &tibia_path, job))
+}
+
+fn auto_load_sprites_impl(state: &AppState, tibia_path: &str, job: &JobHandle) -> Result<usize, String> {
     log::info!("Auto-loading sprites from Tibia directory: {}", tibia_path);
 
     // Force recompilation - First, try to find catalog-content.json in the project root
     let project_root = std::env::current_dir().map_err(|e| format!("Failed to get current directory: {}", e))?;
     let project_catalog_path = project_root.join("catalog-content.json");
 
-    let tibia_dir = PathBuf::from(&tibia_path);
+    let tibia_dir = PathBuf::from(tibia_path);
     let tibia_assets_dir = tibia_dir.join("assets");
 
     // Try catalog first (project root or Canary Studio)
     let sprite_loader = if project_catalog_path.exists() {
         log::info!("Found catalog-content.json in project root: {:?}", project_catalog_path);
-        SpriteLoader::new(&project_catalog_path, &project_root)
+        job.set_message("Reading sprite sheets");
+        SpriteLoader::new_with_job(&project_catalog_path, &project_root, job)
     } else {
         let tibia_catalog_path = tibia_assets_dir.join("catalog-content.json");
         log::info!("Looking for catalog in Canary Studio directory: {:?}", tibia_catalog_path);
 
         if tibia_catalog_path.exists() {
-            SpriteLoader::new(&tibia_catalog_path, &tibia_assets_dir)
+            job.set_message("Reading sprite sheets");
+            SpriteLoader::new_with_job(&tibia_catalog_path, &tibia_assets_dir, job)
         } else {
             log::warn!("catalog-content.json not found; trying legacy .spr detection in {:?}", tibia_dir);
             // Legacy OTC-style .spr files usually sit in the Tibia directory root
@@ -92,6 +113,9 @@ pub async fn auto_load_sprites(tibia_path: String, state: State<'_, AppState>) -
         }
     }
     .map_err(|e| {
+        if job.is_cancelled() {
+            return JOB_CANCELLED.to_string();
+        }
         log::error!("Failed to create SpriteLoader: {}", e);
         format!("Failed to auto-load sprites: {}", e)
     })?;
@@ -305,6 +329,20 @@ pub async fn clear_sprite_cache(state: State<'_, AppState>) -> Result<usize, Str
     Ok(cache_size + preview_size + png_size)
 }
 
+/// Get statistics of the persistent sprite disk cache (None when it could not be opened)
+#[tauri::command]
+pub async fn get_sprite_disk_cache_stats(state: State<'_, AppState>) -> Result<Option<DiskCacheStats>, String> {
+    Ok(state.sprite_disk_cache.read().as_ref().map(|cache| cache.stats()))
+}
+
+/// Delete every entry of the persistent sprite disk cache
+#[tauri::command]
+pub async fn clear_sprite_disk_cache(state: State<'_, AppState>) -> Result<usize, String> {
+    let removed = state.sprite_disk_cache.read().as_ref().map(|cache| cache.clear()).unwrap_or(0);
+    log::info!("Cleared sprite disk cache ({} entries)", removed);
+    Ok(removed)
+}
+
 /// Get sprite cache statistics
 /// Optimized: Lock-free cache statistics
 /// NOTE: DashMap doesn't implement IntoParallelRefIterator, so we use sequential iteration
@@ -101,12 +101,22 @@ impl LegacySpriteLoader {
         self.sheets.last().map(|s| s.end_id as usize).unwrap_or(0)
     }
 
+    #[inline]
+    pub fn contains_sprite(&self, sprite_id: u32) -> bool {
+        self.sheets.iter().any(|s| s.contains_id(sprite_id))
+    }
+
     #[inline]
     pub fn get_all_sprite_ids(&self) -> Vec<u32> {
         let total = self.sprite_count();
         (1..=total as u32).collect()
     }
 
+    /// Path of the `.spr` that contains `sprite_id`.
+    pub fn sheet_path(&self, sprite_id: u32) -> Option<&Path> {
+        self.sheets.iter().find(|s| s.contains_id(sprite_id)).map(|s| s.path.as_path())
+    }
+
     pub fn get_sprite(&self, sprite_id: u32) -> Result<TibiaSprite> {
         if let Some(sprite) = self.sprite_cache.get(&sprite_id) {
             return Ok((*sprite).as_ref().clone());
@@ -209,3 +219,209 @@ fn extract_numeric_suffix(path: &Path) -> Option<u32> {
         digits.parse::<u32>().ok()
     }
 }
+
+// ── Reading and writing (OTClient layout) ──────────────────────────────────────────────
+
+/// Bytes of one 32x32 RGBA tile.
+pub const LEGACY_TILE_BYTES: usize = (LEGACY_SPRITE_WIDTH * LEGACY_SPRITE_HEIGHT * 4) as usize;
+const LEGACY_COLOR_KEY: [u8; 3] = [0xFF, 0x00, 0xFF];
+
+/// RLE-encodes one 32x32 RGBA tile as `(transparent u16, colored u16, pixels...)` runs.
+/// Pixels are RGB, or RGBA when `transparency` is set; trailing transparency is dropped.
+pub fn encode_legacy_sprite_pixels(rgba: &[u8], transparency: bool) -> Vec<u8> {
+    let is_transparent = |pixel: &[u8]| {
+        if transparency {
+            pixel[3] == 0
+        } else {
+            pixel[3] < 0x80
+        }
+    };
+    let pixels: Vec<&[u8]> = rgba.chunks_exact(4).collect();
+
+    let mut out = Vec::new();
+    let mut index = 0;
+    while index < pixels.len() {
+        let transparent = pixels[index..].iter().take_while(|p| is_transparent(p)).count();
+        index += transparent;
+        if index >= pixels.len() {
+            break;
+        }
+        let colored = pixels[index..].iter().take_while(|p| !is_transparent(p)).count();
+        out.extend_from_slice(&(transparent as u16).to_le_bytes());
+        out.extend_from_slice(&(colored as u16).to_le_bytes());
+        for pixel in &pixels[index..index + colored] {
+            out.extend_from_slice(if transparency {
+                &pixel[..4]
+            } else {
+                &pixel[..3]
+            });
+        }
+        index += colored;
+    }
+    out
+}
+
+/// Builds a `.spr` file: signature, u32 count, offset table, then each sprite as
+/// colour key + u16 size + RLE data. `tiles[i]` becomes sprite id `i + 1`; empty
+/// tiles get offset 0 so the client treats them as blank.
+pub fn write_legacy_spr(signature: u32, tiles: &[Vec<u8>], transparency: bool) -> Result<Vec<u8>> {
+    let count = u32::try_from(tiles.len()).map_err(|_| anyhow!("Too many sprites for a legacy .spr"))?;
+    let header_len = 8 + tiles.len() * 4;
+    let mut offsets = Vec::with_capacity(tiles.len());
+    let mut body = Vec::new();
+
+    for (index, tile) in tiles.iter().enumerate() {
+        if tile.len() != LEGACY_TILE_BYTES {
+            return Err(anyhow!("Sprite {} is not a 32x32 RGBA tile", index + 1));
+        }
+        let encoded = encode_legacy_sprite_pixels(tile, transparency);
+        if encoded.is_empty() {
+            offsets.push(0u32);
+            continue;
+        }
+        let offset = u32::try_from(header_len + body.len()).map_err(|_| anyhow!("Legacy .spr exceeds 4 GiB"))?;
+        offsets.push(offset);
+        body.extend_from_slice(&LEGACY_COLOR_KEY);
+        body.extend_from_slice(&(encoded.len() as u16).to_le_bytes());
+        body.extend_from_slice(&encoded);
+    }
+
+    let mut out = Vec::with_capacity(header_len + body.len());
+    out.extend_from_slice(&signature.to_le_bytes());
+    out.extend_from_slice(&count.to_le_bytes());
+    for offset in offsets {
+        out.extend_from_slice(&offset.to_le_bytes());
+    }
+    out.extend_from_slice(&body);
+    Ok(out)
+}
+
+/// Inverse of [`encode_legacy_sprite_pixels`]: expands RLE runs into a 32x32 RGBA tile.
+pub fn decode_legacy_sprite_pixels(encoded: &[u8], transparency: bool) -> Result<Vec<u8>> {
+    let pixel_size = if transparency {
+        4
+    } else {
+        3
+    };
+    let mut out = vec![0u8; LEGACY_TILE_BYTES];
+    let mut cursor = Cursor::new(encoded);
+    let mut index = 0usize;
+    while (cursor.position() as usize) < encoded.len() {
+        let transparent = cursor.read_u16::<LittleEndian>().context("Failed to read transparent pixel count")? as usize;
+        let colored = cursor.read_u16::<LittleEndian>().context("Failed to read colored pixel count")? as usize;
+        index += transparent;
+        if (index + colored) * 4 > LEGACY_TILE_BYTES {
+            return Err(anyhow!("Sprite pixels overflow the 32x32 tile"));
+        }
+        for _ in 0..colored {
+            let mut pixel = [0xFFu8; 4];
+            cursor.read_exact(&mut pixel[..pixel_size]).context("Failed to read sprite pixel")?;
+            out[index * 4..index * 4 + 4].copy_from_slice(&pixel);
+            index += 1;
+        }
+    }
+    Ok(out)
+}
+
+/// A `.spr` in the layout written by [`write_legacy_spr`], read from memory.
+/// Unlike [`LegacySpriteSheet`] the signature is only recorded, not checked,
+/// since it differs per client version.
+pub struct LegacySprFile<'a> {
+    pub signature: u32,
+    data: &'a [u8],
+    offsets: Vec<u32>,
+    transparency: bool,
+}
+
+impl<'a> LegacySprFile<'a> {
+    pub fn parse(data: &'a [u8], transparency: bool) -> Result<Self> {
+        let mut cursor = Cursor::new(data);
+        let signature = cursor.read_u32::<LittleEndian>().context("Failed to read legacy signature")?;
+        let count = cursor.read_u32::<LittleEndian>().context("Failed to read legacy sprite count")?;
+        if 8 + count as u64 * 4 > data.len() as u64 {
+            return Err(anyhow!("Legacy .spr offset table for {} sprites is truncated", count));
+        }
+        let offsets = (0..count).map(|_| cursor.read_u32::<LittleEndian>()).collect::<std::io::Result<Vec<u32>>>().context("Failed to read sprite offset")?;
+        Ok(Self {
+            signature,
+            data,
+            offsets,
+            transparency,
+        })
+    }
+
+    pub fn sprite_count(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// RGBA pixels of sprite `id` (1-based); id 0 and offset 0 are blank.
+    pub fn tile(&self, id: u32) -> Result<Vec<u8>> {
+        if id == 0 {
+            return Ok(vec![0u8; LEGACY_TILE_BYTES]);
+        }
+        let offset = *self.offsets.get(id as usize - 1).ok_or_else(|| anyhow!("Sprite {} is past the end of the .spr ({} sprites)", id, self.offsets.len()))? as usize;
+        if offset == 0 {
+            return Ok(vec![0u8; LEGACY_TILE_BYTES]);
+        }
+        // Colour key, then the u16 size of the RLE data.
+        let header = self.data.get(offset..offset + 5).ok_or_else(|| anyhow!("Sprite {} offset {} out of bounds", id, offset))?;
+        let size = u16::from_le_bytes([header[3], header[4]]) as usize;
+        let encoded = self.data.get(offset + 5..offset + 5 + size).ok_or_else(|| anyhow!("Sprite {} data is truncated", id))?;
+        decode_legacy_sprite_pixels(encoded, self.transparency).with_context(|| format!("Failed to decode sprite {}", id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tile_with(pixels: &[(usize, [u8; 4])]) -> Vec<u8> {
+        let mut tile = vec![0u8; LEGACY_TILE_BYTES];
+        for (index, color) in pixels {
+            tile[index * 4..index * 4 + 4].copy_from_slice(color);
+        }
+        tile
+    }
+
+    #[test]
+    fn rle_skips_transparent_runs() {
+        let tile = tile_with(&[(2, [10, 20, 30, 255]), (3, [40, 50, 60, 255]), (10, [1, 2, 3, 200])]);
+        let encoded = encode_legacy_sprite_pixels(&tile, false);
+        assert_eq!(encoded, vec![2, 0, 2, 0, 10, 20, 30, 40, 50, 60, 6, 0, 1, 0, 1, 2, 3]);
+
+        let with_alpha = encode_legacy_sprite_pixels(&tile, true);
+        assert_eq!(&with_alpha[4..8], &[10, 20, 30, 255]);
+    }
+
+    #[test]
+    fn spr_layout_has_offsets_and_blank_tiles() {
+        let tiles = vec![vec![0u8; LEGACY_TILE_BYTES], tile_with(&[(0, [255, 0, 0, 255])])];
+        let spr = write_legacy_spr(0x57BB_D603, &tiles, false).unwrap();
+        assert_eq!(&spr[0..4], &0x57BB_D603u32.to_le_bytes());
+        assert_eq!(&spr[4..8], &2u32.to_le_bytes());
+        assert_eq!(&spr[8..12], &0u32.to_le_bytes());
+        assert_eq!(&spr[12..16], &16u32.to_le_bytes());
+        assert_eq!(&spr[16..], &[0xFF, 0x00, 0xFF, 7, 0, 0, 0, 1, 0, 255, 0, 0]);
+    }
+
+    #[test]
+    fn spr_reads_back_what_was_written() {
+        let tiles = vec![tile_with(&[(5, [9, 8, 7, 255]), (1023, [1, 2, 3, 128])]), vec![0u8; LEGACY_TILE_BYTES]];
+        for transparency in [false, true] {
+            let spr = write_legacy_spr(0x57BB_D603, &tiles, transparency).unwrap();
+            let file = LegacySprFile::parse(&spr, transparency).unwrap();
+            assert_eq!(file.signature, 0x57BB_D603);
+            assert_eq!(file.sprite_count(), 2);
+            let tile = file.tile(1).unwrap();
+            assert_eq!(&tile[5 * 4..6 * 4], &[9, 8, 7, 255]);
+            let last = if transparency {
+                [1, 2, 3, 128]
+            } else {
+                [1, 2, 3, 255]
+            };
+            assert_eq!(&tile[1023 * 4..], &last);
+            assert_eq!(file.tile(2).unwrap(), tiles[1]);
+            assert!(file.tile(3).is_err());
+        }
+    }
+}
@@ -12,6 +12,7 @@ use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
 
 use super::legacy::LegacySpriteLoader;
+use crate::core::jobs::{JobHandle, JOB_CANCELLED};
 
 /// Represents a sprite from Tibia
 /// Uses Arc for zero-copy sharing of sprite data
@@ -117,6 +118,12 @@ impl SpriteCatalog {
         self.sprite_map.get(&sprite_id).and_then(|&index| self.entries.get(index))
     }
 
+    /// Whether any catalog entry covers `sprite_id` (no decoding)
+    #[inline]
+    pub fn contains_sprite(&self, sprite_id: u32) -> bool {
+        self.sprite_map.contains_key(&sprite_id)
+    }
+
     /// Get all sprite IDs available in the catalog
     pub fn get_all_sprite_ids(&self) -> Vec<u32> {
         let mut ids: Vec<u32> = self.sprite_map.keys().copied().collect();
@@ -157,20 +164,30 @@ impl CatalogBackend {
     }
 
     /// Preload all CWM files into RAM (still compressed). Eliminates disk I/O on sprite access.
-    fn preload_all_files(&self) {
+    fn preload_all_files(&self, job: &JobHandle) -> Result<()> {
         let filenames: Vec<String> = self.catalog.entries.iter().map(|e| e.file.clone()).collect();
+        job.set_total(filenames.len());
         let loaded: Vec<(String, Arc<Vec<u8>>)> = filenames
             .par_iter()
             .filter_map(|filename| {
+                if job.is_cancelled() {
+                    return None;
+                }
                 let path = self.assets_dir.join(filename);
-                fs::read(&path).ok().map(|data| (filename.clone(), Arc::new(data)))
+                let data = fs::read(&path).ok().map(|data| (filename.clone(), Arc::new(data)));
+                job.advance(1);
+                data
             })
             .collect();
+        if job.is_cancelled() {
+            return Err(anyhow!(JOB_CANCELLED));
+        }
         let count = loaded.len();
         for (name, data) in loaded {
             self.preloaded_files.insert(name, data);
         }
         log::info!("Preloaded {} sprite files into RAM", count);
+        Ok(())
     }
 
     fn load_sprite_sheet_for_entry(&self, entry: &SpriteCatalogEntry) -> Result<Vec<TibiaSprite>> {
@@ -387,6 +404,12 @@ impl SpriteLoader {
     /// Create a new sprite loader with catalog and assets directory.
     /// Preloads all CWM files into RAM (compressed) to eliminate disk I/O.
     pub fn new<P: AsRef<Path>>(catalog_path: P, assets_dir: P) -> Result<Self> {
+        Self::new_with_job(catalog_path, assets_dir, &JobHandle::detached("load_sprites"))
+    }
+
+    /// Same as [`SpriteLoader::new`], reporting the sheet preload to `job` and
+    /// stopping early when it is cancelled.
+    pub fn new_with_job<P: AsRef<Path>>(catalog_path: P, assets_dir: P, job: &JobHandle) -> Result<Self> {
         let catalog = SpriteCatalog::load(catalog_path)?;
         let assets_dir = assets_dir.as_ref().to_path_buf();
 
@@ -396,7 +419,7 @@ impl SpriteLoader {
             sprite_cache: DashMap::new(),
             preloaded_files: DashMap::new(),
         };
-        backend.preload_all_files();
+        backend.preload_all_files(job)?;
 
         Ok(SpriteLoader {
             backend: SpriteBackend::Catalog(backend),
@@ -436,6 +459,15 @@ impl SpriteLoader {
         }
     }
 
+    /// Cheap existence check for `sprite_id`, without loading the sprite sheet.
+    #[inline]
+    pub fn contains_sprite(&self, sprite_id: u32) -> bool {
+        match &self.backend {
+            SpriteBackend::Catalog(backend) => backend.catalog.contains_sprite(sprite_id),
+            SpriteBackend::Legacy(loader) => loader.contains_sprite(sprite_id),
+        }
+    }
+
     #[inline]
     pub fn sprite_count(&self) -> usize {
         match &self.backend {
@@ -451,6 +483,15 @@ impl SpriteLoader {
             SpriteBackend::Legacy(loader) => loader.get_all_sprite_ids(),
         }
     }
+
+    /// File holding `sprite_id` (catalog sheet or legacy `.spr`), used to detect
+    /// which sprites changed on disk.
+    pub fn sprite_source(&self, sprite_id: u32) -> Option<PathBuf> {
+        match &self.backend {
+            SpriteBackend::Catalog(backend) => backend.catalog.get_entry_for_sprite(sprite_id).map(|entry| backend.assets_dir.join(&entry.file)),
+            SpriteBackend::Legacy(loader) => loader.sheet_path(sprite_id).map(Path::to_path_buf),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -109,6 +109,7 @@ fn write_with_format<P: AsRef<Path>>(path: P, buf: Vec<u8>) -> Result<()> {
         StaticDataFormat::Lzma => crate::core::lzma::compress(&buf).context("Failed to LZMA-compress staticdata")?,
     };
 
+    crate::core::backups::snapshot_before_write(path);
     fs::write(path, &out).context(format!("Failed to write staticdata file: {:?}", path))?;
     log::info!("StaticData saved ({:?}), on-disk: {} bytes", format, out.len());
     Ok(())
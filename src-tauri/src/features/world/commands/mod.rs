@@ -0,0 +1,3 @@
+mod world;
+
+pub use world::*;
@@ -0,0 +1,78 @@
+// Tauri commands for the OTBM world model
+
+use std::path::Path;
+use std::sync::Arc;
+use tauri::{AppHandle, State};
+
+use crate::features::jobs::{finish_job, start_job};
+use crate::features::world::parsers::{House, MapTile, Region, Spawn, SpawnKind, Town, Waypoint, World, WorldSummary};
+use crate::state::AppState;
+
+/// Largest region `get_world_tiles` returns in one call (tiles).
+pub const MAX_REGION_TILES: u64 = 512 * 512;
+
+/// The loaded world, cloned out of the lock so long reads don't block a reload.
+pub fn loaded_world(state: &AppState) -> Result<Arc<World>, String> {
+    state.world.read().clone().ok_or_else(|| "No world map loaded".to_string())
+}
+
+/// Index an `.otbm` (and its house/spawn XML files) as the shared world model.
+/// The indexing pass reads the whole file, so it runs on a blocking thread.
+#[tauri::command]
+pub async fn load_world(path: String, app: AppHandle, state: State<'_, AppState>) -> Result<WorldSummary, String> {
+    let job = start_job(&app, state.inner(), "world_load");
+    let worker_job = job.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || World::open(Path::new(&path), &worker_job).map_err(|e| format!("Failed to load world map: {}", e)))
+        .await
+        .map_err(|e| format!("World load task failed: {}", e))
+        .and_then(|result| result);
+    let world = finish_job(state.inner(), &job, result)?;
+    let summary = world.summary();
+    *state.world.write() = Some(Arc::new(world));
+    Ok(summary)
+}
+
+#[tauri::command]
+pub async fn unload_world(state: State<'_, AppState>) -> Result<(), String> {
+    *state.world.write() = None;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_world_summary(state: State<'_, AppState>) -> Result<WorldSummary, String> {
+    Ok(loaded_world(&state)?.summary())
+}
+
+/// Tiles of one floor inside an inclusive rectangle.
+#[tauri::command]
+pub async fn get_world_tiles(region: Region, state: State<'_, AppState>) -> Result<Vec<MapTile>, String> {
+    if region.x_max < region.x_min || region.y_max < region.y_min {
+        return Err("Invalid region".to_string());
+    }
+    if region.tile_count() > MAX_REGION_TILES {
+        return Err(format!("Region too large ({} tiles, max {})", region.tile_count(), MAX_REGION_TILES));
+    }
+    loaded_world(&state)?.tiles_in(&region).map_err(|e| format!("Failed to read tiles: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_world_towns(state: State<'_, AppState>) -> Result<Vec<Town>, String> {
+    Ok(loaded_world(&state)?.towns().to_vec())
+}
+
+#[tauri::command]
+pub async fn get_world_waypoints(state: State<'_, AppState>) -> Result<Vec<Waypoint>, String> {
+    Ok(loaded_world(&state)?.waypoints().to_vec())
+}
+
+#[tauri::command]
+pub async fn get_world_houses(state: State<'_, AppState>) -> Result<Vec<House>, String> {
+    Ok(loaded_world(&state)?.houses().to_vec())
+}
+
+/// Monster and NPC spawns, or only one `kind`.
+#[tauri::command]
+pub async fn get_world_spawns(kind: Option<SpawnKind>, state: State<'_, AppState>) -> Result<Vec<Spawn>, String> {
+    let world = loaded_world(&state)?;
+    Ok(world.spawns().iter().filter(|s| kind.is_none_or(|k| s.kind == k)).cloned().collect())
+}
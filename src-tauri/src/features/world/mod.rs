@@ -0,0 +1,7 @@
+// World feature: the server map (`.otbm`) as a shared, read-only data source.
+// Tiles, items, houses, spawns, towns and waypoints, indexed once and read
+// back by floor and region so other features can derive client data from it.
+pub mod commands;
+pub mod parsers;
+
+pub use parsers::{World, WorldSummary};
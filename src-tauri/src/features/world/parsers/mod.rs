@@ -0,0 +1,8 @@
+pub mod nodes;
+mod otbm;
+mod world;
+mod xml;
+
+pub use otbm::*;
+pub use world::*;
+pub use xml::*;
@@ -0,0 +1,237 @@
+// Streaming reader for the OpenTibia node format shared by `.otb` and `.otbm`:
+//   0xFE <type u8> <props...> <child nodes...> 0xFF
+// with 0xFD/0xFE/0xFF inside props escaped by a leading 0xFD. Unlike the
+// items.otb parser this never builds the tree: it yields one node at a time, so
+// a multi-hundred-megabyte map is read with only the current node's props in
+// memory.
+
+use anyhow::{anyhow, bail, Result};
+use std::io::BufRead;
+
+pub const NODE_START: u8 = 0xFE;
+pub const NODE_END: u8 = 0xFF;
+pub const ESCAPE: u8 = 0xFD;
+
+#[derive(Debug, PartialEq)]
+pub enum NodeEvent {
+    /// A node began. `offset` is the position of its 0xFE, usable with `NodeReader::at`.
+    Enter {
+        kind: u8,
+        offset: u64,
+        props: Vec<u8>,
+    },
+    /// The innermost open node ended.
+    Leave,
+}
+
+pub struct NodeReader<R> {
+    inner: R,
+    pos: u64,
+    depth: usize,
+    /// Delimiter that ended the last props block, not yet turned into an event.
+    pending: Option<u8>,
+}
+
+impl<R: BufRead> NodeReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self::at(inner, 0)
+    }
+
+    /// Reader whose `inner` is already positioned at byte `offset` of the file.
+    pub fn at(inner: R, offset: u64) -> Self {
+        Self {
+            inner,
+            pos: offset,
+            depth: 0,
+            pending: None,
+        }
+    }
+
+    /// Bytes consumed so far, as a file offset.
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Consumes a fixed-size preamble (the 4-byte file identifier).
+    pub fn skip_preamble(&mut self, len: usize) -> Result<Vec<u8>> {
+        let mut buf = vec![0; len];
+        self.inner.read_exact(&mut buf).map_err(|e| anyhow!("File too small: {}", e))?;
+        self.pos += len as u64;
+        Ok(buf)
+    }
+
+    fn byte(&mut self) -> Result<Option<u8>> {
+        let buf = self.inner.fill_buf()?;
+        let Some(&byte) = buf.first() else {
+            return Ok(None);
+        };
+        self.inner.consume(1);
+        self.pos += 1;
+        Ok(Some(byte))
+    }
+
+    /// Reads unescaped props up to the next unescaped 0xFE/0xFF, which is returned.
+    fn read_props(&mut self, props: &mut Vec<u8>) -> Result<u8> {
+        loop {
+            let buf = self.inner.fill_buf()?;
+            if buf.is_empty() {
+                bail!("Unterminated node at offset {}", self.pos);
+            }
+            let special = buf.iter().position(|b| matches!(*b, NODE_START | NODE_END | ESCAPE));
+            let plain = special.unwrap_or(buf.len());
+            props.extend_from_slice(&buf[..plain]);
+            self.inner.consume(plain);
+            self.pos += plain as u64;
+            if special.is_none() {
+                continue;
+            }
+            match self.byte()?.expect("byte was buffered") {
+                ESCAPE => {
+                    let escaped = self.byte()?.ok_or_else(|| anyhow!("Dangling escape at offset {}", self.pos))?;
+                    props.push(escaped);
+                }
+                delimiter => return Ok(delimiter),
+            }
+        }
+    }
+
+    /// Next node event, or None at the end of input once every node is closed.
+    pub fn next_event(&mut self) -> Result<Option<NodeEvent>> {
+        let byte = match self.pending.take() {
+            Some(byte) => byte,
+            None => match self.byte()? {
+                Some(byte) => byte,
+                None if self.depth == 0 => return Ok(None),
+                None => bail!("Unexpected end of file inside a node"),
+            },
+        };
+        match byte {
+            NODE_START => {
+                let offset = self.pos - 1;
+                let kind = self.byte()?.ok_or_else(|| anyhow!("Truncated node at offset {}", offset))?;
+                let mut props = Vec::new();
+                self.pending = Some(self.read_props(&mut props)?);
+                self.depth += 1;
+                Ok(Some(NodeEvent::Enter {
+                    kind,
+                    offset,
+                    props,
+                }))
+            }
+            NODE_END if self.depth > 0 => {
+                self.depth -= 1;
+                Ok(Some(NodeEvent::Leave))
+            }
+            other => bail!("Unexpected byte 0x{:02X} between nodes at offset {}", other, self.pos - 1),
+        }
+    }
+
+    /// Skips the rest of the node entered last, including its children.
+    pub fn skip_node(&mut self) -> Result<()> {
+        let target = self.depth.checked_sub(1).ok_or_else(|| anyhow!("No open node to skip"))?;
+        while self.depth > target {
+            if self.next_event()?.is_none() {
+                bail!("Unexpected end of file while skipping a node");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Little-endian cursor over a node's props.
+pub struct PropReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> PropReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            pos: 0,
+        }
+    }
+
+    pub fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self.pos.checked_add(len).filter(|end| *end <= self.data.len()).ok_or_else(|| anyhow!("Node props truncated at {}", self.pos))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    pub fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn u16(&mut self) -> Result<u16> {
+        let b = self.take(2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    pub fn u32(&mut self) -> Result<u32> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    /// u16 length-prefixed string (Latin-1 in practice; decoded lossily).
+    pub fn string(&mut self) -> Result<String> {
+        let len = self.u16()? as usize;
+        Ok(String::from_utf8_lossy(self.take(len)?).to_string())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufReader, Cursor, Seek, SeekFrom};
+
+    #[test]
+    fn events_unescape_props_and_track_offsets() {
+        // root(1) [0xFE escaped] { child(2) [1 2] {} child(3) [] {} }
+        let bytes = [0, 0, 0, 0, NODE_START, 1, ESCAPE, NODE_START, NODE_START, 2, 1, 2, NODE_END, NODE_START, 3, NODE_END, NODE_END];
+        let mut reader = NodeReader::new(Cursor::new(&bytes[..]));
+        reader.skip_preamble(4).unwrap();
+        assert_eq!(
+            reader.next_event().unwrap(),
+            Some(NodeEvent::Enter {
+                kind: 1,
+                offset: 4,
+                props: vec![NODE_START]
+            })
+        );
+        assert_eq!(
+            reader.next_event().unwrap(),
+            Some(NodeEvent::Enter {
+                kind: 2,
+                offset: 8,
+                props: vec![1, 2]
+            })
+        );
+        assert_eq!(reader.next_event().unwrap(), Some(NodeEvent::Leave));
+        reader.next_event().unwrap();
+        reader.skip_node().unwrap();
+        assert_eq!(reader.next_event().unwrap(), Some(NodeEvent::Leave));
+        assert_eq!(reader.next_event().unwrap(), None);
+
+        // Re-entering mid-file at a recorded offset.
+        let mut cursor = BufReader::new(Cursor::new(&bytes[..]));
+        cursor.seek(SeekFrom::Start(13)).unwrap();
+        let mut reader = NodeReader::at(cursor, 13);
+        assert_eq!(
+            reader.next_event().unwrap(),
+            Some(NodeEvent::Enter {
+                kind: 3,
+                offset: 13,
+                props: Vec::new()
+            })
+        );
+    }
+}
@@ -0,0 +1,474 @@
+// Server world map (`.otbm`).
+//
+// Layout: 4-byte identifier ("OTBM" or zeros), then a node tree:
+//   root      u32 version, u16 width, u16 height, u32 items major, u32 items minor
+//   map data  attributes (description, spawn/house/npc/zone file names)
+//     tile area   u16 x, u16 y, u8 z          (tiles are offsets from it)
+//       tile        u8 dx, u8 dy, attributes, item children
+//       house tile  u8 dx, u8 dy, u32 house id, attributes, item children
+//     towns > town          u32 id, string name, u16 x, u16 y, u8 z
+//     waypoints > waypoint  string name, u16 x, u16 y, u8 z
+// Item attributes have per-attribute sizes, so an unknown one ends parsing of
+// that item's props (its id and children are still read).
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::io::BufRead;
+
+use super::nodes::{NodeEvent, NodeReader, PropReader};
+
+pub mod node {
+    pub const ROOT: u8 = 0;
+    pub const MAP_DATA: u8 = 2;
+    pub const TILE_AREA: u8 = 4;
+    pub const TILE: u8 = 5;
+    pub const ITEM: u8 = 6;
+    pub const TOWNS: u8 = 12;
+    pub const TOWN: u8 = 13;
+    pub const HOUSE_TILE: u8 = 14;
+    pub const WAYPOINTS: u8 = 15;
+    pub const WAYPOINT: u8 = 16;
+}
+
+pub mod attr {
+    pub const DESCRIPTION: u8 = 1;
+    pub const TILE_FLAGS: u8 = 3;
+    pub const ACTION_ID: u8 = 4;
+    pub const UNIQUE_ID: u8 = 5;
+    pub const TEXT: u8 = 6;
+    pub const DESC: u8 = 7;
+    pub const TELE_DEST: u8 = 8;
+    pub const ITEM: u8 = 9;
+    pub const DEPOT_ID: u8 = 10;
+    pub const EXT_SPAWN_FILE: u8 = 11;
+    pub const RUNE_CHARGES: u8 = 12;
+    pub const EXT_HOUSE_FILE: u8 = 13;
+    pub const HOUSE_DOOR_ID: u8 = 14;
+    pub const COUNT: u8 = 15;
+    pub const DURATION: u8 = 16;
+    pub const DECAYING_STATE: u8 = 17;
+    pub const WRITTEN_DATE: u8 = 18;
+    pub const WRITTEN_BY: u8 = 19;
+    pub const SLEEPER_GUID: u8 = 20;
+    pub const SLEEP_START: u8 = 21;
+    pub const CHARGES: u8 = 22;
+    pub const EXT_SPAWN_NPC_FILE: u8 = 23;
+    pub const EXT_ZONE_FILE: u8 = 24;
+    pub const ATTRIBUTE_MAP: u8 = 128;
+}
+
+/// Tile flag bits (`tileflags_t` in the server sources).
+pub mod tile_flag {
+    pub const PROTECTION_ZONE: u32 = 1 << 0;
+    pub const NO_PVP: u32 = 1 << 2;
+    pub const NO_LOGOUT: u32 = 1 << 3;
+    pub const PVP_ZONE: u32 = 1 << 4;
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Position {
+    pub x: u16,
+    pub y: u16,
+    pub z: u8,
+}
+
+impl Position {
+    pub fn new(x: u16, y: u16, z: u8) -> Self {
+        Self {
+            x,
+            y,
+            z,
+        }
+    }
+
+    fn read(props: &mut PropReader) -> Result<Self> {
+        Ok(Self {
+            x: props.u16()?,
+            y: props.u16()?,
+            z: props.u8()?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OtbmHeader {
+    pub version: u32,
+    pub width: u16,
+    pub height: u16,
+    pub items_major_version: u32,
+    pub items_minor_version: u32,
+    pub descriptions: Vec<String>,
+    /// Companion files, relative to the map's folder.
+    pub spawn_file: Option<String>,
+    pub npc_file: Option<String>,
+    pub house_file: Option<String>,
+    pub zone_file: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MapItem {
+    /// Server id (items.otb); map through `ItemsOtb` for the client id.
+    pub id: u16,
+    pub count: Option<u8>,
+    pub action_id: Option<u16>,
+    pub unique_id: Option<u16>,
+    pub text: Option<String>,
+    pub description: Option<String>,
+    pub teleport_destination: Option<Position>,
+    pub depot_id: Option<u16>,
+    pub house_door_id: Option<u8>,
+    pub charges: Option<u16>,
+    /// Container contents.
+    pub items: Vec<MapItem>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MapTile {
+    pub position: Position,
+    pub flags: u32,
+    pub house_id: Option<u32>,
+    /// Bottom to top, ground first.
+    pub items: Vec<MapItem>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Town {
+    pub id: u32,
+    pub name: String,
+    pub temple: Position,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Waypoint {
+    pub name: String,
+    pub position: Position,
+}
+
+/// Reads the identifier and root props; the reader is left inside the root node.
+pub fn read_header<R: BufRead>(reader: &mut NodeReader<R>) -> Result<OtbmHeader> {
+    let identifier = reader.skip_preamble(4)?;
+    if identifier != b"OTBM" && identifier != [0, 0, 0, 0] {
+        bail!("Not an OTBM file");
+    }
+    let Some(NodeEvent::Enter {
+        props,
+        ..
+    }) = reader.next_event()?
+    else {
+        bail!("OTBM root node missing");
+    };
+    let mut props = PropReader::new(&props);
+    let header = OtbmHeader {
+        version: props.u32()?,
+        width: props.u16()?,
+        height: props.u16()?,
+        items_major_version: props.u32()?,
+        items_minor_version: props.u32()?,
+        ..Default::default()
+    };
+    if header.version == 0 {
+        // Version 0 stores counts inline and needs items.otb to parse; editors upgrade it on save.
+        bail!("OTBM version 0 is not supported; re-save the map with a current map editor");
+    }
+    Ok(header)
+}
+
+/// Fills the description and companion file names from the map data props.
+/// Every map data attribute is a string, so unknown ones (newer editors) are
+/// logged and skipped rather than failing the load.
+pub fn read_map_data(header: &mut OtbmHeader, props: &[u8]) -> Result<()> {
+    let mut props = PropReader::new(props);
+    while !props.is_empty() {
+        let kind = props.u8()?;
+        let value = props.string()?;
+        match kind {
+            attr::DESCRIPTION => header.descriptions.push(value),
+            attr::EXT_SPAWN_FILE => header.spawn_file = Some(value),
+            attr::EXT_SPAWN_NPC_FILE => header.npc_file = Some(value),
+            attr::EXT_HOUSE_FILE => header.house_file = Some(value),
+            attr::EXT_ZONE_FILE => header.zone_file = Some(value),
+            other => log::warn!("Skipping unknown OTBM map data attribute {} ({:?})", other, value),
+        }
+    }
+    Ok(())
+}
+
+pub fn read_area_base(props: &[u8]) -> Result<Position> {
+    Position::read(&mut PropReader::new(props))
+}
+
+pub fn read_town(props: &[u8]) -> Result<Town> {
+    let mut props = PropReader::new(props);
+    Ok(Town {
+        id: props.u32()?,
+        name: props.string()?,
+        temple: Position::read(&mut props)?,
+    })
+}
+
+pub fn read_waypoint(props: &[u8]) -> Result<Waypoint> {
+    let mut props = PropReader::new(props);
+    Ok(Waypoint {
+        name: props.string()?,
+        position: Position::read(&mut props)?,
+    })
+}
+
+/// Skips one entry of the custom attribute map (string key, typed value).
+fn skip_custom_attribute(props: &mut PropReader) -> Result<()> {
+    props.string()?;
+    match props.u8()? {
+        1 => {
+            props.string()?;
+        }
+        2 | 3 => {
+            props.take(8)?;
+        }
+        4 => {
+            props.u8()?;
+        }
+        other => bail!("Unknown custom attribute type {}", other),
+    }
+    Ok(())
+}
+
+fn read_item_props(item: &mut MapItem, props: &mut PropReader) -> Result<()> {
+    while !props.is_empty() {
+        match props.u8()? {
+            attr::COUNT => item.count = Some(props.u8()?),
+            attr::RUNE_CHARGES => item.charges = Some(props.u8()? as u16),
+            attr::CHARGES => item.charges = Some(props.u16()?),
+            attr::ACTION_ID => item.action_id = Some(props.u16()?),
+            attr::UNIQUE_ID => item.unique_id = Some(props.u16()?),
+            attr::TEXT => item.text = Some(props.string()?),
+            attr::DESC => item.description = Some(props.string()?),
+            attr::WRITTEN_BY => {
+                props.string()?;
+            }
+            attr::TELE_DEST => item.teleport_destination = Some(Position::read(props)?),
+            attr::DEPOT_ID => item.depot_id = Some(props.u16()?),
+            attr::HOUSE_DOOR_ID => item.house_door_id = Some(props.u8()?),
+            attr::DECAYING_STATE => {
+                props.u8()?;
+            }
+            attr::DURATION | attr::WRITTEN_DATE | attr::SLEEPER_GUID | attr::SLEEP_START => {
+                props.u32()?;
+            }
+            attr::ATTRIBUTE_MAP => {
+                for _ in 0..props.u16()? {
+                    skip_custom_attribute(props)?;
+                }
+            }
+            other => bail!("Unknown item attribute {}", other),
+        }
+    }
+    Ok(())
+}
+
+/// Reads an item node whose Enter event carried `props`, through its Leave.
+fn read_item<R: BufRead>(reader: &mut NodeReader<R>, props: &[u8]) -> Result<MapItem> {
+    let mut props = PropReader::new(props);
+    let mut item = MapItem {
+        id: props.u16()?,
+        ..Default::default()
+    };
+    if let Err(e) = read_item_props(&mut item, &mut props) {
+        log::debug!("Item {}: {}; remaining attributes skipped", item.id, e);
+    }
+    loop {
+        match reader.next_event()? {
+            Some(NodeEvent::Enter {
+                kind: node::ITEM,
+                props,
+                ..
+            }) => item.items.push(read_item(reader, &props)?),
+            Some(NodeEvent::Enter {
+                ..
+            }) => reader.skip_node()?,
+            Some(NodeEvent::Leave) => return Ok(item),
+            None => bail!("Unexpected end of file inside item {}", item.id),
+        }
+    }
+}
+
+/// Reads a tile or house tile node (`kind`, `props` from its Enter event) through its Leave.
+pub fn read_tile<R: BufRead>(reader: &mut NodeReader<R>, base: Position, kind: u8, props: &[u8]) -> Result<MapTile> {
+    let mut props = PropReader::new(props);
+    let mut tile = MapTile {
+        position: Position::new(base.x.wrapping_add(props.u8()? as u16), base.y.wrapping_add(props.u8()? as u16), base.z),
+        ..Default::default()
+    };
+    if kind == node::HOUSE_TILE {
+        tile.house_id = Some(props.u32()?);
+    }
+    while !props.is_empty() {
+        match props.u8()? {
+            attr::TILE_FLAGS => tile.flags = props.u32()?,
+            attr::ITEM => tile.items.push(MapItem {
+                id: props.u16()?,
+                ..Default::default()
+            }),
+            other => {
+                log::debug!("Tile {:?}: unknown attribute {}", tile.position, other);
+                break;
+            }
+        }
+    }
+    loop {
+        match reader.next_event()? {
+            Some(NodeEvent::Enter {
+                kind: node::ITEM,
+                props,
+                ..
+            }) => tile.items.push(read_item(reader, &props)?),
+            Some(NodeEvent::Enter {
+                ..
+            }) => reader.skip_node()?,
+            Some(NodeEvent::Leave) => return Ok(tile),
+            None => bail!("Unexpected end of file inside tile {:?}", tile.position),
+        }
+    }
+}
+
+/// Reads the tiles of a tile area node (Enter already consumed) through its Leave.
+pub fn read_tile_area<R: BufRead>(reader: &mut NodeReader<R>, base: Position, mut visit: impl FnMut(MapTile)) -> Result<()> {
+    loop {
+        match reader.next_event()? {
+            Some(NodeEvent::Enter {
+                kind: kind @ (node::TILE | node::HOUSE_TILE),
+                props,
+                ..
+            }) => visit(read_tile(reader, base, kind, &props)?),
+            Some(NodeEvent::Enter {
+                ..
+            }) => reader.skip_node()?,
+            Some(NodeEvent::Leave) => return Ok(()),
+            None => bail!("Unexpected end of file inside tile area {:?}", base),
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::super::nodes::{ESCAPE, NODE_END, NODE_START};
+    use super::*;
+
+    fn push_node(out: &mut Vec<u8>, kind: u8, props: &[u8], children: impl FnOnce(&mut Vec<u8>)) {
+        out.push(NODE_START);
+        out.push(kind);
+        for &byte in props {
+            if matches!(byte, NODE_START | NODE_END | ESCAPE) {
+                out.push(ESCAPE);
+            }
+            out.push(byte);
+        }
+        children(out);
+        out.push(NODE_END);
+    }
+
+    fn string(s: &str) -> Vec<u8> {
+        let mut out = (s.len() as u16).to_le_bytes().to_vec();
+        out.extend_from_slice(s.as_bytes());
+        out
+    }
+
+    /// A small map: two tile areas on floor 7 (one tile is a house tile with a
+    /// door and a container), one area on floor 6, a town and a waypoint.
+    pub(crate) fn sample_otbm() -> Vec<u8> {
+        let mut out = b"OTBM".to_vec();
+        let mut root = 2u32.to_le_bytes().to_vec();
+        root.extend_from_slice(&2048u16.to_le_bytes());
+        root.extend_from_slice(&2048u16.to_le_bytes());
+        root.extend_from_slice(&3u32.to_le_bytes());
+        root.extend_from_slice(&57u32.to_le_bytes());
+        push_node(&mut out, node::ROOT, &root, |out| {
+            let mut data = vec![attr::DESCRIPTION];
+            data.extend(string("Test map"));
+            data.push(attr::EXT_HOUSE_FILE);
+            data.extend(string("test-house.xml"));
+            push_node(out, node::MAP_DATA, &data, |out| {
+                let area = |x: u16, y: u16, z: u8| [x.to_le_bytes().as_slice(), y.to_le_bytes().as_slice(), &[z]].concat();
+                push_node(out, node::TILE_AREA, &area(1000, 1000, 7), |out| {
+                    // Plain tile with compact ground 0x01FE (escaped on disk) and a stacked item.
+                    push_node(out, node::TILE, &[1, 2, attr::ITEM, 0xFE, 0x01], |out| {
+                        push_node(out, node::ITEM, &[0x64, 0x00, attr::COUNT, 5], |_| {});
+                    });
+                    // House tile 42 with PZ, a door, and a container holding one item.
+                    let mut house = vec![3, 3];
+                    house.extend_from_slice(&42u32.to_le_bytes());
+                    house.push(attr::TILE_FLAGS);
+                    house.extend_from_slice(&tile_flag::PROTECTION_ZONE.to_le_bytes());
+                    push_node(out, node::HOUSE_TILE, &house, |out| {
+                        push_node(out, node::ITEM, &[0x10, 0x00, attr::HOUSE_DOOR_ID, 1], |_| {});
+                        push_node(out, node::ITEM, &[0x20, 0x00, attr::ACTION_ID, 0xE8, 0x03], |out| {
+                            push_node(out, node::ITEM, &[0x21, 0x00], |_| {});
+                        });
+                    });
+                });
+                push_node(out, node::TILE_AREA, &area(1256, 1000, 7), |out| {
+                    push_node(out, node::TILE, &[0, 0, attr::ITEM, 0x66, 0x00], |_| {});
+                });
+                push_node(out, node::TILE_AREA, &area(1000, 1000, 6), |out| {
+                    push_node(out, node::TILE, &[5, 5, attr::ITEM, 0x67, 0x00], |_| {});
+                });
+                let mut town = 1u32.to_le_bytes().to_vec();
+                town.extend(string("Thais"));
+                town.extend(area(1001, 1002, 7));
+                push_node(out, node::TOWNS, &[], |out| push_node(out, node::TOWN, &town, |_| {}));
+                let mut waypoint = string("temple");
+                waypoint.extend(area(1001, 1002, 7));
+                push_node(out, node::WAYPOINTS, &[], |out| push_node(out, node::WAYPOINT, &waypoint, |_| {}));
+            });
+        });
+        out
+    }
+
+    #[test]
+    fn reads_header_and_tiles_with_nested_items() {
+        let bytes = sample_otbm();
+        let mut reader = NodeReader::new(std::io::Cursor::new(&bytes[..]));
+        let mut header = read_header(&mut reader).unwrap();
+        assert_eq!((header.version, header.width, header.items_minor_version), (2, 2048, 57));
+        let Some(NodeEvent::Enter {
+            props,
+            ..
+        }) = reader.next_event().unwrap()
+        else {
+            panic!("map data")
+        };
+        read_map_data(&mut header, &props).unwrap();
+        assert_eq!(header.house_file.as_deref(), Some("test-house.xml"));
+
+        // An attribute from a newer editor doesn't stop the rest being read.
+        let mut data = vec![0x7F];
+        data.extend(string("unknown"));
+        data.push(attr::EXT_SPAWN_FILE);
+        data.extend(string("test-spawn.xml"));
+        let mut other = OtbmHeader::default();
+        read_map_data(&mut other, &data).unwrap();
+        assert_eq!(other.spawn_file.as_deref(), Some("test-spawn.xml"));
+
+        let Some(NodeEvent::Enter {
+            props,
+            ..
+        }) = reader.next_event().unwrap()
+        else {
+            panic!("tile area")
+        };
+        let mut tiles = Vec::new();
+        read_tile_area(&mut reader, read_area_base(&props).unwrap(), |tile| tiles.push(tile)).unwrap();
+        assert_eq!(tiles.len(), 2);
+        assert_eq!(tiles[0].position, Position::new(1001, 1002, 7));
+        assert_eq!(tiles[0].items.iter().map(|i| i.id).collect::<Vec<_>>(), vec![0x01FE, 100]);
+        assert_eq!(tiles[0].items[1].count, Some(5));
+        assert_eq!((tiles[1].house_id, tiles[1].flags), (Some(42), tile_flag::PROTECTION_ZONE));
+        assert_eq!(tiles[1].items[0].house_door_id, Some(1));
+        assert_eq!(tiles[1].items[1].action_id, Some(1000));
+        assert_eq!(tiles[1].items[1].items[0].id, 0x21);
+    }
+}
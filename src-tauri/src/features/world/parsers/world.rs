@@ -0,0 +1,380 @@
+// In-memory world model over an `.otbm` file.
+//
+// Opening a map streams it once: towns, waypoints, houses (tiles and doors)
+// and per-floor bounds are kept, but tiles are not. Each tile area's file
+// offset is indexed instead, and region queries re-read only the areas they
+// touch, through a small LRU of decoded areas. Whole-floor passes (minimap
+// generation and the like) go through `for_each_tile`, which never holds more
+// than one area.
+
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use super::nodes::{NodeEvent, NodeReader};
+use super::otbm::{node, read_area_base, read_header, read_map_data, read_tile_area, read_town, read_waypoint, MapTile, OtbmHeader, Position, Town, Waypoint};
+use super::xml::{parse_houses_xml, parse_spawns_xml, House, Spawn, SpawnKind};
+use crate::core::cache::LRUCache;
+use crate::core::jobs::JobHandle;
+
+/// Decoded tile areas kept for region queries (an area is at most 256x256 tiles).
+const AREA_CACHE_SIZE: usize = 64;
+const READ_BUFFER_BYTES: usize = 1 << 16;
+
+/// Inclusive tile rectangle on one floor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Region {
+    pub x_min: u16,
+    pub y_min: u16,
+    pub x_max: u16,
+    pub y_max: u16,
+    pub z: u8,
+}
+
+impl Region {
+    pub fn contains(&self, position: Position) -> bool {
+        position.z == self.z && (self.x_min..=self.x_max).contains(&position.x) && (self.y_min..=self.y_max).contains(&position.y)
+    }
+
+    pub fn tile_count(&self) -> u64 {
+        (self.x_max.saturating_sub(self.x_min) as u64 + 1) * (self.y_max.saturating_sub(self.y_min) as u64 + 1)
+    }
+}
+
+/// One tile area node: where it starts in the file and the bounds of its tiles.
+#[derive(Debug, Clone, Copy)]
+struct AreaRef {
+    offset: u64,
+    z: u8,
+    tiles: u32,
+    min: (u16, u16),
+    max: (u16, u16),
+}
+
+impl AreaRef {
+    fn intersects(&self, region: &Region) -> bool {
+        self.z == region.z && self.tiles > 0 && self.min.0 <= region.x_max && self.max.0 >= region.x_min && self.min.1 <= region.y_max && self.max.1 >= region.y_min
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FloorInfo {
+    pub z: u8,
+    pub tiles: u64,
+    pub areas: usize,
+    pub min: Position,
+    pub max: Position,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorldSummary {
+    pub path: String,
+    pub header: OtbmHeader,
+    pub tiles: u64,
+    pub floors: Vec<FloorInfo>,
+    pub towns: usize,
+    pub waypoints: usize,
+    pub houses: usize,
+    pub monster_spawns: usize,
+    pub npc_spawns: usize,
+}
+
+pub struct World {
+    path: PathBuf,
+    header: OtbmHeader,
+    areas: Vec<AreaRef>,
+    towns: Vec<Town>,
+    waypoints: Vec<Waypoint>,
+    houses: Vec<House>,
+    spawns: Vec<Spawn>,
+    cache: LRUCache<usize, Vec<MapTile>>,
+}
+
+/// Companion file `named` by the map, or `<map stem><suffix>` next to it.
+fn companion_path(map_path: &Path, named: Option<&str>, suffixes: &[&str]) -> Option<PathBuf> {
+    let dir = map_path.parent()?;
+    if let Some(name) = named.filter(|n| !n.trim().is_empty()) {
+        return Some(dir.join(name));
+    }
+    let stem = map_path.file_stem()?.to_string_lossy();
+    suffixes.iter().map(|suffix| dir.join(format!("{}{}", stem, suffix))).find(|p| p.is_file())
+}
+
+fn read_companion(path: Option<PathBuf>) -> Option<String> {
+    let path = path?;
+    match std::fs::read(&path) {
+        Ok(bytes) => Some(String::from_utf8_lossy(&bytes).to_string()),
+        Err(e) => {
+            log::warn!("Skipping {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+fn open_reader(path: &Path, offset: u64) -> Result<NodeReader<BufReader<File>>> {
+    let file = File::open(path).map_err(|e| anyhow!("Failed to open {}: {}", path.display(), e))?;
+    let mut reader = BufReader::with_capacity(READ_BUFFER_BYTES, file);
+    reader.seek(SeekFrom::Start(offset))?;
+    Ok(NodeReader::at(reader, offset))
+}
+
+impl World {
+    /// Streams `path` once to build the index. Reports bytes read on `job`.
+    pub fn open(path: &Path, job: &JobHandle) -> Result<Self> {
+        let file_len = std::fs::metadata(path).map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?.len();
+        job.set_total(file_len as usize);
+        let mut reader = open_reader(path, 0)?;
+        let mut header = read_header(&mut reader)?;
+
+        let mut areas = Vec::new();
+        let mut towns = Vec::new();
+        let mut waypoints = Vec::new();
+        let mut house_tiles: HashMap<u32, House> = HashMap::new();
+        let mut reported = 0u64;
+
+        while let Some(event) = reader.next_event()? {
+            let NodeEvent::Enter {
+                kind,
+                offset,
+                props,
+            } = event
+            else {
+                continue;
+            };
+            match kind {
+                node::MAP_DATA => read_map_data(&mut header, &props)?,
+                node::TILE_AREA => {
+                    let base = read_area_base(&props)?;
+                    let mut area = AreaRef {
+                        offset,
+                        z: base.z,
+                        tiles: 0,
+                        min: (u16::MAX, u16::MAX),
+                        max: (0, 0),
+                    };
+                    read_tile_area(&mut reader, base, |tile| {
+                        let p = tile.position;
+                        area.tiles += 1;
+                        area.min = (area.min.0.min(p.x), area.min.1.min(p.y));
+                        area.max = (area.max.0.max(p.x), area.max.1.max(p.y));
+                        if let Some(house_id) = tile.house_id {
+                            let house = house_tiles.entry(house_id).or_insert_with(|| House {
+                                id: house_id,
+                                ..Default::default()
+                            });
+                            house.tiles.push(p);
+                            house.doors.extend(tile.items.iter().filter_map(|item| item.house_door_id.map(|door| (door, p))));
+                        }
+                    })?;
+                    areas.push(area);
+
+                    job.advance((reader.position() - reported) as usize);
+                    reported = reader.position();
+                    job.check_cancelled().map_err(anyhow::Error::msg)?;
+                }
+                node::TOWNS | node::WAYPOINTS => {}
+                node::TOWN => towns.push(read_town(&props)?),
+                node::WAYPOINT => waypoints.push(read_waypoint(&props)?),
+                _ => reader.skip_node()?,
+            }
+        }
+        if reader.depth() != 0 {
+            bail!("OTBM file ended inside a node");
+        }
+
+        let mut houses: BTreeMap<u32, House> = BTreeMap::new();
+        if let Some(content) = read_companion(companion_path(path, header.house_file.as_deref(), &["-house.xml"])) {
+            houses.extend(parse_houses_xml(&content).into_iter().map(|h| (h.id, h)));
+        }
+        for (id, tiles) in house_tiles {
+            let house = houses.entry(id).or_insert_with(|| House {
+                id,
+                ..Default::default()
+            });
+            house.tiles = tiles.tiles;
+            house.doors = tiles.doors;
+        }
+
+        let mut spawns = Vec::new();
+        if let Some(content) = read_companion(companion_path(path, header.spawn_file.as_deref(), &["-monster.xml", "-spawn.xml"])) {
+            spawns.extend(parse_spawns_xml(&content, SpawnKind::Monster));
+        }
+        if let Some(content) = read_companion(companion_path(path, header.npc_file.as_deref(), &["-npc.xml"])) {
+            spawns.extend(parse_spawns_xml(&content, SpawnKind::Npc));
+        }
+
+        log::info!("Indexed {}: {} tile areas, {} towns, {} houses, {} spawns", path.display(), areas.len(), towns.len(), houses.len(), spawns.len());
+        Ok(Self {
+            path: path.to_path_buf(),
+            header,
+            areas,
+            towns,
+            waypoints,
+            houses: houses.into_values().collect(),
+            spawns,
+            cache: LRUCache::new(AREA_CACHE_SIZE),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn header(&self) -> &OtbmHeader {
+        &self.header
+    }
+
+    pub fn towns(&self) -> &[Town] {
+        &self.towns
+    }
+
+    pub fn waypoints(&self) -> &[Waypoint] {
+        &self.waypoints
+    }
+
+    pub fn houses(&self) -> &[House] {
+        &self.houses
+    }
+
+    pub fn spawns(&self) -> &[Spawn] {
+        &self.spawns
+    }
+
+    /// Floors that have tiles, top (0) to bottom, with their bounds.
+    pub fn floors(&self) -> Vec<FloorInfo> {
+        let mut floors: BTreeMap<u8, FloorInfo> = BTreeMap::new();
+        for area in self.areas.iter().filter(|a| a.tiles > 0) {
+            let floor = floors.entry(area.z).or_insert(FloorInfo {
+                z: area.z,
+                tiles: 0,
+                areas: 0,
+                min: Position::new(u16::MAX, u16::MAX, area.z),
+                max: Position::new(0, 0, area.z),
+            });
+            floor.tiles += area.tiles as u64;
+            floor.areas += 1;
+            floor.min = Position::new(floor.min.x.min(area.min.0), floor.min.y.min(area.min.1), area.z);
+            floor.max = Position::new(floor.max.x.max(area.max.0), floor.max.y.max(area.max.1), area.z);
+        }
+        floors.into_values().collect()
+    }
+
+    pub fn summary(&self) -> WorldSummary {
+        let floors = self.floors();
+        WorldSummary {
+            path: self.path.to_string_lossy().to_string(),
+            header: self.header.clone(),
+            tiles: floors.iter().map(|f| f.tiles).sum(),
+            floors,
+            towns: self.towns.len(),
+            waypoints: self.waypoints.len(),
+            houses: self.houses.len(),
+            monster_spawns: self.spawns.iter().filter(|s| s.kind == SpawnKind::Monster).count(),
+            npc_spawns: self.spawns.iter().filter(|s| s.kind == SpawnKind::Npc).count(),
+        }
+    }
+
+    fn read_area(&self, area: &AreaRef, visit: impl FnMut(MapTile)) -> Result<()> {
+        let mut reader = open_reader(&self.path, area.offset)?;
+        match reader.next_event()? {
+            Some(NodeEvent::Enter {
+                kind: node::TILE_AREA,
+                props,
+                ..
+            }) => read_tile_area(&mut reader, read_area_base(&props)?, visit),
+            _ => bail!("No tile area at offset {} (was the map changed on disk?)", area.offset),
+        }
+    }
+
+    fn cached_area(&self, index: usize) -> Result<Arc<Vec<MapTile>>> {
+        if let Some(tiles) = self.cache.get(&index) {
+            return Ok(tiles);
+        }
+        let mut tiles = Vec::with_capacity(self.areas[index].tiles as usize);
+        self.read_area(&self.areas[index], |tile| tiles.push(tile))?;
+        self.cache.insert(index, tiles);
+        self.cache.get(&index).ok_or_else(|| anyhow!("Tile area cache rejected area {}", index))
+    }
+
+    /// Tiles inside `region`, sorted by position.
+    pub fn tiles_in(&self, region: &Region) -> Result<Vec<MapTile>> {
+        let mut tiles = Vec::new();
+        for (index, _) in self.areas.iter().enumerate().filter(|(_, a)| a.intersects(region)) {
+            tiles.extend(self.cached_area(index)?.iter().filter(|t| region.contains(t.position)).cloned());
+        }
+        tiles.sort_by_key(|t| (t.position.y, t.position.x));
+        Ok(tiles)
+    }
+
+    pub fn tile(&self, position: Position) -> Result<Option<MapTile>> {
+        let region = Region {
+            x_min: position.x,
+            y_min: position.y,
+            x_max: position.x,
+            y_max: position.y,
+            z: position.z,
+        };
+        Ok(self.tiles_in(&region)?.pop())
+    }
+
+    /// Visits every tile (of `floor`, or all floors) one area at a time, bypassing
+    /// the cache. Reports areas done on `job` and stops when it is cancelled.
+    pub fn for_each_tile(&self, floor: Option<u8>, job: &JobHandle, mut visit: impl FnMut(&MapTile)) -> Result<()> {
+        let areas: Vec<&AreaRef> = self.areas.iter().filter(|a| a.tiles > 0 && floor.is_none_or(|z| a.z == z)).collect();
+        job.set_total(areas.len());
+        for area in areas {
+            job.check_cancelled().map_err(anyhow::Error::msg)?;
+            self.read_area(area, |tile| visit(&tile))?;
+            job.advance(1);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::otbm::tests::sample_otbm;
+    use super::*;
+
+    #[test]
+    fn indexes_map_and_answers_region_queries() {
+        let dir = std::env::temp_dir().join(format!("world-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("test.otbm");
+        std::fs::write(&path, sample_otbm()).unwrap();
+        std::fs::write(dir.join("test-house.xml"), r#"<houses><house name="Villa" houseid="42" entryx="1003" entryy="1003" entryz="7" townid="1"/></houses>"#).unwrap();
+
+        let world = World::open(&path, &JobHandle::detached("test")).unwrap();
+        let summary = world.summary();
+        assert_eq!(summary.tiles, 4);
+        assert_eq!(summary.floors.iter().map(|f| f.z).collect::<Vec<_>>(), vec![6, 7]);
+        assert_eq!(world.towns()[0].name, "Thais");
+        assert_eq!(world.waypoints()[0].position, Position::new(1001, 1002, 7));
+        assert_eq!(world.houses()[0].name.as_deref(), Some("Villa"));
+        assert_eq!(world.houses()[0].doors, vec![(1, Position::new(1003, 1003, 7))]);
+
+        let region = Region {
+            x_min: 1000,
+            y_min: 1000,
+            x_max: 1300,
+            y_max: 1002,
+            z: 7,
+        };
+        let tiles = world.tiles_in(&region).unwrap();
+        assert_eq!(tiles.iter().map(|t| t.position.x).collect::<Vec<_>>(), vec![1256, 1001]);
+        assert!(world.tile(Position::new(1005, 1005, 6)).unwrap().is_some());
+
+        let mut ground = Vec::new();
+        world.for_each_tile(Some(7), &JobHandle::detached("test"), |t| ground.push(t.items[0].id)).unwrap();
+        assert_eq!(ground, vec![0x01FE, 0x10, 0x66]);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,168 @@
+// Companion XML files named by the map data node: houses (`-house.xml`) and
+// monster/NPC spawns (`-monster.xml`/`-spawn.xml`, `-npc.xml`). Read with the
+// same tag/attribute regexes used for items.xml elsewhere, since the files are
+// flat and machine-written.
+//
+// Spawn blocks are any element carrying `centerx` (TFS `<spawn>`, Canary
+// `<monster>`/`<npc>`); creatures are the elements inside with a `name`.
+// Creature x/y are offsets from the centre, z is absolute.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use super::otbm::Position;
+
+static RE_TAG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"<(/?)([A-Za-z_][A-Za-z0-9_]*)\b([^>]*?)(/?)>"#).expect("valid tag regex"));
+static RE_ATTR: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"([A-Za-z_][A-Za-z0-9_-]*)\s*=\s*(?:"([^"]*)"|'([^']*)')"#).expect("valid attribute regex"));
+
+fn attributes(raw: &str) -> HashMap<String, String> {
+    RE_ATTR.captures_iter(raw).map(|c| (c[1].to_ascii_lowercase(), c.get(2).or_else(|| c.get(3)).map(|m| m.as_str().to_string()).unwrap_or_default())).collect()
+}
+
+fn number<T: std::str::FromStr>(attrs: &HashMap<String, String>, key: &str) -> Option<T> {
+    attrs.get(key).and_then(|v| v.trim().parse().ok())
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct House {
+    pub id: u32,
+    pub name: Option<String>,
+    pub entry: Option<Position>,
+    pub town_id: Option<u32>,
+    pub rent: Option<u64>,
+    pub size: Option<u32>,
+    pub guildhall: bool,
+    /// Filled from the map's house tiles.
+    pub tiles: Vec<Position>,
+    /// (door id, position) of each house door item.
+    pub doors: Vec<(u8, Position)>,
+}
+
+pub fn parse_houses_xml(content: &str) -> Vec<House> {
+    RE_TAG
+        .captures_iter(content)
+        .filter(|c| c[1].is_empty() && c[2].eq_ignore_ascii_case("house"))
+        .filter_map(|c| {
+            let attrs = attributes(&c[3]);
+            let entry = match (number(&attrs, "entryx"), number(&attrs, "entryy"), number(&attrs, "entryz")) {
+                (Some(x), Some(y), Some(z)) => Some(Position::new(x, y, z)),
+                _ => None,
+            };
+            Some(House {
+                id: number(&attrs, "houseid")?,
+                name: attrs.get("name").cloned(),
+                entry,
+                town_id: number(&attrs, "townid"),
+                rent: number(&attrs, "rent"),
+                size: number(&attrs, "size"),
+                guildhall: attrs.get("guildhall").is_some_and(|v| v == "true" || v == "1"),
+                ..Default::default()
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SpawnKind {
+    Monster,
+    Npc,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpawnCreature {
+    pub name: String,
+    pub position: Position,
+    pub spawn_time: Option<u32>,
+    pub direction: Option<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Spawn {
+    pub kind: SpawnKind,
+    pub center: Position,
+    pub radius: u16,
+    pub creatures: Vec<SpawnCreature>,
+}
+
+fn offset(center: u16, delta: i32) -> u16 {
+    (center as i32 + delta).clamp(0, u16::MAX as i32) as u16
+}
+
+pub fn parse_spawns_xml(content: &str, kind: SpawnKind) -> Vec<Spawn> {
+    let mut spawns = Vec::new();
+    // (tag name, spawn) of the block being read
+    let mut current: Option<(String, Spawn)> = None;
+    for c in RE_TAG.captures_iter(content) {
+        let (closing, tag, self_closing) = (!c[1].is_empty(), &c[2], !c[4].is_empty());
+        if closing {
+            if current.as_ref().is_some_and(|(name, _)| name.eq_ignore_ascii_case(tag)) {
+                spawns.extend(current.take().map(|(_, spawn)| spawn));
+            }
+            continue;
+        }
+        let attrs = attributes(&c[3]);
+        if let (Some(x), Some(y), Some(z)) = (number(&attrs, "centerx"), number(&attrs, "centery"), number(&attrs, "centerz")) {
+            spawns.extend(current.take().map(|(_, spawn)| spawn));
+            let spawn = Spawn {
+                kind,
+                center: Position::new(x, y, z),
+                radius: number(&attrs, "radius").unwrap_or(0),
+                creatures: Vec::new(),
+            };
+            if self_closing {
+                spawns.push(spawn);
+            } else {
+                current = Some((tag.to_string(), spawn));
+            }
+            continue;
+        }
+        let Some((_, spawn)) = current.as_mut() else {
+            continue;
+        };
+        let Some(name) = attrs.get("name") else {
+            continue;
+        };
+        let center = spawn.center;
+        spawn.creatures.push(SpawnCreature {
+            name: name.clone(),
+            position: Position::new(offset(center.x, number(&attrs, "x").unwrap_or(0)), offset(center.y, number(&attrs, "y").unwrap_or(0)), number(&attrs, "z").unwrap_or(center.z)),
+            spawn_time: number(&attrs, "spawntime"),
+            direction: number(&attrs, "direction"),
+        });
+    }
+    spawns.extend(current.map(|(_, spawn)| spawn));
+    spawns
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_tfs_and_canary_spawn_layouts() {
+        let tfs = r#"<spawns><spawn centerx="100" centery="200" centerz="7" radius="3">
+            <monster name="Rat" x="-1" y="2" z="7" spawntime="60" /></spawn></spawns>"#;
+        let canary = r#"<npcs><npc centerx="50" centery="60" centerz="6" radius="1">
+            <npc name="Rashid" x="0" y="0" z="6" spawntime="60" direction="2" /></npc></npcs>"#;
+
+        let monsters = parse_spawns_xml(tfs, SpawnKind::Monster);
+        assert_eq!(monsters.len(), 1);
+        assert_eq!(monsters[0].radius, 3);
+        assert_eq!(monsters[0].creatures[0].position, Position::new(99, 202, 7));
+
+        let npcs = parse_spawns_xml(canary, SpawnKind::Npc);
+        assert_eq!(npcs.len(), 1);
+        assert_eq!(npcs[0].creatures[0].name, "Rashid");
+        assert_eq!(npcs[0].creatures[0].direction, Some(2));
+
+        let houses = parse_houses_xml(r#"<houses><house name="Villa" houseid="42" entryx="1003" entryy="1003" entryz="7" rent="5000" townid="1" size="1" guildhall="true"/></houses>"#);
+        assert_eq!(houses[0].entry, Some(Position::new(1003, 1003, 7)));
+        assert!(houses[0].guildhall);
+    }
+}
@@ -4,6 +4,7 @@ pub mod state;
 
 use features::sounds::commands::SoundsState;
 use state::AppState;
+use tauri::Manager;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -18,6 +19,21 @@ pub fn run() {
         .plugin(tauri_plugin_clipboard_manager::init())
         .manage(AppState::new()) // ✅ OPTIMIZED: Uses LRU caches with bounds
         .manage(SoundsState::new())
+        .setup(|app| {
+            // Persistent sprite PNG cache; without it previews just fall back to decoding.
+            let cache_dir = app.path().app_cache_dir().map(|dir| dir.join("sprites"));
+            match cache_dir.map_err(|e| anyhow::anyhow!("{}", e)).and_then(|dir| core::DiskCache::open(dir, core::disk_cache::DEFAULT_DISK_CACHE_BYTES)) {
+                Ok(cache) => *app.state::<AppState>().sprite_disk_cache.write() = Some(cache),
+                Err(e) => log::warn!("Sprite disk cache disabled: {}", e),
+            }
+            // Versioned snapshots taken before every save.
+            let backup_dir = app.path().app_data_dir().map(|dir| dir.join("backups"));
+            match backup_dir.map_err(|e| anyhow::anyhow!("{}", e)).and_then(|dir| core::backups::BackupManager::open(dir, core::backups::DEFAULT_BACKUPS_PER_FILE)) {
+                Ok(manager) => core::backups::init(manager),
+                Err(e) => log::warn!("File backups disabled: {}", e),
+            }
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             // Appearances API
             features::appearances::commands::load_appearances_file,
@@ -27,6 +43,7 @@ pub fn run() {
             features::appearances::commands::list_appearances_by_category,
             features::appearances::commands::find_appearance_position,
             features::appearances::commands::search_appearances_by_flags,
+            features::appearances::commands::query_appearances,
             features::appearances::commands::get_appearance_details,
             features::appearances::commands::get_appearance_count,
             features::appearances::commands::get_item_subcategories,
@@ -79,12 +96,30 @@ pub fn run() {
             features::appearances::commands::create_empty_appearance,
             features::appearances::commands::copy_appearance_flags,
             features::appearances::commands::paste_appearance_flags,
+            features::appearances::commands::preview_bulk_flag_patch,
+            features::appearances::commands::apply_bulk_flag_patch,
             features::appearances::commands::delete_appearance,
+            features::appearances::commands::undo_appearance_edit,
+            features::appearances::commands::redo_appearance_edit,
+            features::appearances::commands::get_appearance_history,
+            features::appearances::commands::clear_appearance_history,
+            features::appearances::commands::validate_assets,
             // Sprites API
             features::sprites::commands::load_sprites_catalog,
             features::sprites::commands::auto_load_sprites,
             features::sprites::commands::get_sprite_by_id,
             features::sprites::commands::export_sprites_to_png,
+            features::sprites::commands::export_appearance_animation,
+            features::sprites::commands::render_outfit,
+            features::sprites::commands::export_sprite_atlas,
+            features::sprites::commands::import_sprite_atlas,
+            features::sprites::commands::deduplicate_sprites,
+            features::sprites::commands::collect_unused_sprites,
+            features::sprites::commands::compute_sprite_metrics,
+            features::sprites::commands::export_legacy_client,
+            features::sprites::commands::import_legacy_dat,
+            features::sprites::commands::build_sprite_hash_index,
+            features::sprites::commands::find_appearances_by_image,
             features::sprites::commands::save_image_bytes,
             features::sprites::commands::import_image_as_tiles,
             features::sprites::commands::compile_imported_sprites,
@@ -93,6 +128,8 @@ pub fn run() {
             features::sprites::commands::get_appearance_sprites,
             features::sprites::commands::get_appearance_preview_sprite,
             features::sprites::commands::clear_sprite_cache,
+            features::sprites::commands::get_sprite_disk_cache_stats,
+            features::sprites::commands::clear_sprite_disk_cache,
             features::sprites::commands::get_sprite_cache_stats,
             // Cache memory monitoring
             state::get_cache_memory_stats,
@@ -173,14 +210,48 @@ pub fn run() {
             features::staticmapdata::commands::io::load_staticmapdata_file,
             features::staticmapdata::commands::io::list_staticmapdata_files,
             features::staticmapdata::commands::io::get_staticmapdata_houses,
+            // Map API (Cyclopedia areas and NPC pins)
+            features::map::commands::io::load_map_file,
+            features::map::commands::io::list_map_files,
+            features::map::commands::io::save_map_file,
+            features::map::commands::io::get_map_areas,
+            features::map::commands::io::get_map_area_tree,
+            features::map::commands::io::update_map_area,
+            features::map::commands::io::remove_map_area,
+            features::map::commands::io::get_map_npcs,
+            features::map::commands::io::update_map_npc,
+            features::map::commands::io::remove_map_npc,
+            features::map::commands::io::get_map_resource_files,
+            // World map API (.otbm)
+            features::world::commands::load_world,
+            features::world::commands::unload_world,
+            features::world::commands::get_world_summary,
+            features::world::commands::get_world_tiles,
+            features::world::commands::get_world_towns,
+            features::world::commands::get_world_waypoints,
+            features::world::commands::get_world_houses,
+            features::world::commands::get_world_spawns,
             // Minimap (markers + tiles)
             features::minimap::commands::io::minimap_find_markers_file,
             features::minimap::commands::io::minimap_load_markers,
             features::minimap::commands::io::minimap_load_markers_auto,
+            features::minimap::commands::io::minimap_open_markers,
+            features::minimap::commands::io::minimap_get_markers,
+            features::minimap::commands::io::minimap_set_marker,
+            features::minimap::commands::io::minimap_move_marker,
+            features::minimap::commands::io::minimap_retype_marker,
+            features::minimap::commands::io::minimap_remove_marker,
+            features::minimap::commands::io::minimap_save_markers,
+            features::minimap::commands::io::minimap_export_markers,
+            features::minimap::commands::io::minimap_import_markers,
+            features::minimap::commands::io::minimap_import_world_markers,
             features::minimap::commands::io::minimap_list_tiles,
             features::minimap::commands::io::minimap_get_tile,
             features::minimap::commands::io::minimap_otmm_info,
             features::minimap::commands::io::minimap_render_otmm,
+            features::minimap::commands::io::minimap_generate_tiles,
+            features::minimap::commands::io::minimap_otmm_to_client_tiles,
+            features::minimap::commands::io::minimap_client_tiles_to_otmm,
             // QM Translation Editor API
             features::qm::commands::qm_find_files,
             features::qm::commands::qm_load,
@@ -211,6 +282,9 @@ pub fn run() {
             features::dat_merge::staticdata_merge::get_staticdata_merge_preview,
             features::dat_merge::staticdata_merge::execute_staticdata_merge,
             features::dat_merge::save_all_merge,
+            features::dat_merge::diff::diff_appearances_files,
+            features::dat_merge::diff::diff_with_merge_source,
+            features::dat_merge::diff::export_appearances_diff,
             // RCC Editor API
             features::rcc::commands::rcc_load,
             features::rcc::commands::rcc_get_resource,
@@ -249,6 +323,23 @@ pub fn run() {
             features::rcc::exe_commands::exe_apply_resource,
             features::rcc::exe_commands::exe_replace_resource_from_file,
             features::rcc::exe_commands::exe_apply_spell_to_disk,
+            features::otb::commands::load_items_otb,
+            features::otb::commands::save_items_otb,
+            features::otb::commands::get_items_otb_mapping,
+            features::otb::commands::sync_items_otb,
+            features::otb::commands::generate_items_otb,
+            // Jobs API
+            features::jobs::list_jobs,
+            features::jobs::cancel_job,
+            // References API
+            features::references::build_reference_index,
+            features::references::find_references,
+            features::references::preview_id_remap,
+            features::references::apply_id_remap,
+            // Backups API
+            features::backups::list_backups,
+            features::backups::diff_backup,
+            features::backups::restore_backup,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
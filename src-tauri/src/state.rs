@@ -8,11 +8,21 @@ use std::path::PathBuf;
 use std::sync::Arc;
 
 use crate::core::cache::LRUCache;
+use crate::core::disk_cache::DiskCache;
+use crate::core::jobs::JobRegistry;
+use crate::core::protobuf::unknown_fields::UnknownFields;
+use crate::features::appearances::commands::AppearanceJournal;
 use crate::features::appearances::{Appearances, CompleteFlags};
+use crate::features::map::Map;
+use crate::features::minimap::MinimapMarkerFileContent;
+use crate::features::otb::ItemsOtb;
+use crate::features::references::ReferenceIndex;
+use crate::features::sprites::commands::SpriteHashIndex;
 use crate::features::sprites::parsers::SpriteLoader;
 use crate::features::staticdata::StaticData;
 use crate::features::staticdata::parsers::StaticDataDoc;
 use crate::features::staticmapdata::StaticMapData;
+use crate::features::world::World;
 
 /// Application state to hold loaded appearances and sprites
 /// EXTREME OPTIMIZATIONS:
@@ -23,6 +33,8 @@ use crate::features::staticmapdata::StaticMapData;
 pub struct AppState {
     // Core data with parking_lot locks (3x faster than std locks)
     pub appearances: RwLock<Option<Appearances>>,
+    // Fields of the loaded .dat unknown to our schema, written back on save
+    pub appearance_unknown_fields: RwLock<UnknownFields>,
     pub sprite_loader: RwLock<Option<SpriteLoader>>,
     pub tibia_path: Mutex<Option<PathBuf>>,
 
@@ -33,11 +45,24 @@ pub struct AppState {
     pub staticdata: RwLock<Option<StaticData>>,
     pub staticdata_doc: RwLock<Option<StaticDataDoc>>,
     pub staticmapdata: RwLock<Option<StaticMapData>>,
+    // Cyclopedia map file (areas, subareas, NPC pins)
+    pub map: RwLock<Option<Map>>,
+    // Minimap markers being edited (minimapmarkers.bin)
+    pub minimap_markers: RwLock<Option<MinimapMarkerFileContent>>,
+    // Indexed server map (.otbm); shared with long-running readers
+    pub world: RwLock<Option<Arc<World>>>,
+
+    // Server items.otb (server id -> client id), kept in step with objects
+    pub items_otb: RwLock<Option<ItemsOtb>>,
+    // File the loaded items.otb was read from or last saved to
+    pub items_otb_path: Mutex<Option<PathBuf>>,
 
     // ✅ OPTIMIZED: Bounded LRU caches (prevents memory exhaustion)
     pub sprite_cache: LRUCache<String, Vec<Vec<u8>>>, // Max 5000 entries (appearance sprites)
     pub preview_cache: LRUCache<String, Vec<u8>>,     // Max 5000 entries (preview sprites)
     pub png_cache: LRUCache<u32, Vec<u8>>,            // Max 5000 entries (individual PNG by sprite ID)
+    // Encoded sprite PNGs persisted across sessions (opened in app setup)
+    pub sprite_disk_cache: RwLock<Option<DiskCache>>,
 
     // O(1) lookup indexes - no more O(n) linear scans!
     // Maps: ID -> index in Vec for instant lookups
@@ -54,6 +79,8 @@ pub struct AppState {
 
     // DAT merge: secondary appearances loaded for merging
     pub merge_source: RwLock<Option<Appearances>>,
+    // DAT merge: unknown protobuf fields of the merge source
+    pub merge_source_unknown_fields: RwLock<UnknownFields>,
     // DAT merge: new official assets folder (for sprite merge)
     pub merge_source_assets_dir: RwLock<Option<PathBuf>>,
 
@@ -67,6 +94,18 @@ pub struct AppState {
     pub imported_sprites: DashMap<u32, Vec<u8>, ahash::RandomState>,
     pub imported_sprite_hashes: DashMap<u64, u32, ahash::RandomState>,
     pub imported_sprite_next_id: Mutex<Option<u32>>,
+
+    // Perceptual hashes of all sprites (persisted in the app data dir)
+    pub sprite_hash_index: RwLock<Option<SpriteHashIndex>>,
+
+    // Undo/redo history for appearance mutations (cleared on load)
+    pub appearance_journal: Mutex<AppearanceJournal>,
+
+    // Long-running operations (progress + cancellation)
+    pub jobs: JobRegistry,
+
+    // Who points at which object/outfit id (rebuilt on demand)
+    pub reference_index: RwLock<Option<ReferenceIndex>>,
 }
 
 impl AppState {
@@ -74,16 +113,23 @@ impl AppState {
     pub fn new() -> Self {
         Self {
             appearances: RwLock::new(None),
+            appearance_unknown_fields: RwLock::new(UnknownFields::default()),
             sprite_loader: RwLock::new(None),
             tibia_path: Mutex::new(None),
             staticdata: RwLock::new(None),
             staticdata_doc: RwLock::new(None),
             staticmapdata: RwLock::new(None),
+            map: RwLock::new(None),
+            minimap_markers: RwLock::new(None),
+            world: RwLock::new(None),
+            items_otb: RwLock::new(None),
+            items_otb_path: Mutex::new(None),
 
             // LRU caches with size limits
             sprite_cache: LRUCache::new(5000),
             preview_cache: LRUCache::new(5000),
             png_cache: LRUCache::new(5000), // ~250MB max (5000 × ~50KB avg PNG)
+            sprite_disk_cache: RwLock::new(None),
 
             // Indexes
             object_index: DashMap::with_hasher(ahash::RandomState::new()),
@@ -96,6 +142,7 @@ impl AppState {
 
             flags_clipboard: Mutex::new(None),
             merge_source: RwLock::new(None),
+            merge_source_unknown_fields: RwLock::new(UnknownFields::default()),
             merge_source_assets_dir: RwLock::new(None),
             staged_sprite_files: RwLock::new(Vec::new()),
             staged_catalog: RwLock::new(None),
@@ -105,6 +152,11 @@ impl AppState {
             imported_sprites: DashMap::with_hasher(ahash::RandomState::new()),
             imported_sprite_hashes: DashMap::with_hasher(ahash::RandomState::new()),
             imported_sprite_next_id: Mutex::new(None),
+            sprite_hash_index: RwLock::new(None),
+
+            appearance_journal: Mutex::new(AppearanceJournal::default()),
+            jobs: JobRegistry::default(),
+            reference_index: RwLock::new(None),
         }
     }
 